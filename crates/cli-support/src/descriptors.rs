@@ -13,6 +13,7 @@
 use crate::descriptor::Descriptor;
 use crate::interpreter::Interpreter;
 use anyhow::Error;
+use rayon::prelude::*;
 use std::borrow::Cow;
 use std::collections::hash_map::HashMap;
 use walrus::{CustomSection, FunctionId, Module, TypedCustomSectionId};
@@ -51,23 +52,33 @@ impl WasmBindgenDescriptorsSection {
             to_remove.push(id);
         }
 
+        let mut describes = Vec::new();
         for export in module.exports.iter() {
             let prefix = "__wbindgen_describe_";
-            if !export.name.starts_with(prefix) {
+            let Some(name) = export.name.strip_prefix(prefix) else {
                 continue;
-            }
+            };
             let id = match export.item {
                 walrus::ExportItem::Function(id) => id,
                 _ => panic!("{} export not a function", export.name),
             };
-            // Interpret descriptor with 0 args (export descriptors shouldn't take any).
-            let d = interpreter.interpret_descriptor(id, module);
-            let name = &export.name[prefix.len()..];
-            let descriptor = Descriptor::decode(d);
-            self.descriptors.insert(name.to_string(), descriptor);
+            describes.push((name, id));
             to_remove.push(export.id());
         }
 
+        // Descriptors don't depend on each other, so they're interpreted in
+        // parallel, each thread with its own copy of the interpreter.
+        let interpreter = &*interpreter;
+        let descriptors = describes.into_par_iter().map_init(
+            || interpreter.clone(),
+            |interpreter, (name, id)| {
+                // Interpret descriptor with 0 args (export descriptors shouldn't take any).
+                let d = interpreter.interpret_descriptor(id, module);
+                (name.to_string(), Descriptor::decode(d))
+            },
+        );
+        self.descriptors.par_extend(descriptors);
+
         for id in to_remove {
             module.exports.delete(id);
         }
@@ -92,20 +103,30 @@ impl WasmBindgenDescriptorsSection {
         // specially codegen'd so we know the rough structure of them. For each
         // one we delegate to the interpreter to figure out the source and
         // target type descriptors.
-        let mut replace_with_imports = Vec::new();
-        for (func_id, local) in module.funcs.iter_local() {
-            let mut find = FindDescribeCast {
-                wbindgen_describe_cast,
-                found: false,
-            };
-            dfs_in_order(&mut find, local, local.entry_block());
-            if find.found {
-                replace_with_imports.push(func_id);
-            }
-        }
-        for func_id in replace_with_imports {
-            let descriptor = interpreter.interpret_descriptor(func_id, module);
-            let descriptor = Descriptor::decode(descriptor);
+        let replace_with_imports: Vec<FunctionId> = module
+            .funcs
+            .par_iter_local()
+            .filter(|(_, local)| {
+                let mut find = FindDescribeCast {
+                    wbindgen_describe_cast,
+                    found: false,
+                };
+                dfs_in_order(&mut find, local, local.entry_block());
+                find.found
+            })
+            .map(|(func_id, _)| func_id)
+            .collect();
+        let interpreter = &*interpreter;
+        let descriptors: Vec<Descriptor> = replace_with_imports
+            .par_iter()
+            .map_init(
+                || interpreter.clone(),
+                |interpreter, &func_id| {
+                    Descriptor::decode(interpreter.interpret_descriptor(func_id, module))
+                },
+            )
+            .collect();
+        for (func_id, descriptor) in replace_with_imports.into_iter().zip(descriptors) {
             self.cast_imports
                 .entry(descriptor)
                 .or_default()
@@ -28,7 +28,7 @@ use walrus::{ExportId, FunctionId, LocalFunction, LocalId, Module};
 /// An interpreter currently represents effectively cached state. It is reused
 /// between calls to `interpret` and is precomputed from a `Module`. It houses
 /// state like the Wasm stack, Wasm memory, etc.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Interpreter {
     // Function index of the `__wbindgen_describe` and
     // `__wbindgen_describe_cast` imported functions. We special case this
@@ -1343,10 +1343,13 @@ fn instruction(
                         )
                     };
 
+                    let track = js.cx.expose_track_handles_statement(&format!(
+                        "trackHandle('{name}', this.__wbg_ptr);"
+                    ));
                     js.prelude(&format!(
                         "
                         {ptr_assignment}
-                        {name}Finalization.register(this, {register_data}, this);
+                        {name}Finalization.register(this, {register_data}, this);{track}
                         "
                     ));
                     js.push(String::from("this"));
@@ -1660,12 +1663,12 @@ impl Invocation {
 }
 
 #[derive(Debug, Clone, Copy)]
-enum TypePosition {
+pub enum TypePosition {
     Argument,
     Return,
 }
 
-fn adapter2ts(
+pub fn adapter2ts(
     ty: &AdapterType,
     position: TypePosition,
     dst: &mut String,
@@ -1,16 +1,17 @@
 use crate::descriptor::VectorKind;
 use crate::intrinsic::Intrinsic;
 use crate::transforms::{threads as threads_xform, unstart_start_function};
+use crate::wasm_conventions;
 use crate::wit::{
-    Adapter, AdapterId, AdapterJsImportKind, AuxExportedMethodKind, AuxReceiverKind, AuxStringEnum,
-    AuxValue,
+    Adapter, AdapterId, AdapterJsImportKind, AuxDataEnum, AuxExportedMethodKind, AuxReceiverKind,
+    AuxStringEnum, AuxTrait, AuxValue,
 };
 use crate::wit::{AdapterKind, Instruction, InstructionData};
 use crate::wit::{AuxEnum, AuxExport, AuxExportKind, AuxImport, AuxStruct};
 use crate::wit::{JsImport, JsImportName, NonstandardWitSection, WasmBindgenAux};
-use crate::{Bindgen, EncodeInto, OutputMode, PLACEHOLDER_MODULE};
+use crate::{Bindgen, EncodeInto, OutputMode, INLINE_WASM_PLACEHOLDER, PLACEHOLDER_MODULE};
 use anyhow::{anyhow, bail, Context as _, Error};
-use binding::TsReference;
+use binding::{adapter2ts, TsReference, TypePosition};
 use std::borrow::Cow;
 use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
@@ -40,6 +41,8 @@ pub struct Context<'a> {
     intrinsics: Option<BTreeMap<Cow<'static, str>, Cow<'static, str>>>,
     imports_post: String,
     export_name_list: Vec<String>,
+    /// The names of all the definitions exported so far, including namespaces.
+    exported_names: Vec<String>,
     typescript: String,
     config: &'a Bindgen,
     pub module: &'a mut Module,
@@ -152,6 +155,8 @@ struct ExportedClass {
     typescript_fields: HashMap<FieldLocation, FieldInfo>,
     /// The namespace to export the class through, if any
     js_namespace: Option<Vec<String>>,
+    /// The TypeScript interfaces implemented by the class
+    implements: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -179,6 +184,12 @@ const INITIAL_HEAP_VALUES: &[&str] = &["undefined", "null", "true", "false"];
 // Must be kept in sync with `src/lib.rs` of the `wasm-bindgen` crate
 const INITIAL_HEAP_OFFSET: usize = 128;
 
+/// How the web and no-modules init functions default to the inlined module,
+/// for `--inline-wasm`.
+const INLINE_DEFAULT_MODULE: &str = "if (module_or_path === undefined) {
+    module_or_path = __wbg_inline_wasm();
+}";
+
 impl<'a> Context<'a> {
     pub fn new(
         module: &'a mut Module,
@@ -191,6 +202,7 @@ impl<'a> Context<'a> {
             intrinsics: Some(Default::default()),
             imports_post: String::new(),
             export_name_list: Vec::new(),
+            exported_names: Vec::new(),
             typescript: "/* tslint:disable */\n/* eslint-disable */\n".to_string(),
             imported_names: Default::default(),
             js_imports: Default::default(),
@@ -201,7 +213,7 @@ impl<'a> Context<'a> {
             exported_classes: Default::default(),
             exports: Default::default(),
             config,
-            threads_enabled: threads_xform::is_enabled(module),
+            threads_enabled: threads_xform::is_enabled(module, config.memory.as_deref()),
             module,
             npm_dependencies: Default::default(),
             wit,
@@ -266,6 +278,7 @@ impl<'a> Context<'a> {
             return;
         }
         if let Some(export_name) = export_name {
+            self.exported_names.push(export_name.to_string());
             match self.config.mode {
                 OutputMode::Node { module: false } | OutputMode::NoModules { .. } => self
                     .globals
@@ -274,7 +287,8 @@ impl<'a> Context<'a> {
                 | OutputMode::Node { module: true }
                 | OutputMode::Web
                 | OutputMode::Module
-                | OutputMode::Deno => {
+                | OutputMode::Deno
+                | OutputMode::Bun => {
                     if export_name == id {
                         if !decl.is_empty() {
                             self.globals.push_str(&format!("export {decl}"));
@@ -318,6 +332,10 @@ impl<'a> Context<'a> {
         // `__wrap` and such.
         self.write_classes()?;
 
+        if self.threads_enabled && matches!(self.config.mode, OutputMode::Web) {
+            self.generate_thread_pool(module_name)?;
+        }
+
         // Process reexports
         for (export_name, js_import) in self.aux.reexports.clone() {
             let import_name = self.import_name(&js_import)?;
@@ -348,8 +366,10 @@ impl<'a> Context<'a> {
 
         if !self.js_imports.is_empty() {
             region!(self, "js imports", {
+                let chunks = self.split_chunks()?;
                 let imports = self.js_import_header()?;
                 self.globals.push_str(&imports);
+                self.globals.push_str(&chunks);
             });
         }
 
@@ -360,7 +380,7 @@ impl<'a> Context<'a> {
             });
         }
 
-        if let Some(mem) = self.module.memories.iter().next() {
+        for mem in self.module.memories.iter() {
             if let Some(id) = mem.import {
                 self.module.imports.get_mut(id).module = PLACEHOLDER_MODULE.to_owned();
                 let mut init_memory = "new WebAssembly.Memory({".to_string();
@@ -376,9 +396,12 @@ impl<'a> Context<'a> {
             }
         }
 
+        // Only the memory the bindings use can be passed in by the caller,
+        // any other imported memory is always created here.
         let mut has_memory = false;
-        if let Some(mem) = self.module.memories.iter().next() {
-            if let Some(id) = mem.import {
+        let memory = wasm_conventions::get_memory(self.module, self.config.memory.as_deref());
+        if let Ok(memory) = memory {
+            if let Some(id) = self.module.memories.get(memory).import {
                 if let Some(def) = self.wasm_import_definitions.get_mut(&id) {
                     if !self.config.mode.bundler() {
                         def.insert_str(0, "memory || ");
@@ -423,7 +446,7 @@ impl<'a> Context<'a> {
         let needs_manual_start = unstart_start_function(self.module);
         region!(self, "wasm loading", {
             let wasm_loading =
-                self.generate_wasm_loading(module_name, needs_manual_start, has_memory);
+                self.generate_wasm_loading(module_name, needs_manual_start, has_memory)?;
             self.globals.push_str(&wasm_loading);
         });
 
@@ -447,15 +470,28 @@ impl<'a> Context<'a> {
 
         let mut ts = String::new();
 
-        if self.config.mode.no_modules() {
-            let mut iife = "
-            let wasm_bindgen = (function(exports) {
-            let script_src;
-            if (typeof document !== 'undefined' && document.currentScript !== null) {
-                script_src = new URL(document.currentScript.src, location.href).toString();
-            }
-            "
-            .to_owned();
+        let init_ts = if self.config.typescript
+            && matches!(
+                self.config.mode,
+                OutputMode::Web | OutputMode::NoModules { .. }
+            ) {
+            let has_module_or_path_optional =
+                !self.config.omit_default_module_path || self.config.inline_wasm;
+            Some(self.ts_for_init_fn(has_memory, has_module_or_path_optional)?)
+        } else {
+            None
+        };
+
+        if let OutputMode::NoModules { global } = &self.config.mode {
+            let mut iife = format!(
+                "
+                let {global} = (function(exports) {{
+                let script_src;
+                if (typeof document !== 'undefined' && document.currentScript !== null) {{
+                    script_src = new URL(document.currentScript.src, location.href).toString();
+                }}
+                "
+            );
             iife.push_str(&self.globals);
             iife.push_str(
                 "
@@ -464,26 +500,72 @@ impl<'a> Context<'a> {
                 ",
             );
             self.globals = iife;
-            ts = String::from("declare namespace wasm_bindgen {\n");
+            if let Some((_, init_function)) = &init_ts {
+                ts.push_str(init_function);
+                ts.push('\n');
+            }
+            ts.push_str(&format!("declare namespace {global} {{\n"));
             ts.push_str(&self.typescript);
+            if let Some((types, _)) = &init_ts {
+                ts.push('\n');
+                ts.push_str(types);
+            }
             ts.push_str("\n}");
+            if init_ts.is_some() {
+                // The types used to be global, which they stay for a
+                // deprecation cycle.
+                ts.push_str(&format!(
+                    "\n\n\
+                    /** @deprecated Use `{global}.InitInput` instead. */\n\
+                    declare type InitInput = {global}.InitInput;\n\
+                    \n\
+                    /** @deprecated Use `{global}.InitOutput` instead. */\n\
+                    declare type InitOutput = {global}.InitOutput;\n"
+                ));
+            }
         } else {
             ts.push_str(&self.typescript);
+            if let Some((types, init_function)) = &init_ts {
+                ts.push('\n');
+                ts.push_str(types);
+                ts.push('\n');
+                ts.push_str(init_function);
+            }
         }
 
-        // Generate TypeScript definitions for init functions in web and no-modules modes
-        if self.config.typescript
-            && matches!(
-                self.config.mode,
-                OutputMode::Web | OutputMode::NoModules { .. }
-            )
-        {
-            let has_module_or_path_optional = !self.config.omit_default_module_path;
-            let init_ts = self.ts_for_init_fn(has_memory, has_module_or_path_optional)?;
-            ts.push_str(&init_ts);
+        Ok((self.globals.to_owned(), ts, start))
+    }
+
+    /// The names the JS module returned by `finalize` exports, including the
+    /// ones of the code loading the Wasm module.
+    pub fn export_names(&self) -> Vec<String> {
+        let mut names = self.exported_names.clone();
+        let threads = self.config.mode.nodejs() && self.threads_enabled;
+        let loading: &[&str] = match self.config.mode {
+            OutputMode::Web => &["initSync", "default"],
+            _ if threads => &["initSync", "__wbindgen_wasm_module", "memory"],
+            _ => &[],
+        };
+        names.extend(loading.iter().map(|name| name.to_string()));
+        if self.config.debug || threads {
+            names.push("__wasm".to_string());
         }
+        names
+    }
 
-        Ok((self.globals.to_owned(), ts, start))
+    /// The specifiers of the packages the generated JS imports, like `lodash`
+    /// or `@scope/pkg/sub`, as opposed to relative paths and URLs.
+    pub fn package_imports(&self) -> Vec<String> {
+        let mut specifiers = self
+            .js_imports
+            .keys()
+            .filter(|module| {
+                !module.starts_with('.') && !module.starts_with('/') && !module.contains(':')
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        specifiers.sort();
+        specifiers
     }
 
     fn generate_esm_cjs_imports(&mut self, module_name: &str, has_memory: bool) -> String {
@@ -609,7 +691,8 @@ impl<'a> Context<'a> {
             | OutputMode::Node { module: true }
             | OutputMode::Web
             | OutputMode::Module
-            | OutputMode::Deno => {
+            | OutputMode::Deno
+            | OutputMode::Bun => {
                 for (module, items) in crate::sorted_iter(&self.js_imports) {
                     imports.push_str("import { ");
                     for (i, (item, rename)) in items.iter().enumerate() {
@@ -637,11 +720,132 @@ impl<'a> Context<'a> {
         Ok(imports)
     }
 
+    /// Takes the modules split into chunks out of `js_imports`, binding
+    /// their items to stubs until `__wbg_load_chunk` imports the chunk and
+    /// replaces the stubs with the module's exports.
+    ///
+    /// The items are used synchronously by the imports of the Wasm module, so
+    /// the chunk must be loaded before they are first called.
+    fn split_chunks(&mut self) -> Result<String, Error> {
+        let mut chunks = BTreeMap::<String, Vec<_>>::new();
+        let mut modules = self.js_imports.keys().cloned().collect::<Vec<_>>();
+        modules.sort();
+        for module in modules {
+            if let Some(chunk) = self.config.split_chunk_of(&module) {
+                let items = self.js_imports.remove(&module).unwrap();
+                chunks.entry(chunk).or_default().push((module, items));
+            }
+        }
+        if chunks.is_empty() {
+            return Ok(String::new());
+        }
+        if !self.config.mode.uses_es_modules() || self.config.omit_imports {
+            bail!(
+                "`--split-chunk` and `--split-snippets` require a target using ES modules and \
+                 can't be used with `--omit-imports`"
+            );
+        }
+
+        let mut stubs = String::from(
+            "
+            function __wbg_chunk_stub(chunk, name) {
+                return function() {
+                    throw new Error(`\\`${name}\\` is in the chunk \\`${chunk}\\`, which must be loaded with \\`__wbg_load_chunk\\` first`);
+                };
+            }
+            ",
+        );
+        let mut loaders = String::from("const __wbg_chunks = {\n__proto__: null,\n");
+        for (chunk, modules) in &chunks {
+            loaders.push_str(&format!("\"{chunk}\": () => Promise.all([\n"));
+            for (module, items) in modules {
+                loaders.push_str(&format!("import('{module}').then(m => {{\n"));
+                for (item, rename) in items {
+                    let local = rename.as_ref().unwrap_or(item);
+                    stubs.push_str(&format!(
+                        "let {local} = __wbg_chunk_stub(\"{chunk}\", \"{item}\");\n"
+                    ));
+                    if is_valid_ident(item) {
+                        loaders.push_str(&format!("{local} = m.{item};\n"));
+                    } else {
+                        loaders.push_str(&format!("{local} = m['{item}'];\n"));
+                    }
+                }
+                loaders.push_str("}),\n");
+            }
+            loaders.push_str("]),\n");
+        }
+        loaders.push_str("};\n");
+        loaders.push_str("const __wbg_loaded_chunks = new Map();\n");
+
+        let identifier = self.generate_identifier("__wbg_load_chunk");
+        let definition = format!(
+            "function {identifier}(name) {{
+                let loading = __wbg_loaded_chunks.get(name);
+                if (loading === undefined) {{
+                    const load = __wbg_chunks[name];
+                    if (load === undefined) {{
+                        throw new Error(`unknown chunk \\`${{name}}\\``);
+                    }}
+                    loading = load().then(() => {{}}, e => {{
+                        __wbg_loaded_chunks.delete(name);
+                        throw e;
+                    }});
+                    __wbg_loaded_chunks.set(name, loading);
+                }}
+                return loading;
+            }}
+            "
+        );
+        let names = chunks
+            .keys()
+            .map(|chunk| format!("\"{chunk}\""))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        define_export(
+            &mut self.exports,
+            "__wbg_load_chunk",
+            &[],
+            ExportEntry::Definition(ExportDefinition {
+                comments: Some(
+                    "/**\n\
+                     * Imports the JS modules of the chunk `name`, which the bindings using\n\
+                     * them need before they are first called.\n\
+                     * @param {string} name\n\
+                     * @returns {Promise<void>}\n\
+                     */\n"
+                        .to_string(),
+                ),
+                identifier,
+                definition,
+                ts_definition: format!(
+                    "function __wbg_load_chunk(name: {names}): Promise<void>;\n"
+                ),
+                ts_comments: Some(
+                    "/**\n\
+                     * Imports the JS modules of the chunk `name`, which the bindings using\n\
+                     * them need before they are first called.\n\
+                     */\n"
+                        .to_string(),
+                ),
+                private: false,
+            }),
+        )?;
+
+        Ok(format!("{stubs}\n{loaders}\n"))
+    }
+
+    /// Returns the declarations of the types and `initSync`, and separately
+    /// the one of the init function.
+    ///
+    /// With `--target no-modules` the types and `initSync` go in the namespace
+    /// of the global, and the init function is the global itself, declared
+    /// before the namespace so that TypeScript merges the two.
     fn ts_for_init_fn(
         &self,
         has_memory: bool,
         has_module_or_path_optional: bool,
-    ) -> Result<String, Error> {
+    ) -> Result<(String, String), Error> {
         let output = crate::wasm2es6js::interface(self.module)?;
 
         let (memory_doc, memory_param) = if has_memory {
@@ -658,44 +862,39 @@ impl<'a> Context<'a> {
             ""
         };
         let arg_optional = if has_module_or_path_optional { "?" } else { "" };
-        // With TypeScript 3.8.3, I'm seeing that any "export"s at the root level cause TypeScript to ignore all "declare" statements.
-        // So using "declare" everywhere for at least the NoModules option.
-        // Also in (at least) the NoModules, the `init()` method is renamed to `wasm_bindgen()`.
-        let setup_function_declaration;
-        let mut sync_init_function = String::new();
-        let declare_or_export;
-        if self.config.mode.no_modules() {
-            declare_or_export = "declare";
-            setup_function_declaration = "declare function wasm_bindgen";
-        } else {
-            declare_or_export = "export";
-
-            sync_init_function.push_str(&format!(
-                "\
-                {declare_or_export} type SyncInitInput = BufferSource | WebAssembly.Module;\n\n\
-                /**\n\
-                * Instantiates the given `module`, which can either be bytes or\n\
-                * a precompiled `WebAssembly.Module`.\n\
-                *\n\
-                * @param {{{{ module: SyncInitInput{memory_param}{stack_size} }}}} module - Passing `SyncInitInput` directly is deprecated.\n\
-                {memory_doc}\
-                *\n\
-                * @returns {{InitOutput}}\n\
-                */\n\
-                export function initSync(module: {{ module: SyncInitInput{memory_param}{stack_size} }} | SyncInitInput{memory_param}): InitOutput;\n\n\
-                "
-            ));
-
-            setup_function_declaration = "export default function __wbg_init";
-        }
-        Ok(format!(
-            "\n\
-            {declare_or_export} type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;\n\
+        let (init_function_declaration, namespace) = match &self.config.mode {
+            OutputMode::NoModules { global } => {
+                (format!("declare function {global}"), format!("{global}."))
+            }
+            _ => (
+                "export default function __wbg_init".to_string(),
+                String::new(),
+            ),
+        };
+        let types = format!(
+            "\
+            export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;\n\
             \n\
-            {declare_or_export} interface InitOutput {{\n\
+            export interface InitOutput {{\n\
             {output}}}\n\
             \n\
-            {sync_init_function}\
+            export type SyncInitInput = BufferSource | WebAssembly.Module;\n\
+            \n\
+            /**\n\
+            * Instantiates the given `module`, which can either be bytes or\n\
+            * a precompiled `WebAssembly.Module`.\n\
+            *\n\
+            * @param {{{{ module: SyncInitInput{memory_param}{stack_size} }}}} module - Passing `SyncInitInput` directly is deprecated.\n\
+            {memory_doc}\
+            *\n\
+            * @returns {{InitOutput}}\n\
+            */\n\
+            export function initSync(module: {{ module: SyncInitInput{memory_param}{stack_size} }} | SyncInitInput{memory_param}): InitOutput;\n\
+            "
+        );
+        let init_input = format!("{namespace}InitInput");
+        let init_function = format!(
+            "\
             /**\n\
             * If `module_or_path` is {{RequestInfo}} or {{URL}}, makes a request and\n\
             * for everything else, calls `WebAssembly.instantiate` directly.\n\
@@ -705,14 +904,20 @@ impl<'a> Context<'a> {
             *\n\
             * @returns {{Promise<InitOutput>}}\n\
             */\n\
-            {setup_function_declaration} \
-                (module_or_path{arg_optional}: {{ module_or_path: InitInput | Promise<InitInput>{memory_param}{stack_size} }} | InitInput | Promise<InitInput>{memory_param}): Promise<InitOutput>;\n",
-        ))
+            {init_function_declaration} \
+                (module_or_path{arg_optional}: {{ module_or_path: {init_input} | Promise<{init_input}>{memory_param}{stack_size} }} | {init_input} | Promise<{init_input}>{memory_param}): Promise<{namespace}InitOutput>;\n",
+        );
+        Ok((types, init_function))
     }
 
     fn generate_module_wasm_loading(&self, module_name: &str, needs_manual_start: bool) -> String {
+        let import = if self.config.inline_wasm {
+            "const wasmModule = new WebAssembly.Module(__wbg_inline_wasm());".to_string()
+        } else {
+            format!(r#"import source wasmModule from "./{module_name}_bg.wasm";"#)
+        };
         format!(
-            r#"import source wasmModule from "./{module_name}_bg.wasm";
+            r#"{import}
             const wasmInstance = new WebAssembly.Instance(wasmModule, __wbg_get_imports());
             let wasm = wasmInstance.exports;
             {start}
@@ -786,7 +991,8 @@ impl<'a> Context<'a> {
         needs_manual_start: bool,
         default_module_path: &str,
         has_memory: bool,
-    ) -> String {
+    ) -> Result<String, Error> {
+        let (init_sync_docs, init_docs) = self.jsdoc_for_init_fns(has_memory)?;
         let mut init_memviews = String::new();
         for &(num, ref views) in self.memories.values() {
             for kind in views {
@@ -800,7 +1006,7 @@ impl<'a> Context<'a> {
                 .unwrap()
             }
         }
-        format!(
+        Ok(format!(
             "let wasmModule, wasm;
             function __wbg_finalize_init(instance, module{init_stack_size_arg}) {{
                 wasm = instance.exports;
@@ -847,7 +1053,7 @@ impl<'a> Context<'a> {
                 }}
             }}
 
-            function initSync(module{init_memory_arg}) {{
+            {init_sync_docs}function initSync(module{init_memory_arg}) {{
                 if (wasm !== undefined) return wasm;
 
                 {init_stack_size}
@@ -859,7 +1065,7 @@ impl<'a> Context<'a> {
                     }}
                 }}
 
-                const imports = __wbg_get_imports({init_memory_arg_alone});
+                {inline_module}const imports = __wbg_get_imports({init_memory_arg_alone});
                 if (!(module instanceof WebAssembly.Module)) {{
                     module = new WebAssembly.Module(module);
                 }}
@@ -867,7 +1073,7 @@ impl<'a> Context<'a> {
                 return __wbg_finalize_init(instance, module{init_stack_size_arg});
             }}
 
-            async function __wbg_init(module_or_path{init_memory_arg}) {{
+            {init_docs}async function __wbg_init(module_or_path{init_memory_arg}) {{
                 if (wasm !== undefined) return wasm;
 
                 {init_stack_size}
@@ -898,6 +1104,11 @@ impl<'a> Context<'a> {
             } else {
                 ""
             },
+            inline_module = if self.config.inline_wasm {
+                "if (module === undefined) module = __wbg_inline_wasm();\n"
+            } else {
+                ""
+            },
             init_stack_size = if self.threads_enabled {
                 "let thread_stack_size"
             } else {
@@ -928,12 +1139,96 @@ impl<'a> Context<'a> {
             } else {
                 ""
             },
-        )
+        ))
+    }
+
+    /// Declares a type for the JSDoc of `--jsdoc-types` with `typedef`, its
+    /// `@typedef` tag and those following it, documented by `comments`. Plain
+    /// JS has no other declaration of the type its JSDoc refers to.
+    fn jsdoc_typedef(&mut self, comments: &str, typedef: String) {
+        let docs = format_doc_comments(comments, Some(typedef));
+        self.global(&format!("\n{docs}"));
+    }
+
+    /// With `--jsdoc-types`, the JSDoc of `initSync` and of the init function,
+    /// the former preceded by typedefs of the types they use, as declared in
+    /// the TypeScript of `ts_for_init_fn`.
+    fn jsdoc_for_init_fns(&self, has_memory: bool) -> Result<(String, String), Error> {
+        if !self.config.jsdoc_types {
+            return Ok((String::new(), String::new()));
+        }
+        let output = crate::wasm2es6js::interface(self.module)?.lines().fold(
+            String::new(),
+            |mut output, line| {
+                let _ = writeln!(output, " * {line}");
+                output
+            },
+        );
+        let (memory_doc, memory_param) = if has_memory {
+            (
+                " * @param {WebAssembly.Memory} [memory] - Deprecated.\n",
+                ", memory?: WebAssembly.Memory",
+            )
+        } else {
+            ("", "")
+        };
+        let stack_size = if self.threads_enabled {
+            ", thread_stack_size?: number"
+        } else {
+            ""
+        };
+        let module_or_path = if !self.config.omit_default_module_path || self.config.inline_wasm {
+            "[module_or_path]"
+        } else {
+            "module_or_path"
+        };
+        let init_sync = format!(
+            "\
+            /** @typedef {{RequestInfo | URL | Response | BufferSource | WebAssembly.Module}} InitInput */\n\
+            /**\n\
+             * @typedef {{{{\n\
+            {output} * }}}} InitOutput\n\
+             */\n\
+            /** @typedef {{BufferSource | WebAssembly.Module}} SyncInitInput */\n\
+            \n\
+            /**\n\
+             * Instantiates the given `module`, which can either be bytes or\n\
+             * a precompiled `WebAssembly.Module`.\n\
+             *\n\
+             * @param {{{{ module: SyncInitInput{memory_param}{stack_size} }} | SyncInitInput}} module - Passing `SyncInitInput` directly is deprecated.\n\
+            {memory_doc}\
+             * @returns {{InitOutput}}\n\
+             */\n"
+        );
+        let init = format!(
+            "\
+            /**\n\
+             * If `module_or_path` is {{RequestInfo}} or {{URL}}, makes a request and\n\
+             * for everything else, calls `WebAssembly.instantiate` directly.\n\
+             *\n\
+             * @param {{{{ module_or_path: InitInput | Promise<InitInput>{memory_param}{stack_size} }} | InitInput | Promise<InitInput>}} {module_or_path} - Passing `InitInput` directly is deprecated.\n\
+            {memory_doc}\
+             * @returns {{Promise<InitOutput>}}\n\
+             */\n"
+        );
+        Ok((init_sync, init))
     }
 
     fn generate_deno_wasm_loading(&self, module_name: &str, needs_manual_start: bool) -> String {
         // Deno added support for .wasm imports in 2024 in https://github.com/denoland/deno/issues/2552.
         // It's fairly recent, so use old-school Wasm loading for broader compat for now.
+        if self.config.inline_wasm {
+            return format!(
+                "const wasmModule = new WebAssembly.Module(__wbg_inline_wasm());
+                const wasm = new WebAssembly.Instance(wasmModule, __wbg_get_imports()).exports;
+                {start}",
+                start = if needs_manual_start {
+                    "wasm.__wbindgen_start();\n"
+                } else {
+                    ""
+                },
+            );
+        }
         format!(
             "const wasmUrl = new URL('{module_name}_bg.wasm', import.meta.url);
             const wasmInstantiated = await WebAssembly.instantiateStreaming(fetch(wasmUrl), __wbg_get_imports());
@@ -947,11 +1242,52 @@ impl<'a> Context<'a> {
         )
     }
 
+    fn generate_bun_wasm_loading(&self, module_name: &str, needs_manual_start: bool) -> String {
+        // Importing the Wasm file with the `file` type resolves its path at
+        // runtime and lets `bun build` bundle it along with the glue.
+        let load = if self.config.inline_wasm {
+            "const wasmModule = new WebAssembly.Module(__wbg_inline_wasm());".to_string()
+        } else {
+            format!(
+                "import wasmPath from './{module_name}_bg.wasm' with {{ type: 'file' }};
+                const wasmModule = new WebAssembly.Module(await Bun.file(wasmPath).arrayBuffer());"
+            )
+        };
+        format!(
+            "{load}
+            const wasm = new WebAssembly.Instance(wasmModule, __wbg_get_imports()).exports;
+            {start}",
+            start = if needs_manual_start {
+                "wasm.__wbindgen_start();\n"
+            } else {
+                ""
+            },
+        )
+    }
+
     fn generate_node_esm_wasm_loading(
         &self,
         module_name: &str,
         needs_manual_start: bool,
     ) -> String {
+        let (read_module, read_bytes) = if self.config.inline_wasm {
+            (
+                "module = __wbg_inline_wasm();".to_string(),
+                "const wasmBytes = __wbg_inline_wasm();".to_string(),
+            )
+        } else {
+            (
+                format!(
+                    "const wasmUrl = new URL('{module_name}_bg.wasm', import.meta.url);
+        module = readFileSync(wasmUrl);"
+                ),
+                format!(
+                    "import {{ readFileSync }} from 'node:fs';
+            const wasmUrl = new URL('{module_name}_bg.wasm', import.meta.url);
+            const wasmBytes = readFileSync(wasmUrl);"
+                ),
+            )
+        };
         if self.threads_enabled {
             // For threads: generate initSync that accepts custom memory
             let start_call = if needs_manual_start {
@@ -980,8 +1316,7 @@ export function initSync(opts = {{}}) {{
     let {{ module, memory, thread_stack_size }} = opts;
 
     if (module === undefined) {{
-        const wasmUrl = new URL('{module_name}_bg.wasm', import.meta.url);
-        module = readFileSync(wasmUrl);
+        {read_module}
     }}
 
     if (!(module instanceof WebAssembly.Module)) {{
@@ -1010,9 +1345,7 @@ export {{ wasm as __wasm, wasmModule as __wbindgen_wasm_module, __wbg_memory as
             )
         } else {
             format!(
-                r#"import {{ readFileSync }} from 'node:fs';
-            const wasmUrl = new URL('{module_name}_bg.wasm', import.meta.url);
-            const wasmBytes = readFileSync(wasmUrl);
+                r#"{read_bytes}
             const wasmModule = new WebAssembly.Module(wasmBytes);
             let wasm = new WebAssembly.Instance(wasmModule, __wbg_get_imports()).exports;
             {start}"#,
@@ -1030,6 +1363,23 @@ export {{ wasm as __wasm, wasmModule as __wbindgen_wasm_module, __wbg_memory as
         module_name: &str,
         needs_manual_start: bool,
     ) -> String {
+        let (read_module, read_bytes) = if self.config.inline_wasm {
+            (
+                "module = __wbg_inline_wasm();".to_string(),
+                "const wasmBytes = __wbg_inline_wasm();".to_string(),
+            )
+        } else {
+            (
+                format!(
+                    "const wasmPath = `${{__dirname}}/{module_name}_bg.wasm`;
+        module = require('fs').readFileSync(wasmPath);"
+                ),
+                format!(
+                    "const wasmPath = `${{__dirname}}/{module_name}_bg.wasm`;
+            const wasmBytes = require('fs').readFileSync(wasmPath);"
+                ),
+            )
+        };
         if self.threads_enabled {
             // For threads: generate initSync that accepts custom memory
             let start_call = if needs_manual_start {
@@ -1060,8 +1410,7 @@ exports.initSync = function(opts) {{
     let thread_stack_size = opts.thread_stack_size;
 
     if (module === undefined) {{
-        const wasmPath = `${{__dirname}}/{module_name}_bg.wasm`;
-        module = require('fs').readFileSync(wasmPath);
+        {read_module}
     }}
 
     if (!(module instanceof WebAssembly.Module)) {{
@@ -1090,8 +1439,7 @@ if (require('worker_threads').isMainThread) {{
             )
         } else {
             format!(
-                r#"const wasmPath = `${{__dirname}}/{module_name}_bg.wasm`;
-            const wasmBytes = require('fs').readFileSync(wasmPath);
+                r#"{read_bytes}
             const wasmModule = new WebAssembly.Module(wasmBytes);
             let wasm = new WebAssembly.Instance(wasmModule, __wbg_get_imports()).exports;
             {start}"#,
@@ -1109,13 +1457,44 @@ if (require('worker_threads').isMainThread) {{
         module_name: &str,
         needs_manual_start: bool,
         has_memory: bool,
-    ) -> String {
-        match self.config.mode {
+    ) -> Result<String, Error> {
+        let mut loading = String::new();
+        if self.config.inline_wasm {
+            // The Wasm module is encoded in place of the placeholder once it's
+            // emitted.
+            loading.push_str(&format!(
+                "function __wbg_inline_wasm() {{
+                    const base64 = \"{INLINE_WASM_PLACEHOLDER}\";
+                    if (typeof Buffer === 'function') {{
+                        return Buffer.from(base64, 'base64');
+                    }}
+                    return Uint8Array.from(atob(base64), c => c.charCodeAt(0));
+                }}
+
+                "
+            ));
+        }
+        loading.push_str(&self.generate_wasm_loading_for_mode(
+            module_name,
+            needs_manual_start,
+            has_memory,
+        )?);
+        Ok(loading)
+    }
+
+    fn generate_wasm_loading_for_mode(
+        &self,
+        module_name: &str,
+        needs_manual_start: bool,
+        has_memory: bool,
+    ) -> Result<String, Error> {
+        Ok(match self.config.mode {
             OutputMode::Module => {
                 self.generate_module_wasm_loading(module_name, needs_manual_start)
             }
             OutputMode::Bundler { .. } => self.generate_bundler_wasm_loading(),
             OutputMode::Deno => self.generate_deno_wasm_loading(module_name, needs_manual_start),
+            OutputMode::Bun => self.generate_bun_wasm_loading(module_name, needs_manual_start),
             OutputMode::Node { module: true } => {
                 self.generate_node_esm_wasm_loading(module_name, needs_manual_start)
             }
@@ -1123,7 +1502,9 @@ if (require('worker_threads').isMainThread) {{
                 self.generate_node_cjs_wasm_loading(module_name, needs_manual_start)
             }
             OutputMode::Web => {
-                let default_module_path = if self.config.omit_default_module_path {
+                let default_module_path = if self.config.inline_wasm {
+                    INLINE_DEFAULT_MODULE
+                } else if self.config.omit_default_module_path {
                     ""
                 } else {
                     &format!(
@@ -1133,23 +1514,25 @@ if (require('worker_threads').isMainThread) {{
                     )
                 };
                 let mut loading =
-                    self.generate_web_loading(needs_manual_start, default_module_path, has_memory);
+                    self.generate_web_loading(needs_manual_start, default_module_path, has_memory)?;
 
                 loading.push_str("\nexport { initSync, __wbg_init as default };");
 
                 loading
             }
             OutputMode::NoModules { .. } => {
-                let default_module_path = if self.config.omit_default_module_path {
+                let default_module_path = if self.config.inline_wasm {
+                    INLINE_DEFAULT_MODULE
+                } else if self.config.omit_default_module_path {
                     ""
                 } else {
                     r#"if (module_or_path === undefined && script_src !== undefined) {
                         module_or_path = script_src.replace(/\.js$/, "_bg.wasm");
                     }"#
                 };
-                self.generate_web_loading(needs_manual_start, default_module_path, has_memory)
+                self.generate_web_loading(needs_manual_start, default_module_path, has_memory)?
             }
-        }
+        })
     }
 
     fn require_class<'b>(&'b mut self, name: &str) -> &'b mut ExportedClass {
@@ -1178,7 +1561,14 @@ if (require('worker_threads').isMainThread) {{
     fn write_class(&mut self, name: &str, class: ExportedClass) -> Result<(), Error> {
         let identifier = &class.identifier;
         let mut dst = format!("class {identifier} {{\n");
-        let mut ts_dst = dst.clone();
+        let mut ts_dst = if class.implements.is_empty() {
+            dst.clone()
+        } else {
+            format!(
+                "class {identifier} implements {} {{\n",
+                class.implements.join(", ")
+            )
+        };
 
         if !class.has_constructor {
             // declare the constructor as private to prevent direct instantiation
@@ -1208,13 +1598,15 @@ if (require('worker_threads').isMainThread) {{
                 ("obj.__wbg_ptr = ptr;", "obj.__wbg_ptr")
             };
 
+            let track =
+                self.expose_track_handles_statement(&format!("trackHandle('{identifier}', ptr);"));
             dst.push_str(&format!(
                 "\
                 static __wrap(ptr) {{
                     ptr = ptr >>> 0;
                     const obj = Object.create({identifier}.prototype);
                     {ptr_assignment}
-                    {identifier}Finalization.register(obj, {register_data}, obj);
+                    {identifier}Finalization.register(obj, {register_data}, obj);{track}
                     return obj;
                 }}
                 "
@@ -1234,10 +1626,19 @@ if (require('worker_threads').isMainThread) {{
             ));
         }
 
+        let untrack =
+            self.expose_track_handles_statement(&format!("untrackHandle('{identifier}', ptr);"));
         let finalization_callback = if self.config.generate_reset_state {
             format!(
                 "({{ ptr, instance }}) => {{
-                if (instance === __wbg_instance_id) wasm.{}(ptr >>> 0, 1);
+                if (instance === __wbg_instance_id) wasm.{}(ptr >>> 0, 1);{untrack}
+            }}",
+                wasm_bindgen_shared::free_function(name)
+            )
+        } else if self.config.track_handles {
+            format!(
+                "ptr => {{{untrack}
+                wasm.{}(ptr >>> 0, 1);
             }}",
                 wasm_bindgen_shared::free_function(name)
             )
@@ -1315,7 +1716,7 @@ if (require('worker_threads').isMainThread) {{
             __destroy_into_raw() {{
                 const ptr = this.__wbg_ptr;
                 this.__wbg_ptr = 0;
-                {identifier}Finalization.unregister(this);
+                {identifier}Finalization.unregister(this);{untrack}
                 return ptr;
             }}
             free() {{
@@ -1594,11 +1995,12 @@ if (require('worker_threads').isMainThread) {{
         // the linked list of heap slots that are free.
         self.expose_global_heap();
         self.expose_global_heap_next();
+        let untrack = self.expose_track_handles_statement("untrackHandle('JsValue', idx);");
         intrinsic(&mut self.intrinsics, "drop_ref".into(), || {
             format!(
                 "
                 function dropObject(idx) {{
-                    if (idx < {}) return;
+                    if (idx < {}) return;{untrack}
                     heap[idx] = heap_next;
                     heap_next = idx;
                 }}
@@ -2443,6 +2845,7 @@ if (require('worker_threads').isMainThread) {{
         // (starting at `heap_next`). Once that linked list is exhausted we'll
         // be pointing beyond the end of the array, at which point we'll reserve
         // one more slot and use that.
+        let track = self.expose_track_handles_statement("trackHandle('JsValue', idx);");
         intrinsic(&mut self.intrinsics, "add_heap_object".into(), || {
             format!(
                 "
@@ -2451,7 +2854,7 @@ if (require('worker_threads').isMainThread) {{
                     const idx = heap_next;
                     heap_next = heap[idx];
                     {}
-                    heap[idx] = obj;
+                    heap[idx] = obj;{track}
                     return idx;
                 }}
                 ",
@@ -2765,6 +3168,169 @@ if (require('worker_threads').isMainThread) {{
         });
     }
 
+    /// Returns `statement` on its own line when handle tracking is enabled,
+    /// pulling in `trackHandle`/`untrackHandle`, and nothing otherwise.
+    fn expose_track_handles_statement(&mut self, statement: &str) -> String {
+        if !self.config.track_handles {
+            return String::new();
+        }
+        intrinsic(&mut self.intrinsics, "track_handles".into(), || {
+            "
+            const liveHandles = new Map();
+
+            let nextHandleId = 0;
+
+            function trackHandle(kind, handle) {
+                // Drop the `Error` header (V8 only) and the `trackHandle` frame.
+                const stack = (new Error().stack || '')
+                    .split('\\n')
+                    .filter(line => line && line !== 'Error' && !line.includes('trackHandle'))
+                    .join('\\n');
+                liveHandles.set(`${kind}:${handle}`, { id: nextHandleId++, kind, handle, stack });
+            }
+
+            function untrackHandle(kind, handle) {
+                liveHandles.delete(`${kind}:${handle}`);
+            }
+            "
+            .into()
+        });
+        format!("\n{statement}")
+    }
+
+    /// Exports `__wbg_live_handles`, listing every handle the glue handed out
+    /// that hasn't been released yet, with the stack it was created at.
+    fn generate_live_handles(&mut self) -> Result<(), Error> {
+        // Make sure the tracking globals exist even if nothing was hooked.
+        self.expose_track_handles_statement("");
+
+        // With externref the Rust side releases slots without calling back
+        // into JS, so a slot that has since been cleared is no longer live.
+        let released = match self.aux.externref_table {
+            Some(table) => {
+                let table = self.export_name_of(table);
+                format!(
+                    "
+                    if (record.kind === 'JsValue' && wasm.{table}.get(record.handle) === null) {{
+                        liveHandles.delete(key);
+                        continue;
+                    }}
+                    "
+                )
+            }
+            None => String::new(),
+        };
+
+        let identifier = self.generate_identifier("__wbg_live_handles");
+        let definition = format!(
+            "\
+            function {identifier}() {{
+                const handles = [];
+                for (const [key, record] of liveHandles) {{
+                    {released}
+                    handles.push({{ ...record }});
+                }}
+                return handles;
+            }}
+            "
+        );
+        define_export(
+            &mut self.exports,
+            "__wbg_live_handles",
+            &[],
+            ExportEntry::Definition(ExportDefinition {
+                comments: None,
+                identifier,
+                definition,
+                ts_definition: "function __wbg_live_handles(): { id: number, kind: string, handle: number, stack: string }[];\n".to_string(),
+                ts_comments: None,
+                private: false,
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Exports `__wbg_init_thread_pool`, which starts workers running the
+    /// bootstrap script of `worker_bootstrap` on the same memory.
+    fn generate_thread_pool(&mut self, module_name: &str) -> Result<(), Error> {
+        let memory = wasm_conventions::get_memory(self.module, self.config.memory.as_deref())?;
+        let memory = self.export_name_of(memory);
+        let identifier = self.generate_identifier("__wbg_init_thread_pool");
+        let definition = format!(
+            "\
+            function {identifier}(count, entry, ...args) {{
+                if (wasm === undefined) {{
+                    throw new Error('the module must be initialized before starting threads');
+                }}
+                const url = new URL('./{module_name}.worker.js', import.meta.url);
+                return Promise.all(Array.from({{ length: count }}, () => new Promise((resolve, reject) => {{
+                    const worker = new Worker(url, {{ type: 'module' }});
+                    worker.addEventListener('message', ({{ data }}) => {{
+                        if (data.error === undefined) resolve(worker); else reject(data.error);
+                    }}, {{ once: true }});
+                    worker.addEventListener('error', reject, {{ once: true }});
+                    worker.postMessage({{ module: wasmModule, memory: wasm.{memory}, entry, args }});
+                }})));
+            }}
+            "
+        );
+        let docs = "\
+            Starts `count` workers sharing this module's memory, resolving to them\n\
+            once they're initialized. If `entry` is given, each worker then calls\n\
+            the export of that name with `args`, like a thread pool's worker loop.\n";
+        define_export(
+            &mut self.exports,
+            "__wbg_init_thread_pool",
+            &[],
+            ExportEntry::Definition(ExportDefinition {
+                comments: Some(format_doc_comments(
+                    docs,
+                    Some(
+                        "@param {number} count\n\
+                         @param {string} [entry]\n\
+                         @param {...any} args\n\
+                         @returns {Promise<Worker[]>}\n"
+                            .to_string(),
+                    ),
+                )),
+                identifier,
+                definition,
+                ts_definition: "function __wbg_init_thread_pool(count: number, entry?: string, ...args: any[]): Promise<Worker[]>;\n".to_string(),
+                ts_comments: Some(format_doc_comments(docs, None)),
+                private: false,
+            }),
+        )?;
+        Ok(())
+    }
+
+    /// The script the workers of `__wbg_init_thread_pool` run, emitted as
+    /// `{module_name}.worker.js` for `--target web` when the module uses
+    /// threads.
+    pub fn worker_bootstrap(&self, module_name: &str) -> Option<String> {
+        if !self.threads_enabled || !matches!(self.config.mode, OutputMode::Web) {
+            return None;
+        }
+        Some(format!(
+            "\
+            import __wbg_init, * as bindings from './{module_name}.js';
+
+            self.addEventListener('message', async ({{ data: {{ module, memory, entry, args }} }}) => {{
+                try {{
+                    await __wbg_init({{ module_or_path: module, memory }});
+                }} catch (error) {{
+                    postMessage({{ error }});
+                    return;
+                }}
+                postMessage({{}});
+                if (entry !== undefined) {{
+                    bindings[entry](...args);
+                }}
+            }}, {{ once: true }});
+            "
+        ))
+    }
+
     fn generate_reset_state(&mut self) -> Result<(), Error> {
         self.global("let __wbg_instance_id = 0;");
 
@@ -2826,6 +3392,10 @@ if (require('worker_threads').isMainThread) {{
             reset_statements.push(heap_reset);
         }
 
+        if self.has_intrinsic("track_handles") {
+            reset_statements.push("liveHandles.clear();".to_string());
+        }
+
         reset_statements.push(
             "
             const wasmInstance = new WebAssembly.Instance(wasmModule, __wbg_get_imports());
@@ -3019,11 +3589,12 @@ if (require('worker_threads').isMainThread) {{
         assert!(self.config.externref);
         let drop = self.export_name_of(drop);
         let table = self.export_name_of(table);
+        let untrack = self.expose_track_handles_statement("untrackHandle('JsValue', idx);");
         intrinsic(&mut self.intrinsics, view.to_string().into(), || {
             format!(
                 "
                 function {view}(idx) {{
-                    const value = wasm.{table}.get(idx);
+                    const value = wasm.{table}.get(idx);{untrack}
                     wasm.{drop}(idx);
                     return value;
                 }}
@@ -3040,12 +3611,13 @@ if (require('worker_threads').isMainThread) {{
         assert!(self.config.externref);
         let alloc = self.export_name_of(alloc);
         let table = self.export_name_of(table);
+        let track = self.expose_track_handles_statement("trackHandle('JsValue', idx);");
         intrinsic(&mut self.intrinsics, view.to_string().into(), || {
             format!(
                 "
                     function {view}(obj) {{
                         const idx = wasm.{alloc}();
-                        wasm.{table}.set(idx, obj);
+                        wasm.{table}.set(idx, obj);{track}
                         return idx;
                     }}
                 ",
@@ -3077,6 +3649,13 @@ if (require('worker_threads').isMainThread) {{
         for (_, e) in crate::sorted_iter(&self.aux.enums) {
             self.generate_enum(e)?;
         }
+        // Before string enums, which are only declared when referenced.
+        for (_, e) in crate::sorted_iter(&self.aux.data_enums) {
+            self.generate_data_enum(e)?;
+        }
+        for (_, t) in crate::sorted_iter(&self.aux.traits) {
+            self.generate_trait(t)?;
+        }
         for (_, e) in crate::sorted_iter(&self.aux.string_enums) {
             self.generate_string_enum(e)?;
         }
@@ -3099,6 +3678,10 @@ if (require('worker_threads').isMainThread) {{
 
         self.export_destructor();
 
+        if self.config.track_handles {
+            self.generate_live_handles()?;
+        }
+
         // Generate reset state function last, to ensure it knows about all other state.
         if self.config.generate_reset_state {
             self.generate_reset_state()?;
@@ -3463,6 +4046,15 @@ if (require('worker_threads').isMainThread) {{
             return Ok(false);
         }
 
+        // Items of modules split into chunks are bound once the chunk is
+        // loaded, so they need a shim looking them up on each call.
+        if self
+            .js_module_of(&js.name)
+            .is_some_and(|module| self.config.split_chunk_of(&module).is_some())
+        {
+            return Ok(false);
+        }
+
         // If there's no field projection happening here and this is a direct
         // import from an ES-looking module, then we can actually just hook this
         // up directly in the Wasm file itself. Note that this is covered in the
@@ -3535,6 +4127,23 @@ if (require('worker_threads').isMainThread) {{
         Ok(true)
     }
 
+    /// The JS module an import is imported from, if it isn't a global.
+    fn js_module_of(&self, name: &JsImportName) -> Option<String> {
+        match name {
+            JsImportName::Module { module, .. } => Some(module.clone()),
+            JsImportName::LocalModule { module, .. } => Some(self.config.local_module_name(module)),
+            JsImportName::InlineJs {
+                unique_crate_identifier,
+                snippet_idx_in_crate,
+                ..
+            } => Some(
+                self.config
+                    .inline_js_module_name(unique_crate_identifier, *snippet_idx_in_crate),
+            ),
+            JsImportName::Global { .. } | JsImportName::VendorPrefixed { .. } => None,
+        }
+    }
+
     fn representable_without_js_glue(&self, instrs: &[InstructionData]) -> bool {
         use Instruction::*;
 
@@ -3840,6 +4449,7 @@ if (require('worker_threads').isMainThread) {{
                         | OutputMode::Bundler { .. }
                         | OutputMode::Module
                         | OutputMode::Deno
+                        | OutputMode::Bun
                         | OutputMode::Node { module: true } => "import.meta.url",
                         OutputMode::Node { module: false } => {
                             "require('url').pathToFileURL(__filename)"
@@ -4137,10 +4747,11 @@ if (require('worker_threads').isMainThread) {{
 
                 match self.config.mode {
                     OutputMode::Web | OutputMode::NoModules { .. } |
-                    OutputMode::Node { .. } | OutputMode::Module => "wasmModule",
+                    OutputMode::Node { .. } | OutputMode::Module | OutputMode::Bun => "wasmModule",
                     _ => bail!(
                         "`wasm_bindgen::module` is currently only supported with \
-                         `--target no-modules`, `--target web`, `--target module` and `--target nodejs`"
+                         `--target no-modules`, `--target web`, `--target module`, `--target nodejs` \
+                         and `--target bun`"
                     ),
                 }
                 .to_string()
@@ -4309,6 +4920,142 @@ if (require('worker_threads').isMainThread) {{
         Ok(())
     }
 
+    fn generate_trait(&mut self, trait_: &AuxTrait) -> Result<(), Error> {
+        // Each method is written as `name(args): ret`, with a `?` after the
+        // name of methods that have a default implementation in Rust.
+        let mut methods = Vec::new();
+        for method in trait_.methods.iter() {
+            let mut args = Vec::new();
+            for (name, ty) in method.args.iter() {
+                let mut arg = format!("{name}: ");
+                adapter2ts(
+                    ty,
+                    TypePosition::Argument,
+                    &mut arg,
+                    Some(&mut self.typescript_refs),
+                );
+                args.push(arg);
+            }
+            let mut ret = String::new();
+            match &method.ret {
+                Some(ty) => adapter2ts(
+                    ty,
+                    TypePosition::Return,
+                    &mut ret,
+                    Some(&mut self.typescript_refs),
+                ),
+                None => ret.push_str("void"),
+            }
+            methods.push((method, args, ret));
+        }
+
+        if trait_.generate_typescript {
+            let docs = format_doc_comments(&trait_.comments, None);
+            self.typescript.push('\n');
+            self.typescript.push_str(&docs);
+            self.typescript.push_str("export interface ");
+            self.typescript.push_str(&trait_.name);
+            self.typescript.push_str(" {\n");
+            for (method, args, ret) in methods.iter() {
+                if !method.comments.is_empty() {
+                    for line in format_doc_comments(&method.comments, None).lines() {
+                        self.typescript.push_str("  ");
+                        self.typescript.push_str(line);
+                        self.typescript.push('\n');
+                    }
+                }
+                let optional = if method.optional { "?" } else { "" };
+                self.typescript.push_str(&format!(
+                    "  {}{optional}({}): {ret};\n",
+                    method.name,
+                    args.join(", ")
+                ));
+            }
+            self.typescript.push_str("}\n");
+        }
+
+        if self.config.jsdoc_types {
+            let properties = methods
+                .iter()
+                .map(|(method, args, ret)| {
+                    let name = if method.optional {
+                        format!("[{}]", method.name)
+                    } else {
+                        method.name.clone()
+                    };
+                    format!("@property {{({}) => {ret}}} {name}", args.join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.jsdoc_typedef(
+                &trait_.comments,
+                format!("@typedef {{Object}} {}\n{properties}", trait_.name),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn generate_data_enum(&mut self, enum_: &AuxDataEnum) -> Result<(), Error> {
+        // Each variant is an object with its name in `kind` and its fields.
+        let mut variants = Vec::new();
+        for variant in enum_.variants.iter() {
+            let mut members = vec![format!("kind: \"{}\"", variant.name)];
+            for (name, ty) in variant.fields.iter() {
+                let mut member = if is_valid_ident(name) {
+                    name.clone()
+                } else {
+                    format!("\"{name}\"")
+                };
+                member.push_str(": ");
+                adapter2ts(
+                    ty,
+                    TypePosition::Return,
+                    &mut member,
+                    Some(&mut self.typescript_refs),
+                );
+                members.push(member);
+            }
+            variants.push((members, &variant.comments));
+        }
+
+        if enum_.generate_typescript {
+            let docs = format_doc_comments(&enum_.comments, None);
+            self.typescript.push_str(&docs);
+            self.typescript.push_str("\nexport type ");
+            self.typescript.push_str(&enum_.name);
+            self.typescript.push_str(" =");
+            for (members, comments) in variants.iter() {
+                self.typescript.push('\n');
+                if !comments.is_empty() {
+                    for line in format_doc_comments(comments, None).lines() {
+                        self.typescript.push_str("  ");
+                        self.typescript.push_str(line);
+                        self.typescript.push('\n');
+                    }
+                }
+                self.typescript.push_str("  | { ");
+                self.typescript.push_str(&members.join("; "));
+                self.typescript.push_str(" }");
+            }
+            self.typescript.push_str(";\n");
+        }
+
+        if self.config.jsdoc_types {
+            let type_expr = variants
+                .iter()
+                .map(|(members, _)| format!("{{{}}}", members.join(", ")))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            self.jsdoc_typedef(
+                &enum_.comments,
+                format!("@typedef {{{type_expr}}} {}", enum_.name),
+            );
+        }
+
+        Ok(())
+    }
+
     fn generate_string_enum(&mut self, string_enum: &AuxStringEnum) -> Result<(), Error> {
         let variants: Vec<_> = string_enum
             .variant_values
@@ -4336,6 +5083,22 @@ if (require('worker_threads').isMainThread) {{
             self.typescript.push_str(";\n");
         }
 
+        if self.config.jsdoc_types
+            && self
+                .typescript_refs
+                .contains(&TsReference::StringEnum(string_enum.name.clone()))
+        {
+            let type_expr = if variants.is_empty() {
+                "never".to_string()
+            } else {
+                variants.join(" | ")
+            };
+            self.jsdoc_typedef(
+                &string_enum.comments,
+                format!("@typedef {{{type_expr}}} {}", string_enum.name),
+            );
+        }
+
         if self.used_string_enums.contains(&string_enum.name) {
             // only generate the internal string enum array if it's actually used
             self.global(&format!(
@@ -4353,7 +5116,23 @@ if (require('worker_threads').isMainThread) {{
     }
 
     fn generate_struct(&mut self, struct_: &AuxStruct) -> Result<(), Error> {
+        // Only interfaces with a TypeScript declaration can be implemented.
+        let implements = self
+            .aux
+            .implements
+            .get(&struct_.name)
+            .into_iter()
+            .flatten()
+            .filter(|name| {
+                self.aux
+                    .traits
+                    .get(*name)
+                    .is_some_and(|t| t.generate_typescript)
+            })
+            .cloned()
+            .collect();
         let class = self.require_class(&struct_.name);
+        class.implements = implements;
         class.comments = format_doc_comments(&struct_.comments, None);
         class.is_inspectable = struct_.is_inspectable;
         class.generate_typescript = struct_.generate_typescript;
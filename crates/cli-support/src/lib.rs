@@ -1,4 +1,6 @@
 use anyhow::{bail, Context, Error};
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use rayon::prelude::*;
 use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs;
@@ -6,8 +8,11 @@ use std::mem;
 use std::path::{Path, PathBuf};
 use std::str;
 use walrus::Module;
+use wasm_bindgen_shared::identifier::is_valid_ident;
 
 pub(crate) const PLACEHOLDER_MODULE: &str = "__wbindgen_placeholder__";
+/// Replaced by the base64 of the Wasm module in the JS of `--inline-wasm`.
+pub(crate) const INLINE_WASM_PLACEHOLDER: &str = "__wbindgen_inline_wasm__";
 
 mod decode;
 mod descriptor;
@@ -16,11 +21,13 @@ mod externref;
 mod interpreter;
 mod intrinsic;
 mod js;
+mod minify;
 mod multivalue;
 mod transforms;
 pub mod wasm2es6js;
 mod wasm_conventions;
 mod wit;
+mod wit_world;
 
 pub struct Bindgen {
     input: Input,
@@ -34,12 +41,28 @@ pub struct Bindgen {
     keep_debug: bool,
     remove_name_section: bool,
     remove_producers_section: bool,
+    keep_custom_sections: Option<Vec<String>>,
     omit_default_module_path: bool,
     emit_start: bool,
+    export_start: bool,
     externref: bool,
+    /// `None` to generate the fallback where it's supported.
+    externref_fallback: Option<bool>,
     multi_value: bool,
     encode_into: EncodeInto,
     split_linked_modules: bool,
+    split_chunks: Vec<(String, String)>,
+    split_snippets: bool,
+    import_map: HashMap<String, String>,
+    inline_wasm: bool,
+    snippet_layout: SnippetLayout,
+    minify_js: bool,
+    track_handles: bool,
+    memory: Option<String>,
+    emit_wit: bool,
+    dual_package: bool,
+    deno_json: bool,
+    jsdoc_types: bool,
     generate_reset_state: bool,
 }
 
@@ -47,6 +70,14 @@ pub struct Output {
     module: walrus::Module,
     stem: String,
     generated: Generated,
+    fallback: Option<Fallback>,
+}
+
+/// The bindings without reference types, and the name of the module choosing
+/// between them and the main ones.
+struct Fallback {
+    output: Box<Output>,
+    loader: String,
 }
 
 struct Generated {
@@ -58,6 +89,19 @@ struct Generated {
     local_modules: HashMap<String, String>,
     npm_dependencies: HashMap<String, (PathBuf, String)>,
     typescript: bool,
+    inline_wasm: bool,
+    snippet_layout: SnippetLayout,
+    minify_js: bool,
+    /// The snippets under `snippets/` that are linked with `link_to!`.
+    linked_snippets: HashSet<String>,
+    wit_world: Option<wit_world::WitWorld>,
+    /// The names exported by `js`.
+    exports: Vec<String>,
+    /// The bootstrap script of the workers of a threaded module.
+    worker: Option<String>,
+    dual_package: bool,
+    /// With `--deno-json`, the specifiers of the packages `js` imports.
+    deno_imports: Option<Vec<String>>,
 }
 
 #[derive(Clone)]
@@ -67,6 +111,7 @@ enum OutputMode {
     NoModules { global: String },
     Node { module: bool },
     Deno,
+    Bun,
     Module,
 }
 
@@ -84,6 +129,21 @@ pub enum EncodeInto {
     Never,
 }
 
+/// How the JS snippets of `inline_js` and local modules are laid out under
+/// `snippets/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetLayout {
+    /// In a directory per crate, named after the crate and a hash of its
+    /// version, e.g. `snippets/foo-1234abcd/inline0.js`.
+    Hashed,
+    /// In a directory per crate named after the crate, e.g.
+    /// `snippets/foo/inline0.js`, so that paths don't change between versions.
+    Stable,
+    /// Concatenated into the single module `snippets/index.js`. Snippets
+    /// linked with `link_to!` are still laid out as with `Hashed`.
+    Concat,
+}
+
 impl Bindgen {
     pub fn new() -> Bindgen {
         let externref =
@@ -103,12 +163,27 @@ impl Bindgen {
             keep_debug: false,
             remove_name_section: false,
             remove_producers_section: false,
+            keep_custom_sections: None,
             emit_start: true,
+            export_start: false,
             externref,
+            externref_fallback: None,
             multi_value,
             encode_into: EncodeInto::Test,
             omit_default_module_path: true,
             split_linked_modules: false,
+            split_chunks: Vec::new(),
+            split_snippets: false,
+            import_map: HashMap::new(),
+            inline_wasm: false,
+            snippet_layout: SnippetLayout::Hashed,
+            minify_js: false,
+            track_handles: false,
+            memory: None,
+            emit_wit: false,
+            dual_package: false,
+            deno_json: false,
+            jsdoc_types: false,
             generate_reset_state: false,
         }
     }
@@ -129,6 +204,22 @@ impl Bindgen {
         self
     }
 
+    /// Whether to generate the bindings both with and without reference
+    /// types, behind a JS module at `{stem}.js` loading the former in engines
+    /// supporting them and the latter elsewhere. By default they are, except
+    /// for `--target no-modules` and with `--experimental-emit-wit`, which
+    /// don't support it.
+    pub fn externref_fallback(&mut self, externref_fallback: bool) -> &mut Bindgen {
+        self.externref_fallback = Some(externref_fallback);
+        self
+    }
+
+    /// Whether the bindings are generated with and without reference types.
+    fn has_fallback(&self) -> bool {
+        self.externref_fallback
+            .unwrap_or(!self.mode.no_modules() && !self.emit_wit)
+    }
+
     /// Explicitly specify the already parsed input module.
     pub fn input_module(&mut self, name: &str, module: Module) -> &mut Bindgen {
         let name = name.to_string();
@@ -217,6 +308,13 @@ impl Bindgen {
         Ok(self)
     }
 
+    pub fn bun(&mut self, bun: bool) -> Result<&mut Bindgen, Error> {
+        if bun {
+            self.switch_mode(OutputMode::Bun, "--target bun")?;
+        }
+        Ok(self)
+    }
+
     pub fn module(&mut self, source_phase: bool) -> Result<&mut Bindgen, Error> {
         if source_phase {
             self.switch_mode(OutputMode::Module, "--target module")?;
@@ -272,11 +370,37 @@ impl Bindgen {
         self
     }
 
+    /// Strips the custom sections of the Wasm module except those named in
+    /// `keep`, where a trailing `*` matches any suffix. The DWARF sections are
+    /// kept together, when `keep_debug` is set and `.debug_info` is kept.
+    pub fn strip_custom_sections(&mut self, keep: &[String]) -> &mut Bindgen {
+        self.keep_custom_sections = Some(keep.to_vec());
+        self
+    }
+
+    /// Whether the custom section `name` isn't stripped by
+    /// `strip_custom_sections`.
+    pub fn keeps_custom_section(&self, name: &str) -> bool {
+        self.keep_custom_sections.as_ref().is_none_or(|keep| {
+            keep.iter().any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+        })
+    }
+
     pub fn emit_start(&mut self, emit: bool) -> &mut Bindgen {
         self.emit_start = emit;
         self
     }
 
+    /// Exports the start function as `__wbindgen_test_start` when it isn't
+    /// emitted, for the doctest harness of the test runner to call.
+    pub fn export_start(&mut self, export: bool) -> &mut Bindgen {
+        self.export_start = export;
+        self
+    }
+
     pub fn encode_into(&mut self, mode: EncodeInto) -> &mut Bindgen {
         self.encode_into = mode;
         self
@@ -292,6 +416,120 @@ impl Bindgen {
         self
     }
 
+    /// Loads the JS `module` with a dynamic `import()` when the chunk `chunk`
+    /// is loaded with `__wbg_load_chunk`, rather than along with the glue.
+    pub fn split_chunk(&mut self, chunk: &str, module: &str) -> &mut Bindgen {
+        self.split_chunks
+            .push((chunk.to_string(), module.to_string()));
+        self
+    }
+
+    /// Embeds the Wasm module in the JS as base64 instead of emitting it as
+    /// its own file.
+    pub fn inline_wasm(&mut self, inline_wasm: bool) -> &mut Bindgen {
+        self.inline_wasm = inline_wasm;
+        self
+    }
+
+    pub fn snippet_layout(&mut self, layout: SnippetLayout) -> &mut Bindgen {
+        self.snippet_layout = layout;
+        self
+    }
+
+    /// Strips comments and whitespace from the generated JS and shortens the
+    /// names of its internal helpers.
+    pub fn minify_js(&mut self, minify_js: bool) -> &mut Bindgen {
+        self.minify_js = minify_js;
+        self
+    }
+
+    /// Loads each JS snippet as its own chunk, see [`Bindgen::split_chunk`].
+    pub fn split_snippets(&mut self, split_snippets: bool) -> &mut Bindgen {
+        self.split_snippets = split_snippets;
+        self
+    }
+
+    /// Remaps the specifiers of `#[wasm_bindgen(module = "...")]` through the
+    /// `imports` of the import map at `path`. Later import maps override the
+    /// specifiers of earlier ones.
+    pub fn import_map<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Bindgen, Error> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let map: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse `{}`", path.display()))?;
+        let Some(imports) = map.get("imports") else {
+            return Ok(self);
+        };
+        let Some(imports) = imports.as_object() else {
+            bail!("`imports` of `{}` isn't an object", path.display());
+        };
+        for (specifier, address) in imports {
+            let Some(address) = address.as_str() else {
+                bail!(
+                    "`{specifier}` of `{}` isn't mapped to a string",
+                    path.display()
+                );
+            };
+            if specifier.ends_with('/') && !address.ends_with('/') {
+                bail!(
+                    "`{specifier}` of `{}` is mapped to `{address}`, which must end with `/` too",
+                    path.display()
+                );
+            }
+            self.import_map
+                .insert(specifier.clone(), address.to_string());
+        }
+        Ok(self)
+    }
+
+    /// Makes the generated glue record every `JsValue` and exported object
+    /// handle it gives out, along with the stack it was created at, and export
+    /// `__wbg_live_handles()` to list the ones not released yet.
+    pub fn track_handles(&mut self, track_handles: bool) -> &mut Bindgen {
+        self.track_handles = track_handles;
+        self
+    }
+
+    /// Chooses the memory the bindings use in modules with multiple memories,
+    /// by the name it's exported or imported as, instead of `memory`.
+    pub fn memory(&mut self, name: &str) -> &mut Bindgen {
+        self.memory = Some(name.to_string());
+        self
+    }
+
+    /// Also describes the exports as a WIT world in `{stem}.wit`, implemented
+    /// by the bindings as presented in `{stem}.wit.js`. No component is built.
+    pub fn emit_wit(&mut self, emit: bool) -> &mut Bindgen {
+        self.emit_wit = emit;
+        self
+    }
+
+    /// For `--target nodejs`, also emits an ES module entry point at
+    /// `{stem}.mjs` re-exporting the CommonJS one, along with a `package.json`
+    /// whose `exports` map picks the one matching how the package is loaded.
+    pub fn dual_package(&mut self, dual_package: bool) -> &mut Bindgen {
+        self.dual_package = dual_package;
+        self
+    }
+
+    /// For `--target deno`, also emits a `deno.json` exporting the bindings
+    /// and mapping the npm packages they import to `npm:` specifiers, so that
+    /// the output can be used or published to JSR as a Deno package.
+    pub fn deno_json(&mut self, deno_json: bool) -> &mut Bindgen {
+        self.deno_json = deno_json;
+        self
+    }
+
+    /// Embeds JSDoc typedefs for every type the generated JavaScript's JSDoc
+    /// refers to that is otherwise only declared in the `.d.ts`, such as
+    /// string enums and the argument and result of the init functions, so
+    /// that editors can type check plain JavaScript using the bindings.
+    pub fn jsdoc_types(&mut self, jsdoc_types: bool) -> &mut Bindgen {
+        self.jsdoc_types = jsdoc_types;
+        self
+    }
+
     pub fn reset_state_function(&mut self, generate_reset_state: bool) -> &mut Bindgen {
         self.generate_reset_state = generate_reset_state;
         self
@@ -312,6 +550,18 @@ impl Bindgen {
         })
     }
 
+    /// The names the bindings are emitted under, as `{name}.js` and
+    /// `{name}_bg.wasm`: those of the versions with and without reference
+    /// types, or the stem with `--no-externref-fallback`.
+    pub fn binding_stems(&self) -> Result<Vec<String>, Error> {
+        let stem = self.stem()?;
+        Ok(if self.has_fallback() {
+            vec![format!("{stem}_externref"), format!("{stem}_fallback")]
+        } else {
+            vec![stem.to_string()]
+        })
+    }
+
     pub fn generate_output(&mut self) -> Result<Output, Error> {
         let mut module = match self.input {
             Input::None => bail!("must have an input by now"),
@@ -331,11 +581,45 @@ impl Bindgen {
                 .context("failed getting Wasm module")?,
         };
 
-        // Enable reference type transformations if the module is already using it.
-        if let Ok(true) = wasm_conventions::target_feature(&module, "reference-types") {
-            self.externref = true;
+        let stem = self.stem()?.to_string();
+        if !self.has_fallback() {
+            // Enable reference type transformations if the module is already using it.
+            if let Ok(true) = wasm_conventions::target_feature(&module, "reference-types") {
+                self.externref = true;
+            }
+            return self.generate_module(module, &stem);
+        }
+
+        if self.mode.no_modules() {
+            bail!("--externref-fallback is not supported for --target no-modules")
+        }
+        if self.emit_wit {
+            bail!("--externref-fallback can't be combined with --experimental-emit-wit")
         }
 
+        // Both versions are generated from the module as it's given, before
+        // `generate_module` transforms it.
+        let fallback = self.module_from_bytes(&module.emit_wasm())?;
+        let externref = self.externref;
+        self.externref = true;
+        let output = self.generate_module(module, &format!("{stem}_externref"));
+        self.externref = false;
+        let result = output.and_then(|mut output| {
+            let mut fallback = self.generate_module(fallback, &format!("{stem}_fallback"))?;
+            // The package's entry points are the loader's.
+            fallback.generated.dual_package = false;
+            output.fallback = Some(Fallback {
+                output: Box::new(fallback),
+                loader: stem,
+            });
+            Ok(output)
+        });
+        self.externref = externref;
+        result
+    }
+
+    /// Generates the bindings of `module`, emitted as `{stem}.js`.
+    fn generate_module(&mut self, mut module: Module, stem: &str) -> Result<Output, Error> {
         // Enable multivalue transformations if the module is already using it.
         if let Ok(true) = wasm_conventions::target_feature(&module, "multivalue") {
             self.multi_value = true;
@@ -358,7 +642,31 @@ impl Bindgen {
             bail!("--experimental-reset-state-function is only supported for --target module, --target web, or --target nodejs")
         }
 
-        let thread_count = transforms::threads::run(&mut module)
+        // Bundlers load the Wasm file themselves.
+        if self.inline_wasm && self.mode.bundler() {
+            bail!("--inline-wasm is not supported for --target bundler")
+        }
+
+        // The WIT world's implementation imports the bindings.
+        if self.emit_wit && !self.mode.uses_es_modules() {
+            bail!("--experimental-emit-wit is only supported for targets emitting ES modules")
+        }
+
+        if self.dual_package && !matches!(self.mode, OutputMode::Node { module: false }) {
+            bail!("--dual-package is only supported for --target nodejs")
+        }
+
+        if self.deno_json && !matches!(self.mode, OutputMode::Deno) {
+            bail!("--deno-json is only supported for --target deno")
+        }
+
+        // Later passes treat a module without a memory to use as one that
+        // doesn't need any, so make sure it's found among several up front.
+        if module.memories.iter().count() > 1 || self.memory.is_some() {
+            wasm_conventions::get_memory(&module, self.memory.as_deref())?;
+        }
+
+        let thread_count = transforms::threads::run(&mut module, self.memory.as_deref())
             .with_context(|| "failed to prepare module for threading")?;
 
         // If requested, turn all mangled symbols into prettier unmangled
@@ -430,7 +738,7 @@ impl Bindgen {
         // Using all of our metadata convert our module to a multi-value using
         // module if applicable.
         if self.multi_value {
-            multivalue::run(&mut module)
+            multivalue::run(&mut module, self.memory.as_deref())
                 .context("failed to transform return pointers into multi-value Wasm")?;
         }
 
@@ -439,8 +747,6 @@ impl Bindgen {
         // unnecessary things here.
         gc_module_and_adapters(&mut module);
 
-        let stem = self.stem()?;
-
         // Now we execute the JS generation passes to actually emit JS/TypeScript/etc.
         let aux = module
             .customs
@@ -450,24 +756,87 @@ impl Bindgen {
             .customs
             .delete_typed::<wit::NonstandardWitSection>()
             .unwrap();
+        if self.snippet_layout == SnippetLayout::Stable {
+            let mut dirs = HashMap::new();
+            let crates = aux
+                .snippets
+                .iter()
+                .filter(|(_, list)| !list.is_empty())
+                .map(|(id, _)| id.as_str())
+                .chain(
+                    aux.local_modules
+                        .keys()
+                        .filter_map(|module| Some(module.split_once('/')?.0)),
+                );
+            for id in crates {
+                let dir = SnippetLayout::crate_name(id);
+                if let Some(prev) = dirs.insert(dir, id) {
+                    if prev != id {
+                        bail!(
+                            "two versions of crate `{dir}` have JS snippets, which would \
+                             overwrite each other with `--snippets stable`"
+                        );
+                    }
+                }
+            }
+        }
+        let linked_snippets = aux
+            .import_map
+            .values()
+            .filter_map(|import| match import {
+                wit::AuxImport::LinkTo(path, _) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let wit_world = self.emit_wit.then(|| {
+            let init = matches!(self.mode, OutputMode::Web);
+            wit_world::generate(stem, &aux, &adapters, init)
+        });
+
         let mut cx = js::Context::new(&mut module, self, &adapters, &aux)?;
         cx.generate()?;
+        let deno_imports = self.deno_json.then(|| cx.package_imports());
         let (js, ts, start) = cx.finalize(stem)?;
+        let exports = cx.export_names();
+        let worker = cx.worker_bootstrap(stem);
         let generated = Generated {
             snippets: aux.snippets.clone(),
             local_modules: aux.local_modules.clone(),
             mode: self.mode.clone(),
             typescript: self.typescript,
+            inline_wasm: self.inline_wasm,
+            snippet_layout: self.snippet_layout,
+            minify_js: self.minify_js,
+            linked_snippets,
+            wit_world,
+            exports,
+            worker,
+            dual_package: self.dual_package,
+            deno_imports,
             npm_dependencies: cx.npm_dependencies.clone(),
             js,
             ts,
             start,
         };
 
+        // Only the sections walrus doesn't handle itself are left, now that
+        // the passes reading them are done.
+        let stripped = module
+            .customs
+            .iter()
+            .filter(|(_, section)| !self.keeps_custom_section(section.name()))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for id in stripped {
+            module.customs.delete(id);
+        }
+
         Ok(Output {
             module,
             stem: stem.to_string(),
             generated,
+            fallback: None,
         })
     }
 
@@ -480,15 +849,45 @@ impl Bindgen {
             // include shared memory, so it fails that part of
             // validation!
             .strict_validate(false)
-            .generate_dwarf(self.keep_debug)
-            .generate_name_section(!self.remove_name_section)
-            .generate_producers_section(!self.remove_producers_section)
+            .generate_dwarf(self.keep_debug && self.keeps_custom_section(".debug_info"))
+            .generate_name_section(!self.remove_name_section && self.keeps_custom_section("name"))
+            .generate_producers_section(
+                !self.remove_producers_section && self.keeps_custom_section("producers"),
+            )
             .parse(bytes)
             .context("failed to parse input as wasm")
     }
 
+    /// The chunk the imported JS `module` is split into, if any.
+    ///
+    /// Modules of `--split-chunk` starting with `/` are local modules, found
+    /// under `snippets/`. With `--split-snippets`, each other snippet is a
+    /// chunk named after its path under `snippets/`.
+    fn split_chunk_of(&self, module: &str) -> Option<String> {
+        let snippet = module.strip_prefix("./snippets/");
+        let chunk = self.split_chunks.iter().find(|(_, split)| {
+            split == module
+                || (split.starts_with('/')
+                    && snippet.is_some_and(|snippet| {
+                        snippet
+                            .split_once('/')
+                            .is_some_and(|(_, path)| path == &split[1..])
+                    }))
+        });
+        match (chunk, snippet) {
+            (Some((chunk, _)), _) => Some(chunk.clone()),
+            (None, Some(snippet)) if self.split_snippets => {
+                Some(snippet.strip_suffix(".js").unwrap_or(snippet).to_string())
+            }
+            _ => None,
+        }
+    }
+
     fn local_module_name(&self, module: &str) -> String {
-        format!("./snippets/{module}")
+        match self.snippet_layout {
+            SnippetLayout::Concat => "./snippets/index.js".to_string(),
+            layout => format!("./snippets/{}", layout.path(module)),
+        }
     }
 
     fn inline_js_module_name(
@@ -496,7 +895,27 @@ impl Bindgen {
         unique_crate_identifier: &str,
         snippet_idx_in_crate: usize,
     ) -> String {
-        format!("./snippets/{unique_crate_identifier}/inline{snippet_idx_in_crate}.js",)
+        self.local_module_name(&format!(
+            "{unique_crate_identifier}/inline{snippet_idx_in_crate}.js"
+        ))
+    }
+}
+
+impl SnippetLayout {
+    /// The path under `snippets/` of the snippet or linked module at `path`,
+    /// relative to the directory of its crate's unique identifier.
+    fn path(self, path: &str) -> String {
+        match (self, path.split_once('/')) {
+            (SnippetLayout::Stable, Some((id, path))) => {
+                format!("{}/{path}", SnippetLayout::crate_name(id))
+            }
+            _ => path.to_string(),
+        }
+    }
+
+    /// The name of the crate with the unique identifier `id`, `name-hash`.
+    fn crate_name(id: &str) -> &str {
+        id.rsplit_once('-').map_or(id, |(name, _)| name)
     }
 }
 
@@ -554,19 +973,24 @@ fn reset_indentation(s: &str) -> String {
 ///
 /// issue: <https://github.com/wasm-bindgen/wasm-bindgen/issues/4820>
 fn demangle(module: &mut Module) {
-    let (lower, upper) = module.funcs.iter().size_hint();
-    let mut counter: HashMap<String, i32> = HashMap::with_capacity(upper.unwrap_or(lower));
-
-    for func in module.funcs.iter_mut() {
-        let Some(name) = &func.name else {
-            continue;
-        };
-
-        let Ok(sym) = rustc_demangle::try_demangle(name) else {
+    // Large modules have hundreds of thousands of symbols, so demangle them in
+    // parallel and only number the duplicates in order.
+    let names: Vec<_> = module
+        .funcs
+        .iter()
+        .map(|func| func.name.as_deref())
+        .collect();
+    let demangled: Vec<Option<String>> = names
+        .into_par_iter()
+        .map(|name| Some(rustc_demangle::try_demangle(name?).ok()?.to_string()))
+        .collect();
+    let mut counter: HashMap<String, i32> = HashMap::with_capacity(demangled.len());
+
+    for (func, demangled) in module.funcs.iter_mut().zip(demangled) {
+        let Some(demangled) = demangled else {
             continue;
         };
 
-        let demangled = sym.to_string();
         match counter.entry(demangled) {
             Entry::Occupied(mut entry) => {
                 func.name = Some(format!("{}[{}]", entry.key(), entry.get()));
@@ -588,6 +1012,7 @@ impl OutputMode {
                 | OutputMode::Web
                 | OutputMode::Node { module: true }
                 | OutputMode::Deno
+                | OutputMode::Bun
                 | OutputMode::Module
         )
     }
@@ -669,41 +1094,93 @@ impl Output {
         let wasm_path = out_dir.join(&wasm_name).with_extension("wasm");
         fs::create_dir_all(out_dir)?;
 
-        let wasm_bytes = self.module.emit_wasm();
-        fs::write(&wasm_path, wasm_bytes)
-            .with_context(|| format!("failed to write `{}`", wasm_path.display()))?;
+        // First, so that the files shared by both versions, like
+        // `package.json`, are the ones of these bindings.
+        if let Some(fallback) = &mut self.fallback {
+            fallback.output._emit(out_dir)?;
+        }
+        // The stem of the package's entry point.
+        let entry = match &self.fallback {
+            Some(fallback) => fallback.loader.clone(),
+            None => self.stem.clone(),
+        };
 
+        let wasm_bytes = self.module.emit_wasm();
         let gen = &self.generated;
+        let js = if gen.inline_wasm {
+            let base64 = BASE64_STANDARD.encode(&wasm_bytes);
+            gen.js.replacen(INLINE_WASM_PLACEHOLDER, &base64, 1)
+        } else {
+            fs::write(&wasm_path, wasm_bytes)
+                .with_context(|| format!("failed to write `{}`", wasm_path.display()))?;
+            gen.js.clone()
+        };
 
         // Write out all local JS snippets to the final destination now that
         // we've collected them from all the programs.
+        let mut snippets = BTreeMap::new();
         for (identifier, list) in gen.snippets.iter() {
             for (i, js) in list.iter().enumerate() {
-                let name = format!("inline{i}.js");
-                let path = out_dir.join("snippets").join(identifier).join(name);
-                fs::create_dir_all(path.parent().unwrap())?;
-                fs::write(&path, js)
-                    .with_context(|| format!("failed to write `{}`", path.display()))?;
+                snippets.insert(format!("{identifier}/inline{i}.js"), js);
             }
         }
-
         for (path, contents) in gen.local_modules.iter() {
-            let path = out_dir.join("snippets").join(path);
+            snippets.insert(path.clone(), contents);
+        }
+
+        let mut concat = String::new();
+        for (path, contents) in snippets {
+            let linked = gen.linked_snippets.contains(&format!("snippets/{path}"));
+            if gen.snippet_layout == SnippetLayout::Concat && !linked {
+                concat.push_str(&format!("// {path}\n{contents}\n"));
+                continue;
+            }
+            let path = out_dir
+                .join("snippets")
+                .join(gen.snippet_layout.path(&path));
             fs::create_dir_all(path.parent().unwrap())?;
             fs::write(&path, contents)
                 .with_context(|| format!("failed to write `{}`", path.display()))?;
         }
+        if !concat.is_empty() {
+            let path = out_dir.join("snippets").join("index.js");
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(&path, concat)
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+        }
 
         let is_genmode_nodemodule = matches!(gen.mode, OutputMode::Node { module: true });
-        if !gen.npm_dependencies.is_empty() || is_genmode_nodemodule {
+        if !gen.npm_dependencies.is_empty() || is_genmode_nodemodule || gen.dual_package {
             #[derive(serde::Serialize)]
             struct PackageJson<'a> {
                 #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
                 ty: Option<&'static str>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                main: Option<String>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                exports: Option<BTreeMap<&'static str, Conditions>>,
                 dependencies: BTreeMap<&'a str, &'a str>,
             }
+            // In order of precedence, which is that of the fields.
+            #[derive(serde::Serialize)]
+            struct Conditions {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                types: Option<String>,
+                import: String,
+                require: String,
+            }
+            let exports = gen.dual_package.then(|| {
+                let conditions = Conditions {
+                    types: gen.typescript.then(|| format!("./{entry}.d.ts")),
+                    import: format!("./{entry}.mjs"),
+                    require: format!("./{entry}.js"),
+                };
+                BTreeMap::from([(".", conditions)])
+            });
             let pj = PackageJson {
                 ty: is_genmode_nodemodule.then_some("module"),
+                main: gen.dual_package.then(|| format!("./{entry}.js")),
+                exports,
                 dependencies: gen
                     .npm_dependencies
                     .iter()
@@ -714,6 +1191,70 @@ impl Output {
             fs::write(out_dir.join("package.json"), json)?;
         }
 
+        if let Some(specifiers) = &gen.deno_imports {
+            #[derive(serde::Serialize)]
+            #[serde(rename_all = "camelCase")]
+            struct DenoJson {
+                exports: String,
+                #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+                imports: BTreeMap<String, String>,
+                #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+                permissions: BTreeMap<&'static str, Permissions>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                compiler_options: Option<CompilerOptions>,
+            }
+            #[derive(serde::Serialize)]
+            struct Permissions {
+                read: Vec<String>,
+            }
+            #[derive(serde::Serialize)]
+            struct CompilerOptions {
+                lib: [&'static str; 2],
+            }
+            let mut imports = BTreeMap::new();
+            for specifier in specifiers {
+                // Scoped packages are named by their first two segments.
+                let segments = if specifier.starts_with('@') { 2 } else { 1 };
+                let package = specifier
+                    .splitn(segments + 1, '/')
+                    .take(segments)
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let version = match gen.npm_dependencies.get(&package) {
+                    Some((_, version)) => format!("@{version}"),
+                    None => String::new(),
+                };
+                if *specifier != package {
+                    imports.insert(format!("{package}/"), format!("npm:/{package}{version}/"));
+                }
+                imports.insert(package.clone(), format!("npm:{package}{version}"));
+            }
+            // The bindings fetch their `.wasm` file, and those of the fallback
+            // too, from disk unless it's inlined, which Deno only allows with
+            // read permission.
+            let mut permissions = BTreeMap::new();
+            if !gen.inline_wasm {
+                let mut read = vec![format!("./{wasm_name}.wasm")];
+                if let Some(fallback) = &self.fallback {
+                    read.push(format!("./{}_bg.wasm", fallback.output.stem));
+                }
+                permissions.insert("default", Permissions { read });
+            }
+            // The declarations give classes a `[Symbol.dispose]` method.
+            let compiler_options = gen.typescript.then_some(CompilerOptions {
+                lib: ["deno.window", "esnext.disposable"],
+            });
+            let deno = DenoJson {
+                exports: format!("./{entry}.js"),
+                imports,
+                permissions,
+                compiler_options,
+            };
+            let mut json = serde_json::to_string_pretty(&deno)?;
+            json.push('\n');
+            fs::write(out_dir.join("deno.json"), json)?;
+        }
+
         // And now that we've got all our JS and TypeScript, actually write it
         // out to the filesystem.
         let extension = "js";
@@ -727,12 +1268,20 @@ impl Output {
                 .with_context(|| format!("failed to write `{}`", path.as_ref().display()))
         }
 
+        let format = |js: &str| {
+            if gen.minify_js {
+                minify::minify(js, gen.mode.no_modules())
+            } else {
+                reset_indentation(js)
+            }
+        };
+
         let js_path = out_dir.join(&self.stem).with_extension(extension);
-        write(&js_path, reset_indentation(&gen.js))?;
+        write(&js_path, format(&js))?;
 
         if let Some(start) = &gen.start {
             let js_path = out_dir.join(wasm_name).with_extension(extension);
-            write(&js_path, reset_indentation(start))?;
+            write(&js_path, format(start))?;
         }
 
         if gen.typescript {
@@ -741,15 +1290,92 @@ impl Output {
                 .with_context(|| format!("failed to write `{}`", ts_path.display()))?;
         }
 
-        if gen.typescript {
+        if let Some(worker) = &gen.worker {
+            write(js_path.with_extension("worker.js"), format(worker))?;
+        }
+
+        if let Some(wit_world) = &gen.wit_world {
+            write(js_path.with_extension("wit"), &wit_world.wit)?;
+            write(js_path.with_extension("wit.js"), format(&wit_world.js))?;
+        }
+
+        if gen.typescript && !gen.inline_wasm {
             let ts_path = wasm_path.with_extension("wasm.d.ts");
             let ts = wasm2es6js::typescript(&self.module)?;
             fs::write(&ts_path, reset_indentation(&ts))
                 .with_context(|| format!("failed to write `{}`", ts_path.display()))?;
         }
 
+        if let Some(fallback) = &self.fallback {
+            let loader = fallback.loader.clone();
+            let js_path = out_dir.join(&loader).with_extension(extension);
+            write(&js_path, format(&self.loader(&loader)))?;
+            // Both versions have the same interface.
+            if gen.typescript {
+                write(js_path.with_extension("d.ts"), reset_indentation(&gen.ts))?;
+            }
+        }
+
+        if gen.dual_package {
+            let js = format!(
+                "import bindings from './{entry}.js';\n{}",
+                reexports(&gen.exports, "bindings")
+            );
+            write(out_dir.join(&entry).with_extension("mjs"), format(&js))?;
+        }
+
         Ok(())
     }
+
+    /// The JS of the externref fallback at `{loader}.js`, which loads these
+    /// bindings if the engine supports reference types and the fallback ones
+    /// otherwise.
+    fn loader(&self, loader: &str) -> String {
+        let gen = &self.generated;
+        let fallback = &self.fallback.as_ref().unwrap().output.stem;
+        // A function dropping a `ref.null extern`, which only validates with
+        // reference types.
+        let detect = "const externref = WebAssembly.validate(new Uint8Array([\
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 10, 7, 1, 5, 0, 208, 111, 26, 11,\
+        ]));";
+        let path = format!("externref ? './{}.js' : './{fallback}.js'", self.stem);
+        if !gen.mode.uses_es_modules() {
+            return format!("{detect}\nmodule.exports = require({path});\n");
+        }
+
+        let mut js = String::new();
+        if gen.typescript && !gen.mode.bundler() {
+            js.push_str(&format!("/* @ts-self-types=\"./{loader}.d.ts\" */\n"));
+        }
+        js.push_str(&format!(
+            "{detect}\nconst bindings = await import({path});\n"
+        ));
+        js.push_str(&reexports(&gen.exports, "bindings"));
+        js
+    }
+}
+
+/// ES module exports of each of the `names` of the object `bindings`.
+fn reexports(names: &[String], bindings: &str) -> String {
+    let mut js = String::new();
+    let (idents, others): (Vec<_>, Vec<_>) = names
+        .iter()
+        .partition(|name| is_valid_ident(name) && name.as_str() != "default");
+    if !idents.is_empty() {
+        let idents = idents.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+        js.push_str(&format!(
+            "export const {{ {} }} = {bindings};\n",
+            idents.join(", ")
+        ));
+    }
+    for (i, name) in others.iter().enumerate() {
+        js.push_str(&format!(
+            "const __wbg_export{i} = {bindings}[\"{}\"];\nexport {{ __wbg_export{i} as \"{}\" }};\n",
+            name.escape_default(),
+            name.escape_default(),
+        ));
+    }
+    js
 }
 
 fn gc_module_and_adapters(module: &mut Module) {
@@ -0,0 +1,532 @@
+//! Minification of the generated JS glue, for `--minify-js`.
+//!
+//! Comments and indentation are stripped, and the glue's own helpers are
+//! renamed to short identifiers. Line breaks are kept, as the glue relies on
+//! automatic semicolon insertion in places. Names that are exported, imported,
+//! or used as object keys or class members are never renamed, so the output
+//! behaves exactly like the input.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    /// Identifiers, keywords and numbers.
+    Word,
+    Punct,
+    /// Strings, regexes and the chunks of template literals.
+    Literal,
+    /// A comment that's kept, such as `@ts-self-types` or `#__PURE__`.
+    Comment,
+    Newline,
+}
+
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    /// Whether whitespace or a comment preceded the token.
+    space_before: bool,
+}
+
+/// Words after which a `/` starts a regex rather than a division.
+const EXPRESSION_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "do",
+    "else",
+    "yield",
+    "await",
+];
+
+/// Reserved words short enough to be picked as new names.
+const RESERVED: &[&str] = &[
+    "do", "if", "in", "of", "as", "for", "let", "new", "try", "var", "get", "set", "NaN", "case",
+    "else", "enum", "eval", "null", "this", "true", "void", "with",
+];
+
+/// Returns `js` minified, or `js` as is if it can't be tokenized.
+///
+/// In a `script`, as opposed to a module, top-level declarations are globals,
+/// so they aren't renamed.
+pub(crate) fn minify(js: &str, script: bool) -> String {
+    match tokenize(js) {
+        Some(tokens) => print(&tokens, &renames(&tokens, script)),
+        None => js.to_string(),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || !c.is_ascii()
+}
+
+fn tokenize(js: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    // Whether each open `{` belongs to the substitution of a template literal.
+    let mut braces = Vec::new();
+    let mut space_before = false;
+    let mut chars = js.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let rest = &js[start..];
+        let mut end = |len: usize| {
+            while chars.peek().is_some_and(|&(i, _)| i < start + len) {
+                chars.next();
+            }
+            &js[start..start + len]
+        };
+
+        let kind = match c {
+            '\n' => {
+                space_before = true;
+                tokens.push(Token {
+                    kind: Kind::Newline,
+                    text: "\n",
+                    space_before,
+                });
+                continue;
+            }
+            c if c.is_whitespace() => {
+                space_before = true;
+                continue;
+            }
+            '/' if rest.starts_with("//") => {
+                end(rest.find('\n').unwrap_or(rest.len()));
+                space_before = true;
+                continue;
+            }
+            '/' if rest.starts_with("/*") => {
+                let comment = end(rest[2..].find("*/")? + 4);
+                space_before = true;
+                let kept = ["@ts-self-types", "#__PURE__", "@__PURE__"];
+                if kept.iter().any(|annotation| comment.contains(annotation)) {
+                    tokens.push(Token {
+                        kind: Kind::Comment,
+                        text: comment,
+                        space_before,
+                    });
+                } else if comment.contains('\n') {
+                    tokens.push(Token {
+                        kind: Kind::Newline,
+                        text: "\n",
+                        space_before,
+                    });
+                }
+                continue;
+            }
+            '/' if starts_regex(&tokens) => {
+                end(regex_len(rest)?);
+                Kind::Literal
+            }
+            '\'' | '"' => {
+                end(string_len(rest, c)?);
+                Kind::Literal
+            }
+            '`' => {
+                end(1 + template_len(&rest[1..], &mut braces)?);
+                Kind::Literal
+            }
+            '}' if braces.last() == Some(&true) => {
+                braces.pop();
+                end(1 + template_len(&rest[1..], &mut braces)?);
+                Kind::Literal
+            }
+            c if is_word_char(c) => {
+                end(rest.find(|c| !is_word_char(c)).unwrap_or(rest.len()));
+                Kind::Word
+            }
+            c => {
+                match c {
+                    '{' => braces.push(false),
+                    '}' => {
+                        braces.pop();
+                    }
+                    _ => {}
+                }
+                end(c.len_utf8());
+                Kind::Punct
+            }
+        };
+        let text = &js[start..chars.peek().map_or(js.len(), |&(i, _)| i)];
+        tokens.push(Token {
+            kind,
+            text,
+            space_before,
+        });
+        space_before = false;
+    }
+    Some(tokens)
+}
+
+/// Whether a `/` following `tokens` starts a regex.
+fn starts_regex(tokens: &[Token]) -> bool {
+    let prev = tokens
+        .iter()
+        .rev()
+        .find(|token| !matches!(token.kind, Kind::Newline | Kind::Comment));
+    match prev {
+        None => true,
+        Some(token) => match token.kind {
+            Kind::Word => EXPRESSION_KEYWORDS.contains(&token.text),
+            Kind::Punct => !matches!(token.text, ")" | "]" | "}"),
+            _ => false,
+        },
+    }
+}
+
+fn string_len(s: &str, quote: char) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return Some(i + 1),
+            '\n' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn regex_len(s: &str) -> Option<usize> {
+    let mut class = false;
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => class = true,
+            ']' => class = false,
+            '/' if !class => {
+                let flags = s[i + 1..]
+                    .find(|c| !is_word_char(c))
+                    .unwrap_or(s.len() - i - 1);
+                return Some(i + 1 + flags);
+            }
+            '\n' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The length of the rest of a template literal chunk, up to its closing
+/// backtick or the `${` of a substitution, which is pushed to `braces`.
+fn template_len(s: &str, braces: &mut Vec<bool>) -> Option<usize> {
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '`' => return Some(i + 1),
+            '$' if s[i + 1..].starts_with('{') => {
+                braces.push(true);
+                return Some(i + 2);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether the word at `i` in `tokens` is a property, an object key or a
+/// class member rather than a variable, so it isn't renamed.
+fn is_property(tokens: &[&Token], i: usize) -> bool {
+    let prev = i.checked_sub(1).map(|i| tokens[i].text);
+    let next = tokens.get(i + 1).map(|token| token.text);
+    matches!(prev, Some("." | "#")) || (matches!(prev, Some("{" | ",")) && next == Some(":"))
+}
+
+/// Picks short names for the glue's helpers.
+fn renames<'a>(all_tokens: &[Token<'a>], script: bool) -> HashMap<&'a str, String> {
+    // The significant tokens, and whether each starts a line.
+    let mut tokens = Vec::new();
+    let mut line_starts = Vec::new();
+    let mut line_start = false;
+    for token in all_tokens {
+        match token.kind {
+            Kind::Newline => line_start = true,
+            Kind::Comment => {}
+            _ => {
+                tokens.push(token);
+                line_starts.push(mem::take(&mut line_start));
+            }
+        }
+    }
+    let text = |i: usize| tokens.get(i).map_or("", |token| token.text);
+
+    let mut declared = Vec::new();
+    let mut excluded = HashSet::new();
+    // The depth of each open class body, and whether the current `{` opens one.
+    let mut class_bodies = Vec::new();
+    let mut class_pending = false;
+    let mut depth = 0;
+    let mut import = false;
+    let mut export_list = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.text {
+            "{" => {
+                depth += 1;
+                if class_pending {
+                    class_bodies.push(depth);
+                    class_pending = false;
+                }
+                if text(i.wrapping_sub(1)) == "export" {
+                    export_list = true;
+                }
+            }
+            "}" => {
+                if class_bodies.last() == Some(&depth) {
+                    class_bodies.pop();
+                }
+                depth -= 1;
+                export_list = false;
+            }
+            "import" if !matches!(text(i + 1), "(" | ".") => import = true,
+            "from" | ";" => import = false,
+            "class" => class_pending = true,
+            _ => {}
+        }
+        if token.kind != Kind::Word {
+            continue;
+        }
+        let prev = text(i.wrapping_sub(1));
+        let next = text(i + 1);
+
+        if import || export_list {
+            excluded.insert(token.text);
+        }
+        match prev {
+            "function" | "let" | "const" | "var" if script && depth == 0 => {
+                excluded.insert(token.text);
+            }
+            "function" | "let" | "const" | "var" => declared.push(token.text),
+            "class" | "default" => {
+                excluded.insert(token.text);
+            }
+            _ => {}
+        }
+        // `export [default] [async] function|class|let|const|var name`.
+        let modifiers = [
+            "function", "async", "default", "let", "const", "var", "class", "*",
+        ];
+        let mut j = i;
+        while j > 0 && modifiers.contains(&text(j - 1)) {
+            j -= 1;
+        }
+        if text(j.wrapping_sub(1)) == "export" {
+            excluded.insert(token.text);
+        }
+        if prev == "." && text(i.wrapping_sub(2)) == "exports" {
+            excluded.insert(token.text);
+        }
+        // Shorthand properties and destructuring, with defaults unless the
+        // `=` is an assignment on its own line in a block.
+        let assignment = next == "=" && !matches!(text(i + 2), "=" | ">");
+        if matches!(prev, "{" | ",")
+            && (matches!(next, "," | "}") || (assignment && !line_starts[i]))
+        {
+            excluded.insert(token.text);
+        }
+        // Class members.
+        if class_bodies.last() == Some(&depth)
+            && matches!(
+                prev,
+                "{" | "}" | ";" | "static" | "get" | "set" | "async" | "*"
+            )
+        {
+            excluded.insert(token.text);
+        }
+        if prev == "#" {
+            excluded.insert(token.text);
+        }
+    }
+
+    let mut counts = HashMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == Kind::Word && !is_property(&tokens, i) {
+            *counts.entry(token.text).or_insert(0) += 1;
+        }
+    }
+    let mut seen = HashSet::new();
+    let mut candidates = declared
+        .into_iter()
+        .filter(|name| !excluded.contains(name) && seen.insert(*name))
+        .collect::<Vec<_>>();
+    // Stable, so the output is deterministic.
+    candidates.sort_by_key(|name| std::cmp::Reverse(counts[name]));
+
+    let used = tokens
+        .iter()
+        .filter(|token| token.kind == Kind::Word)
+        .map(|token| token.text)
+        .collect::<HashSet<_>>();
+    let mut names = (0..)
+        .map(short_name)
+        .filter(|name| !used.contains(name.as_str()) && !RESERVED.contains(&name.as_str()))
+        .peekable();
+    let mut renames = HashMap::new();
+    for name in candidates {
+        if names.peek().unwrap().len() < name.len() {
+            renames.insert(name, names.next().unwrap());
+        }
+    }
+    renames
+}
+
+/// The `i`th of `a`, ..., `Z`, `aa`, `ba`, ...
+fn short_name(mut i: usize) -> String {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let mut name = String::from(FIRST[i % FIRST.len()] as char);
+    i /= FIRST.len();
+    while i > 0 {
+        i -= 1;
+        name.push(REST[i % REST.len()] as char);
+        i /= REST.len();
+    }
+    name
+}
+
+fn print(tokens: &[Token], renames: &HashMap<&str, String>) -> String {
+    let significant = tokens
+        .iter()
+        .filter(|token| !matches!(token.kind, Kind::Newline | Kind::Comment))
+        .collect::<Vec<_>>();
+    let mut properties = HashSet::new();
+    for i in 0..significant.len() {
+        if is_property(&significant, i) {
+            properties.insert(significant[i] as *const Token);
+        }
+    }
+
+    let mut dst = String::new();
+    let mut prev: Option<&Token> = None;
+    for token in tokens {
+        if token.kind == Kind::Newline {
+            if prev.is_some() && !dst.ends_with('\n') {
+                dst.push('\n');
+            }
+            continue;
+        }
+        let text = match renames.get(token.text) {
+            Some(new) if token.kind == Kind::Word && !properties.contains(&(token as *const _)) => {
+                new.as_str()
+            }
+            _ => token.text,
+        };
+        if let Some(prev) = prev.filter(|_| token.space_before && !dst.ends_with('\n')) {
+            let last = dst.chars().next_back().unwrap();
+            let first = text.chars().next().unwrap();
+            let needs_space = (is_word_char(last) && is_word_char(first))
+                || (last == first && matches!(last, '+' | '-'))
+                || (prev.kind == Kind::Word && text.starts_with('`'))
+                || token.kind == Kind::Comment
+                || prev.kind == Kind::Comment;
+            if needs_space {
+                dst.push(' ');
+            }
+        }
+        dst.push_str(text);
+        prev = Some(token);
+    }
+    if !dst.ends_with('\n') {
+        dst.push('\n');
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_comments_and_whitespace() {
+        let js = "
+            // A comment.
+            /** Docs. */
+            export function add(a, b) {
+                return a + +b; /* inline */
+            }
+        ";
+        assert_eq!(
+            minify(js, false),
+            "export function add(a,b){\nreturn a+ +b;\n}\n"
+        );
+    }
+
+    #[test]
+    fn renames_helpers() {
+        let js = "
+            let cachedMemory = null;
+            function getMemory() {
+                if (cachedMemory === null) cachedMemory = new Uint8Array(wasm.memory.buffer);
+                return cachedMemory;
+            }
+            export function len() {
+                return getMemory().length;
+            }
+        ";
+        assert_eq!(
+            minify(js, false),
+            "let a=null;\nfunction b(){\nif(a===null)a=new Uint8Array(wasm.memory.buffer);\n\
+             return a;\n}\nexport function len(){\nreturn b().length;\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_properties_and_exports() {
+        let js = "
+            function getObject(idx) { return heap[idx]; }
+            function dropObject(idx) { heap[idx] = undefined; }
+            const heap = [];
+            const obj = { getObject: dropObject, heap };
+            class Foo { getObject() { return getObject(0); } }
+            export { obj };
+        ";
+        assert_eq!(
+            minify(js, false),
+            "function getObject(idx){return heap[idx];}\nfunction a(idx){heap[idx]=undefined;}\n\
+             const heap=[];\nconst obj={getObject:a,heap};\n\
+             class Foo{getObject(){return getObject(0);}}\nexport{obj};\n"
+        );
+    }
+
+    #[test]
+    fn keeps_globals_of_scripts() {
+        let js = "
+            let wasm_bindgen = (function(exports) {
+                let script_src;
+                return script_src;
+            })();
+        ";
+        assert_eq!(
+            minify(js, true),
+            "let wasm_bindgen=(function(exports){\nlet a;\nreturn a;\n})();\n"
+        );
+    }
+
+    #[test]
+    fn keeps_literals() {
+        let js = "
+            function debug(val) {
+                return `${debug(val)} // ${'}'}` + '/* x */' + /[/]\\//g.source;
+            }
+        ";
+        assert_eq!(
+            minify(js, false),
+            "function a(val){\nreturn `${a(val)} // ${'}'}`+'/* x */'+/[/]\\//g.source;\n}\n"
+        );
+    }
+}
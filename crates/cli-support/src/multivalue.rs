@@ -5,7 +5,7 @@ use crate::wit::{AdapterKind, Instruction, WasmBindgenAux};
 use anyhow::{anyhow, Error};
 use walrus::Module;
 
-pub fn run(module: &mut Module) -> Result<(), Error> {
+pub fn run(module: &mut Module, memory: Option<&str>) -> Result<(), Error> {
     let mut adapters = module
         .customs
         .delete_typed::<NonstandardWitSection>()
@@ -30,7 +30,7 @@ pub fn run(module: &mut Module) -> Result<(), Error> {
         .expect("aux section should be present")
         .stack_pointer
         .ok_or_else(|| anyhow!("failed to find stack pointer in Wasm module"))?;
-    let memory = wasm_conventions::get_memory(module)?;
+    let memory = wasm_conventions::get_memory(module, memory)?;
     let wrappers = multi_value_xform::run(module, memory, stack_pointer, &to_xform)?;
 
     for (export, id) in exports.into_iter().zip(wrappers) {
@@ -16,6 +16,7 @@
 //! `externref` and valid Wasm modules going out which use `externref` at the fringes.
 
 use anyhow::{anyhow, bail, Context as _, Error};
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 
 use crate::wasm_conventions;
@@ -588,23 +589,36 @@ impl Transform<'_> {
     }
 
     fn rewrite_calls(&mut self, module: &mut Module) -> Result<(), Error> {
-        for (id, func) in module.funcs.iter_local_mut() {
-            if self.shims.contains(&id) {
-                continue;
-            }
+        // Functions are rewritten in parallel, so add their scratch locals
+        // beforehand, in order for the output to stay the same.
+        let scratch: HashMap<FunctionId, LocalId> = module
+            .funcs
+            .iter_local()
+            .filter(|(id, _)| !self.shims.contains(id))
+            .map(|(id, _)| (id, module.locals.add(ValType::I32)))
+            .collect();
+        if scratch.is_empty() {
+            return Ok(());
+        }
+        let clone_ref = self.clone_ref()?;
+        let heap_dealloc = self.heap_dealloc()?;
+        let xform = &*self;
+        module.funcs.par_iter_local_mut().for_each(|(id, func)| {
+            let Some(&scratch_i32) = scratch.get(&id) else {
+                return;
+            };
             let entry = func.entry_block();
-            let scratch_i32 = module.locals.add(ValType::I32);
             dfs_pre_order_mut(
                 &mut Rewrite {
-                    clone_ref: self.clone_ref()?,
-                    heap_dealloc: self.heap_dealloc()?,
-                    xform: self,
+                    clone_ref,
+                    heap_dealloc,
+                    xform,
                     scratch_i32,
                 },
                 func,
                 entry,
             );
-        }
+        });
 
         return Ok(());
 
@@ -19,14 +19,14 @@ const ATOMIC_MEM_ARG: MemArg = MemArg {
 pub struct ThreadCount(walrus::LocalId);
 
 /// Is threaded Wasm enabled?
-pub fn is_enabled(module: &Module) -> bool {
+pub fn is_enabled(module: &Module, memory: Option<&str>) -> bool {
     // Compatibility with older LLVM outputs. Newer LLVM outputs, when
     // atomics are enabled, emit a shared memory. That's a good indicator
     // that we have work to do. If shared memory isn't enabled, though then
     // this isn't an atomic module so there's nothing to do. We still allow,
     // though, an environment variable to force us to go down this path to
     // remain compatible with older LLVM outputs.
-    match wasm_conventions::get_memory(module) {
+    match wasm_conventions::get_memory(module, memory) {
         Ok(memory) => module.memories.get(memory).shared,
         Err(_) => false,
     }
@@ -52,12 +52,12 @@ pub fn is_enabled(module: &Module) -> bool {
 /// * Some stack space is prepared for each thread after the first one.
 ///
 /// More and/or less may happen here over time, stay tuned!
-pub fn run(module: &mut Module) -> Result<Option<ThreadCount>, Error> {
-    if !is_enabled(module) {
+pub fn run(module: &mut Module, memory: Option<&str>) -> Result<Option<ThreadCount>, Error> {
+    if !is_enabled(module, memory) {
         return Ok(None);
     }
 
-    let memory = wasm_conventions::get_memory(module)?;
+    let memory = wasm_conventions::get_memory(module, memory)?;
 
     // Now we need to allocate extra static memory for:
     // - A thread id counter.
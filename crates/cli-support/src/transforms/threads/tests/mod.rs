@@ -18,7 +18,7 @@ fn runtest(test: &Test) -> Result<String> {
         .generate_producers_section(false)
         .parse(&wasm)?;
 
-    super::run(&mut module)?;
+    super::run(&mut module, None)?;
     walrus::passes::gc::run(&mut module);
 
     // We add an extra parameter to the start function, making it invalid for the start section.
@@ -10,20 +10,30 @@ use std::io::Cursor;
 
 use anyhow::{anyhow, bail, Context, Result};
 use walrus::{
-    ir::Value, ConstExpr, ElementItems, FunctionBuilder, FunctionId, FunctionKind, GlobalId,
-    GlobalKind, MemoryId, Module, RawCustomSection, ValType,
+    ir::Value, ConstExpr, ElementItems, ExportItem, FunctionBuilder, FunctionId, FunctionKind,
+    GlobalId, GlobalKind, ImportKind, MemoryId, Module, RawCustomSection, ValType,
 };
 use wasmparser::BinaryReader;
 
-/// Get a Wasm module's canonical linear memory.
-pub fn get_memory(module: &Module) -> Result<MemoryId> {
+/// Get a Wasm module's canonical linear memory, the one holding the stack and
+/// the data the bindings exchange.
+///
+/// In modules with multiple memories that's the memory exported or imported
+/// as `name`, or as `memory` by default, which is what lld names it.
+pub fn get_memory(module: &Module, name: Option<&str>) -> Result<MemoryId> {
+    if let Some(name) = name {
+        return find_memory(module, name)
+            .ok_or_else(|| anyhow!("module has no memory exported or imported as `{name}`"));
+    }
     let mut memories = module.memories.iter().map(|m| m.id());
     let memory = memories.next();
     if memories.next().is_some() {
-        bail!(
-            "expected a single memory, found multiple; multiple memories \
-             currently not supported"
-        );
+        return find_memory(module, "memory").ok_or_else(|| {
+            anyhow!(
+                "module has multiple memories and none is exported or imported as \
+                 `memory`; pass the name of the one the bindings use with `--memory`"
+            )
+        });
     }
     memory.ok_or_else(|| {
         anyhow!(
@@ -33,6 +43,20 @@ pub fn get_memory(module: &Module) -> Result<MemoryId> {
     })
 }
 
+/// Finds the memory exported as `name`, or else imported as `name`.
+fn find_memory(module: &Module, name: &str) -> Option<MemoryId> {
+    let exported = module.exports.iter().find_map(|export| match export.item {
+        ExportItem::Memory(id) if export.name == name => Some(id),
+        _ => None,
+    });
+    exported.or_else(|| {
+        module.imports.iter().find_map(|import| match import.kind {
+            ImportKind::Memory(id) if import.name == name => Some(id),
+            _ => None,
+        })
+    })
+}
+
 /// Get the `__stack_pointer`.
 pub fn get_stack_pointer(module: &Module) -> Option<GlobalId> {
     if let Some(g) = module
@@ -279,3 +303,58 @@ pub fn insert_target_feature(module: &mut Module, new_feature: &str) -> Result<(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::get_memory;
+    use walrus::Module;
+
+    fn memory_index(wat: &str, name: Option<&str>) -> anyhow::Result<usize> {
+        let module = Module::from_buffer(&wat::parse_str(wat)?)?;
+        let memory = get_memory(&module, name)?;
+        Ok(module
+            .memories
+            .iter()
+            .position(|m| m.id() == memory)
+            .unwrap())
+    }
+
+    #[test]
+    fn single_memory() {
+        let wat = r#"(module (memory 1))"#;
+        assert_eq!(memory_index(wat, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn multiple_memories_default_to_memory_export() {
+        let wat = r#"
+            (module
+                (memory (export "scratch") 1)
+                (memory (export "memory") 1))
+        "#;
+        assert_eq!(memory_index(wat, None).unwrap(), 1);
+        assert_eq!(memory_index(wat, Some("scratch")).unwrap(), 0);
+    }
+
+    #[test]
+    fn multiple_memories_by_import_name() {
+        let wat = r#"
+            (module
+                (import "env" "memory" (memory 1))
+                (memory (export "scratch") 1))
+        "#;
+        assert_eq!(memory_index(wat, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn multiple_memories_need_a_name() {
+        let wat = r#"
+            (module
+                (memory (export "a") 1)
+                (memory (export "b") 1))
+        "#;
+        assert!(memory_index(wat, None).is_err());
+        assert!(memory_index(wat, Some("c")).is_err());
+        assert_eq!(memory_index(wat, Some("b")).unwrap(), 1);
+    }
+}
@@ -3,8 +3,9 @@ use crate::descriptor::{Descriptor, Function};
 use crate::descriptors::WasmBindgenDescriptorsSection;
 use crate::intrinsic::Intrinsic;
 use crate::transforms::threads::ThreadCount;
-use crate::{decode, wasm_conventions, Bindgen, PLACEHOLDER_MODULE};
+use crate::{decode, wasm_conventions, Bindgen, SnippetLayout, PLACEHOLDER_MODULE};
 use anyhow::{anyhow, bail, ensure, Error};
+use rayon::prelude::*;
 use std::collections::{BTreeSet, HashMap};
 use std::str;
 use walrus::ir::VisitorMut;
@@ -34,7 +35,10 @@ struct Context<'a> {
     externref_enabled: bool,
     thread_count: Option<ThreadCount>,
     support_start: bool,
+    export_start: bool,
     linked_modules: bool,
+    import_map: &'a HashMap<String, String>,
+    snippet_layout: SnippetLayout,
 }
 
 struct InstructionBuilder<'a, 'b> {
@@ -59,13 +63,16 @@ pub fn process(
         vendor_prefixes: Default::default(),
         descriptors: Default::default(),
         unique_crate_identifier: "",
-        memory: wasm_conventions::get_memory(module).ok(),
+        memory: wasm_conventions::get_memory(module, bindgen.memory.as_deref()).ok(),
         module,
         start_found: false,
         externref_enabled: bindgen.externref,
         thread_count,
         support_start: bindgen.emit_start,
+        export_start: bindgen.export_start,
         linked_modules: bindgen.split_linked_modules,
+        import_map: &bindgen.import_map,
+        snippet_layout: bindgen.snippet_layout,
     };
     cx.init()?;
 
@@ -314,11 +321,14 @@ impl<'a> Context<'a> {
                 }
             }
         }
+        self.module
+            .funcs
+            .par_iter_local_mut()
+            .for_each(|(_id, func)| {
+                let entry = func.entry_block();
+                walrus::ir::dfs_pre_order_mut(&mut Replace { map }, func, entry);
+            });
         let mut replace = Replace { map };
-        for (_id, func) in self.module.funcs.iter_local_mut() {
-            let entry = func.entry_block();
-            walrus::ir::dfs_pre_order_mut(&mut replace, func, entry);
-        }
         for elems in self.module.elements.iter_mut() {
             match &mut elems.items {
                 ElementItems::Functions(funcs) => {
@@ -453,7 +463,7 @@ impl<'a> Context<'a> {
         let id = self.import_adapter(id, descriptor, AdapterJsImportKind::Normal)?;
         let (path, content) = match module {
             decode::ImportModule::Named(n) => (
-                format!("snippets/{n}"),
+                format!("snippets/{}", self.snippet_layout.path(n)),
                 local_modules
                     .iter()
                     .find(|m| m.identifier == *n)
@@ -462,9 +472,12 @@ impl<'a> Context<'a> {
             decode::ImportModule::RawNamed(n) => (n.to_string(), None),
             decode::ImportModule::Inline(idx) => (
                 format!(
-                    "snippets/{}/inline{}.js",
-                    self.unique_crate_identifier,
-                    *idx as usize + offset
+                    "snippets/{}",
+                    self.snippet_layout.path(&format!(
+                        "{}/inline{}.js",
+                        self.unique_crate_identifier,
+                        *idx as usize + offset
+                    ))
                 ),
                 Some(inline_js[*idx as usize]),
             ),
@@ -480,6 +493,9 @@ impl<'a> Context<'a> {
         let decode::Program {
             exports,
             enums,
+            data_enums,
+            traits,
+            trait_impls,
             imports,
             structs,
             typescript_custom_sections,
@@ -551,6 +567,19 @@ impl<'a> Context<'a> {
         for enum_ in enums {
             self.enum_(enum_)?;
         }
+        for enum_ in data_enums {
+            self.data_enum(enum_)?;
+        }
+        for trait_ in traits {
+            self.trait_(trait_)?;
+        }
+        for impl_ in trait_impls {
+            self.aux
+                .implements
+                .entry(impl_.class.to_string())
+                .or_default()
+                .insert(impl_.interface.to_string());
+        }
         for struct_ in structs {
             self.struct_(struct_)?;
         }
@@ -592,6 +621,12 @@ impl<'a> Context<'a> {
 
         if export.start {
             self.add_start_function(id)?;
+
+            // Tests don't run the start function on instantiation, so export
+            // it for the doctest harness to run before `main`.
+            if !self.support_start && self.export_start {
+                self.module.exports.add("__wbindgen_test_start", id);
+            }
         }
 
         let classless_this = matches!(
@@ -1129,6 +1164,93 @@ impl<'a> Context<'a> {
         result
     }
 
+    fn data_enum(&mut self, enum_: decode::DataEnum<'_>) -> Result<(), Error> {
+        let mut variants = Vec::new();
+        for variant in enum_.variants.iter() {
+            let mut fields = Vec::new();
+            for field in variant.fields.iter() {
+                // Each field has a descriptor of its own with the type that
+                // the field is converted to JS as.
+                let describe =
+                    wasm_bindgen_shared::data_enum_field(enum_.name, variant.name, field);
+                let ty = match self.descriptors.remove(&describe) {
+                    Some(descriptor) => {
+                        let mut builder = self.instruction_builder(true);
+                        builder.outgoing(&descriptor)?;
+                        builder.output.pop().unwrap_or(AdapterType::Externref)
+                    }
+                    None => AdapterType::Externref,
+                };
+                fields.push((field.to_string(), ty));
+            }
+            variants.push(AuxDataVariant {
+                name: variant.name.to_string(),
+                comments: concatenate_comments(&variant.comments),
+                fields,
+            });
+        }
+        let aux = AuxDataEnum {
+            name: enum_.name.to_string(),
+            comments: concatenate_comments(&enum_.comments),
+            variants,
+            generate_typescript: enum_.generate_typescript,
+        };
+        let mut result = Ok(());
+        self.aux
+            .data_enums
+            .entry(aux.name.clone())
+            .and_modify(|existing| {
+                result = Err(anyhow!("duplicate enums:\n{existing:?}\n{aux:?}"));
+            })
+            .or_insert(aux);
+        result
+    }
+
+    fn trait_(&mut self, trait_: decode::Trait<'_>) -> Result<(), Error> {
+        let mut methods = Vec::new();
+        for method in trait_.methods.iter() {
+            // The signature of each method is described like an imported
+            // function, since that's how Rust calls into JS objects.
+            let describe = wasm_bindgen_shared::trait_method_describe(trait_.name, method.name);
+            let signature = match self.descriptors.remove(&describe) {
+                Some(d) => d.unwrap_function(),
+                None => continue,
+            };
+            let mut args = Vec::new();
+            for (name, arg) in method.args.iter().zip(signature.arguments.iter()) {
+                let mut builder = self.instruction_builder(false);
+                builder.outgoing(arg)?;
+                let ty = builder.output.pop().unwrap_or(AdapterType::Externref);
+                args.push((name.to_string(), ty));
+            }
+            let mut builder = self.instruction_builder(true);
+            builder.incoming(&signature.ret)?;
+            let ret = builder.input.pop();
+            methods.push(AuxTraitMethod {
+                name: method.name.to_string(),
+                comments: concatenate_comments(&method.comments),
+                optional: method.optional,
+                args,
+                ret,
+            });
+        }
+        let aux = AuxTrait {
+            name: trait_.name.to_string(),
+            comments: concatenate_comments(&trait_.comments),
+            methods,
+            generate_typescript: trait_.generate_typescript,
+        };
+        let mut result = Ok(());
+        self.aux
+            .traits
+            .entry(aux.name.clone())
+            .and_modify(|existing| {
+                result = Err(anyhow!("duplicate traits:\n{existing:?}\n{aux:?}"));
+            })
+            .or_insert(aux);
+        result
+    }
+
     fn struct_(&mut self, struct_: decode::Struct<'_>) -> Result<(), Error> {
         for field in struct_.fields {
             let getter = wasm_bindgen_shared::struct_field_get(struct_.name, field.name);
@@ -1310,12 +1432,22 @@ impl<'a> Context<'a> {
         };
 
         let name = match module {
-            Some(decode::ImportModule::Named(module)) => JsImportName::LocalModule {
-                module: module.to_string(),
-                name,
-            },
+            Some(decode::ImportModule::Named(module)) => {
+                // Local modules are named after the crate and their path
+                // in it, which is what `--import-map` maps.
+                let path = module.strip_prefix(self.unique_crate_identifier);
+                match path.and_then(|path| self.resolve_import(path)) {
+                    Some(module) => JsImportName::Module { module, name },
+                    None => JsImportName::LocalModule {
+                        module: module.to_string(),
+                        name,
+                    },
+                }
+            }
             Some(decode::ImportModule::RawNamed(module)) => JsImportName::Module {
-                module: module.to_string(),
+                module: self
+                    .resolve_import(module)
+                    .unwrap_or_else(|| module.to_string()),
                 name,
             },
             Some(decode::ImportModule::Inline(idx)) => {
@@ -1336,6 +1468,19 @@ impl<'a> Context<'a> {
         Ok(JsImport { name, fields })
     }
 
+    /// Resolves `specifier` through `--import-map`, as browsers do: an exact
+    /// match first, then the longest prefix ending with `/`.
+    fn resolve_import(&self, specifier: &str) -> Option<String> {
+        if let Some(address) = self.import_map.get(specifier) {
+            return Some(address.clone());
+        }
+        self.import_map
+            .iter()
+            .filter(|(prefix, _)| prefix.ends_with('/') && specifier.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, address)| format!("{address}{}", &specifier[prefix.len()..]))
+    }
+
     /// Perform a small verification pass over the module to perform some
     /// internal sanity checks.
     fn verify(&self) -> Result<(), Error> {
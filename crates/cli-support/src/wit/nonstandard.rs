@@ -1,7 +1,7 @@
 use crate::intrinsic::Intrinsic;
-use crate::wit::AdapterId;
+use crate::wit::{AdapterId, AdapterType};
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
 use walrus::TypedCustomSectionId;
 
@@ -48,6 +48,14 @@ pub struct WasmBindgenAux {
     /// Auxiliary information to go into JS/TypeScript bindings describing the
     /// exported string enums from Rust.
     pub string_enums: HashMap<String, AuxStringEnum>,
+    /// Auxiliary information to go into TypeScript bindings describing the
+    /// exported enums with data from Rust.
+    pub data_enums: HashMap<String, AuxDataEnum>,
+    /// Auxiliary information to go into TypeScript bindings describing the
+    /// exported traits from Rust.
+    pub traits: HashMap<String, AuxTrait>,
+    /// The interfaces implemented by each exported class, keyed by class name.
+    pub implements: HashMap<String, BTreeSet<String>>,
 
     /// Auxiliary information to go into JS/TypeScript bindings describing the
     /// exported structs from Rust and their fields they've got exported.
@@ -219,6 +227,54 @@ pub struct AuxStringEnum {
     pub js_namespace: Option<Vec<String>>,
 }
 
+#[derive(Debug)]
+pub struct AuxDataEnum {
+    /// The name of this enum
+    pub name: String,
+    /// The copied Rust comments to forward to JS
+    pub comments: String,
+    /// The variants of this enum
+    pub variants: Vec<AuxDataVariant>,
+    /// Whether typescript bindings should be generated for this enum.
+    pub generate_typescript: bool,
+}
+
+#[derive(Debug)]
+pub struct AuxDataVariant {
+    /// The name of this variant, which is its `kind` in JS
+    pub name: String,
+    /// The copied Rust comments to forward to JS
+    pub comments: String,
+    /// The name and type of each field of this variant
+    pub fields: Vec<(String, AdapterType)>,
+}
+
+#[derive(Debug)]
+pub struct AuxTrait {
+    /// The name of this trait, which is the name of the interface in JS
+    pub name: String,
+    /// The copied Rust comments to forward to JS
+    pub comments: String,
+    /// The methods of this trait
+    pub methods: Vec<AuxTraitMethod>,
+    /// Whether typescript bindings should be generated for this trait.
+    pub generate_typescript: bool,
+}
+
+#[derive(Debug)]
+pub struct AuxTraitMethod {
+    /// The name of this method
+    pub name: String,
+    /// The copied Rust comments to forward to JS
+    pub comments: String,
+    /// Whether the method has a default implementation in Rust
+    pub optional: bool,
+    /// The name and type of each argument of this method
+    pub args: Vec<(String, AdapterType)>,
+    /// The type this method returns, if it returns anything
+    pub ret: Option<AdapterType>,
+}
+
 #[derive(Debug)]
 pub struct AuxStruct {
     /// The name of this struct
@@ -0,0 +1,744 @@
+//! Experimental WIT output, for `--experimental-emit-wit`.
+//!
+//! The interface of the generated JS is described as a WIT world in
+//! `{stem}.wit`, and `{stem}.wit.js` presents the bindings the way component
+//! tooling like `jco` expects an implementation of that world:
+//! functions and methods under the camel-cased form of their WIT names, and
+//! enums as their WIT case names. Exports whose types have no WIT equivalent,
+//! such as `JsValue`s and closures, are listed in a comment of the WIT file
+//! instead.
+//!
+//! No component is built from these: wrapping the core module would take
+//! lowering its wasm-bindgen ABI to the canonical ABI, which is out of scope.
+
+use crate::descriptor::VectorKind;
+use crate::wit::{
+    Adapter, AdapterKind, AdapterType, AuxExport, AuxExportKind, AuxExportedMethodKind,
+    Instruction, NonstandardWitSection, WasmBindgenAux,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use wasm_bindgen_shared::identifier::is_valid_ident;
+
+/// The name of the interface holding everything exported.
+const INTERFACE: &str = "api";
+
+/// Names reserved by WIT, which have to be escaped with `%`.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "error-context",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "from",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "own",
+    "option",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+pub(crate) struct WitWorld {
+    /// The WIT world.
+    pub wit: String,
+    /// The ES module implementing the world with the bindings.
+    pub js: String,
+}
+
+/// An exported class, as a WIT resource.
+struct Resource {
+    /// The WIT name.
+    name: String,
+    comments: String,
+    /// The WIT declarations of its constructor and methods.
+    members: Vec<String>,
+    /// The WIT names of its members, to leave out colliding ones.
+    member_names: HashSet<String>,
+}
+
+/// An enum used by the exports, as a WIT enum.
+struct Enum {
+    /// The WIT name.
+    name: String,
+    comments: String,
+    /// The WIT name of each case, with its comments and the JS literal of
+    /// its value.
+    cases: Vec<(String, String, String)>,
+    /// Whether the bindings use values other than the WIT case names, which
+    /// then have to be converted.
+    converted: bool,
+}
+
+/// The WIT form of a type.
+struct Type {
+    wit: String,
+    /// The enum, by its JS name, whose values have to be converted.
+    converted: Option<String>,
+}
+
+struct Generator<'a> {
+    aux: &'a WasmBindgenAux,
+    /// Exported classes, by JS name.
+    resources: BTreeMap<String, Resource>,
+    /// Enums used by the exports, by JS name.
+    enums: BTreeMap<String, Enum>,
+    /// The WIT declarations of free functions.
+    functions: Vec<String>,
+    /// The WIT names of the interface's types and functions.
+    names: HashSet<String>,
+    /// The members of the JS object implementing the interface.
+    members: Vec<String>,
+    /// JS statements adding methods under their camel-cased WIT names.
+    patches: Vec<String>,
+    /// Exports left out, with why.
+    skipped: Vec<String>,
+}
+
+/// Describes the exports of the bindings of `stem` as a WIT world.
+///
+/// `init` is whether the bindings have to be initialized before use, as with
+/// `--target web`.
+pub(crate) fn generate(
+    stem: &str,
+    aux: &WasmBindgenAux,
+    adapters: &NonstandardWitSection,
+    init: bool,
+) -> WitWorld {
+    let mut cx = Generator {
+        aux,
+        resources: BTreeMap::new(),
+        enums: BTreeMap::new(),
+        functions: Vec::new(),
+        names: HashSet::new(),
+        members: Vec::new(),
+        patches: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    for class in aux.structs.iter() {
+        if class.private || class.js_namespace.is_some() {
+            continue;
+        }
+        let name = wit_name(&class.name);
+        if !cx.names.insert(name.clone()) {
+            cx.skipped
+                .push(format!("`{}`: its WIT name `{name}` is taken", class.name));
+            continue;
+        }
+        cx.members.push(format!(
+            "{}: {},",
+            pascal_case(&name),
+            member("bindings", &class.name)
+        ));
+        cx.resources.insert(
+            class.name.clone(),
+            Resource {
+                name,
+                comments: class.comments.clone(),
+                members: Vec::new(),
+                member_names: HashSet::new(),
+            },
+        );
+    }
+
+    let mut exports = aux.export_map.iter().collect::<Vec<_>>();
+    exports.sort_by(|a, b| (&a.1.debug_name, a.0).cmp(&(&b.1.debug_name, b.0)));
+    for (id, export) in exports {
+        let adapter = &adapters.adapters[id];
+        if let Err(why) = cx.export(adapter, export) {
+            let name = match &export.kind {
+                AuxExportKind::Function(name) | AuxExportKind::FunctionThis(name) => name.clone(),
+                AuxExportKind::Constructor(class) => format!("{class}::constructor"),
+                AuxExportKind::Method {
+                    class,
+                    name,
+                    kind: AuxExportedMethodKind::Method,
+                    ..
+                } => format!("{class}::{name}"),
+                // Already says which accessor it is.
+                AuxExportKind::Method { .. } => export.debug_name.clone(),
+            };
+            cx.skipped.push(format!("`{name}`: {why}"));
+        }
+    }
+
+    WitWorld {
+        wit: cx.wit(stem),
+        js: cx.js(stem, init),
+    }
+}
+
+impl Generator<'_> {
+    fn export(&mut self, adapter: &Adapter, export: &AuxExport) -> Result<(), String> {
+        let AdapterKind::Local { instructions } = &adapter.kind else {
+            return Err("isn't implemented in Wasm".to_string());
+        };
+        if export.asyncness {
+            return Err("is async".to_string());
+        }
+        if export.js_namespace.is_some() {
+            return Err("is exported in a namespace".to_string());
+        }
+
+        let mut params = adapter.params.iter();
+        let (class, js_name, kind) = match &export.kind {
+            AuxExportKind::Function(name) => (None, name, "func"),
+            AuxExportKind::FunctionThis(_) => return Err("receives JS `this`".to_string()),
+            AuxExportKind::Constructor(class) => (Some(class), class, "constructor"),
+            AuxExportKind::Method {
+                kind: AuxExportedMethodKind::Getter | AuxExportedMethodKind::Setter,
+                ..
+            } => return Err("is a property accessor".to_string()),
+            AuxExportKind::Method {
+                class,
+                name,
+                receiver,
+                ..
+            } => {
+                if receiver.is_static() {
+                    (Some(class), name, "static func")
+                } else {
+                    // The leading parameter is the object itself.
+                    params.next();
+                    (Some(class), name, "func")
+                }
+            }
+        };
+        if let Some(class) = class {
+            if !self.resources.contains_key(class) {
+                return Err(format!("`{class}` isn't exported"));
+            }
+        }
+
+        // Classes passed by value are owned, others are borrowed, in the
+        // order of the parameters.
+        let mut owned = instructions.iter().filter_map(|instr| match instr.instr {
+            Instruction::I32FromExternrefRustOwned { .. }
+            | Instruction::I32FromOptionRust { .. } => Some(true),
+            Instruction::I32FromExternrefRustBorrow { .. } => Some(false),
+            _ => None,
+        });
+        let mut wit_params = Vec::new();
+        let mut js_args = Vec::new();
+        for (i, param) in params.enumerate() {
+            let name = export
+                .args
+                .as_ref()
+                .and_then(|args| args.get(i))
+                .map(|arg| wit_name(&arg.name))
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("arg{i}"));
+            let ty = self.ty(param, &mut owned)?;
+            wit_params.push(format!("{}: {}", escape(&name), ty.wit));
+            js_args.push(convert("fromWit", &ty, &format!("a{i}")));
+        }
+        let result = match (kind, adapter.results.first()) {
+            ("constructor", _) | (_, None) => None,
+            (_, Some(result)) => Some(self.ty(result, &mut std::iter::empty())?),
+        };
+
+        let params = wit_params.join(", ");
+        let returns = result
+            .as_ref()
+            .map(|result| format!(" -> {}", result.wit))
+            .unwrap_or_default();
+        let converts = result.as_ref().is_some_and(|r| r.converted.is_some())
+            || js_args
+                .iter()
+                .enumerate()
+                .any(|(i, arg)| *arg != format!("a{i}"));
+        let args = (0..js_args.len())
+            .map(|i| format!("a{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let call = |callee: String| {
+            let call = format!("{callee}({})", js_args.join(", "));
+            match &result {
+                Some(result) => convert("toWit", result, &call),
+                None => call,
+            }
+        };
+
+        let mut docs = doc_comments(&export.comments);
+        let Some(class) = class else {
+            let name = wit_name(js_name);
+            if !self.names.insert(name.clone()) {
+                return Err(format!("its WIT name `{name}` is taken"));
+            }
+            let function = member("bindings", js_name);
+            let value = if converts {
+                format!("({args}) => {}", call(function))
+            } else {
+                function
+            };
+            self.members
+                .push(format!("{}: {value},", camel_case(&name)));
+            docs.push_str(&format!("{}: func({params}){returns};", escape(&name)));
+            self.functions.push(docs);
+            return Ok(());
+        };
+
+        let target = member("bindings", class);
+        let (name, declaration) = if kind == "constructor" {
+            if converts {
+                return Err("converts enums in its constructor".to_string());
+            }
+            ("constructor".to_string(), format!("constructor({params});"))
+        } else {
+            let name = wit_name(js_name);
+            let declaration = format!("{}: {kind}({params}){returns};", escape(&name));
+            let key = camel_case(&name);
+            let (target, this) = if kind == "func" {
+                (format!("{target}.prototype"), "this".to_string())
+            } else {
+                (target.clone(), target)
+            };
+            if key == *js_name && converts {
+                return Err("would have to replace itself to convert enums".to_string());
+            }
+            if key != *js_name {
+                let value = if converts {
+                    format!(
+                        "function ({args}) {{\n    return {};\n}}",
+                        call(member(&this, js_name))
+                    )
+                } else {
+                    member(&target, js_name)
+                };
+                self.patches
+                    .push(format!("{} = {value};", member(&target, &key)));
+            }
+            (name, declaration)
+        };
+
+        let resource = self.resources.get_mut(class).unwrap();
+        if !resource.member_names.insert(name.clone()) {
+            return Err(format!("its WIT name `{name}` is taken"));
+        }
+        docs.push_str(&declaration);
+        if name == "constructor" {
+            resource.members.insert(0, docs);
+        } else {
+            resource.members.push(docs);
+        }
+        Ok(())
+    }
+
+    fn ty(
+        &mut self,
+        ty: &AdapterType,
+        owned: &mut dyn Iterator<Item = bool>,
+    ) -> Result<Type, String> {
+        let wit = match ty {
+            AdapterType::S8 => "s8",
+            AdapterType::S16 => "s16",
+            AdapterType::S32 | AdapterType::I32 => "s32",
+            AdapterType::S64 | AdapterType::I64 => "s64",
+            AdapterType::U8 => "u8",
+            AdapterType::U16 => "u16",
+            AdapterType::U32 => "u32",
+            AdapterType::U64 => "u64",
+            AdapterType::F32 => "f32",
+            AdapterType::F64 => "f64",
+            AdapterType::Bool => "bool",
+            AdapterType::String => "string",
+            AdapterType::S128 | AdapterType::U128 => {
+                return Err("WIT has no 128-bit integers".to_string())
+            }
+            AdapterType::Externref | AdapterType::NamedExternref(_) => {
+                return Err("JS values have no WIT type".to_string())
+            }
+            AdapterType::Function => return Err("JS functions have no WIT type".to_string()),
+            AdapterType::NonNull => return Err("pointers have no WIT type".to_string()),
+            AdapterType::Vector(kind) => {
+                let element = match kind {
+                    VectorKind::I8 => "s8",
+                    VectorKind::U8 | VectorKind::ClampedU8 => "u8",
+                    VectorKind::I16 => "s16",
+                    VectorKind::U16 => "u16",
+                    VectorKind::I32 => "s32",
+                    VectorKind::U32 => "u32",
+                    VectorKind::I64 => "s64",
+                    VectorKind::U64 => "u64",
+                    VectorKind::F32 => "f32",
+                    VectorKind::F64 => "f64",
+                    VectorKind::String => "string",
+                    VectorKind::Externref | VectorKind::NamedExternref(_) => {
+                        return Err("JS values have no WIT type".to_string())
+                    }
+                };
+                return Ok(Type {
+                    wit: format!("list<{element}>"),
+                    converted: None,
+                });
+            }
+            AdapterType::Option(inner) => {
+                let inner = self.ty(inner, owned)?;
+                return Ok(Type {
+                    wit: format!("option<{}>", inner.wit),
+                    converted: inner.converted,
+                });
+            }
+            AdapterType::Struct(class) => {
+                let resource = self
+                    .resources
+                    .get(class)
+                    .ok_or_else(|| format!("`{class}` isn't exported"))?;
+                let name = escape(&resource.name);
+                let wit = match owned.next() {
+                    Some(false) => format!("borrow<{name}>"),
+                    _ => name,
+                };
+                return Ok(Type {
+                    wit,
+                    converted: None,
+                });
+            }
+            AdapterType::Enum(name) | AdapterType::StringEnum(name) => {
+                let e = self.enumeration(name)?;
+                return Ok(Type {
+                    wit: escape(&e.name),
+                    converted: e.converted.then(|| name.clone()),
+                });
+            }
+        };
+        Ok(Type {
+            wit: wit.to_string(),
+            converted: None,
+        })
+    }
+
+    /// The WIT enum for the JS enum `name`, added to the interface the first
+    /// time it's used.
+    fn enumeration(&mut self, name: &str) -> Result<&Enum, String> {
+        if !self.enums.contains_key(name) {
+            let (comments, cases) = if let Some(e) = self.aux.enums.get(name) {
+                let cases = e
+                    .variants
+                    .iter()
+                    .map(|(case, value, comments)| {
+                        (wit_name(case), comments.clone(), value.to_string())
+                    })
+                    .collect::<Vec<_>>();
+                (e.comments.clone(), cases)
+            } else if let Some(e) = self.aux.string_enums.get(name) {
+                let cases = e
+                    .variant_values
+                    .iter()
+                    .map(|value| {
+                        let literal = serde_json::to_string(value).unwrap();
+                        (wit_name(value), String::new(), literal)
+                    })
+                    .collect::<Vec<_>>();
+                (e.comments.clone(), cases)
+            } else {
+                return Err(format!("`{name}` isn't exported"));
+            };
+
+            let mut seen = HashSet::new();
+            if cases
+                .iter()
+                .any(|(case, _, _)| case.is_empty() || !seen.insert(case))
+            {
+                return Err(format!(
+                    "the variants of `{name}` have no distinct WIT names"
+                ));
+            }
+            let wit = wit_name(name);
+            if !self.names.insert(wit.clone()) {
+                return Err(format!("the WIT name `{wit}` of `{name}` is taken"));
+            }
+            let converted = cases
+                .iter()
+                .any(|(case, _, literal)| serde_json::to_string(case).unwrap() != *literal);
+            self.enums.insert(
+                name.to_string(),
+                Enum {
+                    name: wit,
+                    comments,
+                    cases,
+                    converted,
+                },
+            );
+        }
+        Ok(&self.enums[name])
+    }
+
+    fn wit(&self, stem: &str) -> String {
+        let world = wit_name(stem);
+        let mut items = Vec::new();
+        for e in self.enums.values() {
+            let mut item = doc_comments(&e.comments);
+            let _ = writeln!(item, "enum {} {{", escape(&e.name));
+            for (case, comments, _) in e.cases.iter() {
+                for line in doc_comments(comments).lines() {
+                    let _ = writeln!(item, "    {line}");
+                }
+                let _ = writeln!(item, "    {},", escape(case));
+            }
+            item.push('}');
+            items.push(item);
+        }
+        for resource in self.resources.values() {
+            let mut item = doc_comments(&resource.comments);
+            let _ = write!(item, "resource {}", escape(&resource.name));
+            if resource.members.is_empty() {
+                item.push(';');
+            } else {
+                item.push_str(" {\n");
+                for line in resource.members.iter().flat_map(|m| m.lines()) {
+                    let _ = writeln!(item, "    {line}");
+                }
+                item.push('}');
+            }
+            items.push(item);
+        }
+        items.extend(self.functions.iter().cloned());
+        if !self.skipped.is_empty() {
+            let mut item = "// Left out, as they can't be described in WIT:\n".to_string();
+            for skipped in self.skipped.iter() {
+                let _ = writeln!(item, "// - {skipped}");
+            }
+            items.push(item.trim_end().to_string());
+        }
+
+        let mut wit = format!(
+            "// Generated by wasm-bindgen with `--experimental-emit-wit`.\n\
+             package local:{world};\n\n\
+             interface {INTERFACE} {{\n"
+        );
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                wit.push('\n');
+            }
+            for line in item.lines() {
+                let _ = writeln!(wit, "    {line}");
+            }
+        }
+        let _ = write!(
+            wit,
+            "}}\n\nworld {} {{\n    export {INTERFACE};\n}}\n",
+            escape(&world)
+        );
+        wit
+    }
+
+    fn js(&self, stem: &str, init: bool) -> String {
+        let mut js = format!(
+            "// Implements the `{INTERFACE}` interface described in `./{stem}.wit` with the\n\
+             // bindings in `./{stem}.js`.\n\
+             import * as bindings from './{stem}.js';\n"
+        );
+        if init {
+            js.push_str("\nawait bindings.default();\n");
+        }
+
+        let converted = self.enums.iter().filter(|(_, e)| e.converted);
+        for (name, e) in converted.clone() {
+            let _ = writeln!(js, "\nconst __wbg_enum_{name} = {{");
+            for (case, _, literal) in e.cases.iter() {
+                let _ = writeln!(js, "    '{case}': {literal},");
+            }
+            js.push_str("};\n");
+        }
+        if converted.clone().next().is_some() {
+            js.push_str(
+                "
+function fromWit(cases, value) {
+    return value == null ? value : cases[value];
+}
+
+function toWit(cases, value) {
+    return value == null ? value : Object.keys(cases).find(key => cases[key] === value);
+}
+",
+            );
+        }
+
+        if !self.patches.is_empty() {
+            js.push('\n');
+            for patch in self.patches.iter() {
+                js.push_str(patch);
+                js.push('\n');
+            }
+        }
+
+        let _ = writeln!(js, "\nexport const {INTERFACE} = {{");
+        for member in self.members.iter() {
+            for line in member.lines() {
+                let _ = writeln!(js, "    {line}");
+            }
+        }
+        js.push_str("};\n");
+        js
+    }
+}
+
+/// `value` of type `ty` with its enum converted by `function`, if needed.
+fn convert(function: &str, ty: &Type, value: &str) -> String {
+    match &ty.converted {
+        Some(name) => format!("{function}(__wbg_enum_{name}, {value})"),
+        None => value.to_string(),
+    }
+}
+
+/// Accesses the property `name` of the JS expression `object`.
+fn member(object: &str, name: &str) -> String {
+    if is_valid_ident(name) {
+        format!("{object}.{name}")
+    } else {
+        format!("{object}[{}]", serde_json::to_string(name).unwrap())
+    }
+}
+
+/// Rust comments as WIT doc comments, with a trailing line break.
+fn doc_comments(comments: &str) -> String {
+    if comments.trim().is_empty() {
+        return String::new();
+    }
+    comments.lines().fold(String::new(), |mut output, line| {
+        output.push_str("///");
+        if !line.is_empty() && !line.starts_with(' ') {
+            output.push(' ');
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+        output
+    })
+}
+
+/// Escapes a WIT name that's a keyword.
+fn escape(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("%{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Converts a JS name like `getHTTPStatus` or `get_http_status` into a WIT
+/// name, `get-http-status`. Digits stay with the word before them, as WIT
+/// words can't start with one. Empty if nothing is left.
+fn wit_name(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_ascii_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_ascii_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_ascii_uppercase() && next.is_some_and(|n| n.is_ascii_lowercase()))
+            });
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c.to_ascii_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut name = String::new();
+    for word in words {
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            if name.is_empty() {
+                name.push('x');
+            }
+        } else if !name.is_empty() {
+            name.push('-');
+        }
+        name.push_str(&word);
+    }
+    name
+}
+
+/// The name component tooling gives a function or method named `name` in WIT.
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => pascal,
+    }
+}
+
+/// The name component tooling gives a resource named `name` in WIT.
+fn pascal_case(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{camel_case, escape, pascal_case, wit_name};
+
+    #[test]
+    fn wit_names() {
+        assert_eq!(wit_name("greet"), "greet");
+        assert_eq!(wit_name("greet_user"), "greet-user");
+        assert_eq!(wit_name("greetUser"), "greet-user");
+        assert_eq!(wit_name("HTTPServer"), "http-server");
+        assert_eq!(wit_name("getHTTPStatus"), "get-http-status");
+        assert_eq!(wit_name("vec3d"), "vec3d");
+        assert_eq!(wit_name("point_2d"), "point2d");
+        assert_eq!(wit_name("__private"), "private");
+        assert_eq!(wit_name("2x"), "x2x");
+        assert_eq!(wit_name("_"), "");
+        assert_eq!(escape(&wit_name("type")), "%type");
+    }
+
+    #[test]
+    fn tooling_names() {
+        assert_eq!(camel_case("greet-user"), "greetUser");
+        assert_eq!(camel_case("get-http-status"), "getHttpStatus");
+        assert_eq!(pascal_case("http-server"), "HttpServer");
+        assert_eq!(pascal_case("counter"), "Counter");
+    }
+}
@@ -1,3 +1,6 @@
+mod source_map;
+mod sri;
 pub mod wasm2es6js;
 pub mod wasm_bindgen;
 pub mod wasm_bindgen_test_runner;
+mod wasm_opt;
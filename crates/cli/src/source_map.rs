@@ -0,0 +1,456 @@
+//! Source maps of the JS generated by wasm-bindgen.
+//!
+//! wasm-bindgen copies `inline_js` snippets and local JS modules next to the
+//! generated bindings, so stack traces point at those copies. The copies are
+//! given source maps pointing back at the Rust file declaring an `inline_js`
+//! snippet, or at the crate's own copy of a module. The lines of the glue
+//! calling into the Wasm module are mapped to the Rust function behind the
+//! export they call, as located by the DWARF debug info of the module.
+//!
+//! Node.js applies the maps to stack traces with `--enable-source-maps` and
+//! browsers in their devtools, while the stack traces browsers report are
+//! rewritten by the test runner with [`SourceMaps::rewrite`].
+//!
+//! Snippets are mapped line by line: an `inline_js` snippet is found in the
+//! crate's sources by one of its lines, as string escapes keep others from
+//! appearing verbatim.
+
+use gimli::{EndianRcSlice, LittleEndian, SectionId};
+use log::debug;
+use rouille::url::Url;
+use serde_json::json;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use wasmparser::{ExternalKind, Parser, Payload, TypeRef};
+
+/// Directories of a crate searched for the Rust files declaring snippets.
+const SOURCE_DIRS: [&str; 4] = ["src", "tests", "benches", "examples"];
+
+/// Where a line of generated JS comes from.
+#[derive(Clone)]
+struct Origin {
+    file: PathBuf,
+    /// The line in `file`, from 0.
+    line: usize,
+    /// The column in `file`, from 0, if the line doesn't come verbatim from
+    /// it.
+    column: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct SourceMaps {
+    /// Where the lines of the generated files come from, by line from 0 and
+    /// by path relative to the output directory, like `crate.js` or
+    /// `snippets/crate-1234/inline0.js`.
+    files: HashMap<String, BTreeMap<usize, Origin>>,
+}
+
+impl SourceMaps {
+    /// Writes source maps for the bindings named `stems` in `out_dir` and their
+    /// snippets, which are looked up in the crate at `root`. Files that can't
+    /// be mapped are left alone.
+    pub fn write(out_dir: &Path, stems: &[&str], root: &Path) -> SourceMaps {
+        let mut maps = SourceMaps::default();
+        maps.write_snippets(out_dir, root);
+        for stem in stems {
+            maps.write_glue(out_dir, stem);
+        }
+        maps
+    }
+
+    fn write_snippets(&mut self, out_dir: &Path, root: &Path) {
+        let snippets = out_dir.join("snippets");
+        let mut rust_files = None;
+        for path in files(&snippets) {
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let Ok(rel) = path.strip_prefix(&snippets) else {
+                continue;
+            };
+            let mut components = rel.iter();
+            components.next(); // the unique identifier of the crate
+            let module = components.as_path();
+
+            let is_inline = module
+                .to_str()
+                .and_then(|name| name.strip_prefix("inline")?.strip_suffix(".js"))
+                .is_some_and(|index| index.parse::<usize>().is_ok());
+            let source = if is_inline {
+                let rust_files = rust_files.get_or_insert_with(|| {
+                    SOURCE_DIRS
+                        .iter()
+                        .flat_map(|dir| files(&root.join(dir)))
+                        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+                        .filter_map(|path| Some((fs::read_to_string(&path).ok()?, path)))
+                        .collect::<Vec<_>>()
+                });
+                locate(&contents, rust_files)
+            } else {
+                let original = root.join(module);
+                let is_copy = fs::read_to_string(&original).is_ok_and(|c| c == contents);
+                is_copy.then_some((original, 0))
+            };
+            let Some((source, first_line)) = source else {
+                debug!("no source found for snippet `{}`", rel.display());
+                continue;
+            };
+
+            let lines = (0..contents.lines().count().max(1))
+                .map(|line| {
+                    let origin = Origin {
+                        file: source.clone(),
+                        line: first_line + line,
+                        column: None,
+                    };
+                    (line, origin)
+                })
+                .collect();
+            self.insert(
+                &path,
+                &format!("snippets/{}", rel.display()),
+                &contents,
+                lines,
+            );
+        }
+    }
+
+    /// Maps the lines of the glue calling exports of the Wasm module to the
+    /// Rust functions behind them.
+    fn write_glue(&mut self, out_dir: &Path, stem: &str) {
+        let wasm = out_dir.join(format!("{stem}_bg.wasm"));
+        let exports = match fs::read(&wasm)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| export_origins(&bytes))
+        {
+            Ok(exports) => exports,
+            Err(e) => {
+                debug!(
+                    "failed to locate the exports of `{}`: {e:?}",
+                    wasm.display()
+                );
+                return;
+            }
+        };
+        if exports.is_empty() {
+            return;
+        }
+
+        // The bundler target splits the glue in two.
+        for name in [format!("{stem}.js"), format!("{stem}_bg.js")] {
+            let path = out_dir.join(&name);
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let lines: BTreeMap<_, _> = contents
+                .lines()
+                .enumerate()
+                .filter_map(|(line, code)| {
+                    let origin = called_exports(code).find_map(|export| exports.get(export))?;
+                    Some((line, origin.clone()))
+                })
+                .collect();
+            if !lines.is_empty() {
+                self.insert(&path, &name, &contents, lines);
+            }
+        }
+    }
+
+    /// Writes the source map of the file at `path`, known as `name` in stack
+    /// traces, and records its `lines` to rewrite them.
+    fn insert(&mut self, path: &Path, name: &str, contents: &str, lines: BTreeMap<usize, Origin>) {
+        if let Err(e) = write_map(path, contents, &lines) {
+            debug!("failed to write source map of `{}`: {e}", path.display());
+            return;
+        }
+        self.files.insert(name.replace('\\', "/"), lines);
+    }
+
+    /// Rewrites locations in the generated files in `line`, like
+    /// `http://127.0.0.1:8000/snippets/foo-1234/inline0.js:3:5` in a stack
+    /// trace, into locations in the files they come from.
+    pub fn rewrite<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if !self.files.keys().any(|file| line.contains(file.as_str())) {
+            return Cow::Borrowed(line);
+        }
+
+        let mut out = String::new();
+        let mut copied = 0;
+        let mut searched = 0;
+        while let Some(found) = line[searched..].find('/') {
+            let slash = searched + found;
+            searched = slash + 1;
+            let rest = &line[searched..];
+            let Some(path_end) = rest.find(':') else {
+                break;
+            };
+            let Some(lines) = self.files.get(&rest[..path_end]) else {
+                continue;
+            };
+            let Some((line_number, column, end)) = location(&rest[path_end..]) else {
+                continue;
+            };
+            let Some(origin) = line_number.checked_sub(1).and_then(|l| lines.get(&l)) else {
+                continue;
+            };
+
+            let start = line[copied..slash]
+                .rfind([' ', '\t', '(', '@'])
+                .map_or(copied, |i| copied + i + 1);
+            out.push_str(&line[copied..start]);
+            out.push_str(&format!("{}:{}", origin.file.display(), origin.line + 1));
+            if let Some(column) = origin.column.map(|column| column + 1).or(column) {
+                out.push_str(&format!(":{column}"));
+            }
+            searched += path_end + end;
+            copied = searched;
+        }
+        out.push_str(&line[copied..]);
+        Cow::Owned(out)
+    }
+}
+
+/// Parses `:<line>` or `:<line>:<column>` at the start of `s`, returning them
+/// and the length parsed.
+fn location(s: &str) -> Option<(usize, Option<usize>, usize)> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let rest = s.strip_prefix(':')?;
+    let len = digits(rest);
+    let line = rest[..len].parse().ok()?;
+    let rest = &rest[len..];
+    if let Some(column) = rest.strip_prefix(':') {
+        let column_len = digits(column);
+        if let Ok(column) = column[..column_len].parse() {
+            return Some((line, Some(column), 1 + len + 1 + column_len));
+        }
+    }
+    Some((line, None, 1 + len))
+}
+
+/// Finds the `inline_js` `snippet` in `rust_files`, returning the file and the
+/// line of its first line there.
+fn locate(snippet: &str, rust_files: &[(String, PathBuf)]) -> Option<(PathBuf, usize)> {
+    let lines: Vec<&str> = snippet.lines().collect();
+    for (index, anchor) in lines.iter().enumerate() {
+        // Short lines like `}` are found all over the place.
+        let anchor = anchor.trim();
+        if anchor.len() < 8 {
+            continue;
+        }
+        let mut matches = rust_files.iter().flat_map(|(source, path)| {
+            source
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.trim() == anchor)
+                .map(move |(line_number, _)| (path, line_number))
+        });
+        if let (Some((path, line_number)), None) = (matches.next(), matches.next()) {
+            if let Some(first_line) = line_number.checked_sub(index) {
+                return Some((path.clone(), first_line));
+            }
+        }
+    }
+    None
+}
+
+/// The exports of the Wasm module called in `code`, like `add` in
+/// `const ret = wasm.add(a, b);`.
+fn called_exports(code: &str) -> impl Iterator<Item = &str> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    code.match_indices("wasm.").filter_map(move |(i, marker)| {
+        let before = code[..i].chars().next_back();
+        if before.is_some_and(|c| is_ident(c) || c == '.') {
+            return None;
+        }
+        let rest = &code[i + marker.len()..];
+        let end = rest.find(|c: char| !is_ident(c))?;
+        rest[end..].starts_with('(').then(|| &rest[..end])
+    })
+}
+
+/// Locates the function behind each exported function of the Wasm module
+/// `bytes` in the Rust sources, with its DWARF debug info.
+fn export_origins(bytes: &[u8]) -> anyhow::Result<HashMap<String, Origin>> {
+    type Reader = EndianRcSlice<LittleEndian>;
+
+    let mut exports = Vec::new();
+    let mut bodies: HashMap<u32, Range<usize>> = HashMap::new();
+    let mut code_start = 0;
+    let mut debug_sections = HashMap::new();
+    let mut next_index = 0;
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if let TypeRef::Func(_) = import?.ty {
+                        next_index += 1;
+                    }
+                }
+            }
+            Payload::ExportSection(section) => {
+                for export in section {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        exports.push((export.name.to_string(), export.index));
+                    }
+                }
+            }
+            Payload::CodeSectionStart { range, .. } => code_start = range.start,
+            Payload::CodeSectionEntry(body) => {
+                bodies.insert(next_index, body.range());
+                next_index += 1;
+            }
+            Payload::CustomSection(section) if section.name().starts_with(".debug_") => {
+                debug_sections.insert(section.name().to_string(), section.data());
+            }
+            _ => {}
+        }
+    }
+    if !debug_sections.contains_key(".debug_info") {
+        return Ok(HashMap::new());
+    }
+
+    let dwarf = gimli::Dwarf::load(|id: SectionId| {
+        let data = debug_sections.get(id.name()).copied().unwrap_or_default();
+        Ok::<_, gimli::Error>(Reader::new(Rc::from(data), LittleEndian))
+    })?;
+    let dwarf = addr2line::Context::from_dwarf(dwarf)?;
+    let mut origins = HashMap::new();
+    for (name, index) in exports {
+        // The internals of wasm-bindgen called by the glue aren't worth
+        // pointing at.
+        if name.starts_with("__wbindgen") || name.starts_with("__externref") {
+            continue;
+        }
+        let Some(body) = bodies.get(&index) else {
+            continue;
+        };
+        // DWARF addresses are relative to the contents of the code section.
+        let (Some(start), Some(end)) = (
+            body.start.checked_sub(code_start),
+            body.end.checked_sub(code_start),
+        ) else {
+            continue;
+        };
+        let origin = dwarf
+            .find_location_range(start as u64, end as u64)?
+            .find_map(|(_, _, location)| {
+                let file = PathBuf::from(location.file?);
+                Some(Origin {
+                    file: file.is_absolute().then_some(file)?,
+                    line: location.line?.checked_sub(1)? as usize,
+                    column: location
+                        .column
+                        .and_then(|c| c.checked_sub(1))
+                        .map(|c| c as usize),
+                })
+            });
+        if let Some(origin) = origin {
+            origins.insert(name, origin);
+        }
+    }
+    Ok(origins)
+}
+
+/// Writes a source map of the generated file at `path` mapping its `lines` to
+/// the files they come from, embedding those, and links it from the file.
+fn write_map(path: &Path, contents: &str, lines: &BTreeMap<usize, Origin>) -> anyhow::Result<()> {
+    let mut sources = Vec::<&Path>::new();
+    let mut mappings = Vec::new();
+    // Fields other than the generated column are relative to the previous
+    // mapping.
+    let (mut last_source, mut last_line, mut last_column) = (0, 0, 0);
+    for line in 0..contents.lines().count().max(1) {
+        let Some(origin) = lines.get(&line) else {
+            mappings.push(String::new());
+            continue;
+        };
+        let source = match sources.iter().position(|source| *source == origin.file) {
+            Some(source) => source,
+            None => {
+                sources.push(&origin.file);
+                sources.len() - 1
+            }
+        };
+        let column = origin.column.unwrap_or(0);
+        mappings.push(format!(
+            "A{}{}{}",
+            vlq(source as i64 - last_source as i64),
+            vlq(origin.line as i64 - last_line as i64),
+            vlq(column as i64 - last_column as i64),
+        ));
+        (last_source, last_line, last_column) = (source, origin.line, column);
+    }
+
+    let urls = sources
+        .iter()
+        .map(|source| {
+            Url::from_file_path(source)
+                .map(String::from)
+                .map_err(|()| anyhow::anyhow!("`{}` isn't absolute", source.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let contents_of = sources
+        .iter()
+        .map(|source| fs::read_to_string(source).ok())
+        .collect::<Vec<_>>();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let map = json!({
+        "version": 3,
+        "file": name,
+        "sources": urls,
+        "sourcesContent": contents_of,
+        "names": [],
+        "mappings": mappings.join(";"),
+    });
+    let map_path = path.with_file_name(format!("{name}.map"));
+    fs::write(&map_path, map.to_string())?;
+    fs::write(
+        path,
+        format!("{contents}\n//# sourceMappingURL={name}.map\n"),
+    )?;
+    Ok(())
+}
+
+/// Encodes `value` as a Base64 VLQ, as used by source map mappings.
+fn vlq(value: i64) -> String {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut value = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    let mut out = String::new();
+    loop {
+        let mut digit = value & 0b11111;
+        value >>= 5;
+        if value > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if value == 0 {
+            return out;
+        }
+    }
+}
+
+/// All files under `dir`, recursively.
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(self::files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
@@ -0,0 +1,62 @@
+//! Subresource Integrity hashes of the emitted files, for `--sri`.
+//!
+//! The hashes are written as the `integrity` section of an import map, which
+//! browsers check modules against, and which is also a manifest from which
+//! deployments can fill `integrity` attributes and `fetch` options.
+
+use anyhow::{Context, Error};
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use sha2::{Digest, Sha384};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Writes `{stem}.importmap.json` in `out_dir`, with the SHA-384 hash of each
+/// JS and Wasm file wasm-bindgen emitted for `stem`, whose bindings are named
+/// `bindings`.
+pub fn write(out_dir: &Path, stem: &str, bindings: &[&str]) -> Result<(), Error> {
+    let mut files = vec![format!("{stem}.js")];
+    for binding in bindings {
+        files.extend([
+            format!("{binding}.js"),
+            format!("{binding}_bg.js"),
+            format!("{binding}_bg.wasm"),
+            format!("{binding}.worker.js"),
+        ]);
+    }
+    files.dedup();
+    files.retain(|name| out_dir.join(name).is_file());
+    snippets(out_dir, "snippets", &mut files)?;
+
+    let mut integrity = BTreeMap::new();
+    for name in files {
+        let path = out_dir.join(&name);
+        let contents =
+            fs::read(&path).with_context(|| format!("failed to read `{}`", path.display()))?;
+        let hash = BASE64_STANDARD.encode(Sha384::digest(&contents));
+        integrity.insert(format!("./{name}"), format!("sha384-{hash}"));
+    }
+
+    let map = serde_json::json!({ "integrity": integrity });
+    let path = out_dir.join(format!("{stem}.importmap.json"));
+    let mut contents = serde_json::to_string_pretty(&map)?;
+    contents.push('\n');
+    fs::write(&path, contents).with_context(|| format!("failed to write `{}`", path.display()))
+}
+
+/// Adds the JS files under `dir`, relative to `out_dir`, to `files`.
+fn snippets(out_dir: &Path, dir: &str, files: &mut Vec<String>) -> Result<(), Error> {
+    let Ok(entries) = fs::read_dir(out_dir.join(dir)) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = format!("{dir}/{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            snippets(out_dir, &name, files)?;
+        } else if name.ends_with(".js") {
+            files.push(name);
+        }
+    }
+    Ok(())
+}
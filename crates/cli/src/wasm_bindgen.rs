@@ -1,8 +1,12 @@
+use crate::source_map::SourceMaps;
+use crate::sri;
+use crate::wasm_opt;
 use anyhow::{bail, Error};
 use clap::{Parser, ValueEnum};
+use std::env;
 use std::ffi::OsString;
 use std::path::PathBuf;
-use wasm_bindgen_cli_support::{Bindgen, EncodeInto};
+use wasm_bindgen_cli_support::{Bindgen, EncodeInto, SnippetLayout};
 
 #[derive(Debug, Clone, ValueEnum)]
 #[clap(rename_all = "kebab-case")]
@@ -12,6 +16,7 @@ enum Target {
     Nodejs,
     NoModules,
     Deno,
+    Bun,
     ExperimentalNodejsModule,
     Module,
 }
@@ -70,6 +75,17 @@ struct Args {
     keep_lld_exports: bool,
     #[arg(long, help = "Keep debug sections in Wasm files")]
     keep_debug: bool,
+    #[arg(
+        long,
+        value_name = "KEEP",
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ',',
+        help = "Strip the custom sections of the Wasm file except the comma-separated KEEP ones, \
+                where a trailing `*` matches any suffix, as in \
+                `--strip-custom-sections=name,.debug_*`"
+    )]
+    strip_custom_sections: Option<Vec<String>>,
     #[arg(
         long,
         value_name = "MODE",
@@ -88,11 +104,118 @@ struct Args {
                 If a bundler is used, it needs to be set up accordingly."
     )]
     split_linked_modules: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Remap the modules of `#[wasm_bindgen(module = ...)]` through the `imports` of \
+                an import map. May be repeated, later maps taking precedence"
+    )]
+    import_map: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "CHUNK=MODULE",
+        help = "Import the JS module MODULE only once the chunk CHUNK is loaded with \
+                `__wbg_load_chunk`, which must happen before its bindings are first called. \
+                MODULE is a module of `#[wasm_bindgen(module = ...)]`. May be repeated"
+    )]
+    split_chunk: Vec<String>,
+    #[arg(
+        long,
+        help = "Import each JS snippet only once loaded as a chunk named after its path under \
+                `snippets/`, as with `--split-chunk`"
+    )]
+    split_snippets: bool,
+    #[arg(
+        long,
+        conflicts_with = "wasm_opt",
+        help = "Embed the Wasm module in the generated JS as base64 instead of emitting \
+                `<name>_bg.wasm`, for a single-file output"
+    )]
+    inline_wasm: bool,
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        help = "How to lay out JS snippets under `snippets/`: in directories named after \
+                each crate and a hash of its version, named after each crate only, or \
+                concatenated into `snippets/index.js`",
+        value_parser = ["hashed", "stable", "concat"]
+    )]
+    snippets: Option<String>,
+    #[arg(
+        long,
+        help = "Strip comments and whitespace from the generated JS and shorten the names of \
+                its internal helpers"
+    )]
+    minify_js: bool,
+    #[arg(
+        long,
+        help = "Record the `JsValue` and exported object handles the glue gives out, with \
+                the stack they were created at, and export `__wbg_live_handles()` listing \
+                the ones not released yet"
+    )]
+    track_handles: bool,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "In modules with multiple memories, the name the memory the bindings use is \
+                exported or imported as (default: memory)"
+    )]
+    memory: Option<String>,
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        value_parser = ["0", "1", "2", "3", "4", "s", "z"],
+        help = "Optimize the emitted Wasm file with Binaryen's `wasm-opt -O<LEVEL>`, found \
+                through `WASM_OPT` or `PATH`"
+    )]
+    wasm_opt: Option<String>,
+    #[arg(
+        long,
+        help = "Emit source maps of the generated JS and JS snippets, mapping the glue to the \
+                Rust functions it calls with `--keep-debug`"
+    )]
+    source_map: bool,
+    #[arg(
+        long,
+        help = "Emit `<name>.importmap.json`, an import map with the Subresource Integrity hashes \
+                of the emitted JS and Wasm files"
+    )]
+    sri: bool,
+    #[arg(
+        long,
+        help = "Only generate the bindings as the module was compiled, instead of both with and \
+                without reference types behind a module loading the ones the engine supports"
+    )]
+    no_externref_fallback: bool,
+    #[arg(
+        long,
+        help = "With `--target nodejs`, also emit an ES module entry point and a `package.json` \
+                exporting both"
+    )]
+    dual_package: bool,
+    #[arg(
+        long,
+        help = "With `--target deno`, also emit a `deno.json` exporting the bindings and mapping \
+                the npm packages they import to `npm:` specifiers"
+    )]
+    deno_json: bool,
+    #[arg(
+        long,
+        help = "Embed JSDoc typedefs for the types the generated JavaScript refers to, for editor \
+                type checking without TypeScript"
+    )]
+    jsdoc_types: bool,
     #[arg(
         long = "experimental-reset-state-function",
         help = "Generate __wbg_reset_state function for WASM reinitialization (experimental)"
     )]
     generate_reset_state: bool,
+    #[arg(
+        long = "experimental-emit-wit",
+        help = "Also describe the exports as a WIT world, implemented by the bindings as \
+                presented in `<name>.wit.js`, without building a component (experimental)"
+    )]
+    emit_wit: bool,
     // The options below are deprecated. They're still parsed for backwards compatibility,
     // but we don't want to show them in `--help` to avoid distracting users.
     #[arg(long, hide = true)]
@@ -143,6 +266,7 @@ fn rmain(args: &Args) -> Result<(), Error> {
         Target::NoModules => b.no_modules(true)?,
         Target::Nodejs => b.nodejs(true)?,
         Target::Deno => b.deno(true)?,
+        Target::Bun => b.bun(true)?,
         Target::ExperimentalNodejsModule => b.nodejs_module(true)?,
         Target::Module => b.module(true)?,
     };
@@ -162,15 +286,48 @@ fn rmain(args: &Args) -> Result<(), Error> {
         .omit_imports(args.omit_imports)
         .omit_default_module_path(args.omit_default_module_path)
         .split_linked_modules(args.split_linked_modules)
+        .split_snippets(args.split_snippets)
+        .inline_wasm(args.inline_wasm)
+        .minify_js(args.minify_js)
+        .track_handles(args.track_handles)
+        .emit_wit(args.emit_wit)
+        .dual_package(args.dual_package)
+        .deno_json(args.deno_json)
+        .jsdoc_types(args.jsdoc_types)
         .reference_types(args.reference_types)
         .reset_state_function(args.generate_reset_state);
+    if args.no_externref_fallback {
+        b.externref_fallback(false);
+    }
 
+    for import_map in &args.import_map {
+        b.import_map(import_map)?;
+    }
+    // `wasm-opt` reads the features it may use from the `target_features`
+    // section, so it strips the section itself.
+    let mut strip_target_features = false;
+    if let Some(keep) = &args.strip_custom_sections {
+        b.strip_custom_sections(keep);
+        if args.wasm_opt.is_some() && !b.keeps_custom_section("target_features") {
+            strip_target_features = true;
+            b.strip_custom_sections(&[keep.as_slice(), &["target_features".to_string()]].concat());
+        }
+    }
+    for split in &args.split_chunk {
+        let Some((chunk, module)) = split.split_once('=') else {
+            bail!("`--split-chunk` expects `CHUNK=MODULE`, found `{split}`");
+        };
+        b.split_chunk(chunk, module);
+    }
     if let Some(ref name) = args.no_modules_global {
         b.no_modules_global(name)?;
     }
     if let Some(ref name) = args.out_name {
         b.out_name(name);
     }
+    if let Some(ref name) = args.memory {
+        b.memory(name);
+    }
 
     if let Some(mode) = &args.encode_into {
         let mode = match mode.as_str() {
@@ -183,5 +340,48 @@ fn rmain(args: &Args) -> Result<(), Error> {
         b.encode_into(mode);
     }
 
-    b.generate(&args.out_dir)
+    if let Some(layout) = &args.snippets {
+        let layout = match layout.as_str() {
+            "hashed" => SnippetLayout::Hashed,
+            "stable" => SnippetLayout::Stable,
+            "concat" => SnippetLayout::Concat,
+            // clap guarantees
+            _ => unreachable!(),
+        };
+        b.snippet_layout(layout);
+    }
+
+    b.generate(&args.out_dir)?;
+
+    let stems = b.binding_stems()?;
+    let stems = stems.iter().map(String::as_str).collect::<Vec<_>>();
+
+    if let Some(level) = &args.wasm_opt {
+        let options = wasm_opt::Options {
+            level,
+            debug_info: (args.keep_debug && b.keeps_custom_section(".debug_info"))
+                || (!args.remove_name_section && b.keeps_custom_section("name")),
+            strip_producers: args.remove_producers_section || !b.keeps_custom_section("producers"),
+            strip_target_features,
+        };
+        for stem in &stems {
+            let wasm = args.out_dir.join(format!("{stem}_bg.wasm"));
+            wasm_opt::run(&wasm, &options)?;
+        }
+    }
+
+    if args.source_map {
+        // Snippets are looked up in the crate being built.
+        let root = match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => env::current_dir()?,
+        };
+        SourceMaps::write(&args.out_dir, &stems, &root);
+    }
+
+    // Last, as the other steps change the files.
+    if args.sri {
+        sri::write(&args.out_dir, b.stem()?, &stems)?;
+    }
+    Ok(())
 }
@@ -11,24 +11,47 @@
 //! For more documentation about this see the `wasm-bindgen-test` crate README
 //! and source code.
 
-use anyhow::{bail, Context};
+use crate::source_map::SourceMaps;
+use anyhow::{anyhow, bail, Context};
 use clap::Parser;
 use clap::ValueEnum;
+use server::{Compression, Mount};
+use server_config::ServerConfig;
+use std::collections::BTreeMap;
 use std::env;
 use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, IsTerminal};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::thread;
+use symbols::WasmSymbols;
 use wasm_bindgen_cli_support::Bindgen;
+use webdriver::WebDriverConfig;
 
+mod bidi;
+mod cache;
+mod cdp;
+mod coverage;
+mod debug;
 mod deno;
 mod doctest;
+mod examples;
+mod flamegraph;
 mod headless;
+mod instructions;
 mod node;
+mod proxy;
 mod server;
+mod server_config;
 mod shell;
+mod size;
+mod symbols;
+mod video;
+mod webdriver;
 
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(name = "wasm-bindgen-test-runner", version, about, long_about = None)]
 struct Cli {
     #[arg(
@@ -50,20 +73,307 @@ struct Cli {
         help = "Skip tests whose names contain FILTER (this flag can be used multiple times)"
     )]
     skip: Vec<String>,
+    #[arg(
+        long,
+        help = "Expect a doctest run in Node.js or Deno to panic \
+                [env: WASM_BINDGEN_TEST_SHOULD_PANIC]"
+    )]
+    should_panic: bool,
+    #[arg(
+        long,
+        value_name = "FILTER",
+        help = "Only run the doctests at FILTER, a `<path>:<line>` location such as \
+                `src/lib.rs:42`, or whose names contain FILTER \
+                [env: WASM_BINDGEN_TEST_DOCTEST_FILTER]"
+    )]
+    doctest_filter: Option<String>,
+    #[arg(
+        long,
+        help = "Fail tests during which a promise is rejected without a handler \
+                [env: WASM_BINDGEN_TEST_FAIL_ON_UNHANDLED_REJECTION]"
+    )]
+    fail_on_unhandled_rejection: bool,
+    #[arg(
+        long,
+        help = "Fail tests during which an error isn't caught, like one reported to \
+                `window.onerror` [env: WASM_BINDGEN_TEST_FAIL_ON_UNCAUGHT_ERROR]"
+    )]
+    fail_on_uncaught_error: bool,
+    #[arg(
+        long,
+        help = "Print the captured output of `console.warn` and `console.error` to stderr and \
+                the rest to stdout, colored by level [env: WASM_BINDGEN_TEST_SPLIT_CONSOLE_OUTPUT]"
+    )]
+    split_console_output: bool,
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        help = "Render objects logged with `console` methods like Node.js' `util.inspect`, \
+                showing objects nested up to DEPTH levels [env: WASM_BINDGEN_TEST_CONSOLE_DEPTH]"
+    )]
+    console_depth: Option<u32>,
+    #[arg(
+        long,
+        value_name = "LENGTH",
+        help = "Render objects logged with `console` methods like Node.js' `util.inspect`, \
+                showing up to LENGTH elements of arrays and properties of objects \
+                [env: WASM_BINDGEN_TEST_CONSOLE_MAX_LENGTH]"
+    )]
+    console_max_length: Option<u32>,
+    #[arg(
+        long,
+        value_name = "LINES",
+        help = "Print at most LINES lines of the output of each failed test, saving the rest \
+                with the test artifacts (default: no limit) \
+                [env: WASM_BINDGEN_TEST_MAX_TEST_OUTPUT_LINES]"
+    )]
+    max_test_output_lines: Option<u32>,
+    #[arg(
+        long,
+        value_name = "LINES",
+        help = "Print at most LINES lines of the output of failed tests in total, saving the \
+                rest with the test artifacts (default: no limit) \
+                [env: WASM_BINDGEN_TEST_MAX_OUTPUT_LINES]"
+    )]
+    max_output_lines: Option<u32>,
+    #[arg(
+        long,
+        help = "Show how long each test took [env: WASM_BINDGEN_TEST_REPORT_TIME]"
+    )]
+    report_time: bool,
+    #[arg(
+        long,
+        help = "Fail tests taking longer than the hard time limit, implies `--report-time` \
+                [env: WASM_BINDGEN_TEST_ENSURE_TIME]"
+    )]
+    ensure_time: bool,
+    #[arg(
+        long,
+        value_name = "WARN_MS,CRITICAL_MS",
+        value_parser = parse_time_limits,
+        help = "Time limits of tests in milliseconds, past which they are listed in a warning \
+                and, with `--ensure-time`, fail (default: 500,1000) \
+                [env: WASM_BINDGEN_TEST_TIME_LIMITS]"
+    )]
+    time_limits: Option<(u32, u32)>,
+    #[arg(
+        long,
+        help = "Record a CPU profile of each test, saved as `<test>.cpuprofile` with the test \
+                artifacts, in Node.js and in Chrome over the DevTools protocol \
+                [env: WASM_BINDGEN_TEST_CPU_PROFILE]"
+    )]
+    cpu_profile: bool,
+    #[arg(
+        long,
+        help = "Render the CPU profiles of `--cpu-profile` into `<test>.folded` stacks and \
+                `<test>.svg` flame graphs, only for the tests exceeding the time limit with \
+                `--report-time`; implies `--cpu-profile` [env: WASM_BINDGEN_TEST_FLAMEGRAPH]"
+    )]
+    flamegraph: bool,
+    #[arg(
+        long,
+        help = "Show how much each test grew the Wasm memory and the JS heap \
+                [env: WASM_BINDGEN_TEST_REPORT_MEMORY]"
+    )]
+    report_memory: bool,
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = parse_bytes,
+        help = "Warn about tests growing the Wasm memory or the JS heap by more than BYTES, \
+                with an optional `KiB`, `MiB` or `GiB` suffix, implies `--report-memory` \
+                (default: 1MiB) [env: WASM_BINDGEN_TEST_MEMORY_THRESHOLD]"
+    )]
+    memory_threshold: Option<u64>,
+    #[arg(
+        long,
+        help = "List the tests leaving DOM elements, pending timers or event listeners behind \
+                [env: WASM_BINDGEN_TEST_DETECT_LEAKS]"
+    )]
+    detect_leaks: bool,
+    #[arg(
+        long,
+        help = "Show how many Wasm instructions each test executed, counted by instrumenting \
+                the module, a measure of performance unaffected by the load of the machine \
+                [env: WASM_BINDGEN_TEST_COUNT_INSTRUCTIONS]"
+    )]
+    count_instructions: bool,
+    #[arg(
+        long,
+        help = "Show the size of the Wasm module after wasm-bindgen processed it, by section, \
+                and its largest functions [env: WASM_BINDGEN_TEST_SIZE_REPORT]"
+    )]
+    size_report: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Compare the size of the Wasm module with the one stored in the JSON file FILE, \
+                which it's added to if it isn't there yet [env: WASM_BINDGEN_TEST_SIZE_BASELINE]"
+    )]
+    size_baseline: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Fail if the Wasm module grew by more than PERCENT over the size baseline \
+                (default: 5) [env: WASM_BINDGEN_TEST_MAX_SIZE_REGRESSION]"
+    )]
+    max_size_regression: Option<f64>,
+    /// Whether the size of the module was already reported by another run of
+    /// this test binary, in another mode.
+    #[arg(skip)]
+    size_reported: bool,
     #[arg(long, help = "List all tests and benchmarks")]
     list: bool,
+    #[arg(
+        long,
+        help = "Group the tests listed by `--list` into their suites \
+                [env: WASM_BINDGEN_TEST_GROUP_BY_SUITE]"
+    )]
+    group_by_suite: bool,
     #[arg(
         long,
         help = "don't capture `console.*()` of each task, allow printing directly"
     )]
     nocapture: bool,
+    #[arg(
+        long,
+        help = "Open browser tests in a browser with devtools and pause before running them"
+    )]
+    debug: bool,
+    #[arg(
+        long,
+        help = "Pause in the debugger when a test panics in interactive browser runs, with the \
+                frames of the panic on the stack [env: WASM_BINDGEN_TEST_BREAK_ON_PANIC]"
+    )]
+    break_on_panic: bool,
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "Seed the inputs of `#[wasm_bindgen_test(proptest)]` tests with SEED, as reported \
+                by a failing one, instead of a random seed [env: WASM_BINDGEN_TEST_PROPTEST_SEED]"
+    )]
+    proptest_seed: Option<u64>,
+    #[arg(
+        long,
+        value_name = "PORT",
+        help = "Port of the HTTP server serving browser tests"
+    )]
+    port: Option<u16>,
+    #[arg(
+        long,
+        value_name = "START-END",
+        value_parser = parse_port_range,
+        help = "Range of ports to pick the port of a spawned WebDriver or Chrome from"
+    )]
+    webdriver_port_range: Option<RangeInclusive<u16>>,
+    #[arg(
+        long,
+        value_name = "IP",
+        help = "Address the HTTP server serving browser tests binds to, e.g. 0.0.0.0 \
+                [env: WASM_BINDGEN_TEST_BIND]"
+    )]
+    bind: Option<IpAddr>,
+    #[arg(
+        long,
+        value_name = "HOST",
+        help = "Host name the browser reaches the HTTP server by \
+                [env: WASM_BINDGEN_TEST_PUBLIC_HOST]"
+    )]
+    public_host: Option<String>,
     #[arg(
         long,
         value_enum,
-        value_name = "terse",
-        help = "Configure formatting of output"
+        value_name = "ENCODING",
+        help = "Compress responses of the HTTP server serving browser tests \
+                [env: WASM_BINDGEN_TEST_COMPRESSION]"
+    )]
+    compression: Option<Compression>,
+    #[arg(
+        long,
+        help = "Print every request handled by the HTTP server serving browser tests \
+                [env: WASM_BINDGEN_TEST_LOG_REQUESTS]"
+    )]
+    log_requests: bool,
+    #[arg(
+        long,
+        help = "Serve browser tests' files from a second origin, exposed to tests as \
+                `wasm_bindgen_test_cross_origin` [env: WASM_BINDGEN_TEST_CROSS_ORIGIN_SERVER]"
+    )]
+    cross_origin_server: bool,
+    #[arg(
+        long,
+        help = "Accept WebSocket connections to `/ws-echo` on the HTTP server serving browser \
+                tests, echoing every message [env: WASM_BINDGEN_TEST_WS_ECHO]"
+    )]
+    ws_echo: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Add the response headers of the rules in the JSON file FILE to what the HTTP \
+                server serving browser tests serves \
+                [env: WASM_BINDGEN_TEST_RESPONSE_HEADERS]"
+    )]
+    response_headers: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Answer requests to the HTTP server serving browser tests with the mock \
+                endpoints in the JSON file FILE [env: WASM_BINDGEN_TEST_MOCKS]"
+    )]
+    mocks: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Accept WebSocket connections to the HTTP server serving browser tests following \
+                the scripted endpoints in the JSON file FILE [env: WASM_BINDGEN_TEST_WEBSOCKETS]"
+    )]
+    websockets: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PREFIX=DIR",
+        value_parser = parse_mount,
+        help = "Serve the files in DIR under the URL PREFIX to browser tests (this flag can be \
+                used multiple times)"
+    )]
+    mount: Vec<Mount>,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        help = "Export the coverage data of the tests to a report in FORMAT under \
+                `target/wbg_coverage` (this flag can be used multiple times) \
+                [env: WASM_BINDGEN_TEST_COVERAGE_FORMAT]"
+    )]
+    coverage_format: Vec<coverage::Format>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Object file with the coverage mapping of the tests, needed to export coverage \
+                reports (this flag can be used multiple times) \
+                [env: WASM_BINDGEN_TEST_COVERAGE_OBJECTS]"
+    )]
+    coverage_object: Vec<PathBuf>,
+    #[arg(
+        long,
+        help = "Also export a coverage report for each mode and browser the tests ran in \
+                [env: WASM_BINDGEN_TEST_COVERAGE_PER_MODE]"
+    )]
+    coverage_per_mode: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        help = "Configure formatting of output, `json` and `junit` reporting the suite of \
+                each test"
     )]
     format: Option<FormatSetting>,
+    #[arg(
+        long,
+        hide = true,
+        value_name = "MODE",
+        help = "Only run the tests running in MODE, like `browser` or `node`, in that mode"
+    )]
+    run_in: Option<String>,
     #[arg(
         index = 2,
         value_name = "FILTER",
@@ -74,23 +384,136 @@ struct Cli {
 }
 
 impl Cli {
-    fn get_args(&self, tests: &Tests) -> String {
-        let include_ignored = self.include_ignored;
+    /// The flame graphs to render from the CPU profiles, if requested.
+    fn flamegraphs(&self) -> Option<flamegraph::Options> {
+        let slower_than_ms = (self.report_time || self.ensure_time)
+            .then(|| self.time_limits.map_or(500, |(warn_ms, _)| warn_ms));
+        (self.cpu_profile && self.flamegraph).then_some(flamegraph::Options { slower_than_ms })
+    }
+
+    /// Returns the JS forwarding the runtime arguments to the `cx` context,
+    /// where the runner saves the output suppressed by its limits to
+    /// `suppressed_output`.
+    fn get_args(&self, tests: &Tests, suppressed_output: &Path) -> String {
+        // With `--ignored` only ignored tests are left to run.
+        let include_ignored = self.include_ignored || self.ignored;
         let filtered = tests.filtered;
+        let js = |value: Option<u32>| value.map_or("undefined".to_string(), |v| v.to_string());
+        let max_test_output_lines = js(self.max_test_output_lines);
+        let max_output_lines = js(self.max_output_lines);
+        let suppressed_output = path_literal(suppressed_output);
+        let colors = console_colors();
 
-        format!(
+        let mut args = format!(
             r#"
             // Forward runtime arguments.
             cx.include_ignored({include_ignored:?});
             cx.filtered_count({filtered});
+            cx.output_limits({max_test_output_lines}, {max_output_lines}, {suppressed_output});
+            cx.colors({colors});
+
+            // Attribute uncaught errors of the page or worker, including
+            // those propagated from workers it spawned, to the running test.
+            if (typeof addEventListener === 'function' && typeof ErrorEvent === 'function') {{
+                addEventListener('error', e => {{
+                    if (!(e instanceof ErrorEvent)) return;
+                    cx.uncaught_error(e.error ?? `${{e.message}} (${{e.filename}}:${{e.lineno}})`);
+                }});
+            }}
         "#
-        )
+        );
+        if self.report_time || self.ensure_time {
+            let (warn_ms, critical_ms) = self.time_limits.unwrap_or((500, 1000));
+            args.push_str(&format!(
+                "cx.time_limits({}, {warn_ms}, {critical_ms});\n",
+                self.ensure_time
+            ));
+        }
+        if self.report_memory || self.memory_threshold.is_some() {
+            let threshold = self.memory_threshold.unwrap_or(1 << 20);
+            args.push_str(&format!("cx.memory_threshold({threshold});\n"));
+        }
+        if self.cpu_profile {
+            args.push_str("cx.cpu_profile(true);\n");
+        }
+        if self.detect_leaks {
+            args.push_str(include_str!("wasm_bindgen_test_runner/leaks.js"));
+        }
+        if self.count_instructions {
+            // `wasm` is the exports of the instance in browsers and the
+            // module of the bindings in Node.js and Deno.
+            args.push_str(&format!(
+                "cx.count_instructions((wasm.__wasm ?? wasm).{});\n",
+                instructions::EXPORT
+            ));
+        }
+        if let Some(format @ (FormatSetting::Json | FormatSetting::Junit)) = self.format {
+            let format = format.to_possible_value().expect("no variant is skipped");
+            let (names, suites): (Vec<_>, Vec<_>) = tests
+                .tests
+                .iter()
+                .map(|test| (&test.name, &test.suite))
+                .unzip();
+            let names = serde_json::to_string(&names).expect("strings serialize to JSON");
+            let suites = serde_json::to_string(&suites).expect("strings serialize to JSON");
+            args.push_str(&format!(
+                "cx.report_format({:?}, {names}, {suites});\n",
+                format.get_name()
+            ));
+        }
+        if let Some(seed) = self.proptest_seed {
+            args.push_str(&format!("globalThis.__wbg_proptest_seed = '{seed}';\n"));
+        }
+        if tests.has_generators() {
+            // Dynamic tests are only known to the harness, which filters them.
+            let filter = serde_json::to_string(&self.filter).expect("strings serialize to JSON");
+            let skip = serde_json::to_string(&self.skip).expect("strings serialize to JSON");
+            args.push_str(&format!(
+                "cx.filter({filter}, {skip}, {}, {});\n",
+                self.exact, self.ignored
+            ));
+        }
+        if !tests.hooks.is_empty() {
+            args.push_str(&format!("cx.hooks({:?});\n", tests.hooks));
+        }
+        if self.fail_on_uncaught_error {
+            args.push_str("cx.fail_on_uncaught_error(true);\n");
+        }
+        if self.split_console_output {
+            args.push_str(&format!("cx.split_console_output({colors});\n"));
+        }
+        if self.console_depth.is_some() || self.console_max_length.is_some() {
+            args.push_str(&format!(
+                "cx.console_inspect({}, {});\n",
+                js(self.console_depth),
+                js(self.console_max_length)
+            ));
+        }
+        if self.fail_on_unhandled_rejection {
+            args.push_str(
+                r#"
+            cx.fail_on_unhandled_rejection(true);
+            if (typeof process === 'object' && typeof process.on === 'function') {
+                process.on('unhandledRejection', reason => cx.unhandled_rejection(reason));
+            } else {
+                addEventListener('unhandledrejection', e => {
+                    e.preventDefault();
+                    cx.unhandled_rejection(e.reason);
+                });
+            }
+            "#,
+            );
+        }
+        args
     }
 }
 
 struct Tests {
     tests: Vec<Test>,
     filtered: usize,
+    // `__wbgh_*` exports of the test module hooks, the test executor and the
+    // generators of dynamic tests
+    hooks: Vec<String>,
 }
 
 impl Tests {
@@ -98,8 +521,15 @@ impl Tests {
         Self {
             tests: Vec::new(),
             filtered: 0,
+            hooks: Vec::new(),
         }
     }
+
+    fn has_generators(&self) -> bool {
+        self.hooks
+            .iter()
+            .any(|hook| hook.starts_with("__wbgh_generator_"))
+    }
 }
 
 struct Test {
@@ -108,6 +538,12 @@ struct Test {
     // symbol name
     export: String,
     ignored: bool,
+    // `timeout_ms` of the test attribute
+    timeout_ms: Option<u32>,
+    // `run_in` of the test attribute, overriding the mode of the test binary
+    run_in: Option<&'static str>,
+    // `suite` of the test attribute, or else the module of the test
+    suite: String,
 }
 
 pub fn run_cli_with_args<I, T>(args: I) -> anyhow::Result<()>
@@ -115,6 +551,10 @@ where
     I: IntoIterator<Item = T>,
     T: Into<OsString> + Clone,
 {
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    if examples::is_subcommand(&args) {
+        return examples::run(args);
+    }
     let cli = match Cli::try_parse_from(args) {
         Ok(a) => a,
         Err(e) => match e.kind() {
@@ -126,20 +566,28 @@ where
             _ => bail!(e),
         },
     };
-    rmain(cli)
+    // The coverage reports are written even if tests failed.
+    let coverage = coverage::Report::new(&cli)?;
+    let result = rmain(cli);
+    if let Some(coverage) = coverage {
+        coverage
+            .write()
+            .context("failed to export the coverage report")?;
+    }
+    result
 }
 
-fn rmain(cli: Cli) -> anyhow::Result<()> {
+fn rmain(mut cli: Cli) -> anyhow::Result<()> {
     // Collect all tests that the test harness is supposed to run. We assume
     // that any exported function with the prefix `__wbg_test` is a test we need
     // to execute.
-    let wasm = fs::read(&cli.file).context("failed to read Wasm file")?;
+    let bytes = fs::read(&cli.file).context("failed to read Wasm file")?;
     let mut wasm = walrus::ModuleConfig::new()
         // generate dwarf by default, it can be controlled by debug profile
         //
         // https://doc.rust-lang.org/cargo/reference/profiles.html#debug
         .generate_dwarf(true)
-        .parse(&wasm)
+        .parse(&bytes)
         .context("failed to deserialize Wasm module")?;
     let mut tests = Tests::new();
 
@@ -147,19 +595,37 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
     let prefix = if cli.bench { "__wbgb_" } else { "__wbgt_" };
 
     'outer: for export in wasm.exports.iter() {
+        if export.name.starts_with("__wbgh_") {
+            tests.hooks.push(export.name.clone());
+            continue;
+        }
         let Some(name) = export.name.strip_prefix(prefix) else {
             continue;
         };
-        let modifiers = name.split_once('_').expect("found invalid identifier").0;
+        let (modifiers, path) = name.split_once('_').expect("found invalid identifier");
 
-        let Some(name) = export.name.split_once("::").map(|s| s.1) else {
+        let Some((krate, name)) = path.split_once("::") else {
             continue;
         };
+        // Tests belong to the suite of their attribute, or else to their
+        // module, or their crate at its root.
+        let (name, suite) = match name.split_once('@') {
+            Some((name, suite)) => (name, suite),
+            None => (
+                name,
+                name.rsplit_once("::").map_or(krate, |(module, _)| module),
+            ),
+        };
 
         let test = Test {
             name: name.into(),
             export: export.name.clone(),
             ignored: modifiers.contains('$'),
+            timeout_ms: modifiers
+                .split_once('t')
+                .and_then(|(_, timeout_ms)| timeout_ms.parse().ok()),
+            run_in: modifiers.chars().find_map(TestMode::name_of_modifier),
+            suite: suite.into(),
         };
 
         if let Some(filter) = &cli.filter {
@@ -196,11 +662,23 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
     }
 
     if cli.list {
-        for test in tests.tests {
-            if cli.bench {
-                println!("{}: benchmark", test.name);
-            } else {
-                println!("{}: test", test.name);
+        let kind = if cli.bench { "benchmark" } else { "test" };
+        // The list stays flat by default, for the tools parsing it like
+        // libtest's.
+        if !cli.group_by_suite && env::var_os("WASM_BINDGEN_TEST_GROUP_BY_SUITE").is_none() {
+            for test in tests.tests {
+                println!("{}: {kind}", test.name);
+            }
+        } else {
+            let mut suites = BTreeMap::<_, Vec<_>>::new();
+            for test in &tests.tests {
+                suites.entry(&test.suite).or_default().push(&test.name);
+            }
+            for (suite, names) in suites {
+                println!("{suite}:");
+                for name in names {
+                    println!("    {name}: {kind}");
+                }
             }
         }
 
@@ -253,22 +731,125 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
     // Right now there's a bug where if no tests are present then the
     // `wasm-bindgen-test` runtime support isn't linked in, so just bail out
     // early saying everything is ok.
-    if tests.tests.is_empty() && !is_doctest {
+    if tests.tests.is_empty() && !tests.has_generators() && !is_doctest {
         println!("no tests to run!");
         return Ok(());
     }
 
+    // Merged doctest bundles run each doctest on its own in Node.js and Deno.
+    let mut doctests = if is_doctest {
+        doctest::export_merged(&mut wasm)?
+    } else {
+        doctest::Doctests::default()
+    };
+    let doctest_filter = cli
+        .doctest_filter
+        .clone()
+        .or_else(|| env::var("WASM_BINDGEN_TEST_DOCTEST_FILTER").ok());
+    if let Some(filter) = doctest_filter.as_deref().filter(|_| is_doctest) {
+        if doctests.is_merged() {
+            doctests.filter(filter);
+        } else if !doctest::is_selected(&wasm, &cli.file, filter) {
+            println!("running 0 doctests");
+            println!();
+            let mut summary = doctest::Summary::start();
+            summary.filtered = 1;
+            summary.finish()?;
+            return Ok(());
+        }
+    }
+    if is_doctest && !doctests.is_merged() {
+        doctests.locate(&wasm, &cli.file);
+    }
+
     // Figure out if this tests is supposed to execute in node.js or a browser.
     // That's done on a per-test-binary basis with the
     // `wasm_bindgen_test_configure` macro, which emits a custom section for us
     // to read later on.
 
     let custom_section = wasm.customs.remove_raw("__wasm_bindgen_test_unstable");
+    // `fail_on_unhandled_rejection` and `fail_on_uncaught_error` are options
+    // next to the test mode.
+    let has_option = |option| {
+        custom_section
+            .as_ref()
+            .is_some_and(|section| section.data.contains(&option))
+    };
+    cli.fail_on_unhandled_rejection |=
+        has_option(0x06) || env::var_os("WASM_BINDGEN_TEST_FAIL_ON_UNHANDLED_REJECTION").is_some();
+    cli.fail_on_uncaught_error |=
+        has_option(0x07) || env::var_os("WASM_BINDGEN_TEST_FAIL_ON_UNCAUGHT_ERROR").is_some();
+    cli.split_console_output |= env::var_os("WASM_BINDGEN_TEST_SPLIT_CONSOLE_OUTPUT").is_some();
+    cli.break_on_panic |= env::var_os("WASM_BINDGEN_TEST_BREAK_ON_PANIC").is_some();
+    let env_u32 = |var: &str| match env::var(var) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|e| anyhow!("invalid `{var}` `{value}`: {e}")),
+        Err(_) => Ok(None),
+    };
+    if cli.console_depth.is_none() {
+        cli.console_depth = env_u32("WASM_BINDGEN_TEST_CONSOLE_DEPTH")?;
+    }
+    if cli.console_max_length.is_none() {
+        cli.console_max_length = env_u32("WASM_BINDGEN_TEST_CONSOLE_MAX_LENGTH")?;
+    }
+    if cli.max_test_output_lines.is_none() {
+        cli.max_test_output_lines = env_u32("WASM_BINDGEN_TEST_MAX_TEST_OUTPUT_LINES")?;
+    }
+    if cli.max_output_lines.is_none() {
+        cli.max_output_lines = env_u32("WASM_BINDGEN_TEST_MAX_OUTPUT_LINES")?;
+    }
+    cli.report_time |= env::var_os("WASM_BINDGEN_TEST_REPORT_TIME").is_some();
+    cli.ensure_time |= env::var_os("WASM_BINDGEN_TEST_ENSURE_TIME").is_some();
+    cli.flamegraph |= env::var_os("WASM_BINDGEN_TEST_FLAMEGRAPH").is_some();
+    cli.cpu_profile |= cli.flamegraph || env::var_os("WASM_BINDGEN_TEST_CPU_PROFILE").is_some();
+    cli.report_memory |= env::var_os("WASM_BINDGEN_TEST_REPORT_MEMORY").is_some();
+    cli.detect_leaks |= env::var_os("WASM_BINDGEN_TEST_DETECT_LEAKS").is_some();
+    cli.count_instructions |= env::var_os("WASM_BINDGEN_TEST_COUNT_INSTRUCTIONS").is_some();
+    cli.size_report |= env::var_os("WASM_BINDGEN_TEST_SIZE_REPORT").is_some();
+    if cli.size_baseline.is_none() {
+        cli.size_baseline = env::var_os("WASM_BINDGEN_TEST_SIZE_BASELINE").map(PathBuf::from);
+    }
+    if let (None, Ok(percent)) = (
+        cli.max_size_regression,
+        env::var("WASM_BINDGEN_TEST_MAX_SIZE_REGRESSION"),
+    ) {
+        cli.max_size_regression = Some(percent.parse().map_err(|e| {
+            anyhow!("invalid `WASM_BINDGEN_TEST_MAX_SIZE_REGRESSION` `{percent}`: {e}")
+        })?);
+    }
+    if let (None, Ok(threshold)) = (
+        cli.memory_threshold,
+        env::var("WASM_BINDGEN_TEST_MEMORY_THRESHOLD"),
+    ) {
+        cli.memory_threshold = Some(parse_bytes(&threshold).map_err(|e| {
+            anyhow!("invalid `WASM_BINDGEN_TEST_MEMORY_THRESHOLD` `{threshold}`: {e}")
+        })?);
+    }
+    if let (None, Ok(limits)) = (cli.time_limits, env::var("WASM_BINDGEN_TEST_TIME_LIMITS")) {
+        cli.time_limits = Some(
+            parse_time_limits(&limits)
+                .map_err(|e| anyhow!("invalid `WASM_BINDGEN_TEST_TIME_LIMITS` `{limits}`: {e}"))?,
+        );
+    }
+    if let (None, Ok(seed)) = (
+        cli.proptest_seed,
+        env::var("WASM_BINDGEN_TEST_PROPTEST_SEED"),
+    ) {
+        let parsed = seed.parse();
+        cli.proptest_seed = Some(
+            parsed
+                .map_err(|e| anyhow!("invalid `WASM_BINDGEN_TEST_PROPTEST_SEED` `{seed}`: {e}"))?,
+        );
+    }
+    let custom_section = custom_section
+        .filter(|section| section.data.iter().any(|byte| ![0x06, 0x07].contains(byte)));
     let no_modules = std::env::var("WASM_BINDGEN_USE_NO_MODULE").is_ok();
     // Force no_modules for ServiceWorker because Firefox < 147 doesn't support
     // ES module service workers. See https://bugzilla.mozilla.org/show_bug.cgi?id=1360870
     let service_worker_no_modules = true;
-    let test_mode = match custom_section {
+    let mut test_mode = match custom_section {
         Some(section) if section.data.contains(&0x01) => TestMode::Browser { no_modules },
         Some(section) if section.data.contains(&0x02) => TestMode::DedicatedWorker { no_modules },
         Some(section) if section.data.contains(&0x03) => TestMode::SharedWorker { no_modules },
@@ -307,9 +888,119 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
         }
     };
 
-    let headless = env::var("NO_HEADLESS").is_err();
+    // Tests can override the mode of the test binary with `run_in`. The tests
+    // of each mode then run in turn, each in a run of their own.
+    // Generators of dynamic tests run in the mode of the test binary.
+    if let Some(mode) = cli.run_in.clone() {
+        tests
+            .tests
+            .retain(|test| test.run_in.unwrap_or(test_mode.name()) == mode);
+        if mode != test_mode.name() {
+            tests
+                .hooks
+                .retain(|hook| !hook.starts_with("__wbgh_generator_"));
+            test_mode = TestMode::from_name(&mode, no_modules)?;
+        }
+    } else if !is_doctest {
+        let mut modes = Vec::new();
+        if tests.has_generators() {
+            modes.push(test_mode.name());
+        }
+        for test in &tests.tests {
+            let mode = test.run_in.unwrap_or(test_mode.name());
+            if !modes.contains(&mode) {
+                modes.push(mode);
+            }
+        }
+        if modes != [test_mode.name()] {
+            let mut failed = Vec::new();
+            for (i, mode) in modes.into_iter().enumerate() {
+                let mut cli = cli.clone();
+                cli.run_in = Some(mode.to_string());
+                cli.size_reported = i > 0;
+                if let Err(e) = rmain(cli) {
+                    println!("error: the tests running in {mode} failed: {e:?}");
+                    failed.push(mode);
+                }
+            }
+            if !failed.is_empty() {
+                bail!("the tests running in {} failed", failed.join(", "));
+            }
+            return Ok(());
+        }
+    }
+
+    // Doctests can be forced into a mode without configuring each of them.
+    let test_mode = match env::var("WASM_BINDGEN_TEST_DOCTEST_MODE") {
+        Ok(mode) if is_doctest => match mode.as_str() {
+            "browser" => TestMode::Browser { no_modules },
+            "node" => TestMode::Node { no_modules: true },
+            "worker" => TestMode::DedicatedWorker { no_modules },
+            _ => bail!(
+                "invalid WASM_BINDGEN_TEST_DOCTEST_MODE value `{mode}`, \
+                 expected `browser`, `node` or `worker`"
+            ),
+        },
+        _ => test_mode,
+    };
+
+    let debug_mode = cli.debug;
+    let headless = env::var("NO_HEADLESS").is_err() && !debug_mode;
+    let port = cli.port;
+    let driver_ports = cli.webdriver_port_range.clone();
+    let mounts = cli.mount.clone();
+    let bind = match cli.bind {
+        Some(bind) => Some(bind),
+        None => match env::var("WASM_BINDGEN_TEST_BIND") {
+            Ok(bind) => Some(
+                bind.parse()
+                    .with_context(|| format!("invalid `WASM_BINDGEN_TEST_BIND` `{bind}`"))?,
+            ),
+            Err(_) => None,
+        },
+    };
+    let compression = match cli.compression {
+        Some(compression) => Some(compression),
+        None => match env::var("WASM_BINDGEN_TEST_COMPRESSION") {
+            Ok(compression) => Some(Compression::from_str(&compression, true).map_err(|e| {
+                anyhow!("invalid `WASM_BINDGEN_TEST_COMPRESSION` `{compression}`: {e}")
+            })?),
+            Err(_) => None,
+        },
+    };
+    let log_requests = cli.log_requests || env::var_os("WASM_BINDGEN_TEST_LOG_REQUESTS").is_some();
+    let should_panic = cli.should_panic || env::var_os("WASM_BINDGEN_TEST_SHOULD_PANIC").is_some();
+    let cross_origin_server =
+        cli.cross_origin_server || env::var_os("WASM_BINDGEN_TEST_CROSS_ORIGIN_SERVER").is_some();
+    let ws_echo = cli.ws_echo || env::var_os("WASM_BINDGEN_TEST_WS_ECHO").is_some();
+    let public_host = cli
+        .public_host
+        .clone()
+        .or_else(|| env::var("WASM_BINDGEN_TEST_PUBLIC_HOST").ok());
+    // The tests expected to report a result, for telling which ones a crash
+    // cut short. Filtered-out tests are already gone from `tests`.
+    let include_ignored = cli.include_ignored || cli.ignored;
+    let test_names: Vec<String> = tests
+        .tests
+        .iter()
+        .filter(|t| !t.ignored || include_ignored)
+        .map(|t| t.name.clone())
+        .collect();
     let debug = env::var("WASM_BINDGEN_NO_DEBUG").is_err();
 
+    // Pages are served cross-origin isolated by default. Threads rely on
+    // `SharedArrayBuffer`, which is only available to such pages, so opting
+    // out is ignored when the module uses shared memory.
+    let shared_memory = wasm.memories.iter().any(|m| m.shared);
+    let no_origin_isolation = env::var("WASM_BINDGEN_TEST_NO_ORIGIN_ISOLATION").is_ok();
+    if shared_memory && no_origin_isolation {
+        println!(
+            "ignoring `WASM_BINDGEN_TEST_NO_ORIGIN_ISOLATION` because the module uses \
+             shared memory, which requires cross-origin isolation"
+        );
+    }
+    let isolate_origin = shared_memory || !no_origin_isolation;
+
     // Gracefully handle requests to execute only node or only web tests.
     let node = matches!(test_mode, TestMode::Node { .. });
 
@@ -336,14 +1027,29 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let driver_timeout = env::var("WASM_BINDGEN_TEST_DRIVER_TIMEOUT")
-        .map(|timeout| {
-            timeout
-                .parse()
-                .expect("Could not parse 'WASM_BINDGEN_TEST_DRIVER_TIMEOUT'")
-        })
-        .unwrap_or(5);
+    // Profiles are collected through the V8 inspector, which only Node.js
+    // and pages of Chrome driven over the DevTools protocol expose to us.
+    if cli.cpu_profile
+        && !(node || (matches!(test_mode, TestMode::Browser { .. }) && cdp::enabled()))
+    {
+        println!(
+            "ignoring `--cpu-profile`, CPU profiles are only recorded in Node.js and in \
+             Chrome over the DevTools protocol (`WASM_BINDGEN_TEST_CDP`)"
+        );
+        cli.cpu_profile = false;
+    }
+    let cpu_profile = cli.cpu_profile;
+    let flamegraphs = cli.flamegraphs();
 
+    let startup = headless::StartupPolicy::from_env()?;
+
+    // Without an explicit timeout, leave the tests time to hit their own.
+    let longest_test_timeout = tests
+        .tests
+        .iter()
+        .filter_map(|test| test.timeout_ms)
+        .max()
+        .map_or(0, |timeout_ms| u64::from(timeout_ms).div_ceil(1000) + 1);
     let browser_timeout = env::var("WASM_BINDGEN_TEST_TIMEOUT")
         .map(|timeout| {
             let timeout = timeout
@@ -352,7 +1058,7 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
             println!("Set timeout to {timeout} seconds...");
             timeout
         })
-        .unwrap_or(20);
+        .unwrap_or(longest_test_timeout.max(20));
 
     let shell = shell::Shell::new();
 
@@ -375,12 +1081,25 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
         }
     };
 
+    // Lets the harness name the handles each test leaked.
+    if cli.detect_leaks {
+        b.track_handles(true);
+    }
     if std::env::var("WASM_BINDGEN_SPLIT_LINKED_MODULES").is_ok() {
         b.split_linked_modules(true);
     }
     if std::env::var("WASM_BINDGEN_KEEP_LLD_EXPORTS").is_ok() {
         b.keep_lld_exports(true);
     }
+    let import_map = std::env::var_os("WASM_BINDGEN_IMPORT_MAP");
+    if let Some(import_map) = &import_map {
+        b.import_map(import_map)?;
+    }
+    // Keep the DWARF debug info of the tests, if any, to locate the frames of
+    // Wasm functions in stack traces.
+    b.keep_debug(true);
+    // The tests run in the engine at hand, so they're only bound one way.
+    b.externref_fallback(false);
 
     // The path of benchmark baseline.
     let benchmark = if let Ok(path) = std::env::var("WASM_BINDGEN_BENCH_RESULT") {
@@ -397,17 +1116,104 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
         path.join("wbg_benchmark.json")
     };
 
+    // Where artifacts like screenshots of failed tests are saved, in a
+    // directory named after the test binary.
+    let artifacts = if let Ok(path) = std::env::var("WASM_BINDGEN_TEST_ARTIFACTS") {
+        PathBuf::from(path)
+    } else {
+        env::current_dir()
+            .context("Failed to get current dir")?
+            .join("target")
+            .join("wbg_artifacts")
+    }
+    .join(cli.file.file_stem().unwrap_or_default());
+    // Where the failure output suppressed by `--max-test-output-lines` and
+    // `--max-output-lines` is saved.
+    let suppressed_output = artifacts.join("suppressed-output.log");
+
+    // Unchanged test binaries reuse the output of wasm-bindgen from a previous
+    // run with the same options. Doctests are built anew for every run.
+    let cache = if is_doctest {
+        None
+    } else {
+        let import_map = match &import_map {
+            Some(path) => fs::read_to_string(path).ok(),
+            None => None,
+        };
+        let options = format!(
+            "{test_mode:?} {debug} {} {} {} {import_map:?}",
+            cli.detect_leaks,
+            std::env::var("WASM_BINDGEN_SPLIT_LINKED_MODULES").is_ok(),
+            std::env::var("WASM_BINDGEN_KEEP_LLD_EXPORTS").is_ok(),
+        );
+        cache::Cache::new(&cli.file, &bytes, &options)
+    };
     // The debug here means adding some assertions and some error messages to the generated js
     // code.
     //
     // It has nothing to do with Rust.
-    let bindgen_result = b
-        .debug(debug)
-        .input_module(module, wasm)
-        .emit_start(false)
-        .generate(&tmpdir_path);
+    let bindgen_result = if cache
+        .as_ref()
+        .is_some_and(|cache| cache.restore(&tmpdir_path))
+    {
+        Ok(())
+    } else {
+        let result = b
+            .debug(debug)
+            .input_module(module, wasm)
+            .emit_start(false)
+            .export_start(is_doctest)
+            .generate(&tmpdir_path);
+        if let (Ok(()), Some(cache)) = (&result, &cache) {
+            cache.store(&tmpdir_path);
+        }
+        result
+    };
     shell.clear();
 
+    // Point locations in the JS snippets back at the files they come from.
+    let source_maps = if bindgen_result.is_ok() {
+        let root = match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => env::current_dir().context("Failed to get current dir")?,
+        };
+        SourceMaps::write(&tmpdir_path, &[module], &root)
+    } else {
+        SourceMaps::default()
+    };
+    // Name the Wasm functions in stack traces.
+    let symbols = if bindgen_result.is_ok() {
+        WasmSymbols::load(&tmpdir_path.join(format!("{module}_bg.wasm")))
+    } else {
+        WasmSymbols::default()
+    };
+
+    // Break down the size of the module, failing once the tests ran if it
+    // grew too much over the baseline.
+    let mut size_regression = None;
+    let size_requested = cli.size_report || cli.size_baseline.is_some();
+    if bindgen_result.is_ok() && !is_doctest && size_requested && !cli.size_reported {
+        let report = size::Report::new(&tmpdir_path.join(format!("{module}_bg.wasm")))?;
+        let binary = size::binary_name(&cli.file);
+        let baseline = match &cli.size_baseline {
+            Some(path) => report.baseline(path, &binary)?,
+            None => None,
+        };
+        if cli.size_report {
+            report.print(&binary, baseline.as_ref());
+        }
+        if let (Some(path), Some(baseline)) = (&cli.size_baseline, &baseline) {
+            let max_regression = cli.max_size_regression.unwrap_or(5.);
+            size_regression = report.regression(baseline, max_regression, &binary, path);
+        }
+    }
+
+    // Count the instructions executed by the tests. This comes last, so that
+    // the size report leaves the counter out.
+    if bindgen_result.is_ok() && !is_doctest && cli.count_instructions {
+        instructions::instrument(&tmpdir_path.join(format!("{module}_bg.wasm")))?;
+    }
+
     // For doctests, if wasm-bindgen fails, try a fallback that executes the raw wasm
     // with stub imports. This handles doctests that use wasm-bindgen types but don't
     // actually need the full wasm-bindgen runtime.
@@ -422,11 +1228,19 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
 
         match test_mode {
             TestMode::Node { no_modules } => {
-                println!("running 1 doctest");
                 if use_fallback {
+                    println!("running 1 doctest");
                     doctest::execute_node_fallback(&cli.file)?;
                 } else {
-                    doctest::execute_node(module, &tmpdir_path, !no_modules)?;
+                    println!("{}", doctest::running(&doctests));
+                    doctest::execute_node(
+                        module,
+                        &tmpdir_path,
+                        !no_modules,
+                        cli.nocapture,
+                        should_panic,
+                        &doctests,
+                    )?;
                 }
             }
             TestMode::DedicatedWorker { no_modules }
@@ -437,7 +1251,6 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
                 // to work in Node.js, enabling Atomics.wait and child worker spawning.
                 //
                 // To use browser worker instead, set WASM_BINDGEN_USE_BROWSER=1
-                println!("running 1 doctest (node worker)");
                 if use_fallback {
                     bail!(
                         "This doctest cannot be processed by wasm-bindgen. \
@@ -445,92 +1258,159 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
                          Consider adding `wasm_bindgen_test` imports to enable full support."
                     );
                 }
-                doctest::execute_node_worker(module, &tmpdir_path, !no_modules)?;
+                println!("{} (node worker)", doctest::running(&doctests));
+                doctest::execute_node_worker(
+                    module,
+                    &tmpdir_path,
+                    !no_modules,
+                    cli.nocapture,
+                    should_panic,
+                    &doctests,
+                )?;
             }
             TestMode::Deno => {
                 if use_fallback {
-                    bail!(
-                        "This doctest cannot be processed by wasm-bindgen. \
-                         Deno fallback execution is not yet implemented. \
-                         Consider adding `wasm_bindgen_test` imports to enable full support."
-                    );
+                    println!("running 1 doctest");
+                    doctest::execute_deno_fallback(&cli.file)?;
+                } else {
+                    println!("{}", doctest::running(&doctests));
+                    doctest::execute_deno(
+                        module,
+                        &tmpdir_path,
+                        cli.nocapture,
+                        should_panic,
+                        &doctests,
+                    )?;
                 }
-                println!("running 1 doctest");
-                doctest::execute_deno(module, &tmpdir_path)?;
             }
             TestMode::Browser { .. }
             | TestMode::DedicatedWorker { .. }
             | TestMode::SharedWorker { .. }
             | TestMode::ServiceWorker { .. } => {
-                // Browser fallback not yet implemented
-                if use_fallback {
+                // Worker fallback not yet implemented
+                if use_fallback && test_mode.is_worker() {
                     bail!(
                         "This doctest cannot be processed by wasm-bindgen. \
-                         Browser fallback execution is not yet implemented. \
+                         Browser worker fallback execution is not yet implemented. \
                          Consider adding `wasm_bindgen_test` imports to enable full support."
                     );
                 }
                 println!("running 1 doctest");
+                let config = WebDriverConfig::load()?;
+                let server_config = ServerConfig::load(&cli, ws_echo)?;
                 let srv = server::spawn_doctest(
-                    &if headless {
-                        "127.0.0.1:0".parse().unwrap()
-                    } else if let Ok(address) = std::env::var("WASM_BINDGEN_TEST_ADDRESS") {
-                        address.parse().unwrap()
-                    } else {
-                        "127.0.0.1:8000".parse().unwrap()
-                    },
+                    &server_address(headless, port, bind),
                     headless,
                     module,
                     &tmpdir_path,
                     test_mode,
-                    std::env::var("WASM_BINDGEN_TEST_NO_ORIGIN_ISOLATION").is_err(),
+                    use_fallback.then_some(cli.file.as_path()),
+                    isolate_origin,
+                    server_config.headers,
+                    mounts,
+                    server_config.mocks,
+                    server_config.websockets,
+                    compression,
+                    log_requests,
+                    cli.nocapture,
                 )
                 .context("failed to spawn server")?;
                 let addr = srv.server_addr();
 
                 if !headless {
-                    println!("Interactive doctest is now available at http://{addr}");
+                    let url = headless::server_url(&addr, public_host.as_deref());
+                    println!("Interactive doctest is now available at {url}");
                     println!();
-                    println!("Note that interactive mode is enabled because `NO_HEADLESS`");
-                    println!("is specified in the environment of this process. Once you're");
-                    println!("done with testing you'll need to kill this server with");
-                    println!("Ctrl-C.");
+                    interactive_note(debug_mode, &url, &config)?;
                     srv.run();
                     return Ok(());
                 }
 
                 thread::spawn(|| srv.run());
-                headless::run(&addr, &shell, driver_timeout, browser_timeout)?;
+                headless::run(
+                    &addr,
+                    public_host.as_deref(),
+                    &shell,
+                    &startup,
+                    driver_ports.as_ref(),
+                    browser_timeout,
+                    &artifacts,
+                    &test_names,
+                    &source_maps,
+                    &symbols,
+                    false,
+                    None,
+                    config,
+                )?;
             }
         }
     } else {
         // For non-doctests, wasm-bindgen must succeed
         bindgen_result.context("executing `wasm-bindgen` over the Wasm file")?;
         match test_mode {
-            TestMode::Node { no_modules } => {
-                node::execute(module, &tmpdir_path, cli, tests, !no_modules, benchmark)?
-            }
-            TestMode::Deno => deno::execute(module, &tmpdir_path, cli, tests)?,
+            TestMode::Node { no_modules } => node::execute(
+                module,
+                &tmpdir_path,
+                cli,
+                tests,
+                !no_modules,
+                benchmark,
+                &suppressed_output,
+                &artifacts,
+                &symbols,
+            )?,
+            TestMode::Deno => deno::execute(
+                module,
+                &tmpdir_path,
+                cli,
+                tests,
+                &suppressed_output,
+                &symbols,
+            )?,
             TestMode::Browser { .. }
             | TestMode::DedicatedWorker { .. }
             | TestMode::SharedWorker { .. }
             | TestMode::ServiceWorker { .. } => {
+                let config = WebDriverConfig::load()?;
+                let server_config = ServerConfig::load(&cli, ws_echo)?;
+                let cross_origin = if cross_origin_server {
+                    let mut addr = server_address(headless, port, bind);
+                    addr.set_port(0);
+                    let srv = server::spawn_cross_origin(
+                        &addr,
+                        &tmpdir_path,
+                        server_config.headers.clone(),
+                        mounts.clone(),
+                        server_config.mocks.clone(),
+                        server_config.websockets.clone(),
+                        compression,
+                        log_requests,
+                    )
+                    .context("failed to spawn cross-origin server")?;
+                    let url = headless::server_url(&srv.server_addr(), public_host.as_deref());
+                    thread::spawn(|| srv.run());
+                    Some(url)
+                } else {
+                    None
+                };
                 let srv = server::spawn(
-                    &if headless {
-                        "127.0.0.1:0".parse().unwrap()
-                    } else if let Ok(address) = std::env::var("WASM_BINDGEN_TEST_ADDRESS") {
-                        address.parse().unwrap()
-                    } else {
-                        "127.0.0.1:8000".parse().unwrap()
-                    },
+                    &server_address(headless, port, bind),
                     headless,
                     module,
                     &tmpdir_path,
                     cli,
                     tests,
                     test_mode,
-                    std::env::var("WASM_BINDGEN_TEST_NO_ORIGIN_ISOLATION").is_err(),
+                    isolate_origin,
+                    server_config.headers.clone(),
+                    mounts,
+                    server_config.mocks.clone(),
+                    server_config.websockets.clone(),
+                    compression,
+                    log_requests,
+                    cross_origin,
                     benchmark,
+                    suppressed_output,
                 )
                 .context("failed to spawn server")?;
                 let addr = srv.server_addr();
@@ -538,21 +1418,144 @@ fn rmain(cli: Cli) -> anyhow::Result<()> {
                 // TODO: eventually we should provide the ability to exit at some point
                 // (gracefully) here, but for now this just runs forever.
                 if !headless {
-                    println!("Interactive browsers tests are now available at http://{addr}");
+                    let url = headless::server_url(&addr, public_host.as_deref());
+                    println!("Interactive browsers tests are now available at {url}");
                     println!();
-                    println!("Note that interactive mode is enabled because `NO_HEADLESS`");
-                    println!("is specified in the environment of this process. Once you're");
-                    println!("done with testing you'll need to kill this server with");
-                    println!("Ctrl-C.");
+                    interactive_note(debug_mode, &url, &config)?;
                     srv.run();
                     return Ok(());
                 }
 
                 thread::spawn(|| srv.run());
-                headless::run(&addr, &shell, driver_timeout, browser_timeout)?;
+                headless::run(
+                    &addr,
+                    public_host.as_deref(),
+                    &shell,
+                    &startup,
+                    driver_ports.as_ref(),
+                    browser_timeout,
+                    &artifacts,
+                    &test_names,
+                    &source_maps,
+                    &symbols,
+                    cpu_profile,
+                    flamegraphs,
+                    config,
+                )?;
             }
         }
     }
+    match size_regression {
+        Some(regression) => Err(regression),
+        None => Ok(()),
+    }
+}
+
+/// The address the test server listens on. Headless runs use any free local
+/// port, interactive runs `WASM_BINDGEN_TEST_ADDRESS` or `127.0.0.1:8000`.
+/// `--port` overrides the port and `--bind` the IP address of either.
+fn server_address(headless: bool, port: Option<u16>, bind: Option<IpAddr>) -> SocketAddr {
+    let mut addr: SocketAddr = if headless {
+        "127.0.0.1:0".parse().unwrap()
+    } else if let Ok(address) = env::var("WASM_BINDGEN_TEST_ADDRESS") {
+        address.parse().unwrap()
+    } else {
+        "127.0.0.1:8000".parse().unwrap()
+    };
+    if let Some(port) = port {
+        addr.set_port(port);
+    }
+    if let Some(bind) = bind {
+        addr.set_ip(bind);
+    }
+    addr
+}
+
+/// Parses a `--webdriver-port-range` of the form `START-END`, or a single
+/// port.
+fn parse_port_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let parse = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .map_err(|e| format!("invalid port `{port}`: {e}"))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(format!("empty port range `{s}`"));
+    }
+    Ok(start..=end)
+}
+
+/// Parses `--time-limits` of the form `WARN_MS,CRITICAL_MS`.
+fn parse_time_limits(s: &str) -> Result<(u32, u32), String> {
+    let (warn, critical) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `WARN_MS,CRITICAL_MS`, found `{s}`"))?;
+    let parse = |ms: &str| {
+        ms.trim()
+            .parse::<u32>()
+            .map_err(|e| format!("invalid time limit `{ms}`: {e}"))
+    };
+    let (warn, critical) = (parse(warn)?, parse(critical)?);
+    if warn > critical {
+        return Err(format!(
+            "the warning limit `{warn}` is above the critical one `{critical}`"
+        ));
+    }
+    Ok((warn, critical))
+}
+
+/// Parses a number of bytes like `512`, `64KiB`, `16MiB` or `1GiB`.
+fn parse_bytes(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, shift) = [("GiB", 30), ("MiB", 20), ("KiB", 10)]
+        .iter()
+        .find_map(|&(unit, shift)| Some((s.strip_suffix(unit)?, shift)))
+        .unwrap_or((s, 0));
+    let number = number
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| format!("invalid number of bytes `{s}`: {e}"))?;
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("`{s}` is too large"))
+}
+
+fn parse_mount(s: &str) -> Result<Mount, String> {
+    let (prefix, dir) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `PREFIX=DIR`, found `{s}`"))?;
+    let dir = PathBuf::from(dir);
+    if !dir.is_dir() {
+        return Err(format!("`{}` is not a directory", dir.display()));
+    }
+    let prefix = format!("/{}", prefix.trim_matches('/'));
+    Ok(Mount {
+        prefix: prefix.trim_end_matches('/').to_string(),
+        dir,
+    })
+}
+
+/// Explains why interactive mode is enabled and, with `--debug`, opens `url`
+/// in a browser with devtools.
+fn interactive_note(debug_mode: bool, url: &str, config: &WebDriverConfig) -> anyhow::Result<()> {
+    if !debug_mode {
+        println!("Note that interactive mode is enabled because `NO_HEADLESS`");
+        println!("is specified in the environment of this process. Once you're");
+        println!("done with testing you'll need to kill this server with");
+        println!("Ctrl-C.");
+        return Ok(());
+    }
+
+    if !debug::open_browser(url, config)? {
+        println!("No Chrome or Firefox was found, open the tests in a browser");
+        println!("and open its devtools yourself.");
+    }
+    println!("Interactive mode is enabled because `--debug` was passed. The");
+    println!("tests pause until `Run tests` is pressed on the page, and then");
+    println!("break in the debugger right before running. Once you're done");
+    println!("you'll need to kill this server with Ctrl-C.");
     Ok(())
 }
 
@@ -585,6 +1588,43 @@ impl TestMode {
         }
     }
 
+    /// The name of the mode in `#[wasm_bindgen_test(run_in = "...")]`.
+    fn name(self) -> &'static str {
+        match self {
+            TestMode::Node { .. } => "node",
+            TestMode::Deno => "deno",
+            TestMode::Browser { .. } => "browser",
+            TestMode::DedicatedWorker { .. } => "dedicated_worker",
+            TestMode::SharedWorker { .. } => "shared_worker",
+            TestMode::ServiceWorker { .. } => "service_worker",
+        }
+    }
+
+    /// The name of the mode encoded by `modifier` in the export name of a test.
+    fn name_of_modifier(modifier: char) -> Option<&'static str> {
+        match modifier {
+            'n' => Some("node"),
+            'b' => Some("browser"),
+            'd' => Some("dedicated_worker"),
+            's' => Some("shared_worker"),
+            'w' => Some("service_worker"),
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &str, no_modules: bool) -> anyhow::Result<Self> {
+        Ok(match name {
+            "node" => TestMode::Node { no_modules: true },
+            "deno" => TestMode::Deno,
+            "browser" => TestMode::Browser { no_modules },
+            "dedicated_worker" => TestMode::DedicatedWorker { no_modules },
+            "shared_worker" => TestMode::SharedWorker { no_modules },
+            // See `service_worker_no_modules`.
+            "service_worker" => TestMode::ServiceWorker { no_modules: true },
+            _ => bail!("invalid test mode `{name}`"),
+        })
+    }
+
     fn env(self) -> &'static str {
         match self {
             TestMode::Node { .. } => "WASM_BINDGEN_USE_NODE_EXPERIMENTAL",
@@ -597,9 +1637,109 @@ impl TestMode {
     }
 }
 
+/// The pattern of the paths of the `.profraw` files coverage data is written
+/// to, like `LLVM_PROFILE_FILE` which overrides it. By default, they are written
+/// to `target/wbg_coverage`, where `llvm-cov` can be pointed at them.
+///
+/// The `mode` the tests ran in, like `node` or `browser-firefox`, is added
+/// before the extension, so that the runs of a test binary in several modes
+/// don't overwrite each other's data and can be told apart.
+fn coverage_profile_file(mode: Option<&str>) -> anyhow::Result<PathBuf> {
+    let path = match env::var_os("LLVM_PROFILE_FILE") {
+        Some(path) => PathBuf::from(path),
+        None => env::current_dir()
+            .context("Failed to get current dir")?
+            .join("target")
+            .join("wbg_coverage")
+            .join("default_%m_%p.profraw"),
+    };
+    let Some(mode) = mode else {
+        return Ok(path);
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = match name.strip_suffix(".profraw") {
+        Some(stem) => format!("{stem}.{mode}.profraw"),
+        None => format!("{name}.{mode}"),
+    };
+    Ok(path.with_file_name(name))
+}
+
+/// Returns `path` as a JS string literal.
+fn path_literal(path: &Path) -> String {
+    serde_json::to_string(&path.display().to_string()).expect("strings serialize to JSON")
+}
+
+/// Whether captured console output is colored, following `NO_COLOR` and
+/// Cargo's `CARGO_TERM_COLOR`, or else whether stdout is a terminal.
+fn console_colors() -> bool {
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    match env::var("CARGO_TERM_COLOR").as_deref() {
+        Ok("always") => true,
+        Ok("never") => false,
+        _ => io::stdout().is_terminal(),
+    }
+}
+
 /// Possible values for the `--format` option.
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum FormatSetting {
     /// Display one character per test
     Terse,
+    /// Report a JSON event per test, like libtest's `--format json`
+    Json,
+    /// Report the tests as JUnit XML, like libtest's `--format junit`
+    Junit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_range() {
+        assert_eq!(parse_port_range("4444"), Ok(4444..=4444));
+        assert_eq!(parse_port_range("4444-4450"), Ok(4444..=4450));
+        assert_eq!(parse_port_range(" 4444 - 4450 "), Ok(4444..=4450));
+        assert_eq!(
+            parse_port_range("4450-4444"),
+            Err("empty port range `4450-4444`".to_string())
+        );
+        for garbage in ["", "port", "4444-", "-4450", "4444-4450-4460", "70000"] {
+            let error = parse_port_range(garbage).unwrap_err();
+            assert!(error.starts_with("invalid port"), "{garbage}: {error}");
+        }
+    }
+
+    #[test]
+    fn mount() {
+        let dir = tempfile::tempdir().unwrap();
+        let mount = parse_mount(&format!("/assets/={}", dir.path().display())).unwrap();
+        assert_eq!(mount.prefix, "/assets");
+        assert_eq!(mount.dir, dir.path());
+
+        let mount = parse_mount(&format!("a/b={}", dir.path().display())).unwrap();
+        assert_eq!(mount.prefix, "/a/b");
+        let mount = parse_mount(&format!("/={}", dir.path().display())).unwrap();
+        assert_eq!(mount.prefix, "");
+
+        let error = parse_mount("assets").unwrap_err();
+        assert_eq!(error, "expected `PREFIX=DIR`, found `assets`");
+        let missing = dir.path().join("missing");
+        let error = parse_mount(&format!("/assets={}", missing.display())).unwrap_err();
+        assert!(error.ends_with("is not a directory"), "{error}");
+    }
+
+    #[test]
+    fn relative_mount() {
+        // Relative directories are kept relative to the current directory,
+        // which is the crate's during tests.
+        let mount = parse_mount("/src=src").unwrap();
+        assert_eq!(mount.dir, Path::new("src"));
+        let mount = parse_mount("/src=./src/").unwrap();
+        assert_eq!(mount.dir, Path::new("./src/"));
+        let error = parse_mount("/src=no-such-dir").unwrap_err();
+        assert_eq!(error, "`no-such-dir` is not a directory");
+    }
 }
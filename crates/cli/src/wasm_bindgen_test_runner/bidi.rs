@@ -0,0 +1,126 @@
+//! Console and exception capture over WebDriver BiDi.
+//!
+//! Enabled with `WASM_BINDGEN_TEST_BIDI=1`. Instead of relying solely on the
+//! `console.*` patch of the test page, the runner subscribes to the
+//! `log.entryAdded` event of the session's BiDi connection. This also captures
+//! output logged before the harness is loaded, uncaught exceptions, and output
+//! from contexts the patch can't reach.
+
+use anyhow::{bail, Context, Error};
+use serde_json::{json, Value as Json};
+use std::env;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+/// Whether BiDi capture was requested through the environment.
+pub fn enabled() -> bool {
+    env::var_os("WASM_BINDGEN_TEST_BIDI").is_some()
+}
+
+/// Log entries collected in the background from a BiDi connection.
+pub struct LogCapture {
+    entries: Arc<Mutex<Vec<String>>>,
+}
+
+impl LogCapture {
+    /// Connects to the BiDi `url` returned when creating the session and
+    /// subscribes to log entries.
+    pub fn connect(url: &str) -> Result<LogCapture, Error> {
+        let (mut socket, _) =
+            tungstenite::connect(url).with_context(|| format!("failed to connect to `{url}`"))?;
+        let entries = Arc::new(Mutex::new(Vec::new()));
+
+        let request = json!({
+            "id": 1,
+            "method": "session.subscribe",
+            "params": { "events": ["log.entryAdded"] },
+        });
+        socket.send(Message::text(request.to_string()))?;
+        loop {
+            let message = read_json(&mut socket)?.context("BiDi connection closed")?;
+            if message["id"] == 1 {
+                if message["type"] == "error" {
+                    bail!(
+                        "failed to subscribe to BiDi log events: {}",
+                        message["message"]
+                    );
+                }
+                break;
+            }
+            handle_event(&message, &entries);
+        }
+
+        let entries_clone = Arc::clone(&entries);
+        thread::spawn(move || {
+            // The connection is closed by the driver when the session ends.
+            while let Ok(Some(message)) = read_json(&mut socket) {
+                handle_event(&message, &entries_clone);
+            }
+        });
+
+        Ok(LogCapture { entries })
+    }
+
+    /// Takes all entries captured so far.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.entries.lock().unwrap())
+    }
+}
+
+fn read_json(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> Result<Option<Json>, Error> {
+    loop {
+        match socket.read()? {
+            Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Message::Close(_) => return Ok(None),
+            _ => continue,
+        }
+    }
+}
+
+fn handle_event(message: &Json, entries: &Mutex<Vec<String>>) {
+    if message["method"] != "log.entryAdded" {
+        return;
+    }
+    if let Some(entry) = format_entry(&message["params"]) {
+        entries.lock().unwrap().push(entry);
+    }
+}
+
+/// Formats a `log.Entry`, either a console call or an uncaught exception
+/// including its stack trace.
+fn format_entry(entry: &Json) -> Option<String> {
+    let text = entry["text"].as_str().unwrap_or_default();
+    let mut out = match entry["type"].as_str()? {
+        "console" => {
+            let method = entry["method"]
+                .as_str()
+                .or(entry["level"].as_str())
+                .unwrap_or("log");
+            format!("console.{method}: {text}")
+        }
+        "javascript" => format!("uncaught exception: {text}"),
+        _ => return None,
+    };
+    if entry["type"] == "javascript" {
+        for frame in entry["stackTrace"]["callFrames"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            let function = match frame["functionName"].as_str() {
+                Some("") | None => "<anonymous>",
+                Some(name) => name,
+            };
+            out.push_str(&format!(
+                "\n    at {function} ({}:{}:{})",
+                frame["url"].as_str().unwrap_or_default(),
+                frame["lineNumber"].as_u64().unwrap_or_default() + 1,
+                frame["columnNumber"].as_u64().unwrap_or_default() + 1,
+            ));
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,138 @@
+//! Caching of the output of wasm-bindgen for test binaries.
+//!
+//! `cargo test` runs the runner over every test binary of a workspace, most of
+//! which are unchanged since the previous run in large workspaces. A [`Cache`]
+//! keeps the output of wasm-bindgen for a test binary, keyed by the hash of the
+//! Wasm file and of the options it was processed with, so that an unchanged
+//! test binary skips wasm-bindgen entirely.
+//!
+//! Each test binary and set of options has a single entry, replaced when the
+//! Wasm file changes, so that the cache doesn't grow with every build. The
+//! cache lives next to the `deps` directory of the test binaries, where
+//! `cargo clean` removes it, unless `WASM_BINDGEN_TEST_CACHE` points elsewhere.
+//! `WASM_BINDGEN_TEST_NO_CACHE` disables it.
+
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// The file of an entry holding its key, written last.
+const KEY_FILE: &str = ".wbg-cache-key";
+
+pub struct Cache {
+    /// The directory of the entry.
+    dir: PathBuf,
+    /// The hash of the Wasm file, the options and the runner.
+    key: String,
+}
+
+impl Cache {
+    /// The cache entry for the test binary `file` with the contents `wasm`,
+    /// processed with `options`, or `None` if caching is disabled.
+    pub fn new(file: &Path, wasm: &[u8], options: &str) -> Option<Cache> {
+        if env::var_os("WASM_BINDGEN_TEST_NO_CACHE").is_some() {
+            return None;
+        }
+        let root = match env::var_os("WASM_BINDGEN_TEST_CACHE") {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                let mut dir = file.parent()?;
+                if dir.ends_with("deps") {
+                    dir = dir.parent()?;
+                }
+                dir.join("wbg_cache")
+            }
+        };
+
+        let options = Sha256::digest(options);
+        let name = format!(
+            "{}-{}",
+            file.file_stem()?.to_string_lossy(),
+            hex(&options[..8])
+        );
+
+        // Entries made by another build of the runner may differ.
+        let runner = env::current_exe()
+            .and_then(fs::metadata)
+            .map(|metadata| format!("{:?} {}", metadata.modified().ok(), metadata.len()))
+            .unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(wasm);
+        hasher.update(options);
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(runner);
+
+        Some(Cache {
+            dir: root.join(name),
+            key: hex(&hasher.finalize()),
+        })
+    }
+
+    /// Copies the cached output into `out_dir`, returning whether there was
+    /// one for the current Wasm file.
+    pub fn restore(&self, out_dir: &Path) -> bool {
+        match fs::read_to_string(self.dir.join(KEY_FILE)) {
+            Ok(key) if key == self.key => {}
+            _ => return false,
+        }
+        match copy_dir(&self.dir, out_dir) {
+            Ok(()) => {
+                log::debug!(
+                    "reusing the output of wasm-bindgen in {}",
+                    self.dir.display()
+                );
+                true
+            }
+            Err(e) => {
+                log::warn!("failed to reuse {}: {e}", self.dir.display());
+                false
+            }
+        }
+    }
+
+    /// Replaces the entry with the output of wasm-bindgen in `out_dir`. The
+    /// cache is best effort, so failures are only logged.
+    pub fn store(&self, out_dir: &Path) {
+        // Another runner may be storing the same entry, so build it aside and
+        // only move it in place once complete.
+        let tmp = self.dir.with_extension(format!("tmp{}", process::id()));
+        let result = copy_dir(out_dir, &tmp)
+            .and_then(|()| fs::write(tmp.join(KEY_FILE), &self.key))
+            .and_then(|()| match fs::remove_dir_all(&self.dir) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => fs::rename(&tmp, &self.dir),
+            });
+        if let Err(e) = result {
+            log::warn!("failed to cache the output of wasm-bindgen: {e}");
+            let _ = fs::remove_dir_all(&tmp);
+        }
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_name() == KEY_FILE {
+            continue;
+        }
+        let to = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to)?;
+        } else {
+            fs::copy(entry.path(), to)?;
+        }
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
@@ -0,0 +1,309 @@
+//! Running headless tests in Chrome over the DevTools protocol.
+//!
+//! Enabled with `WASM_BINDGEN_TEST_CDP=1`. Instead of going through
+//! `chromedriver`, Chrome is launched directly with
+//! `--remote-debugging-port` and the test page is driven over the Chrome
+//! DevTools Protocol (CDP). This avoids having to keep `chromedriver` in sync
+//! with the installed Chrome version.
+
+use super::headless::{BrowserCrashed, KillOnDrop, Page};
+use super::proxy::Proxy;
+use super::webdriver::BrowserConfig;
+use anyhow::{anyhow, bail, Context, Error};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde_json::{json, Value as Json};
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::mem;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+use ureq::Agent;
+
+/// Whether the DevTools protocol was requested through the environment.
+pub fn enabled() -> bool {
+    env::var_os("WASM_BINDGEN_TEST_CDP").is_some()
+}
+
+/// A Chrome process and a DevTools connection to its only page.
+pub struct Chrome {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+    /// Whether `Page.loadEventFired` was received since the last navigation.
+    loaded: bool,
+    /// CPU profiles finished by `console.profileEnd`, with their titles.
+    cpu_profiles: Vec<(String, Json)>,
+    // Killed on drop, before the profile it uses is removed.
+    _child: KillOnDrop,
+    _profile: TempDir,
+}
+
+impl Chrome {
+    /// Launches Chrome as configured by the `chrome` section of
+    /// `webdriver.json`, waiting up to `timeout` for it to start. The DevTools
+    /// endpoint listens on `port`, or any free port if `None`.
+    pub fn launch(
+        config: &BrowserConfig,
+        timeout: Duration,
+        port: Option<u16>,
+    ) -> Result<Chrome, Error> {
+        let binary = match &config.binary {
+            Some(binary) => PathBuf::from(binary),
+            None => find_chrome()?,
+        };
+        let profile = tempfile::tempdir()?;
+
+        let mut cmd = Command::new(&binary);
+        cmd.arg(format!("--user-data-dir={}", profile.path().display()))
+            .arg(format!("--remote-debugging-port={}", port.unwrap_or(0)))
+            .arg("--no-first-run")
+            .arg("--no-default-browser-check")
+            .args(&config.args);
+        if config.headless() {
+            cmd.arg("--headless=new");
+        }
+        if let Some(proxy) = Proxy::from_env()? {
+            cmd.args(proxy.chrome_args());
+        }
+        cmd.arg("about:blank")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        log::debug!("executing {cmd:?}");
+        let mut child = KillOnDrop(
+            cmd.spawn()
+                .with_context(|| format!("failed to spawn {binary:?}"))?,
+        );
+
+        // Chrome announces the address of its DevTools endpoint on stderr.
+        // The rest of stderr is forwarded to the debug log, which also keeps
+        // the pipe from filling up.
+        let stderr = BufReader::new(child.stderr.take().unwrap());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stderr.lines() {
+                let Ok(line) = line else { break };
+                log::debug!("chrome: {line}");
+                if let Some(url) = line.strip_prefix("DevTools listening on ") {
+                    drop(tx.send(url.to_string()));
+                }
+            }
+        });
+        let browser_url = rx.recv_timeout(timeout).map_err(|_| {
+            anyhow!("Chrome failed to start its DevTools endpoint within {timeout:?}")
+        })?;
+        let page_url = page_target(&browser_url)?;
+        let (socket, _) = tungstenite::connect(&page_url)
+            .with_context(|| format!("failed to connect to `{page_url}`"))?;
+
+        let mut chrome = Chrome {
+            socket,
+            next_id: 0,
+            loaded: false,
+            cpu_profiles: Vec::new(),
+            _child: child,
+            _profile: profile,
+        };
+        // Reports `Inspector.targetCrashed` if the renderer crashes.
+        chrome.call("Inspector.enable", json!({}))?;
+        // Reports `Page.loadEventFired` once a navigation finished loading.
+        chrome.call("Page.enable", json!({}))?;
+        Ok(chrome)
+    }
+
+    /// Calls the DevTools `method` on the page, returning its result.
+    pub fn call(&mut self, method: &str, params: Json) -> Result<Json, Error> {
+        self.call_with_timeout(method, params, None)
+    }
+
+    /// Like [`Chrome::call`], but failing if no response arrives within
+    /// `timeout`.
+    pub fn call_with_timeout(
+        &mut self,
+        method: &str,
+        params: Json,
+        timeout: Option<Duration>,
+    ) -> Result<Json, Error> {
+        self.set_read_timeout(timeout)?;
+        self.next_id += 1;
+        let id = self.next_id;
+        let request = json!({ "id": id, "method": method, "params": params });
+        log::debug!("CDP {request}");
+        self.socket.send(Message::text(request.to_string()))?;
+        loop {
+            let mut message = self.read_message()?;
+            // Anything else without our `id` is an event we don't handle, or a
+            // stale response.
+            if message["id"] != id {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                bail!("`{method}` failed: {error}");
+            }
+            return Ok(message["result"].take());
+        }
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Error> {
+        if let MaybeTlsStream::Plain(stream) = self.socket.get_ref() {
+            stream.set_read_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the next message from the page, handling the events that need
+    /// to be handled whenever they arrive.
+    fn read_message(&mut self) -> Result<Json, Error> {
+        loop {
+            let text = match self.socket.read()? {
+                Message::Text(text) => text,
+                Message::Close(_) => {
+                    return Err(BrowserCrashed("DevTools connection closed".into()).into())
+                }
+                _ => continue,
+            };
+            let mut message: Json = serde_json::from_str(&text)?;
+            if message["method"] == "Inspector.targetCrashed" {
+                return Err(BrowserCrashed("the page's renderer crashed".into()).into());
+            }
+            if message["method"] == "Page.loadEventFired" {
+                self.loaded = true;
+            }
+            if message["method"] == "Profiler.consoleProfileFinished" {
+                let mut params = message["params"].take();
+                let title = params["title"].as_str().unwrap_or_default().to_string();
+                self.cpu_profiles.push((title, params["profile"].take()));
+                continue;
+            }
+            return Ok(message);
+        }
+    }
+
+    /// Records the CPU profiles started and stopped by `console.profile` and
+    /// `console.profileEnd` on the page, for [`Chrome::take_profiles`].
+    pub fn record_profiles(&mut self) -> Result<(), Error> {
+        self.call("Profiler.enable", json!({}))?;
+        Ok(())
+    }
+
+    /// Returns the CPU profiles recorded since the last call, with their
+    /// titles.
+    pub fn take_profiles(&mut self) -> Vec<(String, Json)> {
+        mem::take(&mut self.cpu_profiles)
+    }
+}
+
+impl Page for Chrome {
+    fn goto(&mut self, url: &str, timeout: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        self.loaded = false;
+        let result =
+            self.call_with_timeout("Page.navigate", json!({ "url": url }), Some(timeout))?;
+        if let Some(error) = result["errorText"].as_str() {
+            bail!("failed to navigate to `{url}`: {error}");
+        }
+
+        // The navigation only committed, so the old document may still be
+        // the one queried until the new one has loaded.
+        while !self.loaded {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| !remaining.is_zero());
+            let Some(remaining) = remaining else {
+                bail!("`{url}` didn't finish loading within {timeout:?}");
+            };
+            self.set_read_timeout(Some(remaining))?;
+            self.read_message()
+                .with_context(|| format!("`{url}` didn't finish loading within {timeout:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn text_content(&mut self, selector: &str, offset: usize) -> Result<String, Error> {
+        let expression = format!(
+            "document.querySelector({}).textContent.slice({offset})",
+            serde_json::to_string(selector)?
+        );
+        let result = self.call(
+            "Runtime.evaluate",
+            json!({ "expression": expression, "returnByValue": true }),
+        )?;
+        if let Some(details) = result.get("exceptionDetails") {
+            bail!("failed to evaluate `{expression}`: {details}");
+        }
+        match &result["result"]["value"] {
+            Json::String(s) => Ok(s.clone()),
+            Json::Null => Ok(String::new()),
+            other => bail!("unexpected result from Runtime.evaluate: {other:?}"),
+        }
+    }
+
+    fn screenshot(&mut self) -> Result<Vec<u8>, Error> {
+        let result = self.call("Page.captureScreenshot", json!({ "format": "png" }))?;
+        let data = result["data"]
+            .as_str()
+            .context("Page.captureScreenshot returned no data")?;
+        BASE64_STANDARD
+            .decode(data)
+            .context("screenshot wasn't valid base64")
+    }
+}
+
+impl Drop for Chrome {
+    fn drop(&mut self) {
+        drop(self.socket.close(None));
+    }
+}
+
+/// Finds the DevTools URL of the page target, given the URL of the browser
+/// target `ws://127.0.0.1:<port>/devtools/browser/<id>`.
+fn page_target(browser_url: &str) -> Result<String, Error> {
+    let host = browser_url
+        .strip_prefix("ws://")
+        .and_then(|rest| rest.split('/').next())
+        .with_context(|| format!("unexpected DevTools URL `{browser_url}`"))?;
+    let list = Agent::config_builder()
+        .proxy(None)
+        .build()
+        .new_agent()
+        .get(&format!("http://{host}/json/list"))
+        .call()?
+        .body_mut()
+        .read_to_string()?;
+    let targets: Vec<Json> = serde_json::from_str(&list)?;
+    targets
+        .iter()
+        .find(|target| target["type"] == "page")
+        .and_then(|target| target["webSocketDebuggerUrl"].as_str())
+        .map(str::to_string)
+        .context("Chrome has no page to run the tests in")
+}
+
+/// Searches `PATH` for a Chrome or Chromium binary.
+pub fn find_chrome() -> Result<PathBuf, Error> {
+    let names = [
+        "google-chrome",
+        "google-chrome-stable",
+        "chromium",
+        "chromium-browser",
+        "chrome",
+    ];
+    for path in env::split_paths(&env::var_os("PATH").unwrap_or_default()) {
+        for name in names {
+            let binary = path.join(name).with_extension(env::consts::EXE_EXTENSION);
+            if binary.exists() {
+                return Ok(binary);
+            }
+        }
+    }
+    bail!(
+        "failed to find Chrome in `PATH`; set `chrome.binary` in `webdriver.json` \
+         to the path of the Chrome binary"
+    )
+}
@@ -0,0 +1,381 @@
+//! Export of the coverage data collected from instrumented tests to lcov and
+//! Cobertura reports.
+//!
+//! The `.profraw` files written by the tests are merged with `llvm-profdata`
+//! and exported to lcov with `llvm-cov`, given the object files holding the
+//! coverage mapping. Cobertura reports are converted from the lcov one.
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use super::{coverage_profile_file, Cli};
+
+/// Possible values for the `--coverage-format` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// `lcov.info`, as written by `llvm-cov export -format=lcov`
+    Lcov,
+    /// `cobertura.xml`
+    Cobertura,
+}
+
+/// The coverage reports to write once the tests have run.
+pub struct Report {
+    formats: Vec<Format>,
+    objects: Vec<PathBuf>,
+    /// Whether reports of each mode are written too.
+    per_mode: bool,
+}
+
+impl Report {
+    /// Returns the reports requested with `--coverage-format`, or
+    /// `WASM_BINDGEN_TEST_COVERAGE_FORMAT`, if any.
+    pub fn new(cli: &Cli) -> Result<Option<Report>, Error> {
+        let mut formats = cli.coverage_format.clone();
+        if formats.is_empty() {
+            if let Ok(value) = env::var("WASM_BINDGEN_TEST_COVERAGE_FORMAT") {
+                for format in value.split(',').filter(|format| !format.is_empty()) {
+                    formats.push(Format::from_str(format, true).map_err(|e| {
+                        anyhow!("invalid `WASM_BINDGEN_TEST_COVERAGE_FORMAT` `{value}`: {e}")
+                    })?);
+                }
+            }
+        }
+        if formats.is_empty() || cli.list {
+            return Ok(None);
+        }
+        let mut objects = cli.coverage_object.clone();
+        if objects.is_empty() {
+            if let Some(value) = env::var_os("WASM_BINDGEN_TEST_COVERAGE_OBJECTS") {
+                objects.extend(env::split_paths(&value));
+            }
+        }
+        if objects.is_empty() {
+            bail!(
+                "exporting a coverage report requires the object files of the tests, \
+                 passed with `--coverage-object` or `WASM_BINDGEN_TEST_COVERAGE_OBJECTS`"
+            );
+        }
+        let per_mode =
+            cli.coverage_per_mode || env::var_os("WASM_BINDGEN_TEST_COVERAGE_PER_MODE").is_some();
+        Ok(Some(Report {
+            formats,
+            objects,
+            per_mode,
+        }))
+    }
+
+    /// Writes the reports of all the `.profraw` files next to the ones of this
+    /// run, so that the reports written by the last test binary of a
+    /// `cargo test` cover all of them, whichever mode or browser they ran in.
+    pub fn write(&self) -> Result<(), Error> {
+        let pattern = coverage_profile_file(None)?;
+        let dir = match pattern.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut profiles = Vec::new();
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "profraw") {
+                    profiles.push(path);
+                }
+            }
+        }
+        if profiles.is_empty() {
+            println!(
+                "warning: no coverage data was found in `{}`, make sure the tests are built \
+                 with `-Cinstrument-coverage` and `--cfg=wasm_bindgen_unstable_test_coverage`",
+                dir.display()
+            );
+            return Ok(());
+        }
+        profiles.sort();
+        self.export(&dir, &profiles, "")?;
+
+        if self.per_mode {
+            let mut modes: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+            for profile in profiles {
+                if let Some(mode) = mode(&profile) {
+                    modes.entry(mode).or_default().push(profile);
+                }
+            }
+            for (mode, profiles) in modes {
+                self.export(&dir, &profiles, &format!(".{mode}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges `profiles` and writes their reports to `dir`, with `suffix` after
+    /// the name of the files.
+    fn export(&self, dir: &Path, profiles: &[PathBuf], suffix: &str) -> Result<(), Error> {
+        let profdata = dir.join(format!("wbg{suffix}.profdata"));
+        run(Command::new(llvm_tool("llvm-profdata"))
+            .args(["merge", "-sparse"])
+            .args(profiles)
+            .arg("-o")
+            .arg(&profdata))?;
+        let lcov = export_lcov(&profdata, &self.objects)?;
+
+        for format in &self.formats {
+            let (path, contents) = match format {
+                Format::Lcov => (dir.join(format!("lcov{suffix}.info")), lcov.clone()),
+                Format::Cobertura => {
+                    let source = env::current_dir().context("Failed to get current dir")?;
+                    let path = dir.join(format!("cobertura{suffix}.xml"));
+                    (path, cobertura(&lcov, &source))
+                }
+            };
+            fs::write(&path, contents)
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+            println!("Coverage report written to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// The mode the coverage data of `profile` was collected in, which the runner
+/// adds before the extension, like `default_1_0_2.browser-firefox.profraw`.
+fn mode(profile: &Path) -> Option<String> {
+    let stem = profile.file_stem()?.to_str()?;
+    let (_, mode) = stem.rsplit_once('.')?;
+    Some(mode.to_string())
+}
+
+/// Exports the coverage of `objects` recorded in `profdata` to lcov.
+fn export_lcov(profdata: &Path, objects: &[PathBuf]) -> Result<String, Error> {
+    let mut command = Command::new(llvm_tool("llvm-cov"));
+    command
+        .args(["export", "-format=lcov"])
+        .arg(format!("-instr-profile={}", profdata.display()))
+        .arg(&objects[0]);
+    for object in &objects[1..] {
+        command.arg("-object").arg(object);
+    }
+    run(&mut command)
+}
+
+/// Runs `command`, returning its stdout.
+fn run(command: &mut Command) -> Result<String, Error> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().with_context(|| {
+        format!(
+            "failed to run `{program}`, install the `llvm-tools` component of the nightly \
+             toolchain, or point `LLVM_COV` and `LLVM_PROFDATA` at LLVM tools matching its \
+             LLVM version"
+        )
+    })?;
+    if !output.status.success() {
+        bail!(
+            "`{program}` failed with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Finds the LLVM tool `name`: from the `LLVM_COV` or `LLVM_PROFDATA`
+/// variables like `cargo-llvm-cov` does, then from the `llvm-tools` component
+/// of the toolchain, and otherwise from `PATH`.
+fn llvm_tool(name: &str) -> PathBuf {
+    if let Some(path) = env::var_os(name.to_uppercase().replace('-', "_")) {
+        return PathBuf::from(path);
+    }
+    let rustc = || Command::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()));
+    let output = |command: &mut Command| {
+        command
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let sysroot = output(rustc().args(["--print", "sysroot"]));
+    let host = output(rustc().arg("-vV")).and_then(|version| {
+        version
+            .lines()
+            .find_map(|line| line.strip_prefix("host: ").map(str::to_string))
+    });
+    if let (Some(sysroot), Some(host)) = (sysroot, host) {
+        let path = Path::new(sysroot.trim())
+            .join("lib/rustlib")
+            .join(host.trim())
+            .join("bin")
+            .join(name)
+            .with_extension(env::consts::EXE_EXTENSION);
+        if path.exists() {
+            return path;
+        }
+    }
+    PathBuf::from(name)
+}
+
+/// The coverage of a source file in an lcov report.
+#[derive(Default)]
+struct File {
+    /// Hits of each line.
+    lines: BTreeMap<u32, u64>,
+    /// Branches of each line, and how many were taken.
+    branches: BTreeMap<u32, (u32, u32)>,
+}
+
+/// Converts the `lcov` report to a Cobertura one, with paths relative to
+/// `source` and a package per directory.
+fn cobertura(lcov: &str, source: &Path) -> String {
+    let mut files: BTreeMap<String, File> = BTreeMap::new();
+    let mut current = None;
+    for line in lcov.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key {
+            "SF" => {
+                let path = Path::new(value);
+                let path = path.strip_prefix(source).unwrap_or(path);
+                let path = path.to_string_lossy().replace('\\', "/");
+                files.entry(path.clone()).or_default();
+                current = Some(path);
+            }
+            "DA" | "BRDA" => {
+                let Some(file) = current.as_ref().and_then(|path| files.get_mut(path)) else {
+                    continue;
+                };
+                let fields: Vec<_> = value.split(',').collect();
+                let Some(Ok(number)) = fields.first().map(|n| n.parse::<u32>()) else {
+                    continue;
+                };
+                if key == "DA" {
+                    let hits = fields.get(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+                    *file.lines.entry(number).or_default() += hits;
+                } else {
+                    let taken = fields.get(3).is_some_and(|n| *n != "-" && *n != "0");
+                    let branches = file.branches.entry(number).or_default();
+                    branches.0 += 1;
+                    branches.1 += u32::from(taken);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let rate = |covered: usize, valid: usize| {
+        if valid == 0 {
+            1.0
+        } else {
+            covered as f64 / valid as f64
+        }
+    };
+    // Lines covered and valid, then branches covered and valid.
+    let count = |file: &File| {
+        let lines = file.lines.values().filter(|hits| **hits > 0).count();
+        let (branches, taken) = file.branches.values().fold((0, 0), |(all, taken), branch| {
+            (all + branch.0, taken + branch.1)
+        });
+        [lines, file.lines.len(), taken as usize, branches as usize]
+    };
+    let sum = |files: &mut dyn Iterator<Item = &File>| {
+        files.map(count).fold([0; 4], |mut sum, count| {
+            for (sum, count) in sum.iter_mut().zip(count) {
+                *sum += count;
+            }
+            sum
+        })
+    };
+
+    let mut packages: BTreeMap<&str, Vec<(&str, &File)>> = BTreeMap::new();
+    for (path, file) in &files {
+        let package = path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+        packages.entry(package).or_default().push((path, file));
+    }
+
+    let [lines_covered, lines_valid, branches_covered, branches_valid] = sum(&mut files.values());
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" ?>\n");
+    xml.push_str(
+        "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">\n",
+    );
+    writeln!(
+        xml,
+        "<coverage lines-valid=\"{lines_valid}\" lines-covered=\"{lines_covered}\" \
+         line-rate=\"{:.4}\" branches-valid=\"{branches_valid}\" \
+         branches-covered=\"{branches_covered}\" branch-rate=\"{:.4}\" complexity=\"0\" \
+         version=\"1.9\" timestamp=\"{timestamp}\">",
+        rate(lines_covered, lines_valid),
+        rate(branches_covered, branches_valid),
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "  <sources>\n    <source>{}</source>\n  </sources>",
+        escape(&source.display().to_string())
+    )
+    .unwrap();
+    xml.push_str("  <packages>\n");
+    for (package, files) in &packages {
+        let [lines_covered, lines_valid, branches_covered, branches_valid] =
+            sum(&mut files.iter().map(|(_, file)| *file));
+        writeln!(
+            xml,
+            "    <package name=\"{}\" line-rate=\"{:.4}\" branch-rate=\"{:.4}\" complexity=\"0\">",
+            escape(package),
+            rate(lines_covered, lines_valid),
+            rate(branches_covered, branches_valid),
+        )
+        .unwrap();
+        xml.push_str("      <classes>\n");
+        for (path, file) in files {
+            let [lines_covered, lines_valid, branches_covered, branches_valid] = count(file);
+            let name = path.rsplit_once('/').map_or(*path, |(_, name)| name);
+            writeln!(
+                xml,
+                "        <class name=\"{}\" filename=\"{}\" line-rate=\"{:.4}\" \
+                 branch-rate=\"{:.4}\" complexity=\"0\">",
+                escape(name),
+                escape(path),
+                rate(lines_covered, lines_valid),
+                rate(branches_covered, branches_valid),
+            )
+            .unwrap();
+            xml.push_str("          <methods/>\n          <lines>\n");
+            for (number, hits) in &file.lines {
+                match file.branches.get(number) {
+                    Some(&(branches, taken)) => writeln!(
+                        xml,
+                        "            <line number=\"{number}\" hits=\"{hits}\" branch=\"true\" \
+                         condition-coverage=\"{}% ({taken}/{branches})\"/>",
+                        taken * 100 / branches.max(1),
+                    ),
+                    None => writeln!(
+                        xml,
+                        "            <line number=\"{number}\" hits=\"{hits}\" branch=\"false\"/>"
+                    ),
+                }
+                .unwrap();
+            }
+            xml.push_str("          </lines>\n        </class>\n");
+        }
+        xml.push_str("      </classes>\n    </package>\n");
+    }
+    xml.push_str("  </packages>\n</coverage>\n");
+    xml
+}
+
+/// Escapes `s` for an XML attribute or text.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
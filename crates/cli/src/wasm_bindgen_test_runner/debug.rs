@@ -0,0 +1,69 @@
+//! Opening interactive browser tests with devtools for `--debug`.
+//!
+//! Chrome is started with `--auto-open-devtools-for-tabs` and Firefox with
+//! `-devtools`. The binaries configured in `webdriver.json` take precedence
+//! over the ones found in `PATH`.
+
+use super::cdp;
+use super::webdriver::WebDriverConfig;
+use anyhow::{Context, Error};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Opens `url` in a browser with devtools, returning `false` if no supported
+/// browser was found.
+pub fn open_browser(url: &str, config: &WebDriverConfig) -> Result<bool, Error> {
+    let chrome = config.chrome.binary.as_ref().map(PathBuf::from);
+    let firefox = config.firefox.binary.as_ref().map(PathBuf::from);
+
+    if let Some(chrome) = chrome {
+        launch_chrome(&chrome, url)?;
+    } else if let Some(firefox) = firefox {
+        launch_firefox(&firefox, url)?;
+    } else if let Ok(chrome) = cdp::find_chrome() {
+        launch_chrome(&chrome, url)?;
+    } else if let Some(firefox) = find_firefox() {
+        launch_firefox(&firefox, url)?;
+    } else {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn launch_chrome(binary: &Path, url: &str) -> Result<(), Error> {
+    // A dedicated profile makes sure a new browser process is started, as an
+    // already running Chrome would ignore `--auto-open-devtools-for-tabs`.
+    let profile = env::temp_dir().join("wasm-bindgen-test-debug-profile");
+    spawn(
+        Command::new(binary)
+            .arg(format!("--user-data-dir={}", profile.display()))
+            .args(["--no-first-run", "--no-default-browser-check"])
+            .arg("--auto-open-devtools-for-tabs")
+            .arg(url),
+        binary,
+    )
+}
+
+fn launch_firefox(binary: &Path, url: &str) -> Result<(), Error> {
+    spawn(Command::new(binary).arg("-devtools").arg(url), binary)
+}
+
+fn spawn(cmd: &mut Command, binary: &Path) -> Result<(), Error> {
+    log::debug!("executing {cmd:?}");
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to spawn {binary:?}"))?;
+    Ok(())
+}
+
+fn find_firefox() -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH").unwrap_or_default())
+        .map(|path| {
+            path.join("firefox")
+                .with_extension(env::consts::EXE_EXTENSION)
+        })
+        .find(|binary| binary.exists())
+}
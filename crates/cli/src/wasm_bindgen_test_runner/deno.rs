@@ -1,13 +1,25 @@
-use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::{env, fs, process};
 
 use anyhow::{bail, Context, Error};
 
+use super::symbols::WasmSymbols;
 use super::Tests;
-use super::{node::shared_setup, Cli};
+use super::{coverage_profile_file, node::shared_setup, path_literal, Cli};
 
-pub fn execute(module: &str, tmpdir: &Path, cli: Cli, tests: Tests) -> Result<(), Error> {
+pub fn execute(
+    module: &str,
+    tmpdir: &Path,
+    cli: Cli,
+    tests: Tests,
+    suppressed_output: &Path,
+    symbols: &WasmSymbols,
+) -> Result<(), Error> {
+    let suppressed_output_dir = suppressed_output.parent().unwrap_or(suppressed_output);
+    let coverage = coverage_profile_file(Some("deno"))?;
+    let coverage_dir = coverage.parent().unwrap_or(Path::new("."));
+    let temp_dir = env::temp_dir();
     let mut js_to_execute = format!(
         r#"import * as wasm from "./{module}.js";
 
@@ -22,17 +34,34 @@ pub fn execute(module: &str, tmpdir: &Path, cli: Cli, tests: Tests) -> Result<()
     "#,
         shared_setup = shared_setup(cli.bench),
         nocapture = cli.nocapture || cli.bench,
-        args = cli.get_args(&tests),
+        args = cli.get_args(&tests, suppressed_output),
     );
 
     for test in tests.tests {
         js_to_execute.push_str(&format!("tests.push('{}')\n", test.export));
     }
 
-    js_to_execute.push_str(
+    js_to_execute.push_str(&format!(
         r#"const ok = await cx.run(tests.map(n => wasm.__wasm[n]));
+const coverage = wasm.__wbgtest_cov_dump();
+if (coverage !== undefined) {{
+    const path = wasm.__wbgtest_coverage_path({coverage}, {pid}, {temp_dir}, wasm.__wbgtest_module_signature());
+    const dir = path.replace(/[\\/][^\\/]*$/, '');
+    if (dir !== path) await Deno.mkdir(dir, {{ recursive: true }});
+    await Deno.writeFile(path, coverage);
+}}
+const suppressed_output = cx.suppressed_output();
+if (suppressed_output !== undefined) {{
+    await Deno.mkdir({dir}, {{ recursive: true }});
+    await Deno.writeTextFile({path}, suppressed_output);
+}}
 if (!ok) Deno.exit(1);"#,
-    );
+        dir = path_literal(suppressed_output_dir),
+        path = path_literal(suppressed_output),
+        coverage = path_literal(&coverage),
+        pid = process::id(),
+        temp_dir = path_literal(&temp_dir),
+    ));
 
     let js_path = tmpdir.join("run.js");
     fs::write(&js_path, js_to_execute).context("failed to write JS file")?;
@@ -58,11 +87,17 @@ if (!ok) Deno.exit(1);"#,
             .arg(&js_path)
             .args(args),
     )*/
-    let status = Command::new("deno")
+    let mut command = Command::new("deno");
+    command
         .arg("run")
         .arg("--allow-read")
-        .arg(&js_path)
-        .status()?;
+        .arg(format!(
+            "--allow-write={},{}",
+            suppressed_output_dir.display(),
+            coverage_dir.display()
+        ))
+        .arg(&js_path);
+    let status = symbols.status(&mut command)?;
 
     if !status.success() {
         bail!("Deno failed with exit_code {}", status.code().unwrap_or(1))
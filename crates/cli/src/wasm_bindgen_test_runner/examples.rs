@@ -0,0 +1,481 @@
+//! Support for running doctests on a stable toolchain.
+//!
+//! rustdoc can only build doctests for Wasm with the nightly-only
+//! `--persist-doctests` and `-Zbuild-std` flags. The `doctest` subcommand
+//! avoids rustdoc entirely: it extracts the examples from the doc comments of
+//! a crate, writes each of them as a `#[wasm_bindgen_test]` function of a
+//! generated crate depending on it, and runs that crate with `cargo test`
+//! using this binary as the runner.
+
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use serde_json::Value;
+
+#[derive(Parser)]
+#[command(
+    name = "wasm-bindgen-test-runner doctest",
+    about = "Run the doc comment examples of a crate as regular Wasm tests"
+)]
+struct DoctestCli {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Path to the Cargo.toml of the crate whose examples to run"
+    )]
+    manifest_path: Option<PathBuf>,
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "Arguments passed on to `cargo test`"
+    )]
+    cargo_args: Vec<OsString>,
+}
+
+/// A fenced Rust code block found in a doc comment.
+struct Example {
+    /// Path of the source file, relative to the crate root.
+    path: String,
+    /// Line of the opening fence.
+    line: usize,
+    code: String,
+    no_run: bool,
+    should_panic: bool,
+}
+
+/// Returns whether `args` invoke the `doctest` subcommand.
+pub fn is_subcommand(args: &[OsString]) -> bool {
+    args.get(1).is_some_and(|arg| arg == "doctest")
+}
+
+pub fn run(mut args: Vec<OsString>) -> anyhow::Result<()> {
+    // Drop the subcommand so clap sees the binary name followed by options.
+    args.remove(1);
+    let cli = match DoctestCli::try_parse_from(args) {
+        Ok(a) => a,
+        Err(e) => match e.kind() {
+            clap::error::ErrorKind::DisplayHelp | clap::error::ErrorKind::DisplayVersion => {
+                print!("{e}");
+                return Ok(());
+            }
+            _ => bail!(e),
+        },
+    };
+
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut metadata = Command::new(&cargo);
+    metadata.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(path) = &cli.manifest_path {
+        metadata.arg("--manifest-path").arg(path);
+    }
+    let output = metadata
+        .output()
+        .context("failed to run `cargo metadata`")?;
+    if !output.status.success() {
+        bail!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let metadata: Value =
+        serde_json::from_slice(&output.stdout).context("failed to parse `cargo metadata`")?;
+
+    let manifest = match &cli.manifest_path {
+        Some(path) => fs::canonicalize(path),
+        None => env::current_dir().map(|dir| dir.join("Cargo.toml")),
+    }
+    .context("failed to locate Cargo.toml")?;
+    let package = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|package| package["manifest_path"].as_str().map(Path::new) == Some(&manifest))
+        .with_context(|| {
+            format!(
+                "no package found at `{}`, pass `--manifest-path` to select one",
+                manifest.display()
+            )
+        })?;
+    let name = package["name"].as_str().context("package without a name")?;
+    let root = manifest.parent().unwrap();
+    let lib = package["targets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|target| {
+            target["kind"]
+                .as_array()
+                .is_some_and(|kinds| kinds.iter().any(|kind| kind.as_str() == Some("lib")))
+                || target["crate_types"]
+                    .as_array()
+                    .is_some_and(|types| types.iter().any(|ty| ty.as_str() == Some("rlib")))
+        })
+        .with_context(|| {
+            format!(
+                "package `{name}` has no library that other crates can use, \
+                 add \"rlib\" to its `crate-type`"
+            )
+        })?;
+    let lib_src = Path::new(lib["src_path"].as_str().context("library without a path")?);
+
+    let mut examples = Vec::new();
+    collect(root, lib_src.parent().unwrap(), &mut examples)?;
+    examples.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+
+    let target_dir = PathBuf::from(
+        metadata["target_directory"]
+            .as_str()
+            .context("missing target directory")?,
+    );
+    let crate_dir = target_dir.join("wasm-bindgen-doctests").join(name);
+    fs::create_dir_all(crate_dir.join("src"))
+        .with_context(|| format!("failed to create `{}`", crate_dir.display()))?;
+    write_if_changed(
+        &crate_dir.join("Cargo.toml"),
+        &manifest_for(package, name, root)?,
+    )?;
+    write_if_changed(&crate_dir.join("src/lib.rs"), &source_for(&examples))?;
+
+    println!(
+        "running {} doctest{} of `{name}` as Wasm tests",
+        examples.len(),
+        if examples.len() == 1 { "" } else { "s" }
+    );
+    let mut test = Command::new(&cargo);
+    test.arg("test")
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"))
+        .args(["--target", "wasm32-unknown-unknown", "--lib"])
+        .args(&cli.cargo_args)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            env::current_exe().context("failed to locate the test runner")?,
+        );
+    // Share build artifacts with the crate itself.
+    if env::var_os("CARGO_TARGET_DIR").is_none() {
+        test.env("CARGO_TARGET_DIR", &target_dir);
+    }
+    let status = test.status().context("failed to run `cargo test`")?;
+    if !status.success() {
+        bail!("some doctests failed");
+    }
+    Ok(())
+}
+
+/// Extracts the examples of all Rust files under `dir`.
+fn collect(root: &Path, dir: &Path, examples: &mut Vec<Example>) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read `{}`", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect(root, &path, examples)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read `{}`", path.display()))?;
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            extract(&relative, &source, examples);
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the fenced code blocks of the `///` and `//!` comments of a file.
+fn extract(path: &str, source: &str, examples: &mut Vec<Example>) {
+    // The fence and attributes of the open code block, if any.
+    let mut open: Option<(String, Option<Example>)> = None;
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let doc = match trimmed
+            .strip_prefix("///")
+            .filter(|rest| !rest.starts_with('/'))
+            .or_else(|| trimmed.strip_prefix("//!"))
+        {
+            Some(doc) => doc.strip_prefix(' ').unwrap_or(doc),
+            // A code block can't span beyond its doc comment.
+            None => {
+                open = None;
+                continue;
+            }
+        };
+
+        match &mut open {
+            Some((fence, example)) => {
+                if doc.trim_start().starts_with(fence.as_str()) {
+                    if let Some(example) = example.take() {
+                        examples.push(example);
+                    }
+                    open = None;
+                } else if let Some(example) = example {
+                    example.code.push_str(unhide(doc));
+                    example.code.push('\n');
+                }
+            }
+            None => {
+                let doc = doc.trim_start();
+                let fence = if doc.starts_with("```") {
+                    "```"
+                } else if doc.starts_with("~~~") {
+                    "~~~"
+                } else {
+                    continue;
+                };
+                let fence_len =
+                    doc.len() - doc.trim_start_matches(fence.as_bytes()[0] as char).len();
+                let example = parse_info(&doc[fence_len..]).map(|(no_run, should_panic)| Example {
+                    path: path.to_string(),
+                    line: index + 1,
+                    code: String::new(),
+                    no_run,
+                    should_panic,
+                });
+                open = Some((doc[..fence_len].to_string(), example));
+            }
+        }
+    }
+}
+
+/// Parses the info string of a code block the way rustdoc does.
+///
+/// Returns `None` for blocks that aren't run, and otherwise whether the block
+/// is `no_run` and `should_panic`.
+fn parse_info(info: &str) -> Option<(bool, bool)> {
+    let (mut no_run, mut should_panic) = (false, false);
+    for attr in info
+        .trim_matches(|c: char| c.is_whitespace() || c == '{' || c == '}')
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|attr| attr.trim_start_matches('.'))
+        .filter(|attr| !attr.is_empty())
+    {
+        match attr {
+            "rust" | "test_harness" | "standalone_crate" => {}
+            "no_run" => no_run = true,
+            "should_panic" => should_panic = true,
+            // Compile failures can't be checked as part of a test crate.
+            "ignore" | "compile_fail" => return None,
+            attr if attr.starts_with("edition") || attr.starts_with("ignore-") => {}
+            // Any other language.
+            _ => return None,
+        }
+    }
+    Some((no_run, should_panic))
+}
+
+/// Reveals a line hidden from the documentation with a leading `#`.
+fn unhide(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    if trimmed == "#" {
+        ""
+    } else if let Some(rest) = trimmed.strip_prefix("# ") {
+        rest
+    } else if trimmed.starts_with("##") {
+        &trimmed[1..]
+    } else {
+        line
+    }
+}
+
+/// The manifest of the generated crate, which depends on the crate itself and
+/// everything it can use in its examples.
+fn manifest_for(package: &Value, name: &str, root: &Path) -> anyhow::Result<String> {
+    let edition = package["edition"].as_str().unwrap_or("2021");
+    let mut manifest = format!(
+        "[package]\n\
+         name = \"{name}-doctests\"\n\
+         version = \"0.0.0\"\n\
+         edition = \"{edition}\"\n\
+         publish = false\n\n\
+         [lib]\n\
+         path = \"src/lib.rs\"\n\
+         doctest = false\n\n\
+         # Keep this crate out of the workspace containing the target directory.\n\
+         [workspace]\n\n\
+         [dependencies]\n\
+         {} = {{ path = {} }}\n",
+        toml_key(name),
+        toml_string(&root.to_string_lossy()),
+    );
+
+    let mut has_test_crate = false;
+    let mut targets = String::new();
+    for dep in package["dependencies"].as_array().into_iter().flatten() {
+        // Build dependencies aren't available to code, and optional ones are
+        // only usable through the features forwarded below.
+        if dep["kind"].as_str() == Some("build") || dep["optional"].as_bool() == Some(true) {
+            continue;
+        }
+        let dep_name = dep["name"].as_str().context("dependency without a name")?;
+        if dep_name == name {
+            continue;
+        }
+        has_test_crate |= dep_name == "wasm-bindgen-test";
+        let line = dependency(dep, dep_name, root);
+        match dep["target"].as_str() {
+            Some(target) => {
+                writeln!(
+                    targets,
+                    "\n[target.{}.dependencies]\n{line}",
+                    toml_string(target)
+                )?;
+            }
+            None => manifest.push_str(&line),
+        }
+    }
+    if !has_test_crate {
+        writeln!(
+            manifest,
+            "wasm-bindgen-test = \"{}\"",
+            env!("CARGO_PKG_VERSION")
+        )?;
+    }
+    manifest.push_str(&targets);
+
+    // Forward the features of the crate, so `--features` can select them.
+    manifest.push_str("\n[features]\n");
+    if let Some(features) = package["features"].as_object() {
+        for feature in features.keys() {
+            writeln!(
+                manifest,
+                "{} = [{}]",
+                toml_key(feature),
+                toml_string(&format!("{name}/{feature}"))
+            )?;
+        }
+    }
+    Ok(manifest)
+}
+
+/// Renders a dependency from `cargo metadata` as a manifest line.
+fn dependency(dep: &Value, dep_name: &str, root: &Path) -> String {
+    let mut fields = Vec::new();
+    let key = dep["rename"].as_str().unwrap_or(dep_name);
+    if key != dep_name {
+        fields.push(format!("package = {}", toml_string(dep_name)));
+    }
+    if let Some(req) = dep["req"].as_str().filter(|req| *req != "*") {
+        fields.push(format!("version = {}", toml_string(req)));
+    }
+    if let Some(path) = dep["path"].as_str() {
+        let path = root.join(path);
+        fields.push(format!("path = {}", toml_string(&path.to_string_lossy())));
+    } else if let Some(git) = dep["source"].as_str().and_then(|s| s.strip_prefix("git+")) {
+        let (url, query) = git.split_once('?').unwrap_or((git, ""));
+        let url = url.split('#').next().unwrap();
+        fields.push(format!("git = {}", toml_string(url)));
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            if matches!(key, "branch" | "tag" | "rev") {
+                fields.push(format!("{key} = {}", toml_string(value)));
+            }
+        }
+    }
+    if let Some(registry) = dep["registry"].as_str() {
+        fields.push(format!("registry-index = {}", toml_string(registry)));
+    }
+    if dep["uses_default_features"].as_bool() == Some(false) {
+        fields.push("default-features = false".to_string());
+    }
+    let features: Vec<_> = dep["features"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(toml_string)
+        .collect();
+    if !features.is_empty() {
+        fields.push(format!("features = [{}]", features.join(", ")));
+    }
+    format!("{} = {{ {} }}\n", toml_key(key), fields.join(", "))
+}
+
+/// The source of the generated crate, with one test per example.
+fn source_for(examples: &[Example]) -> String {
+    let mut source = String::from(
+        "// Generated by `wasm-bindgen-test-runner doctest`, do not edit.\n\
+         #![cfg(test)]\n\
+         #![allow(unused, clippy::all)]\n\n\
+         trait DoctestResult {\n    \
+             fn check(self);\n\
+         }\n\n\
+         impl DoctestResult for () {\n    \
+             fn check(self) {}\n\
+         }\n\n\
+         impl<E: core::fmt::Debug> DoctestResult for Result<(), E> {\n    \
+             fn check(self) {\n        \
+                 if let Err(e) = self {\n            \
+                     panic!(\"the doctest returned an error: {e:?}\");\n        \
+                 }\n    \
+             }\n\
+         }\n",
+    );
+    for example in examples {
+        let name = format!(
+            "{}_{}",
+            example
+                .path
+                .replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+            example.line
+        );
+        source.push_str(&format!("\n// {} (line {})\n", example.path, example.line));
+        if example.no_run {
+            source.push_str("#[allow(dead_code)]\n");
+        } else {
+            source.push_str("#[wasm_bindgen_test::wasm_bindgen_test]\n");
+            if example.should_panic {
+                source.push_str("#[should_panic]\n");
+            }
+        }
+        let code = example.code.trim_end();
+        let body = if code.contains("fn main()") {
+            format!("{code}\nDoctestResult::check(main());")
+        } else if code
+            .lines()
+            .last()
+            .is_some_and(|l| l.trim_start().starts_with("Ok::<"))
+        {
+            // rustdoc wraps examples ending in `Ok::<(), E>(())` in a fallible
+            // function, so they can use `?`.
+            format!(
+                "fn _inner() -> Result<(), impl core::fmt::Debug> {{\n{code}\n}}\n_inner().unwrap();"
+            )
+        } else {
+            code.to_string()
+        };
+        source.push_str(&format!("fn {name}() {{\n{body}\n}}\n"));
+    }
+    source
+}
+
+/// Writes a file unless it already has the given contents, so Cargo doesn't
+/// rebuild the generated crate needlessly.
+fn write_if_changed(path: &Path, contents: &str) -> anyhow::Result<()> {
+    if fs::read_to_string(path).ok().as_deref() != Some(contents) {
+        fs::write(path, contents)
+            .with_context(|| format!("failed to write `{}`", path.display()))?;
+    }
+    Ok(())
+}
+
+fn toml_string(s: &str) -> String {
+    // JSON strings are valid TOML basic strings.
+    serde_json::to_string(s).unwrap()
+}
+
+fn toml_key(key: &str) -> String {
+    if key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        toml_string(key)
+    }
+}
@@ -0,0 +1,305 @@
+//! Flame graphs of the CPU profiles recorded by `--cpu-profile`.
+//!
+//! Each `.cpuprofile` is folded into one line per distinct stack, like
+//! `(root);main;my_crate::parse 12`, as read by `flamegraph.pl` and
+//! `inferno`, and rendered into a standalone SVG next to it. Wasm frames are
+//! named from the module's name section and demangled.
+
+use anyhow::{Context, Error};
+use serde_json::Value as Json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::symbols::WasmSymbols;
+
+/// Height of a frame in the SVG.
+const FRAME_HEIGHT: usize = 16;
+/// Width of the SVG.
+const WIDTH: f64 = 1200.;
+/// Approximate width of a character of the labels.
+const CHAR_WIDTH: f64 = 7.;
+
+/// The flame graphs requested with `--flamegraph`.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Only draw the tests taking longer than this many milliseconds, the
+    /// soft time limit of `--report-time`.
+    pub slower_than_ms: Option<u32>,
+}
+
+impl Options {
+    /// Writes the flame graph of the CPU `profile` saved at `path`, a
+    /// `.cpuprofile` file, as `.folded` and `.svg` files next to it. Returns
+    /// whether it was written, as it isn't for fast tests.
+    pub fn write(&self, path: &Path, profile: &Json, symbols: &WasmSymbols) -> Result<bool, Error> {
+        let duration_ms = (profile["endTime"].as_f64().unwrap_or_default()
+            - profile["startTime"].as_f64().unwrap_or_default())
+            / 1000.;
+        if self
+            .slower_than_ms
+            .is_some_and(|limit| duration_ms <= f64::from(limit))
+        {
+            return Ok(false);
+        }
+
+        let title = path.file_stem().unwrap_or_default().to_string_lossy();
+        let stacks = fold(profile, symbols);
+        let mut folded = String::new();
+        for (stack, samples) in &stacks {
+            writeln!(folded, "{stack} {samples}").unwrap();
+        }
+        let folded_path = path.with_extension("folded");
+        fs::write(&folded_path, folded)
+            .with_context(|| format!("failed to write `{}`", folded_path.display()))?;
+        let svg_path = path.with_extension("svg");
+        fs::write(&svg_path, svg(&title, &stacks))
+            .with_context(|| format!("failed to write `{}`", svg_path.display()))?;
+        Ok(true)
+    }
+
+    /// Writes the flame graphs of the `.cpuprofile` files in `dir` saved since
+    /// `since`, as the Node.js runner saves them itself.
+    pub fn write_saved(
+        &self,
+        dir: &Path,
+        since: SystemTime,
+        symbols: &WasmSymbols,
+    ) -> Result<(), Error> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Ok(());
+        };
+        let mut written = 0;
+        for entry in entries {
+            let path = entry?.path();
+            let recent = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified >= since);
+            if !recent || path.extension() != Some("cpuprofile".as_ref()) {
+                continue;
+            }
+            let json = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read `{}`", path.display()))?;
+            let profile = serde_json::from_str(&json)
+                .with_context(|| format!("failed to parse `{}`", path.display()))?;
+            if self.write(&path, &profile, symbols)? {
+                written += 1;
+            }
+        }
+        self.print_written(written, dir);
+        Ok(())
+    }
+
+    /// Tells where the flame graphs of `written` tests were saved.
+    pub fn print_written(&self, written: usize, dir: &Path) {
+        if written > 0 {
+            println!(
+                "flame graphs of {written} test(s) saved to {}",
+                dir.display()
+            );
+        }
+    }
+}
+
+/// Folds the samples of `profile` by stack, from the root to the leaf frame.
+fn fold(profile: &Json, symbols: &WasmSymbols) -> BTreeMap<String, u64> {
+    let empty = Vec::new();
+    let nodes = profile["nodes"].as_array().unwrap_or(&empty);
+    let mut frames = HashMap::new();
+    let mut parents = HashMap::new();
+    for node in nodes {
+        let Some(id) = node["id"].as_u64() else {
+            continue;
+        };
+        frames.insert(id, frame_name(&node["callFrame"], symbols));
+        for child in node["children"].as_array().unwrap_or(&empty) {
+            if let Some(child) = child.as_u64() {
+                parents.insert(child, id);
+            }
+        }
+    }
+
+    let mut samples = HashMap::new();
+    for sample in profile["samples"].as_array().unwrap_or(&empty) {
+        if let Some(id) = sample.as_u64() {
+            *samples.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    let mut stacks = BTreeMap::new();
+    for (id, count) in samples {
+        let mut stack = Vec::new();
+        let mut node = Some(id);
+        while let Some(id) = node {
+            if let Some(frame) = frames.get(&id) {
+                stack.push(frame.as_str());
+            }
+            node = parents.get(&id).copied();
+        }
+        stack.reverse();
+        *stacks.entry(stack.join(";")).or_insert(0) += count;
+    }
+    stacks
+}
+
+/// The name of the function of a `callFrame`, with Wasm functions named from
+/// the name section and demangled.
+fn frame_name(frame: &Json, symbols: &WasmSymbols) -> String {
+    let name = frame["functionName"].as_str().unwrap_or_default();
+    let is_wasm = frame["url"]
+        .as_str()
+        .is_some_and(|url| url.starts_with("wasm://"));
+    let name = if is_wasm {
+        let bare = name.strip_prefix('$').unwrap_or(name);
+        let index = bare
+            .strip_prefix("func")
+            .or_else(|| {
+                bare.strip_prefix("wasm-function[")
+                    .and_then(|rest| rest.strip_suffix(']'))
+            })
+            .and_then(|index| index.parse().ok());
+        match index.and_then(|index| symbols.name(index)) {
+            Some(name) => name.to_string(),
+            None => match rustc_demangle::try_demangle(bare) {
+                Ok(demangled) => format!("{demangled:#}"),
+                Err(_) => bare.to_string(),
+            },
+        }
+    } else if name.is_empty() {
+        "(anonymous)".to_string()
+    } else {
+        name.to_string()
+    };
+    // `;` separates the frames of folded stacks.
+    name.replace(';', ":")
+}
+
+/// A frame of the flame graph, with the number of samples in it and its
+/// callees.
+#[derive(Default)]
+struct Frame {
+    samples: u64,
+    children: BTreeMap<String, Frame>,
+}
+
+impl Frame {
+    fn depth(&self) -> usize {
+        1 + self.children.values().map(Frame::depth).max().unwrap_or(0)
+    }
+}
+
+/// Renders the folded `stacks` into an SVG flame graph, with the root at the
+/// bottom and the callees of each frame above it, ordered by name.
+fn svg(title: &str, stacks: &BTreeMap<String, u64>) -> String {
+    let mut root = Frame::default();
+    for (stack, &samples) in stacks {
+        root.samples += samples;
+        let mut frame = &mut root;
+        for name in stack.split(';') {
+            frame = frame.children.entry(name.to_string()).or_default();
+            frame.samples += samples;
+        }
+    }
+
+    let depth = root.depth();
+    let height = (depth + 2) * FRAME_HEIGHT + 8;
+    let mut out = String::new();
+    writeln!(
+        out,
+        r##"<?xml version="1.0" standalone="no"?>
+<svg version="1.1" width="{WIDTH}" height="{height}" viewBox="0 0 {WIDTH} {height}" xmlns="http://www.w3.org/2000/svg">
+<style>text {{ font-family: monospace; font-size: 12px; }} g:hover rect {{ stroke: black; }}</style>
+<rect width="100%" height="100%" fill="#f8f8f8"/>
+<text x="{}" y="16" text-anchor="middle">{}</text>"##,
+        WIDTH / 2.,
+        escape(title)
+    )
+    .unwrap();
+    if root.samples > 0 {
+        let scale = (WIDTH - 20.) / root.samples as f64;
+        let mut x = 10.;
+        for (name, frame) in &root.children {
+            draw(
+                &mut out,
+                name,
+                frame,
+                x,
+                height - FRAME_HEIGHT - 8,
+                scale,
+                root.samples,
+            );
+            x += frame.samples as f64 * scale;
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Draws `frame` with its bottom left corner at `x`, `y`, and its callees
+/// above it.
+fn draw(out: &mut String, name: &str, frame: &Frame, x: f64, y: usize, scale: f64, total: u64) {
+    let width = frame.samples as f64 * scale;
+    if width < 0.1 {
+        return;
+    }
+    let top = y - FRAME_HEIGHT;
+    let (r, g, b) = color(name);
+    let percent = frame.samples as f64 / total as f64 * 100.;
+    writeln!(
+        out,
+        r#"<g><title>{} ({} samples, {percent:.2}%)</title><rect x="{x:.1}" y="{top}" width="{width:.1}" height="{}" fill="rgb({r},{g},{b})" rx="2"/>"#,
+        escape(name),
+        frame.samples,
+        FRAME_HEIGHT - 1,
+    )
+    .unwrap();
+    let chars = ((width - 6.) / CHAR_WIDTH) as usize;
+    if chars >= 3 {
+        let label = if name.chars().count() > chars {
+            let mut label: String = name.chars().take(chars - 2).collect();
+            label.push_str("..");
+            label
+        } else {
+            name.to_string()
+        };
+        writeln!(
+            out,
+            r#"<text x="{:.1}" y="{}">{}</text>"#,
+            x + 3.,
+            top + FRAME_HEIGHT - 4,
+            escape(&label)
+        )
+        .unwrap();
+    }
+    out.push_str("</g>\n");
+
+    let mut child_x = x;
+    for (child_name, child) in &frame.children {
+        draw(out, child_name, child, child_x, top, scale, total);
+        child_x += child.samples as f64 * scale;
+    }
+}
+
+/// A warm color derived from `name`, so that a function keeps its color
+/// across flame graphs.
+fn color(name: &str) -> (u8, u8, u8) {
+    // FNV-1a
+    let hash = name.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    let [a, b, c, ..] = hash.to_le_bytes();
+    (
+        205 + a % 50,
+        (f64::from(b) / 255. * 230.) as u8,
+        (f64::from(c) / 255. * 55.) as u8,
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
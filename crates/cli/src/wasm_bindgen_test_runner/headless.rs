@@ -1,13 +1,26 @@
+use super::bidi::{self, LogCapture};
+use super::cdp::{self, Chrome};
+use super::flamegraph;
+use super::proxy::{self, Proxy};
 use super::shell::Shell;
-use anyhow::{bail, Context, Error};
+use super::symbols::WasmSymbols;
+use super::video::Recorder;
+use super::webdriver::{apply_browser_config, BrowserConfig, Capabilities, WebDriverConfig};
+use crate::source_map::SourceMaps;
+use anyhow::{anyhow, bail, Context, Error};
+use base64::{prelude::BASE64_STANDARD, Engine};
 use log::{debug, warn};
 use rouille::url::Url;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Map, Value as Json};
+use serde_json::{json, Value as Json};
+use std::borrow::Cow;
 use std::env;
-use std::fs::File;
+use std::fmt;
+use std::fs;
 use std::io::{self, Cursor, ErrorKind, Read, Write};
+use std::mem;
 use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::ops::{Deref, DerefMut, RangeInclusive};
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -16,9 +29,6 @@ use std::thread;
 use std::time::{Duration, Instant};
 use ureq::Agent;
 
-/// Options that can use to customize and configure a WebDriver session.
-type Capabilities = Map<String, Json>;
-
 /// Wrapper for [`Capabilities`] used in `--w3c` mode.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct SpecNewSessionParameters {
@@ -53,16 +63,94 @@ pub struct LegacyNewSessionParameters {
 /// Execute a headless browser tests against a server running on `server`
 /// address.
 ///
+/// The URL the browser reaches the test server listening on `server` by:
+/// `public_host` if given, and the loopback address if the server listens on
+/// all interfaces.
+pub fn server_url(server: &SocketAddr, public_host: Option<&str>) -> String {
+    match public_host {
+        Some(host) => format!("http://{host}:{}", server.port()),
+        None if server.ip().is_unspecified() => {
+            let loopback = match server {
+                SocketAddr::V4(_) => "127.0.0.1",
+                SocketAddr::V6(_) => "[::1]",
+            };
+            format!("http://{loopback}:{}", server.port())
+        }
+        None => format!("http://{server}"),
+    }
+}
+
 /// This function will take care of everything from spawning the WebDriver
 /// binary, controlling it, running tests, scraping output, displaying output,
 /// etc. It will return `Ok` if all tests finish successfully, and otherwise it
 /// will return an error if some tests failed.
+///
+/// A screenshot is saved to `artifacts` for every test that fails. `tests` are
+/// the names of the tests run by the page, used to report the ones cut short if
+/// the browser crashes.
 pub fn run(
     server: &SocketAddr,
+    public_host: Option<&str>,
     shell: &Shell,
-    driver_timeout: u64,
+    startup: &StartupPolicy,
+    driver_ports: Option<&RangeInclusive<u16>>,
     test_timeout: u64,
+    artifacts: &Path,
+    tests: &[String],
+    source_maps: &SourceMaps,
+    symbols: &WasmSymbols,
+    cpu_profile: bool,
+    flamegraphs: Option<flamegraph::Options>,
+    mut config: WebDriverConfig,
 ) -> Result<(), Error> {
+    // Visit our local server to open up the page that runs tests, and then get
+    // some handles to objects on the page which we'll be scraping output from.
+    //
+    // If WASM_BINDGEN_TEST_ADDRESS is set, use it as the local server URL,
+    // trying to inherit the port from the server if it isn't specified.
+    let url = match std::env::var("WASM_BINDGEN_TEST_ADDRESS") {
+        Ok(u) => {
+            let mut url = Url::parse(&u)?;
+            if url.port().is_none() {
+                url.set_port(Some(server.port())).unwrap();
+            }
+            url.to_string()
+        }
+        Err(_) => server_url(server, public_host),
+    };
+
+    if cdp::enabled() {
+        config.chrome.args.push(crash_dumps_arg(artifacts));
+        shell.status("Launching Chrome...");
+        let mut chrome = startup.driver.retry(|| {
+            let port = match driver_ports {
+                Some(ports) => Some(free_port(ports)?.port()),
+                None => None,
+            };
+            Chrome::launch(&config.chrome, startup.driver.timeout, port)
+        })?;
+        if cpu_profile {
+            chrome.record_profiles()?;
+        }
+        println!("Running headless tests in Chrome over the DevTools protocol");
+        let result = run_tests(
+            &mut chrome,
+            &url,
+            shell,
+            startup,
+            test_timeout,
+            artifacts,
+            tests,
+            source_maps,
+            symbols,
+            config.chrome.headless(),
+            None,
+            &mut || (),
+        );
+        save_profiles(chrome.take_profiles(), artifacts, flamegraphs, symbols)?;
+        return result;
+    }
+
     let driver = Driver::find()?;
     let mut drop_log: Box<dyn FnMut()> = Box::new(|| ());
     let driver_url = match driver.location() {
@@ -70,41 +158,46 @@ pub fn run(
         Locate::Local((path, args)) => {
             // Wait for the driver to come online and bind its port before we try to
             // connect to it.
-            let start = Instant::now();
-            let max = Duration::new(driver_timeout, 0);
-
-            let (driver_addr, mut child) = 'outer: loop {
-                // Allow tests to run in parallel (in theory) by finding any open port
-                // available for our driver. We can't bind the port for the driver, but
-                // hopefully the OS gives this invocation unique ports across processes
-                let driver_addr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
-                // Spawn the driver binary, collecting its stdout/stderr in separate
-                // threads. We'll print this output later.
-                let mut cmd = Command::new(path);
-                cmd.args(args).arg(format!("--port={}", driver_addr.port()));
-                let mut child = BackgroundChild::spawn(path, &mut cmd, shell)?;
-
-                // Wait for the driver to come online and bind its port before we try to
-                // connect to it.
+            let (driver_addr, mut child) = startup.driver.retry(|| {
+                let start = Instant::now();
+                let max = startup.driver.timeout;
                 loop {
-                    if child.has_failed() {
-                        if start.elapsed() >= max {
-                            bail!("driver failed to start")
+                    // Allow tests to run in parallel (in theory) by finding any open port
+                    // available for our driver. We can't bind the port for the driver, but
+                    // hopefully the OS gives this invocation unique ports across processes
+                    let driver_addr = match driver_ports {
+                        Some(ports) => free_port(ports)?,
+                        None => TcpListener::bind("127.0.0.1:0")?.local_addr()?,
+                    };
+                    // Spawn the driver binary, collecting its stdout/stderr in separate
+                    // threads. We'll print this output later.
+                    let mut cmd = Command::new(path);
+                    cmd.args(args).arg(format!("--port={}", driver_addr.port()));
+                    proxy::bypass_loopback(&mut cmd);
+                    let mut child = BackgroundChild::spawn(path, &mut cmd, shell)?;
+
+                    // Wait for the driver to come online and bind its port before we try to
+                    // connect to it.
+                    loop {
+                        if child.has_failed() {
+                            if start.elapsed() >= max {
+                                bail!("driver failed to start within {max:?}")
+                            }
+
+                            println!("Failed to start driver, trying again ...");
+
+                            thread::sleep(Duration::from_millis(100));
+                            break;
+                        } else if TcpStream::connect(driver_addr).is_ok() {
+                            return Ok((driver_addr, child));
+                        } else if start.elapsed() >= max {
+                            bail!("driver failed to bind port within {max:?}")
+                        } else {
+                            thread::sleep(Duration::from_millis(100));
                         }
-
-                        println!("Failed to start driver, trying again ...");
-
-                        thread::sleep(Duration::from_millis(100));
-                        break;
-                    } else if TcpStream::connect(driver_addr).is_ok() {
-                        break 'outer (driver_addr, child);
-                    } else if start.elapsed() >= max {
-                        bail!("driver failed to bind port during startup")
-                    } else {
-                        thread::sleep(Duration::from_millis(100));
                     }
                 }
-            };
+            })?;
 
             drop_log = Box::new(move || {
                 let _ = &child;
@@ -120,48 +213,181 @@ pub fn run(
         driver_url.as_str(),
     );
 
+    // A local driver is reached directly even if a proxy is configured for
+    // the browser.
+    let agent = match driver.location() {
+        Locate::Local(_) => Agent::config_builder().proxy(None).build().new_agent(),
+        Locate::Remote(_) => Agent::new_with_defaults(),
+    };
     let mut client = Client {
-        agent: Agent::new_with_defaults(),
+        agent,
         driver_url,
         session: None,
+        web_socket_url: None,
+        request_timeout: None,
     };
-    println!("Try find `webdriver.json` for configure browser's capabilities:");
-    let capabilities: Capabilities = match File::open(
-        std::env::var("WASM_BINDGEN_TEST_WEBDRIVER_JSON").unwrap_or("webdriver.json".to_string()),
-    ) {
-        Ok(file) => {
-            println!("Ok");
-            serde_json::from_reader(file)
-        }
-        Err(_) => {
-            println!("Not found");
-            Ok(Capabilities::new())
-        }
-    }?;
+    config.chrome.args.push(crash_dumps_arg(artifacts));
+    config.edge.args.push(crash_dumps_arg(artifacts));
+    let headless = driver.config(&config).is_some_and(BrowserConfig::headless);
     shell.status("Starting new webdriver session...");
     // Allocate a new session with the webdriver protocol, and once we've done
     // so schedule the browser to get closed with a call to `close_window`.
-    let id = client.new_session(&driver, capabilities)?;
+    client.request_timeout = Some(startup.session.timeout);
+    let id = startup
+        .session
+        .retry(|| client.new_session(&driver, &config))?;
+    client.request_timeout = None;
     client.session = Some(id.clone());
 
-    // Visit our local server to open up the page that runs tests, and then get
-    // some handles to objects on the page which we'll be scraping output from.
-    //
-    // If WASM_BINDGEN_TEST_ADDRESS is set, use it as the local server URL,
-    // trying to inherit the port from the server if it isn't specified.
-    let url = match std::env::var("WASM_BINDGEN_TEST_ADDRESS") {
-        Ok(u) => {
-            let mut url = Url::parse(&u)?;
-            if url.port().is_none() {
-                url.set_port(Some(server.port())).unwrap();
-            }
-            url.to_string()
+    let log_capture = match &client.web_socket_url {
+        Some(url) => Some(LogCapture::connect(url)?),
+        None if bidi::enabled() => {
+            println!(
+                "{} does not support WebDriver BiDi, falling back to capturing \
+                 console output on the page",
+                driver.browser()
+            );
+            None
         }
-        Err(_) => format!("http://{server}"),
+        None => None,
     };
 
+    run_tests(
+        &mut client,
+        &url,
+        shell,
+        startup,
+        test_timeout,
+        artifacts,
+        tests,
+        source_maps,
+        symbols,
+        headless,
+        log_capture.as_ref(),
+        &mut *drop_log,
+    )
+}
+
+/// Timeout and number of retries of one stage of starting a headless test
+/// run.
+#[derive(Clone, Copy, Debug)]
+pub struct Stage {
+    name: &'static str,
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl Stage {
+    /// Reads `WASM_BINDGEN_TEST_<var>_TIMEOUT`, in seconds, and
+    /// `WASM_BINDGEN_TEST_<var>_RETRIES`, falling back to the given defaults.
+    fn from_env(name: &'static str, var: &str, timeout: u64, retries: u32) -> Result<Stage, Error> {
+        let timeout_var = format!("WASM_BINDGEN_TEST_{var}_TIMEOUT");
+        let timeout = match env::var(&timeout_var) {
+            Ok(timeout) => timeout
+                .parse()
+                .with_context(|| format!("could not parse `{timeout_var}`"))?,
+            Err(_) => timeout,
+        };
+        let retries_var = format!("WASM_BINDGEN_TEST_{var}_RETRIES");
+        let retries = match env::var(&retries_var) {
+            Ok(retries) => retries
+                .parse()
+                .with_context(|| format!("could not parse `{retries_var}`"))?,
+            Err(_) => retries,
+        };
+        Ok(Stage {
+            name,
+            timeout: Duration::from_secs(timeout),
+            retries,
+        })
+    }
+
+    /// Runs `f`, retrying it up to `retries` times. The error of the last
+    /// attempt names the stage that failed.
+    pub fn retry<T>(&self, mut f: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    println!(
+                        "{} failed, retrying ({attempt}/{}): {e:#}",
+                        self.name, self.retries
+                    );
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "{} failed after {} attempt(s) with a timeout of {}s each",
+                        self.name,
+                        attempt + 1,
+                        self.timeout.as_secs(),
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Timeouts and retries of starting a headless test run, configured through
+/// `WASM_BINDGEN_TEST_{DRIVER,SESSION,NAVIGATION}_{TIMEOUT,RETRIES}`.
+#[derive(Clone, Copy, Debug)]
+pub struct StartupPolicy {
+    /// Starting the WebDriver binary, or Chrome in CDP mode.
+    pub driver: Stage,
+    /// Creating the WebDriver session.
+    pub session: Stage,
+    /// Navigating to the test page.
+    pub navigation: Stage,
+}
+
+impl StartupPolicy {
+    pub fn from_env() -> Result<StartupPolicy, Error> {
+        Ok(StartupPolicy {
+            driver: Stage::from_env("driver startup", "DRIVER", 5, 0)?,
+            session: Stage::from_env("session creation", "SESSION", 60, 0)?,
+            navigation: Stage::from_env("page navigation", "NAVIGATION", 60, 0)?,
+        })
+    }
+}
+
+/// Operations on the page running the tests, implemented by both the
+/// WebDriver [`Client`] and the DevTools protocol [`Chrome`] backends.
+pub trait Page {
+    /// Navigates to `url`, failing if it takes longer than `timeout`.
+    fn goto(&mut self, url: &str, timeout: Duration) -> Result<(), Error>;
+
+    /// Returns the text content of the element matching `selector`, starting
+    /// at `offset`.
+    fn text_content(&mut self, selector: &str, offset: usize) -> Result<String, Error>;
+
+    /// Takes a PNG screenshot of the page.
+    fn screenshot(&mut self) -> Result<Vec<u8>, Error>;
+}
+
+/// Runs the tests served at `url` in `page`, streaming their output.
+///
+/// `drop_log` is called once the harness has reported a result, at which point
+/// the output of the browser or driver process isn't needed for debugging.
+fn run_tests(
+    page: &mut dyn Page,
+    url: &str,
+    shell: &Shell,
+    startup: &StartupPolicy,
+    test_timeout: u64,
+    artifacts: &Path,
+    tests: &[String],
+    source_maps: &SourceMaps,
+    symbols: &WasmSymbols,
+    headless: bool,
+    log_capture: Option<&LogCapture>,
+    drop_log: &mut dyn FnMut(),
+) -> Result<(), Error> {
+    let mut recorder = Recorder::start(artifacts, headless)?;
+
     shell.status(&format!("Visiting {url}..."));
-    client.goto(&id, &url)?;
+    let navigation = startup.navigation;
+    navigation.retry(|| page.goto(url, navigation.timeout))?;
     shell.status("Loading page elements...");
 
     // At this point we need to wait for the test to finish before we can take a
@@ -180,40 +406,104 @@ pub fn run(
     shell.status("Waiting for test to finish...");
     let start = Instant::now();
     let max = Duration::new(test_timeout, 0);
-    let mut shell_cleared = false;
+    // Number of tests reported as finished, shown on the status line.
+    let mut finished = 0;
     let mut output_buf = String::new();
+    // Offset up to which `output_buf` has been scanned for failed tests.
+    let mut scanned = 0;
+    let mut screenshots = Vec::new();
+    let mut network_errors = NetworkErrors::default();
+    let mut bidi_logs = log_capture.map(BidiLogs::new);
+    let mut streams = OutputStreams::new(source_maps, symbols);
     while start.elapsed() < max {
-        let new_output = client.text_content(&id, "#output", output_buf.len())?;
+        // Read network errors and log entries first, so they're attributed to
+        // the test that reports next rather than the one that reported before
+        // them.
+        network_errors.poll(page)?;
+        if let Some(logs) = &mut bidi_logs {
+            logs.poll();
+        }
+        let new_output = page
+            .text_content("#output", output_buf.len())
+            .map_err(|e| check_crash(e, &output_buf, tests, artifacts))?;
 
         // Print new output as it appears (real-time streaming)
         if !new_output.is_empty() {
-            // Clear shell status before first output so they don't mix
-            if !shell_cleared {
-                shell.clear();
-                shell_cleared = true;
-            }
-            io::stdout().lock().write_all(new_output.as_bytes())?;
+            // Clear shell status before output so they don't mix
+            shell.clear();
+            streams.write(&new_output)?;
             output_buf.push_str(&new_output);
+
+            // Take a screenshot as soon as a test is reported as failed, while
+            // the page is hopefully still showing what the test left behind.
+            for line in complete_lines(&output_buf, &mut scanned) {
+                network_errors.attribute(&line);
+                if let Some(logs) = &mut bidi_logs {
+                    logs.attribute(&line);
+                }
+                if let Some((name, failed)) = test_result(&line) {
+                    finished += 1;
+                    if failed {
+                        match save_screenshot(page, artifacts, &name) {
+                            Ok(path) => screenshots.push((name.into_owned(), path)),
+                            Err(e) => warn!("failed to take screenshot of `{name}`: {e:?}"),
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(recorder) = &mut recorder {
+            if let Err(e) = recorder.capture(|| page.screenshot()) {
+                warn!("failed to record frame: {e:?}");
+            }
         }
 
         if output_buf.contains("test result: ") {
             break;
         }
+
+        // Keep showing progress while long tests run. Output is only written
+        // a line at a time, so the status can't garble it.
+        shell.status(&format!(
+            "Running tests ({finished}/{} finished, {}s)...",
+            tests.len(),
+            start.elapsed().as_secs()
+        ));
         thread::sleep(Duration::from_millis(100));
     }
-    if !shell_cleared {
-        shell.clear();
-    }
+    shell.clear();
 
     // Tests have now finished or have timed out. At this point we need to check
     // what happened. Output was already streamed in real-time above.
 
     // Print any remaining output that might have arrived after the last poll
-    let remaining_output = client.text_content(&id, "#output", output_buf.len())?;
+    let remaining_output = page
+        .text_content("#output", output_buf.len())
+        .map_err(|e| check_crash(e, &output_buf, tests, artifacts))?;
     if !remaining_output.is_empty() {
-        io::stdout().lock().write_all(remaining_output.as_bytes())?;
+        streams.write(&remaining_output)?;
         output_buf.push_str(&remaining_output);
     }
+    streams.flush()?;
+    network_errors.poll(page)?;
+    if let Some(logs) = &mut bidi_logs {
+        logs.poll();
+    }
+    for line in complete_lines(&output_buf, &mut scanned) {
+        network_errors.attribute(&line);
+        if let Some(logs) = &mut bidi_logs {
+            logs.attribute(&line);
+        }
+    }
+    network_errors.print();
+
+    if let Some(recorder) = recorder {
+        match recorder.finish() {
+            Ok(path) => println!("recording saved to {}", path.display()),
+            Err(e) => warn!("failed to save recording: {e:?}"),
+        }
+    }
 
     if output_buf.contains("test result: ") {
         // If the tests harness finished (either successfully or unsuccessfully)
@@ -229,7 +519,7 @@ pub fn run(
         let mut has_console = false;
         let mut console_offset = 0;
         loop {
-            let chunk = client.text_content(&id, "#console_output", console_offset)?;
+            let chunk = page.text_content("#console_output", console_offset)?;
             if chunk.is_empty() {
                 break;
             }
@@ -240,13 +530,180 @@ pub fn run(
             io::stdout().lock().write_all(tab(&chunk).as_bytes())?;
             console_offset += chunk.len();
         }
+        if let Some(logs) = bidi_logs {
+            logs.print()?;
+        }
+
+        return Err(tests_failed(&screenshots));
+    }
+
+    Ok(())
+}
+
+/// Finds a free local port in `ports`, for `--webdriver-port-range`.
+fn free_port(ports: &RangeInclusive<u16>) -> Result<SocketAddr, Error> {
+    ports
+        .clone()
+        .find_map(|port| {
+            TcpListener::bind(("127.0.0.1", port))
+                .ok()?
+                .local_addr()
+                .ok()
+        })
+        .with_context(|| {
+            format!(
+                "no free port in the WebDriver port range {}-{}",
+                ports.start(),
+                ports.end()
+            )
+        })
+}
+
+/// Where Chrome and Edge write minidumps when they crash.
+fn crash_dumps_dir(artifacts: &Path) -> PathBuf {
+    artifacts.join("crashes")
+}
+
+fn crash_dumps_arg(artifacts: &Path) -> String {
+    format!("--crash-dumps-dir={}", crash_dumps_dir(artifacts).display())
+}
+
+/// The error reported when the browser or the page's renderer crashed, or
+/// the driver lost the session because of it.
+#[derive(Debug)]
+pub struct BrowserCrashed(pub String);
 
-        bail!("some tests failed")
+impl fmt::Display for BrowserCrashed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
+}
+
+impl std::error::Error for BrowserCrashed {}
+
+/// Checks whether `error`, returned while polling the test page, means the
+/// browser or the page's renderer crashed. If so the crash is reported along
+/// with any minidumps and the scheduled `tests` that were cut short, so it
+/// isn't mistaken for a timeout.
+fn check_crash(error: Error, output: &str, tests: &[String], artifacts: &Path) -> Error {
+    if !is_crash(&error) {
+        return error;
+    }
+
+    println!();
+    println!("The browser crashed: {error:#}");
+    let dumps = fs::read_dir(crash_dumps_dir(artifacts))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dmp"));
+    for dump in dumps {
+        println!("minidump: {}", dump.display());
+    }
+
+    let crashed = crashed_tests(output, tests);
+    if !crashed.is_empty() {
+        println!("tests that did not finish because of the crash:");
+        for test in crashed {
+            println!("    {test} ... CRASHED");
+        }
+    }
+
+    error.context("the browser crashed while running tests")
+}
 
+/// Whether `error` was caused by a [`BrowserCrashed`] reported by the driver
+/// or DevTools.
+fn is_crash(error: &Error) -> bool {
+    error.chain().any(|e| e.is::<BrowserCrashed>())
+}
+
+/// The tests of `scheduled` that `output` has no result line for.
+fn crashed_tests<'a>(output: &str, scheduled: &'a [String]) -> Vec<&'a str> {
+    let reported: Vec<_> = output
+        .lines()
+        .filter_map(test_result)
+        .map(|(name, _)| name)
+        .collect();
+    scheduled
+        .iter()
+        .map(String::as_str)
+        .filter(|test| !reported.iter().any(|name| name == test))
+        .collect()
+}
+
+/// The name of the artifacts of `test`, with the characters that aren't safe
+/// in file names replaced by `_`.
+fn artifact_name(test: &str) -> String {
+    test.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Takes a screenshot of `page` and saves it as `<artifacts>/<test>.png`,
+/// returning the path it was saved to.
+fn save_screenshot(page: &mut dyn Page, artifacts: &Path, test: &str) -> Result<PathBuf, Error> {
+    let png = page.screenshot()?;
+    let name = artifact_name(test);
+    fs::create_dir_all(artifacts)
+        .with_context(|| format!("failed to create `{}`", artifacts.display()))?;
+    let path = artifacts.join(format!("{name}.png"));
+    fs::write(&path, png).with_context(|| format!("failed to write `{}`", path.display()))?;
+    Ok(path)
+}
+
+/// Saves the CPU profiles recorded by `--cpu-profile` as
+/// `<artifacts>/<test>.cpuprofile`, along with their flame graphs.
+fn save_profiles(
+    profiles: Vec<(String, Json)>,
+    artifacts: &Path,
+    flamegraphs: Option<flamegraph::Options>,
+    symbols: &WasmSymbols,
+) -> Result<(), Error> {
+    if profiles.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(artifacts)
+        .with_context(|| format!("failed to create `{}`", artifacts.display()))?;
+    let mut written = 0;
+    for (test, profile) in &profiles {
+        let path = artifacts.join(format!("{}.cpuprofile", artifact_name(test)));
+        fs::write(&path, profile.to_string())
+            .with_context(|| format!("failed to write `{}`", path.display()))?;
+        if let Some(options) = &flamegraphs {
+            if options.write(&path, profile, symbols)? {
+                written += 1;
+            }
+        }
+    }
+    println!(
+        "CPU profiles of {} test(s) saved to {}",
+        profiles.len(),
+        artifacts.display()
+    );
+    if let Some(options) = &flamegraphs {
+        options.print_written(written, artifacts);
+    }
     Ok(())
 }
 
+/// The error of a run with failed tests, listing the screenshots taken of
+/// them.
+fn tests_failed(screenshots: &[(String, PathBuf)]) -> Error {
+    if !screenshots.is_empty() {
+        println!("screenshots of failed tests:");
+        for (name, path) in screenshots {
+            println!("    {name}: {}", path.display());
+        }
+    }
+
+    anyhow!("some tests failed")
+}
+
 enum Driver {
     Gecko(Locate),
     Safari(Locate),
@@ -362,6 +819,17 @@ an issue against wasm-bindgen/wasm-bindgen!
         }
     }
 
+    /// The `webdriver.json` section of this browser, `None` for Safari which
+    /// can't be configured through it.
+    fn config<'a>(&self, config: &'a WebDriverConfig) -> Option<&'a BrowserConfig> {
+        match self {
+            Driver::Gecko(_) => Some(&config.firefox),
+            Driver::Safari(_) => None,
+            Driver::Chrome(_) => Some(&config.chrome),
+            Driver::Edge(_) => Some(&config.edge),
+        }
+    }
+
     fn location(&self) -> &Locate {
         match self {
             Driver::Gecko(locate) => locate,
@@ -376,9 +844,14 @@ struct Client {
     agent: Agent,
     driver_url: Url,
     session: Option<String>,
+    /// BiDi connection URL of the session, if one was requested.
+    web_socket_url: Option<String>,
+    /// Timeout of requests to the driver, if any.
+    request_timeout: Option<Duration>,
 }
 
 enum Method<'a> {
+    Get,
     Post(&'a str),
     Delete,
 }
@@ -388,37 +861,22 @@ enum Method<'a> {
 // copied the `webdriver-client` crate when writing the below bindings.
 
 impl Client {
-    fn new_session(&mut self, driver: &Driver, mut cap: Capabilities) -> Result<String, Error> {
+    fn new_session(&mut self, driver: &Driver, config: &WebDriverConfig) -> Result<String, Error> {
+        let mut cap = config.capabilities.clone();
+        // A `proxy` capability in `webdriver.json` takes precedence over the
+        // environment.
+        match cap.get_mut("proxy") {
+            Some(proxy) => Proxy::bypass_loopback(proxy),
+            None => {
+                if let Some(proxy) = Proxy::from_env()? {
+                    cap.insert("proxy".to_string(), proxy.capability());
+                }
+            }
+        }
         match driver {
             Driver::Gecko(_) => {
-                #[derive(Deserialize)]
-                struct Response {
-                    value: ResponseValue,
-                }
-
-                #[derive(Deserialize)]
-                struct ResponseValue {
-                    #[serde(rename = "sessionId")]
-                    session_id: String,
-                }
-                cap.entry("moz:firefoxOptions".to_string())
-                    .or_insert_with(|| Json::Object(serde_json::Map::new()))
-                    .as_object_mut()
-                    .expect("moz:firefoxOptions wasn't a JSON object")
-                    .entry("args".to_string())
-                    .or_insert_with(|| Json::Array(vec![]))
-                    .as_array_mut()
-                    .expect("args wasn't a JSON array")
-                    .extend(vec![Json::String("-headless".to_string())]);
-                let session_config = SpecNewSessionParameters {
-                    always_match: cap,
-                    first_match: vec![Capabilities::new()],
-                };
-                let request = json!({
-                    "capabilities": session_config,
-                });
-                let x: Response = self.post("/session", &request)?;
-                Ok(x.value.session_id)
+                apply_browser_config(&mut cap, "moz:firefoxOptions", &config.firefox, "-headless")?;
+                self.new_w3c_session(cap)
             }
             Driver::Safari(_) => {
                 #[derive(Clone, Deserialize)]
@@ -456,22 +914,21 @@ impl Client {
                     #[serde(rename = "sessionId")]
                     session_id: String,
                 }
-                cap.entry("goog:chromeOptions".to_string())
-                    .or_insert_with(|| Json::Object(serde_json::Map::new()))
-                    .as_object_mut()
-                    .expect("goog:chromeOptions wasn't a JSON object")
-                    .entry("args".to_string())
-                    .or_insert_with(|| Json::Array(vec![]))
+                apply_browser_config(&mut cap, "goog:chromeOptions", &config.chrome, "headless")?;
+                cap["goog:chromeOptions"]["args"]
                     .as_array_mut()
                     .expect("args wasn't a JSON array")
                     .extend(vec![
-                        Json::String("headless".to_string()),
                         // See https://stackoverflow.com/questions/50642308/
                         // for what this funky `disable-dev-shm-usage`
                         // option is
                         Json::String("disable-dev-shm-usage".to_string()),
                         Json::String("no-sandbox".to_string()),
                     ]);
+                // BiDi is only available in W3C mode.
+                if bidi::enabled() {
+                    return self.new_w3c_session(cap);
+                }
                 let request = LegacyNewSessionParameters {
                     desired: cap,
                     required: Capabilities::new(),
@@ -485,22 +942,21 @@ impl Client {
                     #[serde(rename = "sessionId")]
                     session_id: String,
                 }
-                cap.entry("ms:edgeOptions".to_string())
-                    .or_insert_with(|| Json::Object(serde_json::Map::new()))
-                    .as_object_mut()
-                    .expect("ms:edgeOptions wasn't a JSON object")
-                    .entry("args".to_string())
-                    .or_insert_with(|| Json::Array(vec![]))
+                apply_browser_config(&mut cap, "ms:edgeOptions", &config.edge, "headless")?;
+                cap["ms:edgeOptions"]["args"]
                     .as_array_mut()
                     .expect("args wasn't a JSON array")
                     .extend(vec![
-                        Json::String("headless".to_string()),
                         // See https://stackoverflow.com/questions/50642308/
                         // for what this funky `disable-dev-shm-usage`
                         // option is
                         Json::String("disable-dev-shm-usage".to_string()),
                         Json::String("no-sandbox".to_string()),
                     ]);
+                // BiDi is only available in W3C mode.
+                if bidi::enabled() {
+                    return self.new_w3c_session(cap);
+                }
                 let request = LegacyNewSessionParameters {
                     desired: cap,
                     required: Capabilities::new(),
@@ -511,51 +967,59 @@ impl Client {
         }
     }
 
-    fn close_window(&mut self, id: &str) -> Result<(), Error> {
+    /// Creates a session using the W3C protocol, requesting a BiDi connection
+    /// if enabled.
+    fn new_w3c_session(&mut self, mut cap: Capabilities) -> Result<String, Error> {
         #[derive(Deserialize)]
-        struct Response {}
-        let _: Response = self.delete(&format!("/session/{id}/window"))?;
-        Ok(())
-    }
+        struct Response {
+            value: ResponseValue,
+        }
 
-    fn goto(&mut self, id: &str, url: &str) -> Result<(), Error> {
-        #[derive(Serialize)]
-        struct Request {
-            url: String,
+        #[derive(Deserialize)]
+        struct ResponseValue {
+            #[serde(rename = "sessionId")]
+            session_id: String,
+            #[serde(default)]
+            capabilities: Capabilities,
+        }
+        if bidi::enabled() {
+            cap.insert("webSocketUrl".to_string(), Json::Bool(true));
         }
+        let session_config = SpecNewSessionParameters {
+            always_match: cap,
+            first_match: vec![Capabilities::new()],
+        };
+        let request = json!({
+            "capabilities": session_config,
+        });
+        let x: Response = self.post("/session", &request)?;
+        self.web_socket_url = x
+            .value
+            .capabilities
+            .get("webSocketUrl")
+            .and_then(Json::as_str)
+            .map(String::from);
+        Ok(x.value.session_id)
+    }
+
+    fn session_id(&self) -> Result<String, Error> {
+        self.session.clone().context("no webdriver session")
+    }
+
+    fn close_window(&mut self, id: &str) -> Result<(), Error> {
         #[derive(Deserialize)]
         struct Response {}
-
-        let request = Request {
-            url: url.to_string(),
-        };
-        let _: Response = self.post(&format!("/session/{id}/url"), &request)?;
+        let _: Response = self.delete(&format!("/session/{id}/window"))?;
         Ok(())
     }
 
-    fn text_content(&mut self, id: &str, selector: &str, offset: usize) -> Result<String, Error> {
-        #[derive(Serialize)]
-        struct Request {
-            script: String,
-            args: Vec<usize>,
-        }
-        #[derive(Deserialize)]
-        struct Response {
-            value: serde_json::Value,
-        }
-        let request = Request {
-            script: format!(
-                "return document.querySelector({}).textContent.slice(arguments[0])",
-                serde_json::to_string(selector)?
-            ),
-            args: vec![offset],
-        };
-        let x: Response = self.post(&format!("/session/{id}/execute/sync"), &request)?;
-        match x.value {
-            serde_json::Value::String(s) => Ok(s),
-            serde_json::Value::Null => Ok(String::new()),
-            other => bail!("unexpected response from execute/sync: {other:?}"),
-        }
+    fn get<U>(&mut self, path: &str) -> Result<U, Error>
+    where
+        U: for<'a> Deserialize<'a>,
+    {
+        debug!("GET {path}");
+        let result = self.doit(path, Method::Get)?;
+        Ok(serde_json::from_str(&result)?)
     }
 
     fn post<T, U>(&mut self, path: &str, data: &T) -> Result<U, Error>
@@ -580,19 +1044,48 @@ impl Client {
 
     fn doit(&mut self, path: &str, method: Method) -> Result<String, Error> {
         let url = self.driver_url.join(path)?;
+        let timeout = self.request_timeout;
         let mut response = match method {
+            Method::Get => self
+                .agent
+                .get(url.as_str())
+                .config()
+                .timeout_global(timeout)
+                .http_status_as_error(false)
+                .build()
+                .call()?,
             Method::Post(data) => self
                 .agent
                 .post(url.as_str())
+                .config()
+                .timeout_global(timeout)
+                .http_status_as_error(false)
+                .build()
                 .content_type("application/json")
                 .send(data.as_bytes())?,
-            Method::Delete => self.agent.delete(url.as_str()).call()?,
+            Method::Delete => self
+                .agent
+                .delete(url.as_str())
+                .config()
+                .timeout_global(timeout)
+                .http_status_as_error(false)
+                .build()
+                .call()?,
         };
 
         let response_code = response.status();
         let result = response.body_mut().read_to_string()?;
 
         if response_code != 200 {
+            // These error codes mean the session's browser or window is
+            // gone, which is how drivers report a crash.
+            // https://www.w3.org/TR/webdriver/#errors
+            let code = serde_json::from_str::<Json>(&result)
+                .ok()
+                .and_then(|json| json["value"]["error"].as_str().map(str::to_string));
+            if let Some(code @ ("invalid session id" | "no such window")) = code.as_deref() {
+                return Err(Error::new(BrowserCrashed(format!("{code}: {result}"))));
+            }
             bail!("non-200 response code: {response_code}\n{result}");
         }
         debug!("got: {result}");
@@ -600,6 +1093,65 @@ impl Client {
     }
 }
 
+impl Page for Client {
+    fn goto(&mut self, url: &str, timeout: Duration) -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct Request {
+            url: String,
+        }
+        #[derive(Deserialize)]
+        struct Response {}
+
+        let id = self.session_id()?;
+        let request = Request {
+            url: url.to_string(),
+        };
+        self.request_timeout = Some(timeout);
+        let result: Result<Response, Error> = self.post(&format!("/session/{id}/url"), &request);
+        self.request_timeout = None;
+        result?;
+        Ok(())
+    }
+
+    fn text_content(&mut self, selector: &str, offset: usize) -> Result<String, Error> {
+        #[derive(Serialize)]
+        struct Request {
+            script: String,
+            args: Vec<usize>,
+        }
+        #[derive(Deserialize)]
+        struct Response {
+            value: serde_json::Value,
+        }
+        let id = self.session_id()?;
+        let request = Request {
+            script: format!(
+                "return document.querySelector({}).textContent.slice(arguments[0])",
+                serde_json::to_string(selector)?
+            ),
+            args: vec![offset],
+        };
+        let x: Response = self.post(&format!("/session/{id}/execute/sync"), &request)?;
+        match x.value {
+            serde_json::Value::String(s) => Ok(s),
+            serde_json::Value::Null => Ok(String::new()),
+            other => bail!("unexpected response from execute/sync: {other:?}"),
+        }
+    }
+
+    fn screenshot(&mut self) -> Result<Vec<u8>, Error> {
+        #[derive(Deserialize)]
+        struct Response {
+            value: String,
+        }
+        let id = self.session_id()?;
+        let x: Response = self.get(&format!("/session/{id}/screenshot"))?;
+        BASE64_STANDARD
+            .decode(x.value)
+            .context("screenshot wasn't valid base64")
+    }
+}
+
 impl Drop for Client {
     fn drop(&mut self) {
         let id = match &self.session {
@@ -612,6 +1164,213 @@ impl Drop for Client {
     }
 }
 
+/// Returns the complete lines of `output` past `scanned`, advancing `scanned`
+/// to the end of the last complete line.
+fn complete_lines(output: &str, scanned: &mut usize) -> Vec<String> {
+    let Some(end) = output.rfind('\n') else {
+        return Vec::new();
+    };
+    if end < *scanned {
+        return Vec::new();
+    }
+    let lines = &output[*scanned..end];
+    *scanned = end + 1;
+    lines.lines().map(String::from).collect()
+}
+
+/// Marks the lines of the page's output meant for stderr, written by the
+/// harness with `--split-console-output`.
+const STDERR_MARKER: char = '\u{1e}';
+
+/// Relays the page's output to stdout, and the lines marked with
+/// [`STDERR_MARKER`] to stderr. Output is written a line at a time, once the
+/// locations in snippets and Wasm functions it mentions are rewritten by
+/// `source_maps` and `symbols`.
+struct OutputStreams<'a> {
+    source_maps: &'a SourceMaps,
+    symbols: &'a WasmSymbols,
+    /// Output of the last line, not written until it ends.
+    pending: String,
+}
+
+impl<'a> OutputStreams<'a> {
+    fn new(source_maps: &'a SourceMaps, symbols: &'a WasmSymbols) -> Self {
+        OutputStreams {
+            source_maps,
+            symbols,
+            pending: String::new(),
+        }
+    }
+
+    fn write(&mut self, output: &str) -> io::Result<()> {
+        self.pending.push_str(output);
+        let Some(end) = self.pending.rfind('\n') else {
+            return Ok(());
+        };
+        let lines: String = self.pending.drain(..=end).collect();
+        for line in lines.split_inclusive('\n') {
+            self.write_line(line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last line, even if it didn't end.
+    fn flush(&mut self) -> io::Result<()> {
+        let line = mem::take(&mut self.pending);
+        if !line.is_empty() {
+            self.write_line(&line)?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        let (line, stderr) = match line.strip_prefix(STDERR_MARKER) {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        let line = self.source_maps.rewrite(line);
+        let line = self.symbols.rewrite(&line);
+        if stderr {
+            io::stderr().lock().write_all(line.as_bytes())
+        } else {
+            io::stdout().lock().write_all(line.as_bytes())
+        }
+    }
+}
+
+/// Returns the name of the test if `line` reports its result, and whether it
+/// failed, either in the default format or as an event of `--format json`.
+fn test_result(line: &str) -> Option<(Cow<'_, str>, bool)> {
+    if line.starts_with('{') {
+        let event: Json = serde_json::from_str(line).ok()?;
+        let failed = match (event["type"].as_str()?, event["event"].as_str()?) {
+            ("test", "ok" | "ignored") => false,
+            ("test", "failed") => true,
+            _ => return None,
+        };
+        let name = event["name"].as_str()?.to_string();
+        return Some((Cow::Owned(name), failed));
+    }
+    let (name, result) = line.strip_prefix("test ")?.split_once(" ... ")?;
+    let failed = result.starts_with("FAIL");
+    let finished = failed || result.starts_with("ok") || result.starts_with("ignored");
+    finished.then_some((Cow::Borrowed(name), failed))
+}
+
+/// Failed network requests reported by the page, attributed to the test that
+/// was running when they happened.
+#[derive(Default)]
+struct NetworkErrors {
+    /// Offset up to which `#network_errors` has been read.
+    offset: usize,
+    /// Errors reported since the last line of the harness' output.
+    pending: Vec<String>,
+    /// Whether the harness has started running tests.
+    started: bool,
+    /// Errors paired with a description of when they happened.
+    errors: Vec<(String, String)>,
+}
+
+impl NetworkErrors {
+    fn poll(&mut self, page: &mut dyn Page) -> Result<(), Error> {
+        let new = page.text_content("#network_errors", self.offset)?;
+        self.offset += new.len();
+        self.pending.extend(new.lines().map(String::from));
+        Ok(())
+    }
+
+    /// Attributes the pending errors to the test reported by `line`, or to
+    /// loading the page once the harness starts running tests.
+    fn attribute(&mut self, line: &str) {
+        let during = if line.starts_with("running ") && !self.started {
+            self.started = true;
+            "while loading the page".to_string()
+        } else if let Some((name, _)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))
+        {
+            format!("during `{name}`")
+        } else {
+            return;
+        };
+        self.errors
+            .extend(self.pending.drain(..).map(|e| (during.clone(), e)));
+    }
+
+    fn print(mut self) {
+        let during = if self.started {
+            "after the last test"
+        } else {
+            "while loading the page"
+        };
+        self.errors
+            .extend(self.pending.drain(..).map(|e| (during.to_string(), e)));
+        if self.errors.is_empty() {
+            return;
+        }
+        println!("failed network requests:");
+        for (during, error) in self.errors {
+            println!("    {during}: {error}");
+        }
+    }
+}
+
+/// The log entries captured over BiDi, attributed to the test that was running
+/// when they were logged, so only those of the failed tests are printed.
+struct BidiLogs<'a> {
+    capture: &'a LogCapture,
+    /// Entries logged since the last test finished.
+    pending: Vec<String>,
+    /// The entries of each failed test.
+    failed: Vec<(String, Vec<String>)>,
+}
+
+impl<'a> BidiLogs<'a> {
+    fn new(capture: &'a LogCapture) -> BidiLogs<'a> {
+        BidiLogs {
+            capture,
+            pending: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    fn poll(&mut self) {
+        self.pending.extend(self.capture.take());
+    }
+
+    /// Attributes the pending entries to the test reported by `line`, keeping
+    /// them only if it failed.
+    fn attribute(&mut self, line: &str) {
+        let Some((name, failed)) = test_result(line) else {
+            return;
+        };
+        let entries = mem::take(&mut self.pending);
+        if failed && !entries.is_empty() {
+            self.failed.push((name.into_owned(), entries));
+        }
+    }
+
+    /// Prints the entries of the failed tests, and those logged after the last
+    /// test finished, which belong to a test that didn't.
+    fn print(self) -> io::Result<()> {
+        let failed = self
+            .failed
+            .iter()
+            .map(|(test, entries)| (format!("BiDi log of `{test}`:"), entries));
+        let unfinished = ("BiDi log after the last test:".to_string(), &self.pending);
+        for (heading, entries) in failed.chain([unfinished]) {
+            if entries.is_empty() {
+                continue;
+            }
+            println!("{heading}");
+            for entry in entries {
+                io::stdout().lock().write_all(tab(entry).as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 fn tab(s: &str) -> String {
     let mut result = String::new();
     for line in s.lines() {
@@ -622,6 +1381,31 @@ fn tab(s: &str) -> String {
     result
 }
 
+/// A child process which is killed and reaped when dropped, so that it doesn't
+/// outlive an error.
+pub struct KillOnDrop(pub Child);
+
+impl Deref for KillOnDrop {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        &self.0
+    }
+}
+
+impl DerefMut for KillOnDrop {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.0
+    }
+}
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        drop(self.0.kill());
+        drop(self.0.wait());
+    }
+}
+
 struct BackgroundChild<'a> {
     child: Child,
     stdout: Option<thread::JoinHandle<io::Result<Vec<u8>>>>,
@@ -711,3 +1495,77 @@ impl Drop for BackgroundChild<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn crashed_tests_are_the_unreported_scheduled_ones() {
+        let output = "running 3 tests\ntest a ... ok\ntest b ... FAILED\n";
+        let scheduled = names(&["a", "b", "c"]);
+        assert_eq!(crashed_tests(output, &scheduled), ["c"]);
+    }
+
+    #[test]
+    fn crashed_tests_only_come_from_the_schedule() {
+        // `ignored` wasn't scheduled, so it isn't blamed on the crash.
+        let output = "test a ... ok\ntest ignored ... ignored\n";
+        let scheduled = names(&["a", "b"]);
+        assert_eq!(crashed_tests(output, &scheduled), ["b"]);
+    }
+
+    #[test]
+    fn no_crashed_tests_when_all_reported() {
+        let output = "test a ... ok\ntest b ... ok\n";
+        assert!(crashed_tests(output, &names(&["a", "b"])).is_empty());
+        assert!(crashed_tests("", &[]).is_empty());
+    }
+
+    #[test]
+    fn crashed_tests_are_read_from_json_events() {
+        let output = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "ok", "name": "a", "suite": "x" }
+{ "type": "test", "event": "failed", "name": "b", "suite": "x" }
+"#;
+        let scheduled = names(&["a", "b", "c"]);
+        assert_eq!(crashed_tests(output, &scheduled), ["c"]);
+    }
+
+    #[test]
+    fn test_results_are_read_in_both_formats() {
+        assert_eq!(
+            test_result("test a ... ok <0.010s>"),
+            Some(("a".into(), false))
+        );
+        assert_eq!(test_result("test a ... FAIL"), Some(("a".into(), true)));
+        assert_eq!(
+            test_result("test a ... ignored, slow"),
+            Some(("a".into(), false))
+        );
+        assert_eq!(
+            test_result(r#"{ "type": "test", "event": "failed", "name": "a", "suite": "x" }"#),
+            Some(("a".into(), true))
+        );
+        assert_eq!(test_result("running 1 test"), None);
+        assert_eq!(
+            test_result(r#"{ "type": "suite", "event": "failed", "passed": 0 }"#),
+            None
+        );
+    }
+
+    #[test]
+    fn crashes_are_detected_from_the_error_type() {
+        let crash = Error::new(BrowserCrashed("invalid session id".into()))
+            .context("failed to poll the test page");
+        assert!(is_crash(&crash));
+
+        // An error merely mentioning a crash isn't one.
+        let timeout = anyhow!("test `crash_handling` timed out");
+        assert!(!is_crash(&timeout));
+    }
+}
@@ -0,0 +1,85 @@
+//! Instrumentation of the Wasm module for `--count-instructions`.
+//!
+//! Each block of instructions, including the body of a function and of each
+//! loop iteration, first adds its number of instructions to a counter, which
+//! the module exports as `__wbgtest_instructions` for the harness to read
+//! before and after each test. A block left early by a branch still counts in
+//! full, so the count approximates the instructions executed, but it only
+//! changes with the code, unlike wall time.
+//!
+//! The module is instrumented after wasm-bindgen processed it, as its
+//! interpreter of the descriptors doesn't support the instrumentation.
+
+use anyhow::{Context, Error};
+use std::path::Path;
+use walrus::ir::{
+    dfs_pre_order_mut, BinaryOp, Binop, Const, GlobalGet, GlobalSet, InstrSeq, UnaryOp, Value,
+    VisitorMut,
+};
+use walrus::{ConstExpr, FunctionBuilder, GlobalId, InstrLocId, ValType};
+
+/// The export returning the number of instructions executed so far.
+pub const EXPORT: &str = "__wbgtest_instructions";
+
+/// Instruments the module at `wasm` in place.
+pub fn instrument(wasm: &Path) -> Result<(), Error> {
+    let bytes =
+        std::fs::read(wasm).with_context(|| format!("failed to read `{}`", wasm.display()))?;
+    let mut module = walrus::ModuleConfig::new()
+        .generate_dwarf(true)
+        .parse(&bytes)
+        .context("failed to deserialize Wasm module")?;
+
+    // Each thread counts its own instructions.
+    let init = ConstExpr::Value(Value::I64(0));
+    let counter = module.globals.add_local(ValType::I64, true, false, init);
+    for (_, func) in module.funcs.iter_local_mut() {
+        let entry = func.entry_block();
+        dfs_pre_order_mut(&mut Count { counter }, func, entry);
+    }
+
+    // Counts above 2^53 lose precision in JS, which is fine for a metric.
+    let mut builder = FunctionBuilder::new(&mut module.types, &[], &[ValType::F64]);
+    builder
+        .func_body()
+        .global_get(counter)
+        .unop(UnaryOp::F64ConvertUI64);
+    let read = builder.finish(Vec::new(), &mut module.funcs);
+    module.exports.add(EXPORT, read);
+
+    module
+        .emit_wasm_file(wasm)
+        .with_context(|| format!("failed to write `{}`", wasm.display()))
+}
+
+struct Count {
+    counter: GlobalId,
+}
+
+impl VisitorMut for Count {
+    fn start_instr_seq_mut(&mut self, seq: &mut InstrSeq) {
+        let count = seq.instrs.len() as i64;
+        if count == 0 {
+            return;
+        }
+        let global = self.counter;
+        let increment = [
+            GlobalGet { global }.into(),
+            Const {
+                value: Value::I64(count),
+            }
+            .into(),
+            Binop {
+                op: BinaryOp::I64Add,
+            }
+            .into(),
+            GlobalSet { global }.into(),
+        ];
+        seq.instrs.splice(
+            0..0,
+            increment
+                .into_iter()
+                .map(|instr| (instr, InstrLocId::default())),
+        );
+    }
+}
@@ -0,0 +1,77 @@
+// Counts what tests can leave behind for `--detect-leaks`, which has the
+// harness compare the counts before and after each test. Elements rather than
+// nodes are counted, as the page appends text nodes to show the output.
+{
+    const timers = new Set();
+    const track = (set, clear) => {
+        const og_set = globalThis[set];
+        const og_clear = globalThis[clear];
+        if (typeof og_set !== 'function' || typeof og_clear !== 'function') return;
+        globalThis[set] = function (handler, ...args) {
+            let id;
+            // A timeout isn't pending anymore once it fired.
+            const wrapped = set === 'setTimeout' && typeof handler === 'function'
+                ? function (...args) {
+                    timers.delete(id);
+                    return handler.apply(this, args);
+                }
+                : handler;
+            id = og_set.call(this, wrapped, ...args);
+            timers.add(id);
+            return id;
+        };
+        globalThis[clear] = function (id) {
+            timers.delete(id);
+            return og_clear.call(this, id);
+        };
+    };
+    track('setTimeout', 'clearTimeout');
+    track('setInterval', 'clearInterval');
+
+    let listeners = 0;
+    const registered = new WeakMap();
+    const proto = globalThis.EventTarget?.prototype;
+    if (proto) {
+        const og_add = proto.addEventListener;
+        const og_remove = proto.removeEventListener;
+        const key = (type, options) =>
+            `${type}:${typeof options === 'boolean' ? options : !!options?.capture}`;
+        proto.addEventListener = function (type, listener, options) {
+            const target = this ?? globalThis;
+            // Listeners removed on their own don't leak.
+            const temporary = typeof options === 'object' && (options?.once || options?.signal);
+            if (listener && !temporary) {
+                let types = registered.get(target);
+                if (!types) registered.set(target, types = new Map());
+                let added = types.get(key(type, options));
+                if (!added) types.set(key(type, options), added = new Set());
+                if (!added.has(listener)) {
+                    added.add(listener);
+                    listeners += 1;
+                }
+            }
+            return og_add.call(this, type, listener, options);
+        };
+        proto.removeEventListener = function (type, listener, options) {
+            const target = this ?? globalThis;
+            if (registered.get(target)?.get(key(type, options))?.delete(listener)) {
+                listeners -= 1;
+            }
+            return og_remove.call(this, type, listener, options);
+        };
+    }
+
+    // Handles the glue gave out and that weren't released yet. Browsers import
+    // `__wbg_live_handles` from the bindings, while `wasm` is the bindings
+    // themselves in Node.js and Deno.
+    const live_handles = typeof __wbg_live_handles === 'function'
+        ? __wbg_live_handles
+        : wasm.__wbg_live_handles;
+
+    cx.detect_leaks(() => [
+        typeof document === 'object' && document ? document.getElementsByTagName('*').length : 0,
+        timers.size,
+        listeners,
+        live_handles?.() ?? [],
+    ]);
+}
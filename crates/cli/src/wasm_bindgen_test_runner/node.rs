@@ -1,13 +1,15 @@
 use std::path::{Path, PathBuf};
 use std::process;
 use std::process::Command;
+use std::time::SystemTime;
 use std::{env, fs};
 
 use anyhow::bail;
 use anyhow::{Context, Error};
 
-use super::Cli;
+use super::symbols::WasmSymbols;
 use super::Tests;
+use super::{coverage_profile_file, path_literal, Cli};
 
 // depends on the variable 'wasm' and initializes te WasmBindgenTestContext cx
 pub fn shared_setup(is_bench: bool) -> String {
@@ -15,12 +17,24 @@ pub fn shared_setup(is_bench: bool) -> String {
         r#"
 const handlers = {{}};
 
+// Node.js implements e.g. `console.group` and `console.table` by calling
+// `console.log`, which mustn't capture their output a second time.
+let in_og = false;
+
 const wrap = method => {{
     const og = console[method];
     const on_method = `on_console_${{method}}`;
     console[method] = function (...args) {{
+        if (in_og) {{
+            return og.apply(this, args);
+        }}
         if (nocapture) {{
-            og.apply(this, args);
+            in_og = true;
+            try {{
+                og.apply(this, args);
+            }} finally {{
+                in_og = false;
+            }}
         }}
         if (handlers[on_method]) {{
             handlers[on_method](args);
@@ -28,8 +42,9 @@ const wrap = method => {{
     }};
 }};
 
-// save original `console.log`
+// save original `console.log` and `console.error`
 global.__wbgtest_og_console_log = console.log;
+global.__wbgtest_og_console_error = console.error;
 // override `console.log` and `console.error` etc... before we import tests to
 // ensure they're bound correctly in wasm. This'll allow us to intercept
 // all these calls and capture the output of tests
@@ -38,6 +53,11 @@ wrap("log");
 wrap("info");
 wrap("warn");
 wrap("error");
+wrap("group");
+wrap("groupCollapsed");
+wrap("groupEnd");
+wrap("table");
+wrap("dir");
 
 const cx = new wasm.WasmBindgenTestContext({is_bench});
 handlers.on_console_debug = wasm.__wbgtest_console_debug;
@@ -45,6 +65,11 @@ handlers.on_console_log = wasm.__wbgtest_console_log;
 handlers.on_console_info = wasm.__wbgtest_console_info;
 handlers.on_console_warn = wasm.__wbgtest_console_warn;
 handlers.on_console_error = wasm.__wbgtest_console_error;
+handlers.on_console_group = wasm.__wbgtest_console_group;
+handlers.on_console_groupCollapsed = wasm.__wbgtest_console_group;
+handlers.on_console_groupEnd = wasm.__wbgtest_console_group_end;
+handlers.on_console_table = wasm.__wbgtest_console_table;
+handlers.on_console_dir = wasm.__wbgtest_console_dir;
 "#
     )
 }
@@ -56,12 +81,12 @@ pub fn execute(
     tests: Tests,
     module_format: bool,
     benchmark: PathBuf,
+    suppressed_output: &Path,
+    artifacts: &Path,
+    symbols: &WasmSymbols,
 ) -> Result<(), Error> {
-    let coverage_env = if let Ok(env) = env::var("LLVM_PROFILE_FILE") {
-        &format!("\"{env}\"")
-    } else {
-        "undefined"
-    };
+    let flamegraphs = cli.flamegraphs();
+    let coverage_env = path_literal(&coverage_profile_file(Some("node"))?);
     let coverage_pid = process::id();
     let coverage_temp_dir = env::temp_dir()
         .to_str()
@@ -82,6 +107,26 @@ pub fn execute(
         async function main(tests) {{
             {args}
 
+            // Save the profiles of `console.profile`, which the harness calls
+            // around each test with `--cpu-profile`.
+            let cpu_profiles = 0;
+            if ({cpu_profile}) {{
+                const {{ Session }} = await import('node:inspector');
+                const {{ mkdirSync, writeFileSync }} = await import('node:fs');
+                const {{ join }} = await import('node:path');
+                const session = new Session();
+                session.connect();
+                session.on('Profiler.consoleProfileFinished', ({{ params }}) => {{
+                    const name = params.title.replace(/[^A-Za-z0-9_.-]/gu, '_');
+                    mkdirSync({artifacts}, {{ recursive: true }});
+                    writeFileSync(join({artifacts}, `${{name}}.cpuprofile`), JSON.stringify(params.profile));
+                    cpu_profiles += 1;
+                }});
+                await new Promise((resolve, reject) => {{
+                    session.post('Profiler.enable', e => e ? reject(e) : resolve());
+                }});
+            }}
+
             if ({is_bench}) {{
                 try {{
                     const benchmark_import = await fs.readFile('{benchmark}');
@@ -93,12 +138,23 @@ pub fn execute(
 
             const ok = await cx.run(tests.map(n => wasm.__wasm[n]));
 
+            if (cpu_profiles > 0)
+                __wbgtest_og_console_log(`CPU profiles of ${{cpu_profiles}} test(s) saved to ${{{artifacts}}}`);
+
             const coverage = wasm.__wbgtest_cov_dump();
             if (coverage !== undefined) {{
                 const path = wasm.__wbgtest_coverage_path({coverage_env}, {coverage_pid}, {coverage_temp_dir:?}, wasm.__wbgtest_module_signature());
+                const dir = path.replace(/[\\/][^\\/]*$/, '');
+                if (dir !== path) await fs.mkdir(dir, {{ recursive: true }});
                 await fs.writeFile(path, coverage);
             }}
 
+            const suppressed_output = cx.suppressed_output();
+            if (suppressed_output !== undefined) {{
+                await fs.mkdir({suppressed_output_dir}, {{ recursive: true }});
+                await fs.writeFile({suppressed_output_path}, suppressed_output);
+            }}
+
             if ({is_bench}) {{
                 const benchmark_dump = wasm.__wbgbench_dump();
                 if (benchmark_dump !== undefined)
@@ -128,9 +184,14 @@ pub fn execute(
             r"import fs from 'node:fs/promises'".to_string()
         },
         is_bench = cli.bench,
+        cpu_profile = cli.cpu_profile,
+        artifacts = path_literal(artifacts),
         nocapture = cli.nocapture || cli.bench,
-        args = cli.get_args(&tests),
-        benchmark = benchmark.display()
+        args = cli.get_args(&tests, suppressed_output),
+        benchmark = benchmark.display(),
+        suppressed_output_dir =
+            path_literal(suppressed_output.parent().unwrap_or(suppressed_output)),
+        suppressed_output_path = path_literal(suppressed_output),
     );
 
     // Note that we're collecting *JS objects* that represent the functions to
@@ -177,13 +238,20 @@ pub fn execute(
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>();
 
-    let status = Command::new("node")
+    let mut command = Command::new("node");
+    command
         .env("NODE_PATH", env::join_paths(&path).unwrap())
         .arg("--expose-gc")
+        .arg("--enable-source-maps")
         .args(&extra_node_args)
-        .arg(&js_path)
-        .status()
+        .arg(&js_path);
+    let started = SystemTime::now();
+    let status = symbols
+        .status(&mut command)
         .context("failed to find or execute Node.js")?;
+    if let Some(flamegraphs) = flamegraphs {
+        flamegraphs.write_saved(artifacts, started, symbols)?;
+    }
 
     if !status.success() {
         bail!("Node failed with exit_code {}", status.code().unwrap_or(1))
@@ -1,13 +1,263 @@
 use std::borrow::Cow;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::net::SocketAddr;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::{env, fs, process};
+use std::time::Instant;
+use std::{env, fs, process, thread};
 
 use anyhow::{anyhow, Context, Error};
-use rouille::{Request, Response, Server};
+use clap::ValueEnum;
+use flate2::write::GzEncoder;
+use rouille::websocket::{self, Message, Websocket};
+use rouille::{Request, Response, ResponseBody, Server};
+
+use super::doctest::{self, STUB_IMPORTS};
+use super::server_config::{HeaderRule, MockEndpoint, WebSocketEndpoint, WebSocketStep};
+use super::{bidi, coverage_profile_file, Cli, TestMode, Tests};
+
+/// A directory served under a URL prefix, from `--mount <prefix>=<dir>`.
+#[derive(Clone, Debug)]
+pub struct Mount {
+    /// The URL prefix, starting with a `/` and without a trailing one.
+    pub prefix: String,
+    pub dir: PathBuf,
+}
 
-use super::{Cli, TestMode, Tests};
+impl Mount {
+    /// The request with the prefix removed if it is below this mount.
+    fn strip(&self, request: &Request) -> Option<Request> {
+        let rest = request.url();
+        let rest = rest.strip_prefix(&self.prefix)?;
+        if !rest.starts_with('/') {
+            return None;
+        }
+        request.remove_prefix(&self.prefix)
+    }
+}
+
+/// Query parameter marking a request for the script of a service worker
+/// registered by the tests, see [`SERVICE_WORKER_CONSOLE_SHIM`].
+const SERVICE_WORKER_PARAM: &str = "__wbgtest_sw";
+
+/// Console shim prepended to the scripts of service workers registered by the
+/// tests. Service workers can't be created from blob URLs like other workers,
+/// so the page tags their script URL with [`SERVICE_WORKER_PARAM`] instead and
+/// the server injects this. Forwards console output and uncaught errors to
+/// all pages, as the worker may not control the page that registered it yet.
+const SERVICE_WORKER_CONSOLE_SHIM: &str = r#"(function() {
+    const post = msg => self.clients.matchAll({ includeUncontrolled: true, type: 'window' })
+        .then(clients => clients.forEach(client => client.postMessage(msg)));
+    ["debug","log","info","warn","error"].forEach(m => {
+        const og = console[m];
+        console[m] = function(...a) {
+            og.apply(this, a);
+            post(["__wbgtest_" + m, a.map(String)]);
+        };
+    });
+    self.addEventListener('error', e => {
+        console.error('Uncaught error in ServiceWorker:', e.message || String(e));
+    });
+    self.addEventListener('unhandledrejection', e => {
+        const reason = e.reason;
+        console.error('Unhandled rejection in ServiceWorker:', (reason && reason.stack) || String(reason));
+    });
+})();
+"#;
+
+/// Backs `wasm_bindgen_test::Sandbox` in browser tests: `open` returns a fresh
+/// container element, and once the last sandbox of a test is closed, or the
+/// harness closes them after the test, the document is put back as it was:
+/// nodes added to `<head>` and `<body>` and event listeners added while they
+/// were open are removed, and the attributes of `<html>` and `<body>` and the
+/// adopted style sheets restored.
+const SANDBOX: &str = r#"
+globalThis.__wbg_sandbox = (() => {
+    const addEventListener = EventTarget.prototype.addEventListener;
+    const attributes = element => Array.from(element.attributes, a => [a.name, a.value]);
+    const restore = (element, attributes) => {
+        for (const name of element.getAttributeNames()) element.removeAttribute(name);
+        for (const [name, value] of attributes) element.setAttribute(name, value);
+    };
+    let open = 0;
+    let state;
+    return {
+        open() {
+            if (open++ === 0) {
+                state = {
+                    head: new Set(document.head.childNodes),
+                    body: new Set(document.body.childNodes),
+                    html: attributes(document.documentElement),
+                    bodyAttributes: attributes(document.body),
+                    styleSheets: document.adoptedStyleSheets,
+                    listeners: [],
+                };
+                EventTarget.prototype.addEventListener = function (...args) {
+                    state.listeners.push([this, args]);
+                    return addEventListener.apply(this, args);
+                };
+            }
+            const container = document.createElement('div');
+            container.dataset.wasmBindgenTestSandbox = '';
+            document.body.appendChild(container);
+            return container;
+        },
+        close(all) {
+            if (open === 0 || (--open > 0 && !all)) return;
+            open = 0;
+            EventTarget.prototype.addEventListener = addEventListener;
+            for (const [target, [type, listener, options]] of state.listeners) {
+                target.removeEventListener(type, listener, options);
+            }
+            for (const [parent, kept] of [[document.head, state.head], [document.body, state.body]]) {
+                for (const node of Array.from(parent.childNodes)) {
+                    if (!kept.has(node)) node.remove();
+                }
+            }
+            restore(document.documentElement, state.html);
+            restore(document.body, state.bodyAttributes);
+            document.adoptedStyleSheets = state.styleSheets;
+            state = undefined;
+        },
+    };
+})();
+"#;
+
+/// Prepends [`SERVICE_WORKER_CONSOLE_SHIM`] to a successful `response`.
+fn shim_service_worker(mut response: Response) -> Response {
+    if !response.is_success() {
+        return response;
+    }
+    match take_body(&mut response) {
+        Ok(body) => {
+            let mut shimmed = SERVICE_WORKER_CONSOLE_SHIM.as_bytes().to_vec();
+            shimmed.extend(body);
+            response.data = ResponseBody::from_data(shimmed);
+            response
+        }
+        Err(response) => response,
+    }
+}
+
+/// Serves a static file from the generated files in `tmpdir`, the `mounts` or
+/// the current directory, in that order.
+fn serve_static(request: &Request, tmpdir: &Path, mounts: &[Mount]) -> Response {
+    let mut response = try_asset(request, tmpdir);
+    for mount in mounts {
+        if response.is_success() {
+            return response;
+        }
+        if let Some(request) = mount.strip(request) {
+            response = try_asset(&request, &mount.dir);
+        }
+    }
+    if !response.is_success() {
+        response = try_asset(request, ".".as_ref());
+    }
+    response
+}
+
+/// Accepts WebSocket connections to the scripted `endpoints`, echoing every
+/// message for those without a script.
+fn serve_websocket(endpoints: &[WebSocketEndpoint], request: &Request) -> Option<Response> {
+    let path = request.url();
+    let script = endpoints
+        .iter()
+        .find(|endpoint| endpoint.matches(&path))?
+        .script
+        .clone();
+    let (response, socket) = match websocket::start(request, None::<&str>) {
+        Ok(accepted) => accepted,
+        Err(e) => {
+            return Some(Response::text(format!("{e:?}")).with_status_code(400));
+        }
+    };
+    thread::spawn(move || {
+        let Ok(mut socket) = socket.recv() else {
+            return;
+        };
+        if script.is_empty() {
+            echo(&mut socket);
+        } else {
+            run_script(&mut socket, &script, &path);
+        }
+    });
+    Some(response)
+}
+
+fn echo(socket: &mut Websocket) {
+    while let Some(message) = socket.next() {
+        let sent = match message {
+            Message::Text(text) => socket.send_text(&text),
+            Message::Binary(data) => socket.send_binary(&data),
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+}
+
+fn run_script(socket: &mut Websocket, script: &[WebSocketStep], path: &str) {
+    for step in script {
+        match step {
+            WebSocketStep::Send(text) => {
+                if socket.send_text(text).is_err() {
+                    return;
+                }
+            }
+            WebSocketStep::Expect(expected) => match socket.next() {
+                Some(Message::Text(text)) if text == *expected => {}
+                Some(Message::Text(text)) => {
+                    log::warn!("WebSocket `{path}` expected `{expected}` but received `{text}`");
+                    return;
+                }
+                Some(Message::Binary(_)) => {
+                    log::warn!("WebSocket `{path}` expected `{expected}` but received binary data");
+                    return;
+                }
+                None => return,
+            },
+        }
+    }
+    // Keep the connection open until the client closes it.
+    for _ in socket {}
+}
+
+/// Answers `request` from the first of the `mocks` matching it, if any.
+fn serve_mock(mocks: &[MockEndpoint], request: &Request) -> Option<Response> {
+    let path = request.url();
+    let mock = mocks
+        .iter()
+        .find(|mock| mock.matches(request.method(), &path))?;
+    thread::sleep(mock.delay());
+
+    let body = match &mock.body {
+        Some(body) => match fs::read(body) {
+            Ok(body) => body,
+            Err(e) => {
+                let s = format!("failed to read `{}`: {e}", body.display());
+                log::error!("{s}");
+                return Some(Response::text(s).with_status_code(500));
+            }
+        },
+        None => Vec::new(),
+    };
+    let content_type = match (&mock.content_type, &mock.body) {
+        (Some(content_type), _) => Cow::Owned(content_type.clone()),
+        (None, Some(body)) => {
+            let extension = body.extension().and_then(|e| e.to_str()).unwrap_or("");
+            Cow::Borrowed(rouille::extension_to_mime(extension))
+        }
+        (None, None) => Cow::Borrowed("text/plain"),
+    };
+    let mut response = Response::from_data(content_type, body).with_status_code(mock.status);
+    for (name, value) in &mock.headers {
+        response
+            .headers
+            .push((Cow::Owned(name.clone()), Cow::Owned(value.clone())));
+    }
+    Some(response)
+}
 
 /// Try to serve an asset from a directory, handling ES module imports without extensions.
 fn try_asset(request: &Request, dir: &Path) -> Response {
@@ -49,23 +299,136 @@ pub(crate) fn spawn(
     tests: Tests,
     test_mode: TestMode,
     isolate_origin: bool,
+    headers: Vec<HeaderRule>,
+    mounts: Vec<Mount>,
+    mocks: Vec<MockEndpoint>,
+    websockets: Vec<WebSocketEndpoint>,
+    compression: Option<Compression>,
+    log_requests: bool,
+    cross_origin: Option<String>,
     benchmark: PathBuf,
+    suppressed_output: PathBuf,
 ) -> Result<Server<impl Fn(&Request) -> Response + Send + Sync>, Error> {
     let mut js_to_execute = String::new();
 
-    // Console shim to inject into user-spawned dedicated workers.
-    // Logs to worker's own DevTools, then forwards to main page for CLI capture.
+    // Exposes the URL of the cross-origin server to the tests.
+    let cross_origin_global = match &cross_origin {
+        Some(url) => format!(
+            "globalThis.wasm_bindgen_test_cross_origin = {};\n",
+            serde_json::to_string(url)?
+        ),
+        None => String::new(),
+    };
+    js_to_execute.push_str(&cross_origin_global);
+
+    // With `--break-on-panic`, the panic hook of wasm-bindgen-test calls this
+    // right where a test panicked, so devtools pause with the Wasm frames of
+    // the panic inspectable. Headless runs have no one to debug them.
+    let break_on_panic_global = if cli.break_on_panic && !headless {
+        "globalThis.__wbg_break_on_panic = () => { debugger; };\n"
+    } else {
+        ""
+    };
+    js_to_execute.push_str(break_on_panic_global);
+
+    // Console shim to inject into user-spawned workers. Called with a function
+    // posting a message to the parent and the URL relative worker URLs resolve
+    // against. Logs to worker's own DevTools, then forwards to the parent, and
+    // patches the worker's own Worker constructor so that workers it spawns are
+    // shimmed too, relaying their logs up to the main page. Panics reported by
+    // the panic hook of wasm-bindgen-test and uncaught errors, with their
+    // stacks, are forwarded the same way.
     let worker_console_shim = r#"
-["debug","log","info","warn","error"].forEach(m => {
-    const og = console[m];
-    console[m] = function(...a) {
-        og.apply(this, a);
-        postMessage(["__wbgtest_" + m, a]);
+(function __wbg_shim(post, base) {
+    ["debug","log","info","warn","error"].forEach(m => {
+        const og = console[m];
+        console[m] = function(...a) {
+            og.apply(this, a);
+            post(["__wbgtest_" + m, a]);
+        };
+    });
+    // The trap a panic ends in adds nothing to the panic itself.
+    let panicked = false;
+    self.__wbg_report_panic = panic => {
+        panicked = true;
+        post(["__wbgtest_panic", panic]);
     };
-});
-"#;
+    self.addEventListener('error', e => {
+        const error = e.error;
+        if (panicked && error instanceof WebAssembly.RuntimeError) return;
+        let text = `${e.message} (${e.filename}:${e.lineno})`;
+        if (error instanceof Error && error.stack) {
+            text = error.stack.startsWith(String(error)) ? error.stack : `${error}\n${error.stack}`;
+        }
+        post(["__wbgtest_uncaught", {error: text}]);
+    });
+    // Instances of the tests' module with their own memory don't have the
+    // panic hook of the page, so it's installed in them. Instances sharing the
+    // page's memory share its hook.
+    const hook = instance => {
+        const exports = instance?.exports ?? {};
+        if (exports.memory instanceof WebAssembly.Memory &&
+            typeof exports.__wbgtest_install_panic_hook === 'function') {
+            exports.__wbgtest_install_panic_hook();
+        }
+    };
+    for (const name of ['instantiate', 'instantiateStreaming']) {
+        const og = WebAssembly[name];
+        if (typeof og !== 'function') continue;
+        WebAssembly[name] = async function(...a) {
+            const result = await og.apply(this, a);
+            hook(result instanceof WebAssembly.Instance ? result : result.instance);
+            return result;
+        };
+    }
+    const OriginalInstance = WebAssembly.Instance;
+    WebAssembly.Instance = function(module, imports) {
+        const instance = new OriginalInstance(module, imports);
+        hook(instance);
+        return instance;
+    };
+    WebAssembly.Instance.prototype = OriginalInstance.prototype;
+    if (typeof Worker === 'undefined') return;
+    const prelude = base =>
+        '(' + __wbg_shim + ')(m => postMessage(m), ' + JSON.stringify(base) + ');\n';
+    const OriginalWorker = Worker;
+    self.Worker = function(url, options) {
+        let scriptUrl = url instanceof URL ? url.href : url;
+        if (typeof scriptUrl === 'string' && !scriptUrl.startsWith('blob:')) {
+            scriptUrl = new URL(scriptUrl, base).href;
+        }
+        if (typeof scriptUrl === 'string' && scriptUrl.startsWith('blob:')) {
+            const xhr = new XMLHttpRequest();
+            xhr.open('GET', scriptUrl, false);
+            xhr.send();
+            if (xhr.status === 200 || xhr.status === 0) {
+                const blob = new Blob([prelude(base) + xhr.responseText], {type: 'application/javascript'});
+                scriptUrl = URL.createObjectURL(blob);
+            }
+        } else if (typeof scriptUrl === 'string') {
+            const isModule = options?.type === 'module';
+            const wrapper = prelude(scriptUrl) + (isModule
+                ? 'await import("' + scriptUrl + '");'
+                : 'importScripts("' + scriptUrl + '");');
+            const blob = new Blob([wrapper], {type: 'application/javascript'});
+            scriptUrl = URL.createObjectURL(blob);
+        }
+        const worker = new OriginalWorker(scriptUrl, options);
+        worker.addEventListener('message', e => {
+            if (Array.isArray(e.data) &&
+                typeof e.data[0] === 'string' &&
+                e.data[0].startsWith('__wbgtest_')) {
+                post(e.data);
+                e.stopImmediatePropagation();
+            }
+        });
+        return worker;
+    };
+    self.Worker.prototype = OriginalWorker.prototype;
+})"#;
 
-    // Console shim for SharedWorkers - needs to track ports from connections.
+    // Console shim for SharedWorkers - needs to track ports from connections
+    // and forwards logs to all of them.
     // Also captures uncaught errors since SharedWorker.onerror on the main thread
     // only fires for script load errors, not runtime errors.
     let shared_worker_console_shim = r#"
@@ -73,16 +436,34 @@ const __wbg_ports = [];
 self.addEventListener('connect', e => {
     __wbg_ports.push(e.ports[0]);
 });
+self.addEventListener('error', e => {
+    const msg = e.message || String(e);
+    console.error('Uncaught error in SharedWorker:', msg);
+});
+"#;
+
+    // Console shim for the modules of AudioWorklets. Worklets have no channel to
+    // the page but the ports of the processors they register, so logs are
+    // forwarded over those, buffering the ones logged before a processor is
+    // created.
+    let audio_worklet_console_shim = r#"
+const ports = [];
+const pending = [];
+const post = m => ports.length ? ports.forEach(p => p.postMessage(m)) : pending.push(m);
 ["debug","log","info","warn","error"].forEach(m => {
     const og = console[m];
     console[m] = function(...a) {
         og.apply(this, a);
-        __wbg_ports.forEach(p => p.postMessage(["__wbgtest_" + m, a]));
+        post(["__wbgtest_" + m, a.map(String)]);
     };
 });
-self.addEventListener('error', e => {
-    const msg = e.message || String(e);
-    console.error('Uncaught error in SharedWorker:', msg);
+const register = registerProcessor;
+globalThis.registerProcessor = (name, processor) => register(name, class extends processor {
+    constructor(...args) {
+        super(...args);
+        ports.push(this.port);
+        pending.splice(0).forEach(m => this.port.postMessage(m));
+    }
 });
 "#;
 
@@ -92,8 +473,33 @@ self.addEventListener('error', e => {
         r#"
 const __wbg_worker_console_shim = {shim};
 const __wbg_shared_worker_console_shim = {shared_shim};
+const __wbg_audio_worklet_console_shim = {audio_worklet_shim};
+
+function __wbg_worker_prelude(base) {{
+    return __wbg_worker_console_shim + '(m => postMessage(m), ' + JSON.stringify(base) + ');\n';
+}}
 
-function __wbg_worker_message_handler(e) {{
+function __wbg_shared_worker_prelude(base) {{
+    return __wbg_shared_worker_console_shim + __wbg_worker_console_shim +
+        '(m => __wbg_ports.forEach(p => p.postMessage(m)), ' + JSON.stringify(base) + ');\n';
+}}
+
+// Returns the output of the running test, which the logs of a user worker
+// spawned now are attributed to, even once another test is running.
+function __wbg_spawning_test_output() {{
+    return typeof __wbg_current_output === 'function' ? __wbg_current_output() : undefined;
+}}
+
+// Generates the id correlating the panics and errors of a worker spawned now
+// with the stack of the page spawning it, leaving out the frames of this
+// function and of the patched constructor.
+let __wbg_worker_count = 0;
+function __wbg_spawn_site() {{
+    const stack = (new Error().stack ?? '').split('\n').filter(l => l.trim() && l !== 'Error');
+    return {{ worker: String(++__wbg_worker_count), spawned_at: stack.slice(2).join('\n') }};
+}}
+
+function __wbg_worker_message_handler(e, output, spawn) {{
     if (e.data && Array.isArray(e.data) &&
         typeof e.data[0] === 'string' &&
         e.data[0].startsWith('__wbgtest_')) {{
@@ -108,6 +514,16 @@ function __wbg_worker_message_handler(e) {{
                     el.appendChild(document.createTextNode(String(msg) + '\n'));
                 }}
             }}
+            const on_method = window[`on_console_${{method}}`];
+            if (output && on_method) {{
+                __wbg_with_output(output, () => on_method(args));
+            }}
+        }} else if (method === 'panic' && output && typeof __wbg_worker_panic === 'function') {{
+            // Fail the test that spawned the worker rather than letting it
+            // wait for a worker that's gone.
+            __wbg_worker_panic(output, {{...args, ...spawn}});
+        }} else if (method === 'uncaught' && output && typeof __wbg_worker_error === 'function') {{
+            __wbg_worker_error(output, {{...args, ...spawn}});
         }}
         e.stopImmediatePropagation();
     }}
@@ -124,23 +540,25 @@ Worker = function(url, options) {{
         xhr.open('GET', scriptUrl, false);
         xhr.send();
         if (xhr.status === 200 || xhr.status === 0) {{
-            const shimmed = __wbg_worker_console_shim + xhr.responseText;
+            const shimmed = __wbg_worker_prelude(location.href) + xhr.responseText;
             const blob = new Blob([shimmed], {{type: 'application/javascript'}});
             scriptUrl = URL.createObjectURL(blob);
         }}
     }} else if (typeof scriptUrl === 'string') {{
         const isModule = options?.type === 'module';
         const wrapper = isModule
-            ? __wbg_worker_console_shim + 'await import("' + scriptUrl + '");'
-            : __wbg_worker_console_shim + 'importScripts("' + scriptUrl + '");';
+            ? __wbg_worker_prelude(scriptUrl) + 'await import("' + scriptUrl + '");'
+            : __wbg_worker_prelude(scriptUrl) + 'importScripts("' + scriptUrl + '");';
         const blob = new Blob([wrapper], {{type: 'application/javascript'}});
         scriptUrl = URL.createObjectURL(blob);
         if (isModule) {{
             options = {{...options, type: 'module'}};
         }}
     }}
+    const spawn = __wbg_spawn_site();
     const worker = new __wbg_OriginalWorker(scriptUrl, options);
-    worker.addEventListener('message', __wbg_worker_message_handler);
+    const output = __wbg_spawning_test_output();
+    worker.addEventListener('message', e => __wbg_worker_message_handler(e, output, spawn));
     return worker;
 }};
 Worker.prototype = __wbg_OriginalWorker.prototype;
@@ -156,29 +574,75 @@ SharedWorker = function(url, options) {{
         xhr.open('GET', scriptUrl, false);
         xhr.send();
         if (xhr.status === 200 || xhr.status === 0) {{
-            const shimmed = __wbg_shared_worker_console_shim + xhr.responseText;
+            const shimmed = __wbg_shared_worker_prelude(location.href) + xhr.responseText;
             const blob = new Blob([shimmed], {{type: 'application/javascript'}});
             scriptUrl = URL.createObjectURL(blob);
         }}
     }} else if (typeof scriptUrl === 'string') {{
         const isModule = options?.type === 'module';
         const wrapper = isModule
-            ? __wbg_shared_worker_console_shim + 'await import("' + scriptUrl + '");'
-            : __wbg_shared_worker_console_shim + 'importScripts("' + scriptUrl + '");';
+            ? __wbg_shared_worker_prelude(scriptUrl) + 'await import("' + scriptUrl + '");'
+            : __wbg_shared_worker_prelude(scriptUrl) + 'importScripts("' + scriptUrl + '");';
         const blob = new Blob([wrapper], {{type: 'application/javascript'}});
         scriptUrl = URL.createObjectURL(blob);
         if (isModule) {{
             options = {{...options, type: 'module'}};
         }}
     }}
+    const spawn = __wbg_spawn_site();
     const worker = new __wbg_OriginalSharedWorker(scriptUrl, options);
-    worker.port.addEventListener('message', __wbg_worker_message_handler);
+    const output = __wbg_spawning_test_output();
+    worker.port.addEventListener('message', e => __wbg_worker_message_handler(e, output, spawn));
     return worker;
 }};
 SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
+
+// Worklet modules are ES modules, so the console shim is imported by a wrapper
+// module before the worklet's own module.
+if (typeof AudioWorklet !== 'undefined') {{
+    const __wbg_OriginalAddModule = AudioWorklet.prototype.addModule;
+    AudioWorklet.prototype.addModule = function(url, options) {{
+        const blob = source => URL.createObjectURL(new Blob([source], {{type: 'application/javascript'}}));
+        const shim = blob(__wbg_audio_worklet_console_shim);
+        const module = new URL(url, location.href).href;
+        const wrapper = blob('import ' + JSON.stringify(shim) + ';\nimport ' + JSON.stringify(module) + ';\n');
+        return __wbg_OriginalAddModule.call(this, wrapper, options);
+    }};
+    const __wbg_OriginalAudioWorkletNode = AudioWorkletNode;
+    AudioWorkletNode = class extends __wbg_OriginalAudioWorkletNode {{
+        constructor(...args) {{
+            super(...args);
+            const output = __wbg_spawning_test_output();
+            this.port.addEventListener('message', e => __wbg_worker_message_handler(e, output));
+            this.port.start();
+        }}
+    }};
+}}
+
+// Service workers must be loaded from their own URL, so tag it for the server
+// to inject the console shim, keeping the script's path and so its scope.
+const __wbg_OriginalServiceWorkerRegister = navigator.serviceWorker
+    ? navigator.serviceWorker.register.bind(navigator.serviceWorker)
+    : undefined;
+let __wbg_service_worker_output;
+if (navigator.serviceWorker) {{
+    navigator.serviceWorker.register = function(url, options) {{
+        __wbg_service_worker_output = __wbg_spawning_test_output();
+        const scriptUrl = new URL(url, location.href);
+        if (scriptUrl.origin === location.origin) {{
+            scriptUrl.searchParams.set('{sw_param}', '1');
+        }}
+        return __wbg_OriginalServiceWorkerRegister(scriptUrl.href, options);
+    }};
+    navigator.serviceWorker.addEventListener('message', e =>
+        __wbg_worker_message_handler(e, __wbg_service_worker_output));
+    navigator.serviceWorker.startMessages();
+}}
 "#,
         shim = serde_json::to_string(worker_console_shim).unwrap(),
-        shared_shim = serde_json::to_string(shared_worker_console_shim).unwrap()
+        shared_shim = serde_json::to_string(shared_worker_console_shim).unwrap(),
+        audio_worklet_shim = serde_json::to_string(audio_worklet_console_shim).unwrap(),
+        sw_param = SERVICE_WORKER_PARAM,
     );
 
     // Add the worker constructor patch at the start
@@ -206,6 +670,18 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
         }
     "#;
 
+    let dump_suppressed = r#"
+        // Save the failure output suppressed by the limits on printed output
+        const suppressed_output = cx.suppressed_output();
+
+        if (suppressed_output !== undefined) {
+            await fetch("/__wasm_bindgen/suppressed-output", {
+                method: "POST",
+                body: suppressed_output
+            });
+        }
+    "#;
+
     let bench_import = if test_mode.no_modules() {
         "let __wbgbench_import = wasm_bindgen.__wbgbench_import;
         let __wbgbench_dump = wasm_bindgen.__wbgbench_dump;"
@@ -234,6 +710,13 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
         }
     "#;
 
+    // The glue tracks handles for `--detect-leaks`, see `leaks.js`.
+    let live_handles_import = match (cli.detect_leaks, test_mode.no_modules()) {
+        (false, _) => "",
+        (true, true) => "let __wbg_live_handles = wasm_bindgen.__wbg_live_handles;",
+        (true, false) => "__wbg_live_handles,",
+    };
+
     let wbg_import_script = if test_mode.no_modules() {
         format!(
             r#"
@@ -243,8 +726,17 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
             let __wbgtest_console_info = wasm_bindgen.__wbgtest_console_info;
             let __wbgtest_console_warn = wasm_bindgen.__wbgtest_console_warn;
             let __wbgtest_console_error = wasm_bindgen.__wbgtest_console_error;
+            let __wbgtest_console_group = wasm_bindgen.__wbgtest_console_group;
+            let __wbgtest_console_group_end = wasm_bindgen.__wbgtest_console_group_end;
+            let __wbgtest_console_table = wasm_bindgen.__wbgtest_console_table;
+            let __wbgtest_console_dir = wasm_bindgen.__wbgtest_console_dir;
+            let __wbgtest_current_output = wasm_bindgen.__wbgtest_current_output;
+            let __wbgtest_with_output = wasm_bindgen.__wbgtest_with_output;
+            let __wbgtest_worker_panic = wasm_bindgen.__wbgtest_worker_panic;
+            let __wbgtest_worker_error = wasm_bindgen.__wbgtest_worker_error;
             {cov_import}
             {bench_import}
+            {live_handles_import}
             let init = wasm_bindgen;
             "#,
         )
@@ -258,8 +750,17 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
                 __wbgtest_console_info,
                 __wbgtest_console_warn,
                 __wbgtest_console_error,
+                __wbgtest_console_group,
+                __wbgtest_console_group_end,
+                __wbgtest_console_table,
+                __wbgtest_console_dir,
+                __wbgtest_current_output,
+                __wbgtest_with_output,
+                __wbgtest_worker_panic,
+                __wbgtest_worker_error,
                 {cov_import}
                 {bench_import}
+                {live_handles_import}
                 default as init,
             }} from './{module}';
             "#,
@@ -268,7 +769,24 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
 
     let nocapture = cli.nocapture || cli.bench;
     let is_bench = cli.bench;
-    let args = cli.get_args(&tests);
+    let args = cli.get_args(&tests, &suppressed_output);
+
+    // With `--debug`, wait for the user to press a button and then pause in
+    // the debugger right before running the tests, so breakpoints can be set
+    // once the Wasm module has been loaded.
+    let debug_pause = if cli.debug {
+        r#"
+        const run = document.createElement('button');
+        run.textContent = 'Run tests';
+        document.body.prepend(run);
+        document.getElementById('output').textContent += "Paused, press `Run tests` to start.\n";
+        await new Promise(resolve => run.addEventListener('click', resolve, { once: true }));
+        run.remove();
+        debugger;
+        "#
+    } else {
+        ""
+    };
 
     if test_mode.is_worker() {
         let mut worker_script = if test_mode.no_modules() {
@@ -277,6 +795,8 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
             String::new()
         };
 
+        worker_script.push_str(&cross_origin_global);
+        worker_script.push_str(break_on_panic_global);
         worker_script.push_str(&wbg_import_script);
 
         match test_mode {
@@ -324,6 +844,11 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
             wrap("info");
             wrap("warn");
             wrap("error");
+            wrap("group");
+            wrap("groupCollapsed");
+            wrap("groupEnd");
+            wrap("table");
+            wrap("dir");
 
             async function run_in_worker(tests) {{
                 const wasm = await init("./{module}_bg.wasm");
@@ -335,6 +860,11 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
                 self.on_console_info = __wbgtest_console_info;
                 self.on_console_warn = __wbgtest_console_warn;
                 self.on_console_error = __wbgtest_console_error;
+                self.on_console_group = __wbgtest_console_group;
+                self.on_console_groupCollapsed = __wbgtest_console_group;
+                self.on_console_groupEnd = __wbgtest_console_group_end;
+                self.on_console_table = __wbgtest_console_table;
+                self.on_console_dir = __wbgtest_console_dir;
 
                 {args}
 
@@ -344,6 +874,7 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
 
                 await cx.run(tests.map(s => wasm[s]));
                 {cov_dump}
+                {dump_suppressed}
 
                 if ({is_bench}) {{
                     {dump_bench}
@@ -410,6 +941,7 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
             }});
 
             async function main(test) {{
+                {debug_pause}
                 port.postMessage(test)
             }}
 
@@ -450,7 +982,7 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
                         format!(
                             r#"
                             const url = "service.js?random=" + crypto.randomUUID();
-                            const registration = await navigator.serviceWorker.register(url, {{type: "{module}"}});
+                            const registration = await __wbg_OriginalServiceWorkerRegister(url, {{type: "{module}"}});
                             if (registration.installing) {{
                                 registration.installing.onerror = function(e) {{
                                     console.error('ServiceWorker error:', e.message);
@@ -478,6 +1010,7 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
         ));
     } else {
         js_to_execute.push_str(&wbg_import_script);
+        js_to_execute.push_str(SANDBOX);
 
         js_to_execute.push_str(&format!(
             r#"
@@ -495,6 +1028,15 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
                 window.on_console_info = __wbgtest_console_info;
                 window.on_console_warn = __wbgtest_console_warn;
                 window.on_console_error = __wbgtest_console_error;
+                window.on_console_group = __wbgtest_console_group;
+                window.on_console_groupCollapsed = __wbgtest_console_group;
+                window.on_console_groupEnd = __wbgtest_console_group_end;
+                window.on_console_table = __wbgtest_console_table;
+                window.on_console_dir = __wbgtest_console_dir;
+                window.__wbg_current_output = __wbgtest_current_output;
+                window.__wbg_with_output = __wbgtest_with_output;
+                window.__wbg_worker_panic = __wbgtest_worker_panic;
+                window.__wbg_worker_error = __wbgtest_worker_error;
 
                 {args}
 
@@ -502,8 +1044,10 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
                     {import_bench}
                 }}
 
+                {debug_pause}
                 await cx.run(test.map(s => wasm[s]));
                 {cov_dump}
+                {dump_suppressed}
 
                 if ({is_bench}) {{
                     {dump_bench}
@@ -525,80 +1069,110 @@ SharedWorker.prototype = __wbg_OriginalSharedWorker.prototype;
     // For now, always run forever on this port. We may update this later!
     let tmpdir = tmpdir.to_path_buf();
     let srv = Server::new(addr, move |request| {
-        // The root path gets our canned `index.html`. The two templates here
-        // differ slightly in the default routing of `console.log`, going to an
-        // HTML element during headless testing so we can try to scrape its
-        // output.
-        if request.url() == "/" {
-            let s = if headless {
-                include_str!("index-headless.html")
-            } else {
-                include_str!("index.html")
-            };
-            let s = s.replace("// {NOCAPTURE}", &format!("const nocapture = {nocapture};"));
-            let s = if !test_mode.is_worker() && test_mode.no_modules() {
-                s.replace(
+        log_request(log_requests, request, || {
+            // The root path gets our canned `index.html`. The two templates here
+            // differ slightly in the default routing of `console.log`, going to an
+            // HTML element during headless testing so we can try to scrape its
+            // output.
+            if request.url() == "/" {
+                let s = if headless {
+                    include_str!("index-headless.html")
+                } else {
+                    include_str!("index.html")
+                };
+                let s = s.replace("// {NOCAPTURE}", &format!("const nocapture = {nocapture};"));
+                let s = s.replace(
+                    "// {FORWARD_CONSOLE}",
+                    &format!("const forward_console = {};", bidi::enabled()),
+                );
+                let s =
+                    if !test_mode.is_worker() && test_mode.no_modules() {
+                        s.replace(
                     "<!-- {IMPORT_SCRIPTS} -->",
                     &format!("<script src='{module}.js'></script>\n<script src='run.js'></script>"),
                 )
-            } else {
-                s.replace(
-                    "<!-- {IMPORT_SCRIPTS} -->",
-                    "<script src='run.js' type=module></script>",
-                )
-            };
+                    } else {
+                        s.replace(
+                            "<!-- {IMPORT_SCRIPTS} -->",
+                            "<script src='run.js' type=module></script>",
+                        )
+                    };
 
-            let mut response = Response::from_data("text/html", s);
+                let mut response = Response::from_data("text/html", s);
 
-            if isolate_origin {
-                set_isolate_origin_headers(&mut response)
+                if isolate_origin {
+                    set_isolate_origin_headers(&mut response)
+                }
+                set_custom_headers(&headers, request, &mut response);
+
+                return response;
+            } else if request.url() == "/__wasm_bindgen/coverage" {
+                let module_signature = request
+                    .header("Module-Signature")
+                    .expect("sent coverage data without module signature")
+                    .parse()
+                    .expect("sent invalid module signature");
+
+                return if let Err(e) = handle_coverage_dump(test_mode, module_signature, request) {
+                    let s: &str = &format!("Failed to dump coverage: {e}");
+                    log::error!("{s}");
+                    let mut ret = Response::text(s);
+                    ret.status_code = 500;
+                    ret
+                } else {
+                    Response::empty_204()
+                };
+            } else if request.url() == "/__wasm_bindgen/suppressed-output" {
+                return if let Err(e) = handle_suppressed_output(&suppressed_output, request) {
+                    let s: &str = &format!("Failed to save suppressed output: {e}");
+                    log::error!("{s}");
+                    let mut ret = Response::text(s);
+                    ret.status_code = 500;
+                    ret
+                } else {
+                    Response::empty_204()
+                };
+            } else if request.url() == "/__wasm_bindgen/bench/fetch" {
+                return handle_benchmark_fetch(&benchmark);
+            } else if request.url() == "/__wasm_bindgen/bench/dump" {
+                return if let Err(e) = handle_benchmark_dump(&benchmark, request) {
+                    let s: &str = &format!("Failed to save benchmark: {e}");
+                    log::error!("{s}");
+                    let mut ret = Response::text(s);
+                    ret.status_code = 500;
+                    ret
+                } else {
+                    Response::empty_204()
+                };
             }
 
-            return response;
-        } else if request.url() == "/__wasm_bindgen/coverage" {
-            let module_signature = request
-                .header("Module-Signature")
-                .expect("sent coverage data without module signature")
-                .parse()
-                .expect("sent invalid module signature");
-
-            return if let Err(e) = handle_coverage_dump(module_signature, request) {
-                let s: &str = &format!("Failed to dump coverage: {e}");
-                log::error!("{s}");
-                let mut ret = Response::text(s);
-                ret.status_code = 500;
-                ret
-            } else {
-                Response::empty_204()
-            };
-        } else if request.url() == "/__wasm_bindgen/bench/fetch" {
-            return handle_benchmark_fetch(&benchmark);
-        } else if request.url() == "/__wasm_bindgen/bench/dump" {
-            return if let Err(e) = handle_benchmark_dump(&benchmark, request) {
-                let s: &str = &format!("Failed to save benchmark: {e}");
-                log::error!("{s}");
-                let mut ret = Response::text(s);
-                ret.status_code = 500;
-                ret
-            } else {
-                Response::empty_204()
-            };
-        }
+            if let Some(response) = serve_websocket(&websockets, request) {
+                return response;
+            }
+            if let Some(mut response) = serve_mock(&mocks, request) {
+                if isolate_origin {
+                    set_isolate_origin_headers(&mut response)
+                }
+                set_custom_headers(&headers, request, &mut response);
+                return compress(request, apply_range(request, response), compression);
+            }
 
-        // Otherwise we need to find the asset here. It may either be in our
-        // temporary directory (generated files) or in the main directory
-        // (relative import paths to JS). Try to find both locations.
-        let mut response = try_asset(request, &tmpdir);
-        if !response.is_success() {
-            response = try_asset(request, ".".as_ref());
-        }
-        // Make sure browsers don't cache anything (Chrome appeared to with this
-        // header?)
-        response.headers.retain(|(k, _)| k != "Cache-Control");
-        if isolate_origin {
-            set_isolate_origin_headers(&mut response)
-        }
-        response
+            // Otherwise we need to find the asset here. It may either be in our
+            // temporary directory (generated files), a directory mounted with
+            // `--mount` or in the main directory (relative import paths to JS).
+            let mut response = serve_static(request, &tmpdir, &mounts);
+            if request.get_param(SERVICE_WORKER_PARAM).is_some() {
+                response = shim_service_worker(response);
+            }
+            // Make sure browsers don't cache anything (Chrome appeared to with this
+            // header?)
+            response.headers.retain(|(k, _)| k != "Cache-Control");
+            if isolate_origin {
+                set_isolate_origin_headers(&mut response)
+            }
+            set_custom_headers(&headers, request, &mut response);
+            compress(request, apply_range(request, response), compression)
+        })
     })
     .map_err(|e| anyhow!("{e}"))?;
     Ok(srv)
@@ -621,17 +1195,40 @@ fn handle_benchmark_dump(path: &Path, request: &Request) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn handle_coverage_dump(module_signature: u64, request: &Request) -> anyhow::Result<()> {
+fn handle_suppressed_output(path: &Path, request: &Request) -> anyhow::Result<()> {
+    let mut data = Vec::new();
+    if let Some(mut body) = request.data() {
+        body.read_to_end(&mut data)?;
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+fn handle_coverage_dump(
+    test_mode: TestMode,
+    module_signature: u64,
+    request: &Request,
+) -> anyhow::Result<()> {
     // This is run after all tests are done and dumps the data received in the request
-    // into a single profraw file
+    // into a single profraw file, named after the mode and the browser
+    let mode = match request.header("User-Agent").and_then(browser_name) {
+        Some(browser) => format!("{}-{browser}", test_mode.name()),
+        None => test_mode.name().to_string(),
+    };
     let profraw_path = wasm_bindgen_test_shared::coverage_path(
-        env::var("LLVM_PROFILE_FILE").ok().as_deref(),
+        Some(&coverage_profile_file(Some(&mode))?.to_string_lossy()),
         process::id(),
         env::temp_dir()
             .to_str()
             .context("failed to parse path to temporary directory")?,
         module_signature,
     );
+    if let Some(dir) = Path::new(&profraw_path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
     let mut profraw = std::fs::File::create(profraw_path)?;
     let mut data = Vec::new();
     if let Some(mut r_data) = request.data() {
@@ -644,6 +1241,20 @@ fn handle_coverage_dump(module_signature: u64, request: &Request) -> anyhow::Res
     Ok(())
 }
 
+/// The name of the browser sending `user_agent`, to tell apart the coverage of
+/// runs in several browsers.
+fn browser_name(user_agent: &str) -> Option<&'static str> {
+    // Chrome and Edge mention Safari, and Edge mentions Chrome.
+    [
+        ("Edg/", "edge"),
+        ("Firefox/", "firefox"),
+        ("Chrome/", "chrome"),
+        ("Safari/", "safari"),
+    ]
+    .into_iter()
+    .find_map(|(token, name)| user_agent.contains(token).then_some(name))
+}
+
 /*
  * Set the Cross-Origin-Opener-Policy and Cross-Origin_Embedder-Policy headers
  * on the Server response to enable worker context sharing, as described in:
@@ -659,6 +1270,306 @@ fn set_isolate_origin_headers(response: &mut Response) {
         Cow::Borrowed("Cross-Origin-Embedder-Policy"),
         Cow::Borrowed("require-corp"),
     ));
+    // Lets subresources, e.g. the scripts of workers, be embedded by a page
+    // requiring CORP regardless of how they're requested.
+    response.headers.push((
+        Cow::Borrowed("Cross-Origin-Resource-Policy"),
+        Cow::Borrowed("same-origin"),
+    ));
+}
+
+/// Adds the headers of the `rules` matching the requested path, replacing any
+/// header of the same name.
+fn set_custom_headers(rules: &[HeaderRule], request: &Request, response: &mut Response) {
+    let path = request.url();
+    for rule in rules.iter().filter(|rule| rule.matches(&path)) {
+        for (name, value) in &rule.headers {
+            response
+                .headers
+                .retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+            response
+                .headers
+                .push((Cow::Owned(name.clone()), Cow::Owned(value.clone())));
+        }
+    }
+}
+
+/// Runs `handler` for `request`, printing the method, path, status and
+/// latency of the request to stderr if `enabled`.
+fn log_request(enabled: bool, request: &Request, handler: impl FnOnce() -> Response) -> Response {
+    let start = Instant::now();
+    let response = handler();
+    if enabled {
+        eprintln!(
+            "[server] {} {} {} {:.1?}",
+            request.method(),
+            request.raw_url(),
+            response.status_code,
+            start.elapsed(),
+        );
+    }
+    response
+}
+
+/// Answers a `Range` request with the requested part of a successful
+/// `response`, as rouille always sends whole files. Requests for multiple
+/// ranges are answered with the whole file, which the specification allows.
+fn apply_range(request: &Request, mut response: Response) -> Response {
+    if response.status_code != 200 {
+        return response;
+    }
+    response
+        .headers
+        .push((Cow::Borrowed("Accept-Ranges"), Cow::Borrowed("bytes")));
+    let Some(header) = request.header("Range") else {
+        return response;
+    };
+
+    let body = match take_body(&mut response) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let len = body.len();
+    match parse_range(header, len) {
+        Some(Some(range)) => {
+            response.status_code = 206;
+            response.headers.push((
+                Cow::Borrowed("Content-Range"),
+                Cow::Owned(format!("bytes {}-{}/{len}", range.start, range.end - 1)),
+            ));
+            response.data = ResponseBody::from_data(&body[range]);
+        }
+        Some(None) => {
+            response.status_code = 416;
+            response.headers.push((
+                Cow::Borrowed("Content-Range"),
+                Cow::Owned(format!("bytes */{len}")),
+            ));
+        }
+        None => response.data = ResponseBody::from_data(body),
+    }
+    response
+}
+
+/// On-the-fly compression of the test server's responses, from
+/// `--compression`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Compression {
+    /// `Content-Encoding: gzip`
+    Gzip,
+    /// `Content-Encoding: br`
+    Brotli,
+}
+
+impl Compression {
+    fn encoding(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Brotli => "br",
+        }
+    }
+
+    fn encode(self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            Compression::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                encoder.write_all(body)?;
+                Ok(encoder.into_inner())
+            }
+        }
+    }
+}
+
+/// Compresses a successful `response` with `compression` if the client
+/// accepts it. Media types that are compressed already are sent as is.
+fn compress(
+    request: &Request,
+    mut response: Response,
+    compression: Option<Compression>,
+) -> Response {
+    let Some(compression) = compression else {
+        return response;
+    };
+    if response.status_code != 200 {
+        return response;
+    }
+    let encoding = compression.encoding();
+    let accepted = request
+        .header("Accept-Encoding")
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|coding| coding.split(';').next())
+        .any(|coding| coding.trim().eq_ignore_ascii_case(encoding));
+    let content_type = response
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Content-Type"))
+        .map(|(_, v)| v.to_ascii_lowercase())
+        .unwrap_or_default();
+    let precompressed = ["image/", "video/", "audio/", "font/woff"]
+        .iter()
+        .any(|prefix| content_type.starts_with(prefix))
+        && content_type != "image/svg+xml";
+    response
+        .headers
+        .push((Cow::Borrowed("Vary"), Cow::Borrowed("Accept-Encoding")));
+    if !accepted || precompressed {
+        return response;
+    }
+
+    let body = match take_body(&mut response) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    match compression.encode(&body) {
+        Ok(encoded) => {
+            response
+                .headers
+                .push((Cow::Borrowed("Content-Encoding"), Cow::Borrowed(encoding)));
+            response.data = ResponseBody::from_data(encoded);
+            response
+        }
+        Err(e) => {
+            let s = format!("failed to compress response: {e}");
+            log::error!("{s}");
+            Response::text(s).with_status_code(500)
+        }
+    }
+}
+
+/// Reads the whole body of `response`, or returns an error response.
+fn take_body(response: &mut Response) -> Result<Vec<u8>, Response> {
+    let (mut reader, _) =
+        std::mem::replace(&mut response.data, ResponseBody::empty()).into_reader_and_size();
+    let mut body = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut body) {
+        let s = format!("failed to read response body: {e}");
+        log::error!("{s}");
+        return Err(Response::text(s).with_status_code(500));
+    }
+    Ok(body)
+}
+
+/// Parses a `Range` header for a body of `len` bytes. Returns `None` if the
+/// header isn't a single byte range, and `Some(None)` if the range can't be
+/// satisfied.
+fn parse_range(header: &str, len: usize) -> Option<Option<Range<usize>>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        // `bytes=-N` requests the last `N` bytes.
+        let suffix: usize = end.parse().ok()?;
+        len.saturating_sub(suffix)..len
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end = if end.is_empty() {
+            len
+        } else {
+            let end: usize = end.parse().ok()?;
+            if end < start {
+                return None;
+            }
+            end.saturating_add(1).min(len)
+        };
+        start..end
+    };
+    if range.start >= len || range.is_empty() {
+        return Some(None);
+    }
+    Some(Some(range))
+}
+
+/// Spawns the server of `--cross-origin-server`, serving the same files, mocks
+/// and WebSocket endpoints as the main server from a different origin.
+pub(crate) fn spawn_cross_origin(
+    addr: &SocketAddr,
+    tmpdir: &Path,
+    headers: Vec<HeaderRule>,
+    mounts: Vec<Mount>,
+    mocks: Vec<MockEndpoint>,
+    websockets: Vec<WebSocketEndpoint>,
+    compression: Option<Compression>,
+    log_requests: bool,
+) -> Result<Server<impl Fn(&Request) -> Response + Send + Sync>, Error> {
+    let tmpdir = tmpdir.to_path_buf();
+    let srv = Server::new(addr, move |request| {
+        log_request(log_requests, request, || {
+            if let Some(response) = serve_websocket(&websockets, request) {
+                return response;
+            }
+            let mut response = match cors_preflight(request) {
+                Some(response) => response,
+                None => match serve_mock(&mocks, request) {
+                    Some(response) => response,
+                    None => {
+                        let mut response = serve_static(request, &tmpdir, &mounts);
+                        response.headers.retain(|(k, _)| k != "Cache-Control");
+                        response
+                    }
+                },
+            };
+            // Lets the test page embed the responses while cross-origin
+            // isolated.
+            response.headers.push((
+                Cow::Borrowed("Cross-Origin-Resource-Policy"),
+                Cow::Borrowed("cross-origin"),
+            ));
+            allow_origin(request, &mut response);
+            set_custom_headers(&headers, request, &mut response);
+            compress(request, apply_range(request, response), compression)
+        })
+    })
+    .map_err(|e| anyhow!("{e}"))?;
+    Ok(srv)
+}
+
+/// Answers a CORS preflight request to the cross-origin server, allowing the
+/// requested method and headers.
+fn cors_preflight(request: &Request) -> Option<Response> {
+    if request.method() != "OPTIONS" {
+        return None;
+    }
+    let method = request.header("Access-Control-Request-Method")?.to_string();
+    let mut response = Response::empty_204()
+        .with_unique_header("Access-Control-Allow-Methods", method)
+        .with_unique_header("Access-Control-Max-Age", "600");
+    if let Some(headers) = request.header("Access-Control-Request-Headers") {
+        response = response.with_unique_header("Access-Control-Allow-Headers", headers.to_string());
+    }
+    Some(response)
+}
+
+/// Lets the origin of `request`, the test page's, read the response of the
+/// cross-origin server, including with credentials. The `headers` rules can
+/// override these.
+fn allow_origin(request: &Request, response: &mut Response) {
+    let headers = &mut response.headers;
+    match request.header("Origin") {
+        Some(origin) => {
+            headers.push((
+                Cow::Borrowed("Access-Control-Allow-Origin"),
+                Cow::Owned(origin.to_string()),
+            ));
+            headers.push((
+                Cow::Borrowed("Access-Control-Allow-Credentials"),
+                Cow::Borrowed("true"),
+            ));
+        }
+        None => headers.push((
+            Cow::Borrowed("Access-Control-Allow-Origin"),
+            Cow::Borrowed("*"),
+        )),
+    }
+    headers.push((Cow::Borrowed("Vary"), Cow::Borrowed("Origin")));
 }
 
 /// Spawn a server for running doctests in a browser.
@@ -669,8 +1580,18 @@ pub(crate) fn spawn_doctest(
     module: &'static str,
     tmpdir: &Path,
     test_mode: TestMode,
+    fallback: Option<&Path>,
     isolate_origin: bool,
+    headers: Vec<HeaderRule>,
+    mounts: Vec<Mount>,
+    mocks: Vec<MockEndpoint>,
+    websockets: Vec<WebSocketEndpoint>,
+    compression: Option<Compression>,
+    log_requests: bool,
+    nocapture: bool,
 ) -> Result<Server<impl Fn(&Request) -> Response + Send + Sync>, Error> {
+    let start = doctest::run_start("wasm");
+
     // For worker modes, we need to create a worker script
     if test_mode.is_worker() {
         let module_type = if test_mode.no_modules() {
@@ -699,6 +1620,7 @@ pub(crate) fn spawn_doctest(
 async function runDoctest() {{
     try {{
         const wasm = await wasm_bindgen('./{module}_bg.wasm');
+        {start}
         wasm.main();
         self.postMessage({{ type: 'success' }});
     }} catch (e) {{
@@ -716,6 +1638,7 @@ runDoctest();
 async function runDoctest() {{
     try {{
         const wasm = await init('./{module}_bg.wasm');
+        {start}
         wasm.main();
         self.postMessage({{ type: 'success' }});
     }} catch (e) {{
@@ -736,7 +1659,7 @@ const worker = new Worker('worker.js', {{ type: '{module_type}' }});
 worker.onmessage = function(e) {{
     if (e.data.type === 'console') {{
         const text = e.data.args.join(' ');
-        document.getElementById('output').textContent += text + '\n';
+        document.getElementById(nocapture ? 'output' : 'console_output').textContent += text + '\n';
     }} else if (e.data.type === 'success') {{
         document.getElementById('output').textContent += "\ntest result: ok. 1 passed; 0 failed\n";
     }} else if (e.data.type === 'error') {{
@@ -776,6 +1699,7 @@ self.addEventListener('connect', async (e) => {{
 
     try {{
         const wasm = await wasm_bindgen('./{module}_bg.wasm');
+        {start}
         wasm.main();
         port.postMessage({{ type: 'success' }});
     }} catch (e) {{
@@ -803,6 +1727,7 @@ self.addEventListener('connect', async (e) => {{
 
     try {{
         const wasm = await init('./{module}_bg.wasm');
+        {start}
         wasm.main();
         port.postMessage({{ type: 'success' }});
     }} catch (e) {{
@@ -824,7 +1749,7 @@ port.start();
 port.onmessage = function(e) {{
     if (e.data.type === 'console') {{
         const text = e.data.args.join(' ');
-        document.getElementById('output').textContent += text + '\n';
+        document.getElementById(nocapture ? 'output' : 'console_output').textContent += text + '\n';
     }} else if (e.data.type === 'success') {{
         document.getElementById('output').textContent += "\ntest result: ok. 1 passed; 0 failed\n";
     }} else if (e.data.type === 'error') {{
@@ -867,6 +1792,7 @@ self.addEventListener('message', async (e) => {{
 
     try {{
         const wasm = await wasm_bindgen('./{module}_bg.wasm');
+        {start}
         wasm.main();
         port.postMessage({{ type: 'success' }});
     }} catch (e) {{
@@ -897,6 +1823,7 @@ self.addEventListener('message', async (e) => {{
 
     try {{
         const wasm = await init('./{module}_bg.wasm');
+        {start}
         wasm.main();
         port.postMessage({{ type: 'success' }});
     }} catch (e) {{
@@ -929,7 +1856,7 @@ document.getElementById('output').textContent = "Running doctest...\n";
     channel.port1.onmessage = function(e) {{
         if (e.data.type === 'console') {{
             const text = e.data.args.join(' ');
-            document.getElementById('output').textContent += text + '\n';
+            document.getElementById(nocapture ? 'output' : 'console_output').textContent += text + '\n';
         }} else if (e.data.type === 'success') {{
             document.getElementById('output').textContent += "\ntest result: ok. 1 passed; 0 failed\n";
         }} else if (e.data.type === 'error') {{
@@ -958,6 +1885,50 @@ document.getElementById('output').textContent = "Running doctest...\n";
 
         let js_path = tmpdir.join("run.js");
         fs::write(&js_path, main_page_script).context("failed to write JS file")?;
+    } else if let Some(wasm_path) = fallback {
+        // Fallback mode (main thread) - run the raw wasm with stub imports, like
+        // `doctest::execute_node_fallback`
+        fs::copy(wasm_path, tmpdir.join("doctest.wasm")).context("failed to copy wasm file")?;
+
+        let js_to_execute = format!(
+            r#"
+{STUB_IMPORTS}
+async function runDoctest() {{
+    const output = document.getElementById('output');
+    output.textContent = "Loading Wasm module...\n";
+    try {{
+        const response = await fetch('doctest.wasm');
+        const wasmModule = await WebAssembly.compile(await response.arrayBuffer());
+
+        // Build import object with stubs for all required imports
+        const imports = {{}};
+        for (const imp of WebAssembly.Module.imports(wasmModule)) {{
+            if (!imports[imp.module]) {{
+                imports[imp.module] = stubImports[imp.module] || {{}};
+            }}
+        }}
+
+        const instance = await WebAssembly.instantiate(wasmModule, imports);
+        if (typeof instance.exports.main !== 'function') {{
+            throw new Error('No main function found in doctest wasm module');
+        }}
+        output.textContent += "Running doctest...\n";
+        instance.exports.main();
+        output.textContent += "\ntest result: ok. 1 passed; 0 failed\n";
+        output.textContent += "\nnote: This doctest ran in fallback mode without wasm-bindgen.\n";
+    }} catch (e) {{
+        console.error('Doctest failed:', e);
+        output.textContent += "\nDoctest failed: " + e + "\n";
+        output.textContent += "test result: FAILED. 0 passed; 1 failed\n";
+        output.textContent += "\nnote: This doctest ran in fallback mode without wasm-bindgen.\n";
+    }}
+}}
+runDoctest();
+"#
+        );
+
+        let js_path = tmpdir.join("run.js");
+        fs::write(&js_path, js_to_execute).context("failed to write JS file")?;
     } else {
         // Browser mode (main thread) - run doctest directly on the page
         let js_to_execute = if test_mode.no_modules() {
@@ -968,6 +1939,7 @@ async function runDoctest() {{
     try {{
         const wasm = await wasm_bindgen('./{module}_bg.wasm');
         document.getElementById('output').textContent += "Running doctest...\n";
+        {start}
         wasm.main();
         document.getElementById('output').textContent += "\ntest result: ok. 1 passed; 0 failed\n";
     }} catch (e) {{
@@ -989,6 +1961,7 @@ async function runDoctest() {{
     try {{
         const wasm = await init('./{module}_bg.wasm');
         document.getElementById('output').textContent += "Running doctest...\n";
+        {start}
         wasm.main();
         document.getElementById('output').textContent += "\ntest result: ok. 1 passed; 0 failed\n";
     }} catch (e) {{
@@ -1006,46 +1979,236 @@ runDoctest();
         fs::write(&js_path, js_to_execute).context("failed to write JS file")?;
     }
 
+    // The fallback script doesn't need the wasm-bindgen generated JS
+    let classic_scripts = test_mode.no_modules() && fallback.is_none();
     let tmpdir = tmpdir.to_path_buf();
     let srv = Server::new(addr, move |request| {
-        if request.url() == "/" {
-            let s = if headless {
-                include_str!("index-headless.html")
-            } else {
-                include_str!("index.html")
-            };
-            let s = s.replace("// {NOCAPTURE}", "const nocapture = true;");
-            let s = if test_mode.no_modules() {
-                s.replace(
+        log_request(log_requests, request, || {
+            if request.url() == "/" {
+                let s = if headless {
+                    include_str!("index-headless.html")
+                } else {
+                    include_str!("index.html")
+                };
+                let s = s.replace("// {NOCAPTURE}", &format!("const nocapture = {nocapture};"));
+                let s = s.replace(
+                    "// {FORWARD_CONSOLE}",
+                    &format!("const forward_console = {};", bidi::enabled()),
+                );
+                let s =
+                    if classic_scripts {
+                        s.replace(
                     "<!-- {IMPORT_SCRIPTS} -->",
                     &format!("<script src='{module}.js'></script>\n<script src='run.js'></script>"),
                 )
-            } else {
-                s.replace(
-                    "<!-- {IMPORT_SCRIPTS} -->",
-                    "<script src='run.js' type=module></script>",
-                )
-            };
+                    } else {
+                        s.replace(
+                            "<!-- {IMPORT_SCRIPTS} -->",
+                            "<script src='run.js' type=module></script>",
+                        )
+                    };
+
+                let mut response = Response::from_data("text/html", s);
+                if isolate_origin {
+                    set_isolate_origin_headers(&mut response)
+                }
+                set_custom_headers(&headers, request, &mut response);
+                return response;
+            }
+
+            if let Some(response) = serve_websocket(&websockets, request) {
+                return response;
+            }
+            if let Some(mut response) = serve_mock(&mocks, request) {
+                if isolate_origin {
+                    set_isolate_origin_headers(&mut response)
+                }
+                set_custom_headers(&headers, request, &mut response);
+                return compress(request, apply_range(request, response), compression);
+            }
 
-            let mut response = Response::from_data("text/html", s);
+            // Serve static files
+            let mut response = serve_static(request, &tmpdir, &mounts);
+            response.headers.retain(|(k, _)| k != "Cache-Control");
             if isolate_origin {
                 set_isolate_origin_headers(&mut response)
             }
-            return response;
-        }
-
-        // Serve static files
-        let mut response = try_asset(request, &tmpdir);
-        if !response.is_success() {
-            response = try_asset(request, ".".as_ref());
-        }
-        response.headers.retain(|(k, _)| k != "Cache-Control");
-        if isolate_origin {
-            set_isolate_origin_headers(&mut response)
-        }
-        response
+            set_custom_headers(&headers, request, &mut response);
+            compress(request, apply_range(request, response), compression)
+        })
     })
     .map_err(|e| anyhow!("{e}"))?;
 
     Ok(srv)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Some(0..100)));
+        assert_eq!(parse_range(" bytes=100 - 199 ", 1000), Some(Some(100..200)));
+        assert_eq!(parse_range("bytes=900-", 1000), Some(Some(900..1000)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Some(900..1000)));
+        // Ranges extending past the end are clamped to it.
+        assert_eq!(parse_range("bytes=900-1999", 1000), Some(Some(900..1000)));
+        assert_eq!(parse_range("bytes=-2000", 1000), Some(Some(0..1000)));
+    }
+
+    #[test]
+    fn unsatisfiable_byte_ranges() {
+        // Answered with `416 Range Not Satisfiable`.
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(None));
+        assert_eq!(parse_range("bytes=1000-1099", 1000), Some(None));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(None));
+        assert_eq!(parse_range("bytes=0-", 0), Some(None));
+    }
+
+    #[test]
+    fn ignored_byte_ranges() {
+        // Answered with the whole body.
+        assert_eq!(parse_range("bytes=0-99,200-299", 1000), None);
+        assert_eq!(parse_range("bytes=99-0", 1000), None);
+        assert_eq!(parse_range("items=0-99", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("bytes=0", 1000), None);
+    }
+
+    const BODY: &[u8] = b"console.log('compressed');\n";
+
+    fn compressed(accept_encoding: &str, content_type: &str, compression: Compression) -> Response {
+        let request = Request::fake_http(
+            "GET",
+            "/index.js",
+            vec![("Accept-Encoding".to_string(), accept_encoding.to_string())],
+            Vec::new(),
+        );
+        let response = Response::from_data(content_type.to_string(), BODY);
+        compress(&request, response, Some(compression))
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &**v)
+    }
+
+    #[test]
+    fn compression_negotiation() {
+        for accept_encoding in ["gzip", "deflate, GZIP;q=0.5", "br,gzip"] {
+            let response = compressed(accept_encoding, "text/javascript", Compression::Gzip);
+            assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+            assert_eq!(header(&response, "Vary"), Some("Accept-Encoding"));
+        }
+        for accept_encoding in ["", "identity", "deflate", "gzip-x"] {
+            let response = compressed(accept_encoding, "text/javascript", Compression::Gzip);
+            assert_eq!(header(&response, "Content-Encoding"), None);
+            assert_eq!(header(&response, "Vary"), Some("Accept-Encoding"));
+        }
+        let response = compressed("gzip", "text/javascript", Compression::Brotli);
+        assert_eq!(header(&response, "Content-Encoding"), None);
+
+        // Media that is compressed already is sent as is.
+        let response = compressed("gzip", "image/png", Compression::Gzip);
+        assert_eq!(header(&response, "Content-Encoding"), None);
+        let response = compressed("gzip", "image/svg+xml", Compression::Gzip);
+        assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+
+        // So are unsuccessful responses, and any without `--compression`.
+        let request = Request::fake_http(
+            "GET",
+            "/missing.js",
+            vec![("Accept-Encoding".to_string(), "gzip".to_string())],
+            Vec::new(),
+        );
+        let response = compress(&request, Response::empty_404(), Some(Compression::Gzip));
+        assert_eq!(header(&response, "Content-Encoding"), None);
+        let response = compress(&request, Response::text("ok"), None);
+        assert_eq!(header(&response, "Content-Encoding"), None);
+    }
+
+    #[test]
+    fn compression_round_trip() {
+        let mut response = compressed("gzip", "text/javascript", Compression::Gzip);
+        let body = take_body(&mut response).ok().unwrap();
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, BODY);
+
+        let mut response = compressed("br", "text/javascript", Compression::Brotli);
+        assert_eq!(header(&response, "Content-Encoding"), Some("br"));
+        let body = take_body(&mut response).ok().unwrap();
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(&body[..], 4096)
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, BODY);
+    }
+
+    fn cross_origin_request(method: &str, headers: &[(&str, &str)]) -> Request {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Request::fake_http(method, "/api/users", headers, Vec::new())
+    }
+
+    #[test]
+    fn cross_origin_headers() {
+        let origin = "http://127.0.0.1:8000";
+        let request = cross_origin_request("GET", &[("Origin", origin)]);
+        assert!(cors_preflight(&request).is_none());
+        let mut response = Response::text("[]");
+        allow_origin(&request, &mut response);
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Origin"),
+            Some(origin)
+        );
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Credentials"),
+            Some("true")
+        );
+        assert_eq!(header(&response, "Vary"), Some("Origin"));
+
+        // Requests without an `Origin`, like navigations, are allowed from
+        // anywhere.
+        let request = cross_origin_request("GET", &[]);
+        let mut response = Response::text("[]");
+        allow_origin(&request, &mut response);
+        assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(header(&response, "Access-Control-Allow-Credentials"), None);
+    }
+
+    #[test]
+    fn cross_origin_preflight() {
+        let request = cross_origin_request(
+            "OPTIONS",
+            &[
+                ("Origin", "http://127.0.0.1:8000"),
+                ("Access-Control-Request-Method", "PUT"),
+                ("Access-Control-Request-Headers", "content-type,x-token"),
+            ],
+        );
+        let response = cors_preflight(&request).unwrap();
+        assert_eq!(response.status_code, 204);
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Methods"),
+            Some("PUT")
+        );
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Headers"),
+            Some("content-type,x-token")
+        );
+        assert_eq!(header(&response, "Access-Control-Max-Age"), Some("600"));
+
+        // Other `OPTIONS` requests are served like any other.
+        let request = cross_origin_request("OPTIONS", &[("Origin", "http://127.0.0.1:8000")]);
+        assert!(cors_preflight(&request).is_none());
+    }
+}
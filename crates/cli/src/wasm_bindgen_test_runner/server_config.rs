@@ -0,0 +1,246 @@
+//! Configuration of the test server serving browser tests: extra response
+//! headers, see [`HeaderRule`], mock endpoints, see [`MockEndpoint`], and
+//! scripted WebSocket endpoints, see [`WebSocketEndpoint`].
+//!
+//! Each is read from a JSON file holding an array, named by
+//! `--response-headers`, `--mocks` and `--websockets`, or by
+//! `WASM_BINDGEN_TEST_RESPONSE_HEADERS`, `WASM_BINDGEN_TEST_MOCKS` and
+//! `WASM_BINDGEN_TEST_WEBSOCKETS`.
+
+use anyhow::{bail, Context, Error};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::Cli;
+
+/// Extra response headers sent by the test server for the paths matching
+/// `path`, a glob where `*` matches within a path segment and `**` across
+/// segments. Later rules override the headers of earlier ones.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderRule {
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+impl HeaderRule {
+    pub fn matches(&self, path: &str) -> bool {
+        glob_match(&self.path, path)
+    }
+}
+
+/// A mock HTTP endpoint implemented by the test server, answering requests
+/// with the method `method` for the paths matching the glob `path` with the
+/// contents of the file `body`. The first matching endpoint wins.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockEndpoint {
+    pub path: String,
+    /// The HTTP method, defaults to `GET`.
+    #[serde(default = "MockEndpoint::default_method")]
+    pub method: String,
+    /// The status code, defaults to `200`.
+    #[serde(default = "MockEndpoint::default_status")]
+    pub status: u16,
+    /// The file the response body is read from, relative to the current
+    /// directory. An empty body is sent if unset.
+    pub body: Option<PathBuf>,
+    /// The `Content-Type`, guessed from the extension of `body` if unset.
+    pub content_type: Option<String>,
+    /// Milliseconds to wait before responding.
+    #[serde(default)]
+    pub delay: u64,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl MockEndpoint {
+    fn default_method() -> String {
+        "GET".to_string()
+    }
+
+    fn default_status() -> u16 {
+        200
+    }
+
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        self.method.eq_ignore_ascii_case(method) && glob_match(&self.path, path)
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay)
+    }
+}
+
+/// A scripted WebSocket endpoint of the test server for the paths matching
+/// the glob `path`. The steps of `script` are run in order for every
+/// connection. Without a script, messages are echoed back.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketEndpoint {
+    pub path: String,
+    #[serde(default)]
+    pub script: Vec<WebSocketStep>,
+}
+
+impl WebSocketEndpoint {
+    /// The `/ws-echo` endpoint of `--ws-echo`.
+    pub fn echo() -> WebSocketEndpoint {
+        WebSocketEndpoint {
+            path: "/ws-echo".to_string(),
+            script: Vec::new(),
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        glob_match(&self.path, path)
+    }
+}
+
+/// A step of a [`WebSocketEndpoint`] script.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebSocketStep {
+    /// Sends a text message.
+    Send(String),
+    /// Waits for a text message, closing the connection if it differs.
+    Expect(String),
+}
+
+/// The configuration of the test server.
+#[derive(Debug, Default)]
+pub struct ServerConfig {
+    pub headers: Vec<HeaderRule>,
+    pub mocks: Vec<MockEndpoint>,
+    pub websockets: Vec<WebSocketEndpoint>,
+}
+
+impl ServerConfig {
+    /// Loads the files given by the options of `cli` or the environment,
+    /// adding the `/ws-echo` endpoint if `ws_echo`.
+    pub fn load(cli: &Cli, ws_echo: bool) -> Result<ServerConfig, Error> {
+        let headers = load_file(
+            cli.response_headers.as_deref(),
+            "WASM_BINDGEN_TEST_RESPONSE_HEADERS",
+        )?;
+        let mocks: Vec<MockEndpoint> = load_file(cli.mocks.as_deref(), "WASM_BINDGEN_TEST_MOCKS")?;
+        for mock in &mocks {
+            if let Some(body) = mock.body.as_ref().filter(|body| !body.is_file()) {
+                bail!(
+                    "body `{}` of the mock endpoint `{}` is not a file",
+                    body.display(),
+                    mock.path
+                );
+            }
+        }
+        let mut websockets = load_file(cli.websockets.as_deref(), "WASM_BINDGEN_TEST_WEBSOCKETS")?;
+        if ws_echo {
+            websockets.insert(0, WebSocketEndpoint::echo());
+        }
+        Ok(ServerConfig {
+            headers,
+            mocks,
+            websockets,
+        })
+    }
+}
+
+/// Reads the JSON array in the file at `path`, or else in the file named by
+/// the environment variable `var`, if any.
+fn load_file<T: DeserializeOwned>(path: Option<&Path>, var: &str) -> Result<Vec<T>, Error> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => match env::var_os(var) {
+            Some(path) => PathBuf::from(path),
+            None => return Ok(Vec::new()),
+        },
+    };
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read `{}`", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| format!("invalid `{}`", path.display()))
+}
+
+/// Matches `path` against a glob where `*` matches any characters but `/`, `**`
+/// any characters and `?` a single character but `/`.
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let boundaries = |s: &str, end: usize| {
+        (0..=end)
+            .filter(|&i| s.is_char_boundary(i))
+            .collect::<Vec<_>>()
+    };
+    if let Some(rest) = pattern.strip_prefix("**") {
+        return boundaries(path, path.len())
+            .into_iter()
+            .any(|i| glob_match(rest, &path[i..]));
+    }
+    if let Some(rest) = pattern.strip_prefix('*') {
+        let segment = path.find('/').unwrap_or(path.len());
+        return boundaries(path, segment)
+            .into_iter()
+            .any(|i| glob_match(rest, &path[i..]));
+    }
+    match (pattern.chars().next(), path.chars().next()) {
+        (None, None) => true,
+        (Some(p), Some(c)) if p == c || (p == '?' && c != '/') => {
+            glob_match(&pattern[p.len_utf8()..], &path[c.len_utf8()..])
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mocks.json");
+        fs::write(
+            &path,
+            r#"[{ "path": "/api/*", "status": 201 }, { "path": "/api/**", "method": "post" }]"#,
+        )
+        .unwrap();
+        let mocks: Vec<MockEndpoint> = load_file(Some(&path), "").unwrap();
+        assert_eq!(mocks[0].status, 201);
+        assert!(mocks[0].matches("GET", "/api/users"));
+        assert!(!mocks[0].matches("POST", "/api/users"));
+        assert!(mocks[1].matches("POST", "/api/users/1"));
+
+        fs::write(&path, r#"{ "mocks": [] }"#).unwrap();
+        let error = load_file::<MockEndpoint>(Some(&path), "").unwrap_err();
+        assert!(format!("{error:#}").contains("invalid type"), "{error:#}");
+
+        let missing = dir.path().join("missing.json");
+        assert!(load_file::<HeaderRule>(Some(&missing), "").is_err());
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("/api/*", "/api/users"));
+        assert!(glob_match("/api/*.json", "/api/users.json"));
+        assert!(glob_match("/api/*", "/api/"));
+        assert!(!glob_match("/api/*", "/api/users/1"));
+        assert!(glob_match("/api/**", "/api/users/1"));
+        assert!(glob_match("**.wasm", "/pkg/test_bg.wasm"));
+
+        assert!(glob_match("/v?/users", "/v1/users"));
+        assert!(!glob_match("/v?/users", "/v10/users"));
+        assert!(!glob_match("/v?users", "/v/users"));
+        assert!(glob_match("/caf?", "/café"));
+
+        // Patterns match the whole path.
+        assert!(glob_match("/index.html", "/index.html"));
+        assert!(!glob_match("/index.html", "/index.html.gz"));
+        assert!(!glob_match("index.html", "/index.html"));
+
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "/"));
+        assert!(!glob_match("/", ""));
+    }
+}
@@ -0,0 +1,273 @@
+//! Size report of the Wasm module of a test binary after `wasm-bindgen`
+//! processed it, for `--size-report` and `--size-baseline`.
+//!
+//! The report breaks the module down by section and lists its largest
+//! functions, named from the name section. A baseline stores the sizes of the
+//! modules of all the test binaries sharing it, by binary name, so that a
+//! regression of any of them past `--max-size-regression` fails the run.
+
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+use wasmparser::{KnownCustom, Name, Parser, Payload, TypeRef};
+
+/// Number of functions listed in the report.
+const LARGEST_FUNCTIONS: usize = 10;
+
+/// The sizes of a module, as stored in a baseline.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Sizes {
+    /// Size of the whole module, in bytes.
+    pub total: u64,
+    /// Size of the contents of each section, by name. Custom sections are
+    /// named `custom <name>`.
+    pub sections: BTreeMap<String, u64>,
+}
+
+/// The breakdown of a module.
+pub struct Report {
+    sizes: Sizes,
+    /// The largest function bodies, largest first, with their names.
+    functions: Vec<(String, u64)>,
+}
+
+impl Report {
+    /// Breaks down the module at `wasm`.
+    pub fn new(wasm: &Path) -> Result<Report, Error> {
+        let bytes =
+            fs::read(wasm).with_context(|| format!("failed to read `{}`", wasm.display()))?;
+        let mut sizes = Sizes {
+            total: bytes.len() as u64,
+            sections: BTreeMap::new(),
+        };
+        let mut bodies = Vec::new();
+        let mut names = HashMap::new();
+        let mut next_index = 0;
+        for payload in Parser::new(0).parse_all(&bytes) {
+            let payload = payload?;
+            if let Some((id, range)) = payload.as_section() {
+                let name = match &payload {
+                    Payload::CustomSection(section) => format!("custom {}", section.name()),
+                    _ => section_name(id).to_string(),
+                };
+                *sizes.sections.entry(name).or_default() += range.len() as u64;
+            }
+            match payload {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        if let TypeRef::Func(_) = import?.ty {
+                            next_index += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    bodies.push((next_index, body.range().len() as u64));
+                    next_index += 1;
+                }
+                Payload::CustomSection(section) => {
+                    if let KnownCustom::Name(reader) = section.as_known() {
+                        for name in reader {
+                            let Name::Function(functions) = name? else {
+                                continue;
+                            };
+                            for naming in functions {
+                                let naming = naming?;
+                                let name = match rustc_demangle::try_demangle(naming.name) {
+                                    Ok(demangled) => format!("{demangled:#}"),
+                                    Err(_) => naming.name.to_string(),
+                                };
+                                names.insert(naming.index, name);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        bodies.sort_by(|(a_index, a), (b_index, b)| b.cmp(a).then(a_index.cmp(b_index)));
+        let functions = bodies
+            .into_iter()
+            .take(LARGEST_FUNCTIONS)
+            .map(|(index, size)| {
+                let name = names
+                    .remove(&index)
+                    .unwrap_or_else(|| format!("wasm-function[{index}]"));
+                (name, size)
+            })
+            .collect();
+        Ok(Report { sizes, functions })
+    }
+
+    /// Prints the breakdown of the module of the test binary `binary`, with
+    /// the changes since `baseline`.
+    pub fn print(&self, binary: &str, baseline: Option<&Sizes>) {
+        let total = self.sizes.total;
+        let change = |size: u64, before: Option<u64>| match before {
+            Some(before) if before != size => format!(" ({})", delta(size, before)),
+            Some(_) => " (unchanged)".to_string(),
+            None => String::new(),
+        };
+        println!(
+            "size of `{binary}` after wasm-bindgen: {}{}",
+            bytes(total),
+            change(total, baseline.map(|baseline| baseline.total))
+        );
+
+        println!("    sections:");
+        let mut sections: Vec<_> = self.sizes.sections.iter().collect();
+        sections.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+        let width = sections.iter().map(|(name, _)| name.len()).max();
+        for (name, &size) in sections {
+            let before =
+                baseline.map(|baseline| baseline.sections.get(name).copied().unwrap_or_default());
+            println!(
+                "        {name:width$}  {:>10}  {:>5.1}%{}",
+                bytes(size),
+                percent(size, total),
+                change(size, before),
+                width = width.unwrap_or_default(),
+            );
+        }
+
+        if !self.functions.is_empty() {
+            println!("    largest functions:");
+            for (name, size) in &self.functions {
+                println!("        {:>10}  {name}", bytes(*size));
+            }
+        }
+        println!();
+    }
+
+    /// Returns the sizes of the module of `binary` in the baseline at `path`.
+    /// The module is added to the baseline if it isn't there yet, returning
+    /// `None`.
+    pub fn baseline(&self, path: &Path, binary: &str) -> Result<Option<Sizes>, Error> {
+        let mut baseline: BTreeMap<String, Sizes> = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).with_context(|| {
+                format!("failed to parse the size baseline `{}`", path.display())
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(Error::from(e).context(format!(
+                    "failed to read the size baseline `{}`",
+                    path.display()
+                )))
+            }
+        };
+        if let Some(sizes) = baseline.get(binary) {
+            return Ok(Some(sizes.clone()));
+        }
+
+        baseline.insert(binary.to_string(), self.sizes.clone());
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create `{}`", dir.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&baseline)? + "\n")
+            .with_context(|| format!("failed to write `{}`", path.display()))?;
+        println!(
+            "saved the size of `{binary}` to the size baseline `{}`",
+            path.display()
+        );
+        Ok(None)
+    }
+
+    /// The error of a module of `binary` that grew by more than
+    /// `max_regression` percent over its size in the `baseline` at `path`.
+    pub fn regression(
+        &self,
+        baseline: &Sizes,
+        max_regression: f64,
+        binary: &str,
+        path: &Path,
+    ) -> Option<Error> {
+        let total = self.sizes.total;
+        let regression = (total as f64 - baseline.total as f64) / baseline.total as f64 * 100.;
+        (regression > max_regression).then(|| {
+            anyhow!(
+                "the Wasm module of `{binary}` grew from {} to {} ({}), more than the allowed \
+                 {max_regression}% over the size baseline `{}`; remove `{binary}` from it to \
+                 accept the new size",
+                bytes(baseline.total),
+                bytes(total),
+                delta(total, baseline.total),
+                path.display(),
+            )
+        })
+    }
+}
+
+/// The name of the test binary at `file`, without the hash Cargo appends to
+/// it, so that it stays the same across builds.
+pub fn binary_name(file: &Path) -> String {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    match stem.rsplit_once('-') {
+        Some((name, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            name.to_string()
+        }
+        _ => stem.into_owned(),
+    }
+}
+
+fn section_name(id: u8) -> &'static str {
+    match id {
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+fn bytes(size: u64) -> String {
+    let mut scaled = size as f64;
+    if size < 1024 {
+        return format!("{size} B");
+    }
+    for unit in ["KiB", "MiB"] {
+        scaled /= 1024.;
+        if scaled < 1024. {
+            return format!("{scaled:.1} {unit}");
+        }
+    }
+    format!("{:.1} GiB", scaled / 1024.)
+}
+
+fn percent(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        size as f64 / total as f64 * 100.
+    }
+}
+
+/// Renders the change from `before` to `size`, like `+1.2 KiB, +0.5%`.
+fn delta(size: u64, before: u64) -> String {
+    let (sign, difference) = if size >= before {
+        ("+", size - before)
+    } else {
+        ("-", before - size)
+    };
+    if before == 0 {
+        format!("{sign}{}", bytes(difference))
+    } else {
+        format!(
+            "{sign}{}, {sign}{:.1}%",
+            bytes(difference),
+            percent(difference, before)
+        )
+    }
+}
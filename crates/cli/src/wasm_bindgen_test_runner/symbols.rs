@@ -0,0 +1,241 @@
+//! Symbolication of Wasm frames in stack traces.
+//!
+//! Engines report frames of Wasm functions by the index of the function and
+//! the offset of the instruction in the module, like
+//! `wasm://wasm/0123abcd:wasm-function[1234]:0x5678`. Browsers and Node.js
+//! differ in whether they show the name of the function next to it, and none
+//! show where in the Rust sources the instruction is. [`WasmSymbols`] rewrites
+//! such locations into the demangled name of the function from the module's
+//! name section and the offset into the function, followed by the location in
+//! the sources when the module has DWARF debug info.
+
+use gimli::{EndianRcSlice, LittleEndian, SectionId};
+use log::debug;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use wasmparser::{KnownCustom, Name, Parser, Payload, TypeRef};
+
+type Reader = EndianRcSlice<LittleEndian>;
+
+#[derive(Default)]
+pub struct WasmSymbols {
+    /// Demangled names of the functions, by index.
+    names: HashMap<u32, String>,
+    /// Ranges of the bodies of the functions in the module, by index.
+    bodies: HashMap<u32, Range<usize>>,
+    /// Offset of the contents of the code section, which DWARF addresses are
+    /// relative to.
+    code_start: usize,
+    dwarf: Option<addr2line::Context<Reader>>,
+}
+
+impl WasmSymbols {
+    /// Reads the symbols of the module at `wasm`. A module that can't be read
+    /// has none, leaving stack traces as they are.
+    pub fn load(wasm: &Path) -> WasmSymbols {
+        fs::read(wasm)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| WasmSymbols::parse(&bytes))
+            .unwrap_or_else(|e| {
+                debug!("failed to read symbols of `{}`: {e:?}", wasm.display());
+                WasmSymbols::default()
+            })
+    }
+
+    fn parse(bytes: &[u8]) -> anyhow::Result<WasmSymbols> {
+        let mut symbols = WasmSymbols::default();
+        let mut debug_sections = HashMap::new();
+        let mut next_index = 0;
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload? {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        if let TypeRef::Func(_) = import?.ty {
+                            next_index += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionStart { range, .. } => symbols.code_start = range.start,
+                Payload::CodeSectionEntry(body) => {
+                    symbols.bodies.insert(next_index, body.range());
+                    next_index += 1;
+                }
+                Payload::CustomSection(section) => match section.as_known() {
+                    KnownCustom::Name(names) => {
+                        for name in names {
+                            let Name::Function(functions) = name? else {
+                                continue;
+                            };
+                            for naming in functions {
+                                let naming = naming?;
+                                let name = match rustc_demangle::try_demangle(naming.name) {
+                                    Ok(demangled) => format!("{demangled:#}"),
+                                    Err(_) => naming.name.to_string(),
+                                };
+                                symbols.names.insert(naming.index, name);
+                            }
+                        }
+                    }
+                    _ if section.name().starts_with(".debug_") => {
+                        debug_sections.insert(section.name().to_string(), section.data());
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        if debug_sections.contains_key(".debug_info") {
+            let dwarf = gimli::Dwarf::load(|id: SectionId| {
+                let data = debug_sections.get(id.name()).copied().unwrap_or_default();
+                Ok::<_, gimli::Error>(Reader::new(Rc::from(data), LittleEndian))
+            })?;
+            symbols.dwarf = Some(addr2line::Context::from_dwarf(dwarf)?);
+        }
+        Ok(symbols)
+    }
+
+    /// The demangled name of the function at `index`, if the module names it.
+    pub fn name(&self, index: u32) -> Option<&str> {
+        self.names.get(&index).map(String::as_str)
+    }
+
+    /// Rewrites the locations of Wasm frames in `line`, like
+    /// `wasm://wasm/0123abcd:wasm-function[1234]:0x5678`, into the function
+    /// and offset they are at, like `my_crate::f+0x1a`, and the location in
+    /// the sources, like `my_crate::f+0x1a at src/lib.rs:12:5`.
+    pub fn rewrite<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        const MARKER: &str = "wasm-function[";
+        if self.names.is_empty() || !line.contains(MARKER) {
+            return Cow::Borrowed(line);
+        }
+
+        let mut out = String::new();
+        let mut copied = 0;
+        let mut searched = 0;
+        while let Some(found) = line[searched..].find(MARKER) {
+            let marker = searched + found;
+            searched = marker + MARKER.len();
+            let rest = &line[searched..];
+            let Some((index, offset, end)) = frame(rest) else {
+                continue;
+            };
+            let Some(name) = self.names.get(&index) else {
+                continue;
+            };
+
+            let start = line[copied..marker]
+                .rfind([' ', '\t', '(', '@'])
+                .map_or(copied, |i| copied + i + 1);
+            out.push_str(&line[copied..start]);
+            out.push_str(name);
+            if let Some(offset) = offset {
+                if let Some(body) = self.bodies.get(&index).filter(|b| b.contains(&offset)) {
+                    out.push_str(&format!("+{:#x}", offset - body.start));
+                }
+                if let Some(location) = self.location(offset) {
+                    out.push_str(&format!(" at {location}"));
+                }
+            }
+            searched += end;
+            copied = searched;
+        }
+        out.push_str(&line[copied..]);
+        Cow::Owned(out)
+    }
+
+    /// Runs `command` like [`Command::status`], relaying its stdout and stderr
+    /// a line at a time with the Wasm frames they mention rewritten.
+    pub fn status(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        if self.names.is_empty() {
+            return command.status();
+        }
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // The symbols can't be shared between threads, so lines are read by a
+        // thread per stream and rewritten here.
+        let (tx, rx) = mpsc::channel();
+        let readers = [
+            read_lines(child.stdout.take(), false, tx.clone()),
+            read_lines(child.stderr.take(), true, tx),
+        ];
+        for (stderr, line) in rx {
+            let line = match String::from_utf8(line) {
+                Ok(line) => self.rewrite(&line).into_owned().into_bytes(),
+                Err(e) => e.into_bytes(),
+            };
+            if stderr {
+                io::stderr().lock().write_all(&line)?;
+            } else {
+                io::stdout().lock().write_all(&line)?;
+            }
+        }
+        for reader in readers {
+            reader.join().unwrap()?;
+        }
+        child.wait()
+    }
+
+    /// The location in the sources of the instruction at `offset` in the
+    /// module, according to its DWARF debug info.
+    fn location(&self, offset: usize) -> Option<String> {
+        let address = offset.checked_sub(self.code_start)?;
+        let location = self.dwarf.as_ref()?.find_location(address as u64).ok()??;
+        let mut out = location.file?.to_string();
+        if let Some(line) = location.line {
+            out.push_str(&format!(":{line}"));
+            if let Some(column) = location.column {
+                out.push_str(&format!(":{column}"));
+            }
+        }
+        Some(out)
+    }
+}
+
+/// Parses `<index>]` or `<index>]:0x<offset>` at the start of `s`, returning
+/// them and the length parsed.
+fn frame(s: &str) -> Option<(u32, Option<usize>, usize)> {
+    let close = s.find(']')?;
+    let index = s[..close].parse().ok()?;
+    let Some(hex) = s[close + 1..].strip_prefix(":0x") else {
+        return Some((index, None, close + 1));
+    };
+    let len = hex
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(hex.len());
+    match usize::from_str_radix(&hex[..len], 16) {
+        Ok(offset) => Some((index, Some(offset), close + 1 + 3 + len)),
+        Err(_) => Some((index, None, close + 1)),
+    }
+}
+
+/// Sends the lines of `stream` to `tx`, with whether they come from stderr.
+fn read_lines(
+    stream: Option<impl Read + Send + 'static>,
+    stderr: bool,
+    tx: mpsc::Sender<(bool, Vec<u8>)>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let Some(stream) = stream else {
+            return Ok(());
+        };
+        let mut stream = BufReader::new(stream);
+        loop {
+            let mut line = Vec::new();
+            if stream.read_until(b'\n', &mut line)? == 0 || tx.send((stderr, line)).is_err() {
+                return Ok(());
+            }
+        }
+    })
+}
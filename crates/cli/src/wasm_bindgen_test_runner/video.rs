@@ -0,0 +1,144 @@
+//! Opt-in video recording of headless browser sessions.
+//!
+//! Enabled with `WASM_BINDGEN_TEST_RECORD_VIDEO=1`. If the browser isn't
+//! headless and an X11 display is available (e.g. `xvfb-run`), the display is
+//! recorded with `ffmpeg -f x11grab`. Otherwise WebDriver screenshots are taken
+//! periodically while the tests run and, if `ffmpeg` is in `PATH`, encoded into
+//! a video afterwards.
+
+use super::headless::KillOnDrop;
+use anyhow::{bail, Context, Error};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Frame rate of the recorded video.
+const FPS: u32 = 5;
+
+pub enum Recorder {
+    /// `ffmpeg` grabbing the X11 display, killed if the recording doesn't
+    /// finish.
+    X11 { ffmpeg: KillOnDrop, output: PathBuf },
+    /// Screenshots saved to `dir`, encoded when the recording finishes.
+    Frames {
+        dir: PathBuf,
+        output: PathBuf,
+        count: usize,
+        last: Option<Instant>,
+    },
+}
+
+impl Recorder {
+    /// Starts recording into `artifacts` if enabled through the environment.
+    pub fn start(artifacts: &Path, headless: bool) -> Result<Option<Recorder>, Error> {
+        if env::var_os("WASM_BINDGEN_TEST_RECORD_VIDEO").is_none() {
+            return Ok(None);
+        }
+        fs::create_dir_all(artifacts)
+            .with_context(|| format!("failed to create `{}`", artifacts.display()))?;
+        let output = artifacts.join("recording.webm");
+
+        if let (false, Some(display), true) = (headless, env::var_os("DISPLAY"), has_ffmpeg()) {
+            let ffmpeg = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-f", "x11grab"])
+                .args(["-framerate", &FPS.to_string(), "-i"])
+                .arg(display)
+                .args(["-c:v", "libvpx", "-b:v", "1M"])
+                .arg(&output)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .context("failed to spawn `ffmpeg`")?;
+            return Ok(Some(Recorder::X11 {
+                ffmpeg: KillOnDrop(ffmpeg),
+                output,
+            }));
+        }
+
+        let dir = artifacts.join("recording");
+        drop(fs::remove_dir_all(&dir));
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create `{}`", dir.display()))?;
+        Ok(Some(Recorder::Frames {
+            dir,
+            output,
+            count: 0,
+            last: None,
+        }))
+    }
+
+    /// Records a frame if one is due, calling `screenshot` for its PNG data.
+    /// This is a no-op when the display is recorded by `ffmpeg`.
+    pub fn capture(
+        &mut self,
+        screenshot: impl FnOnce() -> Result<Vec<u8>, Error>,
+    ) -> Result<(), Error> {
+        let Recorder::Frames {
+            dir, count, last, ..
+        } = self
+        else {
+            return Ok(());
+        };
+        if last.is_some_and(|last| last.elapsed() < Duration::from_secs(1) / FPS) {
+            return Ok(());
+        }
+        *last = Some(Instant::now());
+        let path = dir.join(format!("frame-{count:05}.png"));
+        fs::write(&path, screenshot()?)
+            .with_context(|| format!("failed to write `{}`", path.display()))?;
+        *count += 1;
+        Ok(())
+    }
+
+    /// Stops recording, returning the path of the video or, if it couldn't be
+    /// encoded, of the directory containing the frames.
+    pub fn finish(self) -> Result<PathBuf, Error> {
+        match self {
+            Recorder::X11 { mut ffmpeg, output } => {
+                // `q` asks ffmpeg to finish writing the file and exit.
+                if let Some(stdin) = &mut ffmpeg.stdin {
+                    drop(stdin.write_all(b"q"));
+                }
+                drop(ffmpeg.stdin.take());
+                let status = ffmpeg.wait()?;
+                if !status.success() {
+                    bail!("`ffmpeg` failed to record the display: {status}");
+                }
+                Ok(output)
+            }
+            Recorder::Frames {
+                dir, output, count, ..
+            } => {
+                if count == 0 || !has_ffmpeg() {
+                    return Ok(dir);
+                }
+                let status = Command::new("ffmpeg")
+                    .args(["-y", "-loglevel", "error"])
+                    .args(["-framerate", &FPS.to_string(), "-i"])
+                    .arg(dir.join("frame-%05d.png"))
+                    .args(["-c:v", "libvpx", "-b:v", "1M"])
+                    .arg(&output)
+                    .stdin(Stdio::null())
+                    .status()
+                    .context("failed to spawn `ffmpeg`")?;
+                if !status.success() {
+                    return Ok(dir);
+                }
+                drop(fs::remove_dir_all(&dir));
+                Ok(output)
+            }
+        }
+    }
+}
+
+fn has_ffmpeg() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
@@ -0,0 +1,249 @@
+//! Loading and validation of the `webdriver.json` configuration file.
+//!
+//! The file is a JSON object whose keys are either raw WebDriver capabilities
+//! (passed through to the session unmodified, as has always been the case) or
+//! one of the per-browser sections `firefox`, `chrome`, `edge` and `safari`.
+//! A per-browser section is a friendlier way of spelling the vendor specific
+//! capabilities:
+//!
+//! ```json
+//! {
+//!   "firefox": {
+//!     "binary": "/opt/firefox/firefox",
+//!     "args": ["-private"],
+//!     "prefs": { "dom.workers.maxPerDomain": 1024 },
+//!     "headless": true
+//!   },
+//!   "chrome": {
+//!     "args": ["--use-fake-ui-for-media-stream"]
+//!   }
+//! }
+//! ```
+//!
+//! The test server is configured separately, see [`super::server_config`].
+
+use anyhow::{bail, Context, Error};
+use serde::Deserialize;
+use serde_json::{Map, Value as Json};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+
+/// Options that can use to customize and configure a WebDriver session.
+pub type Capabilities = Map<String, Json>;
+
+/// Per-browser section of `webdriver.json`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrowserConfig {
+    /// Path to the browser binary the driver should launch.
+    pub binary: Option<String>,
+    /// Extra command line arguments for the browser.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Profile preferences, e.g. Firefox's `dom.workers.maxPerDomain`.
+    #[serde(default)]
+    pub prefs: Map<String, Json>,
+    /// Whether the browser is started headless, defaults to `true`.
+    pub headless: Option<bool>,
+}
+
+impl BrowserConfig {
+    pub fn headless(&self) -> bool {
+        self.headless.unwrap_or(true)
+    }
+}
+
+/// The parsed contents of `webdriver.json`.
+#[derive(Debug, Default)]
+pub struct WebDriverConfig {
+    /// Raw capabilities, merged into every new session.
+    pub capabilities: Capabilities,
+    pub firefox: BrowserConfig,
+    pub chrome: BrowserConfig,
+    pub edge: BrowserConfig,
+    pub safari: BrowserConfig,
+}
+
+impl WebDriverConfig {
+    /// Loads `webdriver.json` from the current directory, or from the path
+    /// in `WASM_BINDGEN_TEST_WEBDRIVER_JSON` if set. A missing file yields the
+    /// default configuration.
+    pub fn load() -> Result<WebDriverConfig, Error> {
+        let path = env::var("WASM_BINDGEN_TEST_WEBDRIVER_JSON")
+            .unwrap_or_else(|_| "webdriver.json".to_string());
+        println!("Try find `webdriver.json` for configure browser's capabilities:");
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => {
+                println!("Ok");
+                contents
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                println!("Not found");
+                return Ok(WebDriverConfig::default());
+            }
+            Err(e) => return Err(e).context(format!("failed to read `{path}`")),
+        };
+        let json = serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse `{path}` as JSON"))?;
+        WebDriverConfig::from_json(json).with_context(|| format!("invalid `{path}`"))
+    }
+
+    fn from_json(json: Json) -> Result<WebDriverConfig, Error> {
+        let Json::Object(mut capabilities) = json else {
+            bail!("expected a JSON object at the top level");
+        };
+        let mut section = |name: &str| -> Result<BrowserConfig, Error> {
+            match capabilities.remove(name) {
+                Some(value) => serde_json::from_value(value)
+                    .with_context(|| format!("invalid `{name}` section")),
+                None => Ok(BrowserConfig::default()),
+            }
+        };
+        let firefox = section("firefox")?;
+        let chrome = section("chrome")?;
+        let edge = section("edge")?;
+        let safari = section("safari")?;
+        // These configured the test server before it got its own options.
+        for (key, var) in [
+            ("headers", "WASM_BINDGEN_TEST_RESPONSE_HEADERS"),
+            ("mocks", "WASM_BINDGEN_TEST_MOCKS"),
+            ("websockets", "WASM_BINDGEN_TEST_WEBSOCKETS"),
+        ] {
+            if capabilities.contains_key(key) {
+                bail!("the `{key}` section moved to the file named by `{var}`");
+            }
+        }
+        let config = WebDriverConfig {
+            capabilities,
+            firefox,
+            chrome,
+            edge,
+            safari,
+        };
+
+        let safari = &config.safari;
+        if safari.binary.is_some() || !safari.args.is_empty() || !safari.prefs.is_empty() {
+            bail!(
+                "invalid `safari` section: safaridriver does not support \
+                 `binary`, `args` or `prefs`"
+            );
+        }
+        if safari.headless == Some(true) {
+            bail!("invalid `safari` section: Safari cannot be run headless");
+        }
+        Ok(config)
+    }
+}
+
+/// Merges `config` into the vendor specific `options` object of `cap`, e.g.
+/// `moz:firefoxOptions`, appending `headless_arg` to the browser arguments if
+/// the browser should run headless.
+pub fn apply_browser_config(
+    cap: &mut Capabilities,
+    options: &str,
+    config: &BrowserConfig,
+    headless_arg: &str,
+) -> Result<(), Error> {
+    let Json::Object(options_obj) = cap
+        .entry(options.to_string())
+        .or_insert_with(|| Json::Object(Map::new()))
+    else {
+        bail!("`{options}` capability must be a JSON object");
+    };
+
+    if let Some(binary) = &config.binary {
+        options_obj.insert("binary".to_string(), Json::String(binary.clone()));
+    }
+
+    if !config.prefs.is_empty() {
+        let Json::Object(prefs) = options_obj
+            .entry("prefs".to_string())
+            .or_insert_with(|| Json::Object(Map::new()))
+        else {
+            bail!("`{options}.prefs` capability must be a JSON object");
+        };
+        prefs.extend(config.prefs.clone());
+    }
+
+    let Json::Array(args) = options_obj
+        .entry("args".to_string())
+        .or_insert_with(|| Json::Array(Vec::new()))
+    else {
+        bail!("`{options}.args` capability must be a JSON array");
+    };
+    args.extend(config.args.iter().cloned().map(Json::String));
+    if config.headless() {
+        args.push(Json::String(headless_arg.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error(json: Json) -> String {
+        format!("{:#}", WebDriverConfig::from_json(json).unwrap_err())
+    }
+
+    #[test]
+    fn browser_sections() {
+        let config = WebDriverConfig::from_json(json!({
+            "firefox": {
+                "binary": "/opt/firefox/firefox",
+                "args": ["-private"],
+                "prefs": { "dom.workers.maxPerDomain": 1024 },
+                "headless": false
+            },
+            "chrome": { "args": ["--use-fake-ui-for-media-stream"] },
+            "goog:chromeOptions": { "args": ["--no-sandbox"] }
+        }))
+        .unwrap();
+        let firefox = &config.firefox;
+        assert_eq!(firefox.binary.as_deref(), Some("/opt/firefox/firefox"));
+        assert_eq!(firefox.args, ["-private"]);
+        assert_eq!(firefox.prefs["dom.workers.maxPerDomain"], 1024);
+        assert!(!firefox.headless());
+        assert_eq!(config.chrome.args, ["--use-fake-ui-for-media-stream"]);
+        assert!(config.chrome.headless());
+        assert!(config.edge.binary.is_none() && config.edge.args.is_empty());
+
+        // Sections are removed, the other keys are passed through.
+        assert_eq!(
+            Json::Object(config.capabilities),
+            json!({ "goog:chromeOptions": { "args": ["--no-sandbox"] } })
+        );
+    }
+
+    #[test]
+    fn unknown_keys() {
+        // Unknown top-level keys are raw capabilities.
+        let config = WebDriverConfig::from_json(json!({ "unknown": true })).unwrap();
+        assert_eq!(config.capabilities["unknown"], true);
+
+        let error = error(json!({ "firefox": { "headles": true } }));
+        assert!(error.contains("invalid `firefox` section"), "{error}");
+        assert!(error.contains("unknown field `headles`"), "{error}");
+    }
+
+    #[test]
+    fn type_errors() {
+        assert!(error(json!([])).contains("expected a JSON object"));
+
+        let message = error(json!({ "chrome": { "args": "--no-sandbox" } }));
+        assert!(message.contains("invalid `chrome` section"), "{message}");
+        assert!(message.contains("invalid type"), "{message}");
+
+        let safari = |section| error(json!({ "safari": section }));
+        assert!(safari(json!({ "args": ["-x"] })).contains("does not support"));
+        assert!(safari(json!({ "headless": true })).contains("cannot be run headless"));
+    }
+
+    #[test]
+    fn server_sections_moved() {
+        let message = error(json!({ "mocks": [] }));
+        assert!(message.contains("WASM_BINDGEN_TEST_MOCKS"), "{message}");
+    }
+}
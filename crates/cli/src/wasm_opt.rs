@@ -0,0 +1,77 @@
+//! Optimization of the emitted Wasm module with Binaryen's `wasm-opt`, for
+//! `--wasm-opt`.
+//!
+//! `wasm-opt` is found through the `WASM_OPT` env var, then `PATH`.
+
+use anyhow::{bail, Context, Error};
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+pub struct Options<'a> {
+    /// The optimization level, as in `-O{level}`.
+    pub level: &'a str,
+    /// Whether to preserve the name section and DWARF.
+    pub debug_info: bool,
+    /// Whether to strip the producers section.
+    pub strip_producers: bool,
+    /// Whether to strip the `target_features` section once done.
+    pub strip_target_features: bool,
+}
+
+/// Optimizes the module at `wasm` in place.
+///
+/// The Wasm features `wasm-opt` may use are read from the `target_features`
+/// section, which wasm-bindgen keeps up to date, and exports are never
+/// removed, so the output still matches the generated JS.
+pub fn run(wasm: &Path, options: &Options) -> Result<(), Error> {
+    let (program, env_args) = locate()?;
+    let mut command = Command::new(&program);
+    command
+        .arg(wasm)
+        .arg("-o")
+        .arg(wasm)
+        .arg(format!("-O{}", options.level));
+    if options.debug_info {
+        command.arg("--debuginfo");
+    }
+    if options.strip_producers {
+        command.arg("--strip-producers");
+    }
+    if options.strip_target_features {
+        command.arg("--strip-target-features");
+    }
+    command.args(env_args);
+
+    log::debug!("executing {command:?}");
+    let status = command
+        .status()
+        .with_context(|| format!("failed to execute `{}`", Path::new(&program).display()))?;
+    if !status.success() {
+        bail!("`wasm-opt` failed with {status}");
+    }
+    Ok(())
+}
+
+/// Returns the `wasm-opt` to run and the extra arguments of `WASM_OPT_ARGS`.
+fn locate() -> Result<(OsString, Vec<String>), Error> {
+    let var = env::var("WASM_OPT_ARGS").unwrap_or_default();
+    let env_args =
+        shlex::split(&var).unwrap_or_else(|| var.split_whitespace().map(String::from).collect());
+
+    if let Some(path) = env::var_os("WASM_OPT") {
+        return Ok((path, env_args));
+    }
+
+    let name = Path::new("wasm-opt").with_extension(env::consts::EXE_EXTENSION);
+    let on_path = env::split_paths(&env::var_os("PATH").unwrap_or_default())
+        .any(|path| path.join(&name).exists());
+    if !on_path {
+        bail!(
+            "failed to find `wasm-opt`; install Binaryen and make sure `wasm-opt` is in `PATH`, \
+             or set `WASM_OPT` to its path"
+        );
+    }
+    Ok((name.into(), env_args))
+}
@@ -0,0 +1,29 @@
+/* @ts-self-types="./reference_test.d.ts" */
+
+/**
+ * @param {number} a
+ * @param {number} b
+ * @returns {number}
+ */
+export function add_that_might_fail(a, b) {
+    const ret = wasm.add_that_might_fail(a, b);
+    return ret >>> 0;
+}
+
+function __wbg_get_imports() {
+    const import0 = {
+        __proto__: null,
+        __wbg_random_9526caf33df4270d: function() {
+            const ret = Math.random();
+            return ret;
+        },
+    };
+    return {
+        __proto__: null,
+        "./reference_test_bg.js": import0,
+    };
+}
+
+import wasmPath from './reference_test_bg.wasm' with { type: 'file' };
+const wasmModule = new WebAssembly.Module(await Bun.file(wasmPath).arrayBuffer());
+const wasm = new WebAssembly.Instance(wasmModule, __wbg_get_imports()).exports;
@@ -0,0 +1,4 @@
+/* tslint:disable */
+/* eslint-disable */
+
+export function add_that_might_fail(a: number, b: number): number;
@@ -1,20 +1,3 @@
-declare namespace wasm_bindgen {
-    /* tslint:disable */
-    /* eslint-disable */
-
-    export function add_that_might_fail(a: number, b: number): number;
-
-}
-declare type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
-
-declare interface InitOutput {
-    readonly add_that_might_fail: (a: number, b: number) => number;
-    readonly memory: WebAssembly.Memory;
-    readonly __wbindgen_externrefs: WebAssembly.Table;
-    readonly __wbindgen_thread_destroy: (a?: number, b?: number, c?: number) => void;
-    readonly __wbindgen_start: (a: number) => void;
-}
-
 /**
  * If `module_or_path` is {RequestInfo} or {URL}, makes a request and
  * for everything else, calls `WebAssembly.instantiate` directly.
@@ -24,4 +7,41 @@ declare interface InitOutput {
  *
  * @returns {Promise<InitOutput>}
  */
-declare function wasm_bindgen (module_or_path?: { module_or_path: InitInput | Promise<InitInput>, memory?: WebAssembly.Memory, thread_stack_size?: number } | InitInput | Promise<InitInput>, memory?: WebAssembly.Memory): Promise<InitOutput>;
+declare function wasm_bindgen (module_or_path?: { module_or_path: wasm_bindgen.InitInput | Promise<wasm_bindgen.InitInput>, memory?: WebAssembly.Memory, thread_stack_size?: number } | wasm_bindgen.InitInput | Promise<wasm_bindgen.InitInput>, memory?: WebAssembly.Memory): Promise<wasm_bindgen.InitOutput>;
+
+declare namespace wasm_bindgen {
+    /* tslint:disable */
+    /* eslint-disable */
+
+    export function add_that_might_fail(a: number, b: number): number;
+
+    export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+
+    export interface InitOutput {
+        readonly add_that_might_fail: (a: number, b: number) => number;
+        readonly memory: WebAssembly.Memory;
+        readonly __wbindgen_externrefs: WebAssembly.Table;
+        readonly __wbindgen_thread_destroy: (a?: number, b?: number, c?: number) => void;
+        readonly __wbindgen_start: (a: number) => void;
+    }
+
+    export type SyncInitInput = BufferSource | WebAssembly.Module;
+
+    /**
+     * Instantiates the given `module`, which can either be bytes or
+     * a precompiled `WebAssembly.Module`.
+     *
+     * @param {{ module: SyncInitInput, memory?: WebAssembly.Memory, thread_stack_size?: number }} module - Passing `SyncInitInput` directly is deprecated.
+     * @param {WebAssembly.Memory} memory - Deprecated.
+     *
+     * @returns {InitOutput}
+     */
+    export function initSync(module: { module: SyncInitInput, memory?: WebAssembly.Memory, thread_stack_size?: number } | SyncInitInput, memory?: WebAssembly.Memory): InitOutput;
+
+}
+
+/** @deprecated Use `wasm_bindgen.InitInput` instead. */
+declare type InitInput = wasm_bindgen.InitInput;
+
+/** @deprecated Use `wasm_bindgen.InitOutput` instead. */
+declare type InitOutput = wasm_bindgen.InitOutput;
@@ -1,23 +1,42 @@
+/**
+ * If `module_or_path` is {RequestInfo} or {URL}, makes a request and
+ * for everything else, calls `WebAssembly.instantiate` directly.
+ *
+ * @param {{ module_or_path: InitInput | Promise<InitInput> }} module_or_path - Passing `InitInput` directly is deprecated.
+ *
+ * @returns {Promise<InitOutput>}
+ */
+declare function wasm_bindgen (module_or_path?: { module_or_path: wasm_bindgen.InitInput | Promise<wasm_bindgen.InitInput> } | wasm_bindgen.InitInput | Promise<wasm_bindgen.InitInput>): Promise<wasm_bindgen.InitOutput>;
+
 declare namespace wasm_bindgen {
     /* tslint:disable */
     /* eslint-disable */
 
     export function add_that_might_fail(a: number, b: number): number;
 
-}
-declare type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+    export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+
+    export interface InitOutput {
+        readonly memory: WebAssembly.Memory;
+        readonly add_that_might_fail: (a: number, b: number) => number;
+    }
+
+    export type SyncInitInput = BufferSource | WebAssembly.Module;
+
+    /**
+     * Instantiates the given `module`, which can either be bytes or
+     * a precompiled `WebAssembly.Module`.
+     *
+     * @param {{ module: SyncInitInput }} module - Passing `SyncInitInput` directly is deprecated.
+     *
+     * @returns {InitOutput}
+     */
+    export function initSync(module: { module: SyncInitInput } | SyncInitInput): InitOutput;
 
-declare interface InitOutput {
-    readonly memory: WebAssembly.Memory;
-    readonly add_that_might_fail: (a: number, b: number) => number;
 }
 
-/**
- * If `module_or_path` is {RequestInfo} or {URL}, makes a request and
- * for everything else, calls `WebAssembly.instantiate` directly.
- *
- * @param {{ module_or_path: InitInput | Promise<InitInput> }} module_or_path - Passing `InitInput` directly is deprecated.
- *
- * @returns {Promise<InitOutput>}
- */
-declare function wasm_bindgen (module_or_path?: { module_or_path: InitInput | Promise<InitInput> } | InitInput | Promise<InitInput>): Promise<InitOutput>;
+/** @deprecated Use `wasm_bindgen.InitInput` instead. */
+declare type InitInput = wasm_bindgen.InitInput;
+
+/** @deprecated Use `wasm_bindgen.InitOutput` instead. */
+declare type InitOutput = wasm_bindgen.InitOutput;
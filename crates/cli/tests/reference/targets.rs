@@ -5,6 +5,7 @@
 // FLAGS: --target=nodejs
 // FLAGS: --target=nodejs --experimental-reset-state-function
 // FLAGS: --target=deno
+// FLAGS: --target=bun
 // FLAGS: --target=module
 // FLAGS: --target=module --experimental-reset-state-function
 // FLAGS: --target=experimental-nodejs-module
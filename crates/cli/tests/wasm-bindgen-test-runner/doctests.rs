@@ -107,16 +107,29 @@ impl Project {
         Ok(wasm_path)
     }
 
-    /// Run wasm-bindgen-test-runner on a specific wasm file.
+    /// Run wasm-bindgen-test-runner on a specific wasm file with `--nocapture`,
+    /// so console output is printed even if the doctest passes.
     pub fn run_wasm_bindgen_test_runner(&self, wasm_path: &Path) -> anyhow::Result<Output> {
         self.run_wasm_bindgen_test_runner_with_env(wasm_path, &[])
     }
 
-    /// Run wasm-bindgen-test-runner on a specific wasm file with custom environment variables.
+    /// Run wasm-bindgen-test-runner on a specific wasm file with `--nocapture`
+    /// and custom environment variables.
     pub fn run_wasm_bindgen_test_runner_with_env(
         &self,
         wasm_path: &Path,
         envs: &[(&str, &str)],
+    ) -> anyhow::Result<Output> {
+        self.run_wasm_bindgen_test_runner_with_args(wasm_path, &["--nocapture"], envs)
+    }
+
+    /// Run wasm-bindgen-test-runner on a specific wasm file with custom
+    /// arguments and environment variables.
+    pub fn run_wasm_bindgen_test_runner_with_args(
+        &self,
+        wasm_path: &Path,
+        args: &[&str],
+        envs: &[(&str, &str)],
     ) -> anyhow::Result<Output> {
         let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
         let mut cmd = std::process::Command::new("cargo");
@@ -126,7 +139,8 @@ impl Project {
             .arg("--bin")
             .arg("wasm-bindgen-test-runner")
             .arg("--")
-            .arg(wasm_path);
+            .arg(wasm_path)
+            .args(args);
         for (key, value) in envs {
             cmd.env(key, value);
         }
@@ -315,6 +329,252 @@ fn test_doctest_node() {
     );
 }
 
+/// Test that the console output of a passing doctest is captured.
+#[test]
+fn test_doctest_node_captured() {
+    let mut project = Project::new("test_doctest_node_captured");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! wasm_bindgen_test::console_log!("Hello from captured doctest!");
+//! ```
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_doctest(1));
+
+    let output = project
+        .run_wasm_bindgen_test_runner_with_args(&wasm_path, &[], &[])
+        .expect("Failed to run wasm-bindgen-test-runner");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        !stdout.contains("Hello from captured doctest!")
+            && !stderr.contains("Hello from captured doctest!"),
+        "Expected console output of a passing doctest to be captured.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        output.status.success(),
+        "Expected exit code 0.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that the captured console output of a failing doctest is printed once,
+/// along with its panic message.
+#[test]
+fn test_doctest_node_failure_prints_console() {
+    let mut project = Project::new("test_doctest_node_failure_prints_console");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! wasm_bindgen_test::console_log!("Hello from failing doctest!");
+//! panic!("doctest failure");
+//! ```
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_doctest(1));
+
+    let output = project
+        .run_wasm_bindgen_test_runner_with_args(&wasm_path, &[], &[])
+        .expect("Failed to run wasm-bindgen-test-runner");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(
+        stdout.matches("Hello from failing doctest!").count()
+            + stderr.matches("Hello from failing doctest!").count(),
+        1,
+        "Expected console output of a failing doctest exactly once.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("console output:"),
+        "Expected a `console output:` section.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stderr.contains("Doctest panicked at") && stderr.contains("doctest failure"),
+        "Expected the panic message.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        !output.status.success(),
+        "Expected the doctest to fail.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that a panicking doctest exits like a panicking native binary, and
+/// passes with `--should-panic`.
+#[test]
+fn test_doctest_node_should_panic() {
+    let mut project = Project::new("test_doctest_node_should_panic");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```should_panic
+//! panic!("expected");
+//! ```
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_doctest(1));
+
+    let output = project
+        .run_wasm_bindgen_test_runner(&wasm_path)
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        output.status.code(),
+        Some(101),
+        "Expected the exit code of a panic.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let output = project
+        .run_wasm_bindgen_test_runner_with_args(&wasm_path, &["--should-panic"], &[])
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test result: ok"),
+        "Expected the doctest to pass.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        output.status.success(),
+        "Expected exit code 0.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that the `#[wasm_bindgen(start)]` function of the crate runs before
+/// the doctest.
+#[test]
+fn test_doctest_start_function() {
+    let mut project = Project::new("test_doctest_start_function");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! assert!(test_doctest_start_function::started());
+//! ```
+use std::sync::atomic::{AtomicBool, Ordering};
+use wasm_bindgen::prelude::*;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+#[wasm_bindgen(start)]
+fn start() {
+    STARTED.store(true, Ordering::SeqCst);
+}
+
+pub fn started() -> bool {
+    STARTED.load(Ordering::SeqCst)
+}
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_doctest(1));
+
+    let output = project
+        .run_wasm_bindgen_test_runner(&wasm_path)
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test result: ok"),
+        "Expected the start function to run before the doctest.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        output.status.success(),
+        "Expected exit code 0.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that `--doctest-filter` selects a persisted doctest by its location.
+#[test]
+fn test_doctest_node_filter() {
+    let mut project = Project::new("test_doctest_node_filter");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! assert_eq!(1 + 1, 2);
+//! ```
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_doctest(1));
+
+    let output = project
+        .run_wasm_bindgen_test_runner_with_args(
+            &wasm_path,
+            &["--doctest-filter", "src/lib.rs:7"],
+            &[],
+        )
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("0 passed; 0 failed; 1 filtered out"),
+        "Expected the doctest to be filtered out.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        output.status.success(),
+        "Expected exit code 0.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let output = project
+        .run_wasm_bindgen_test_runner_with_args(
+            &wasm_path,
+            &["--doctest-filter", "src/lib.rs:1"],
+            &[],
+        )
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test result: ok. 1 passed"),
+        "Expected the doctest to run.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that `WASM_BINDGEN_TEST_DOCTEST_MODE` overrides the configured mode.
+#[test]
+fn test_doctest_mode_override() {
+    let mut project = Project::new("test_doctest_mode_override");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! use wasm_bindgen::prelude::*;
+//!
+//! #[wasm_bindgen]
+//! extern "C" {
+//!     fn eval(code: &str) -> JsValue;
+//! }
+//!
+//! wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+//! assert_eq!(eval("typeof process").as_string().as_deref(), Some("object"));
+//! ```
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_doctest(1));
+
+    let output = project
+        .run_wasm_bindgen_test_runner_with_env(
+            &wasm_path,
+            &[("WASM_BINDGEN_TEST_DOCTEST_MODE", "node")],
+        )
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test result: ok"),
+        "Expected the doctest to pass in Node.js.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        output.status.success(),
+        "Expected exit code 0.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
 /// Test that a doctest runs correctly in browser main thread mode.
 #[test]
 fn test_doctest_browser() {
@@ -938,6 +1198,64 @@ mod tests {
     );
 }
 
+/// Test that each doctest of a merged bundle is reported under its own name,
+/// so that a failure points to the doctest that failed.
+#[test]
+fn test_merged_doctest_names() {
+    let mut project = Project::new("test_merged_doctest_names");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! assert_eq!(1 + 1, 2);
+//! ```
+
+/// ```
+/// assert_eq!(1 + 1, 3);
+/// ```
+pub fn fails() {}
+
+/// ```
+/// assert_eq!(2 + 2, 4);
+/// ```
+pub fn passes() {}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_lib() {}
+}
+"#,
+    );
+
+    let wasm_path = require_nightly_or_skip!(project.build_merged_doctest());
+    let output = project
+        .run_wasm_bindgen_test_runner(&wasm_path)
+        .expect("Failed to run wasm-bindgen-test-runner");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        stdout.contains("running 3 doctests"),
+        "Expected every doctest of the bundle to run.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    for (name, outcome) in [
+        ("src/lib.rs - (line 1)", "ok"),
+        ("src/lib.rs - fails (line 5)", "FAILED"),
+        ("src/lib.rs - passes (line 10)", "ok"),
+    ] {
+        assert!(
+            stdout.contains(&format!("test {name} ... {outcome}")),
+            "Expected `{name}` to be reported as {outcome}.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+        );
+    }
+    assert!(
+        !output.status.success(),
+        "Expected the failing doctest to fail the run.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
 /// Build a merged doctest wasm file (the format used in Rust 2024 edition).
 /// Merged doctests use function names like `doctest_runner_2024::main` instead of
 /// the older `__doctest_main_*` format. This format is triggered when running
@@ -1187,3 +1505,60 @@ codegen-units = 1
         "Expected cargo test --doc to succeed.\nstdout:\n{stdout}\nstderr:\n{stderr}"
     );
 }
+
+/// Test that the `doctest` subcommand runs doc examples on a stable toolchain.
+#[test]
+fn test_doctest_subcommand() {
+    let mut project = Project::new("test_doctest_subcommand");
+    project.file(
+        "src/lib.rs",
+        r#"//! ```
+//! assert_eq!(test_doctest_subcommand::add(1, 2), 3);
+//! ```
+//!
+//! ```should_panic
+//! panic!("expected");
+//! ```
+//!
+//! ```text
+//! not rust
+//! ```
+
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+"#,
+    );
+    project.cargo_toml_for_doctest();
+
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = std::process::Command::new("cargo")
+        .arg("run")
+        .arg("--manifest-path")
+        .arg(&runner)
+        .arg("--bin")
+        .arg("wasm-bindgen-test-runner")
+        .arg("--")
+        .arg("doctest")
+        .arg("--manifest-path")
+        .arg(project.root.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .output()
+        .expect("Failed to run wasm-bindgen-test-runner doctest");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        stdout.contains("running 2 doctests"),
+        "Expected both Rust examples to be extracted.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("src_lib_rs_1") && stdout.contains("src_lib_rs_5"),
+        "Expected the examples to run as tests.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        output.status.success(),
+        "Expected exit code 0.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
@@ -2571,3 +2571,971 @@ features = ["Blob", "BlobPropertyBag", "ErrorEvent", "Url", "SharedWorker", "Mes
         stdout, stderr
     );
 }
+
+/// Test that console output of a worker spawned by a user-spawned worker is
+/// captured in run_in_browser mode.
+#[test]
+fn test_nested_worker_logs_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_nested_worker_logs_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser);
+
+            #[wasm_bindgen(inline_js = "
+                export function spawn_nested_worker() {
+                    const inner = 'console.log(\"NESTED_WORKER_MARKER_3Q8W1\"); postMessage(\"done\");';
+                    const outer = `
+                        const url = URL.createObjectURL(new Blob([${JSON.stringify(inner)}]));
+                        new Worker(url).onmessage = e => postMessage(e.data);
+                    `;
+                    return new Promise(resolve => {
+                        const worker = new Worker(URL.createObjectURL(new Blob([outer])));
+                        worker.onmessage = e => {
+                            if (e.data === 'done') resolve();
+                        };
+                    });
+                }
+            ")]
+            extern "C" {
+                async fn spawn_nested_worker();
+            }
+
+            #[wasm_bindgen_test]
+            async fn test_nested_worker() {
+                spawn_nested_worker().await;
+            }
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .arg("--")
+        .arg("--nocapture")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let combined = format!("{stdout}{stderr}");
+
+    assert!(
+        output.status.success(),
+        "Test should pass.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+    assert!(
+        combined.contains("NESTED_WORKER_MARKER_3Q8W1"),
+        "Expected the nested worker's log to be captured.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+}
+
+/// Test that console output of a service worker registered by a test is
+/// captured in run_in_browser mode.
+#[test]
+fn test_user_registered_service_worker_logs_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_user_registered_service_worker_logs_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "sw.js",
+        r#"
+console.log("SERVICE_WORKER_LOG_MARKER_6H2P4");
+console.warn("SERVICE_WORKER_WARN_MARKER_6H2P4");
+self.addEventListener('install', () => self.skipWaiting());
+"#,
+    );
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser);
+
+            #[wasm_bindgen(inline_js = "
+                export async function register_service_worker() {
+                    await navigator.serviceWorker.register('/sw.js');
+                    await navigator.serviceWorker.ready;
+                    await new Promise(resolve => setTimeout(resolve, 500));
+                }
+            ")]
+            extern "C" {
+                async fn register_service_worker();
+            }
+
+            #[wasm_bindgen_test]
+            async fn test_service_worker() {
+                register_service_worker().await;
+            }
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .arg("--")
+        .arg("--nocapture")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let combined = format!("{stdout}{stderr}");
+
+    assert!(
+        output.status.success(),
+        "Test should pass.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+    for marker in [
+        "SERVICE_WORKER_LOG_MARKER_6H2P4",
+        "SERVICE_WORKER_WARN_MARKER_6H2P4",
+    ] {
+        assert!(
+            combined.contains(marker),
+            "Expected `{marker}` from the service worker to be captured.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+        );
+    }
+}
+
+/// Test that uncaught errors of the page and of user-spawned workers fail the
+/// running test with `fail_on_uncaught_error` in run_in_browser mode.
+#[test]
+fn test_fail_on_uncaught_error_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_fail_on_uncaught_error_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser fail_on_uncaught_error);
+
+            #[wasm_bindgen(inline_js = "
+                const sleep = ms => new Promise(resolve => setTimeout(resolve, ms));
+                export async function throw_in_timeout() {
+                    setTimeout(() => { throw new Error('WINDOW_ERROR_MARKER_2D7F5'); }, 0);
+                    await sleep(100);
+                }
+                export async function throw_in_worker() {
+                    const source = 'throw new Error(\"WORKER_ERROR_MARKER_2D7F5\");';
+                    new Worker(URL.createObjectURL(new Blob([source])));
+                    await sleep(500);
+                }
+            ")]
+            extern "C" {
+                async fn throw_in_timeout();
+                async fn throw_in_worker();
+            }
+
+            #[wasm_bindgen_test]
+            async fn test_window_error() {
+                throw_in_timeout().await;
+            }
+
+            #[wasm_bindgen_test]
+            async fn test_worker_error() {
+                throw_in_worker().await;
+            }
+
+            #[wasm_bindgen_test]
+            fn test_no_error() {}
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let combined = format!("{stdout}{stderr}");
+
+    assert!(
+        !output.status.success(),
+        "Tests with uncaught errors should fail.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+    for expected in [
+        "test test_window_error ... FAIL",
+        "test test_worker_error ... FAIL",
+        "test test_no_error ... ok",
+        "errors that were not caught",
+        "WINDOW_ERROR_MARKER_2D7F5",
+        "WORKER_ERROR_MARKER_2D7F5",
+    ] {
+        assert!(
+            combined.contains(expected),
+            "Expected `{expected}` in the output.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+        );
+    }
+}
+
+/// Test that the logs of a user-spawned worker are attributed to the test that
+/// spawned it, rather than to another test running when they arrive.
+#[test]
+fn test_worker_logs_attributed_to_spawning_test_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_worker_logs_attributed_to_spawning_test_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser);
+
+            #[wasm_bindgen(inline_js = "
+                export function spawn_late_logging_worker() {
+                    const script = 'setTimeout(() => console.log(\"SPAWNING_TEST_LOG_5T9C2\"), 200);';
+                    new Worker(URL.createObjectURL(new Blob([script])));
+                }
+
+                export function sleep(ms) {
+                    return new Promise(resolve => setTimeout(resolve, ms));
+                }
+            ")]
+            extern "C" {
+                fn spawn_late_logging_worker();
+                async fn sleep(ms: u32);
+            }
+
+            #[wasm_bindgen_test]
+            async fn spawning_test() {
+                spawn_late_logging_worker();
+                sleep(1000).await;
+                panic!("show the captured output");
+            }
+
+            #[wasm_bindgen_test]
+            async fn unrelated_test() {
+                sleep(1000).await;
+                panic!("show the captured output");
+            }
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let section = |name: &str| {
+        let start = stdout
+            .find(&format!("---- {name} output ----"))
+            .unwrap_or_else(|| panic!("Expected output of `{name}`.\nstdout:\n{stdout}"));
+        let rest = &stdout[start + 1..];
+        &rest[..rest.find("---- ").unwrap_or(rest.len())]
+    };
+
+    assert!(
+        section("spawning_test").contains("SPAWNING_TEST_LOG_5T9C2"),
+        "Expected the worker's log in the output of the test that spawned it.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+    assert!(
+        !section("unrelated_test").contains("SPAWNING_TEST_LOG_5T9C2"),
+        "Expected the worker's log not to be attributed to another test.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+}
+
+/// Test that with --nocapture, console output of a long test is printed while
+/// it still runs rather than once it finishes.
+#[test]
+fn test_nocapture_streams_during_long_test_headless() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_nocapture_streams_during_long_test_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser);
+
+            #[wasm_bindgen(inline_js = "
+                export function sleep(ms) {
+                    return new Promise(resolve => setTimeout(resolve, ms));
+                }
+            ")]
+            extern "C" {
+                async fn sleep(ms: u32);
+            }
+
+            #[wasm_bindgen_test]
+            async fn long_test() {
+                console_log!("STREAMED_EARLY_7K3M1");
+                sleep(3000).await;
+            }
+        "#,
+    );
+
+    // Build first, so that compilation doesn't count towards the timings.
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let cargo = || {
+        let mut cmd = std::process::Command::new("cargo");
+        cmd.current_dir(&project.root)
+            .arg("test")
+            .arg("--target")
+            .arg("wasm32-unknown-unknown")
+            .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+            .env(
+                "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+                format!(
+                    "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                    runner.display()
+                ),
+            )
+            .env(driver_env, &driver_path);
+        cmd
+    };
+    let status = cargo()
+        .arg("--no-run")
+        .status()
+        .expect("failed to build tests");
+    assert!(status.success());
+
+    let mut child = cargo()
+        .arg("--")
+        .arg("--nocapture")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to execute cargo test");
+    let mut streamed = None;
+    let mut finished = None;
+    let mut stdout = String::new();
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        let line = line.unwrap();
+        if line.contains("STREAMED_EARLY_7K3M1") && streamed.is_none() {
+            streamed = Some(Instant::now());
+        }
+        if line.contains("test result: ") {
+            finished = Some(Instant::now());
+        }
+        stdout.push_str(&line);
+        stdout.push('\n');
+    }
+    assert!(child.wait().unwrap().success(), "stdout:\n{stdout}");
+
+    let (Some(streamed), Some(finished)) = (streamed, finished) else {
+        panic!("Expected the log and the test result.\nstdout:\n{stdout}");
+    };
+    assert!(
+        finished - streamed > Duration::from_secs(1),
+        "Expected the log to be printed while the test was running.\nstdout:\n{stdout}"
+    );
+}
+
+/// Test that console output of an AudioWorklet module and its processors is
+/// captured in run_in_browser mode.
+#[test]
+fn test_audio_worklet_logs_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_audio_worklet_logs_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser);
+
+            #[wasm_bindgen(inline_js = "
+                export async function run_audio_worklet() {
+                    const module = `
+                        console.log('WORKLET_MODULE_LOG_2R7D5');
+                        registerProcessor('logging', class extends AudioWorkletProcessor {
+                            constructor() {
+                                super();
+                                console.warn('WORKLET_PROCESSOR_WARN_2R7D5');
+                            }
+                            process() {
+                                return false;
+                            }
+                        });
+                    `;
+                    const context = new OfflineAudioContext(1, 128, 44100);
+                    await context.audioWorklet.addModule(URL.createObjectURL(new Blob([module])));
+                    new AudioWorkletNode(context, 'logging').connect(context.destination);
+                    await context.startRendering();
+                    await new Promise(resolve => setTimeout(resolve, 200));
+                }
+            ")]
+            extern "C" {
+                async fn run_audio_worklet();
+            }
+
+            #[wasm_bindgen_test]
+            async fn test_audio_worklet() {
+                run_audio_worklet().await;
+                panic!("show the captured output");
+            }
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    for expected in ["WORKLET_MODULE_LOG_2R7D5", "WORKLET_PROCESSOR_WARN_2R7D5"] {
+        assert!(
+            stdout.contains(expected),
+            "Expected {expected:?} in the test's output.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+        );
+    }
+}
+
+/// Test that a panic in a worker spawned by a test fails that test with the
+/// panic, rather than leaving it waiting for the worker until it times out.
+#[test]
+fn test_worker_panic_fails_spawning_test_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_worker_panic_fails_spawning_test_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser);
+
+            #[wasm_bindgen(inline_js = "
+                export function spawn_panicking_worker() {
+                    const script = `
+                        import init, { worker_panics } from '${location.origin}/wasm-bindgen-test.js';
+                        await init();
+                        worker_panics();
+                    `;
+                    const url = URL.createObjectURL(new Blob([script], { type: 'text/javascript' }));
+                    new Worker(url, { type: 'module', name: 'panicking-worker' });
+                }
+
+                export function never() {
+                    return new Promise(() => {});
+                }
+            ")]
+            extern "C" {
+                fn spawn_panicking_worker();
+                async fn never();
+            }
+
+            #[wasm_bindgen]
+            pub fn worker_panics() {
+                panic!("PANICKED_IN_WORKER_8Q2X");
+            }
+
+            #[wasm_bindgen_test]
+            async fn waits_for_worker() {
+                spawn_panicking_worker();
+                never().await;
+            }
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("waits_for_worker ... FAIL")
+            && stdout.contains("thread 'panicking-worker' (worker #1)")
+            && stdout.contains("PANICKED_IN_WORKER_8Q2X")
+            && stdout.contains("worker #1 spawned at:"),
+        "Expected the worker's panic to fail the test.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+    );
+}
+
+/// Test that the failure output of a test includes the stack of an error a
+/// worker it spawned threw, along with where the worker was spawned.
+#[test]
+fn test_worker_error_correlated_with_spawn_site_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_worker_error_correlated_with_spawn_site_headless");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen_test::*;
+
+            wasm_bindgen_test_configure!(run_in_browser fail_on_uncaught_error);
+
+            #[wasm_bindgen(inline_js = "
+                export function spawn_throwing_worker() {
+                    const script = 'function throw_in_worker() { throw new Error(\"WORKER_THROW_MARKER_6H3K\"); } setTimeout(throw_in_worker, 0);';
+                    new Worker(URL.createObjectURL(new Blob([script])));
+                }
+
+                export function sleep(ms) {
+                    return new Promise(resolve => setTimeout(resolve, ms));
+                }
+            ")]
+            extern "C" {
+                fn spawn_throwing_worker();
+                async fn sleep(ms: u32);
+            }
+
+            #[wasm_bindgen_test]
+            async fn spawning_test() {
+                spawn_throwing_worker();
+                sleep(1000).await;
+            }
+        "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let section = |name: &str| {
+        let start = stdout
+            .find(&format!("---- {name} output ----"))
+            .unwrap_or_else(|| panic!("Expected output of `{name}`.\nstdout:\n{stdout}"));
+        let rest = &stdout[start + 1..];
+        &rest[..rest.find("---- ").unwrap_or(rest.len())]
+    };
+
+    let output = section("spawning_test");
+    for expected in [
+        "worker #1: Error: WORKER_THROW_MARKER_6H3K",
+        "throw_in_worker",
+        "worker #1 spawned at:",
+        "spawn_throwing_worker",
+    ] {
+        assert!(
+            output.contains(expected),
+            "Expected `{expected}` in the output of the test.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+        );
+    }
+}
+
+/// Test that `should_panic` tests pass or fail by their panic in the browser
+/// and in dedicated and shared workers alike.
+#[test]
+fn test_should_panic_in_browser_and_workers_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_should_panic_in_browser_and_workers_headless");
+    project.file("src/lib.rs", "");
+    let tests = r#"
+        use wasm_bindgen_test::*;
+
+        #[wasm_bindgen_test]
+        #[should_panic]
+        fn panics() {
+            panic!("any message");
+        }
+
+        #[wasm_bindgen_test]
+        #[should_panic(expected = "expected message")]
+        async fn panics_with_expected_message() {
+            panic!("the expected message");
+        }
+
+        #[wasm_bindgen_test]
+        #[should_panic(expected = "expected message")]
+        fn panics_with_other_message() {
+            panic!("another message");
+        }
+
+        #[wasm_bindgen_test]
+        #[should_panic]
+        fn does_not_panic() {}
+    "#;
+    for mode in [
+        "run_in_browser",
+        "run_in_dedicated_worker",
+        "run_in_shared_worker",
+    ] {
+        project.file(
+            &format!("tests/{mode}.rs"),
+            &format!("wasm_bindgen_test::wasm_bindgen_test_configure!({mode});\n{tests}"),
+        );
+    }
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .arg("--no-fail-fast")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for (expected, count) in [
+        ("test panics ... ok", 3),
+        ("test panics_with_expected_message ... ok", 3),
+        ("test panics_with_other_message ... FAIL", 3),
+        ("test does_not_panic ... FAIL", 3),
+        ("note: panic did not contain expected string", 3),
+        ("note: does_not_panic did not panic as expected", 3),
+    ] {
+        assert_eq!(
+            stdout.matches(expected).count(),
+            count,
+            "Expected `{expected}` once per mode.\nstdout:\n{stdout}\nstderr:\n{stderr}",
+        );
+    }
+}
+
+/// Test that `run_in` runs a test in another mode than that of its test
+/// binary, next to the tests running in the binary's mode.
+#[test]
+fn test_run_in_overrides_mode_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_run_in_overrides_mode_headless");
+    project.file("src/lib.rs", "");
+    project.file(
+        "tests/modes.rs",
+        r#"
+        use wasm_bindgen::prelude::*;
+        use wasm_bindgen_test::*;
+
+        wasm_bindgen_test_configure!(run_in_browser);
+
+        #[wasm_bindgen(inline_js = "
+            export function environment() {
+                if (typeof process === 'object') return 'node';
+                if (typeof window === 'object') return 'browser';
+                return 'worker';
+            }
+        ")]
+        extern "C" {
+            fn environment() -> String;
+        }
+
+        #[wasm_bindgen_test]
+        fn in_browser() {
+            assert_eq!(environment(), "browser");
+        }
+
+        #[wasm_bindgen_test(run_in = "dedicated_worker")]
+        fn in_worker() {
+            assert_eq!(environment(), "worker");
+        }
+
+        #[wasm_bindgen_test(run_in = "node")]
+        async fn in_node() {
+            assert_eq!(environment(), "node");
+        }
+
+        #[wasm_bindgen_test(run_in = "node")]
+        fn fails_in_node() {
+            panic!("FAILED_IN_NODE_7Y3K");
+        }
+    "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test in_browser ... ok")
+            && stdout.contains("test in_worker ... ok")
+            && stdout.contains("test in_node ... ok")
+            && stdout.contains("test fails_in_node ... FAIL")
+            && stdout.contains("FAILED_IN_NODE_7Y3K"),
+        "Expected each test to run in its mode.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        !output.status.success() && stdout.contains("the tests running in node failed"),
+        "Expected the failure in one mode to fail the run.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that `Sandbox` gives a test a container element and puts the document
+/// back as it was once it's dropped.
+#[test]
+fn test_sandbox_cleans_up_document_headless() {
+    let Some((driver_env, driver_path)) = find_webdriver() else {
+        eprintln!("Skipping headless test: no webdriver found");
+        return;
+    };
+
+    let mut project = Project::new("test_sandbox_cleans_up_document_headless");
+    project.file("src/lib.rs", "");
+    project.file(
+        "tests/sandbox.rs",
+        r#"
+        use wasm_bindgen::prelude::*;
+        use wasm_bindgen_test::*;
+
+        wasm_bindgen_test_configure!(run_in_browser);
+
+        #[wasm_bindgen(inline_js = "
+            let calls = 0;
+            export function pollute(container) {
+                container.textContent = 'IN_SANDBOX';
+                document.body.appendChild(document.createElement('aside'));
+                document.head.appendChild(document.createElement('style'));
+                document.body.setAttribute('class', 'dark');
+                window.addEventListener('wbg-sandbox', () => calls++);
+            }
+            export function is_clean() {
+                window.dispatchEvent(new Event('wbg-sandbox'));
+                return calls === 0
+                    && document.querySelector('[data-wasm-bindgen-test-sandbox]') === null
+                    && document.querySelector('aside, style') === null
+                    && !document.body.hasAttribute('class');
+            }
+            export function text(container) {
+                return container.textContent;
+            }
+        ")]
+        extern "C" {
+            fn pollute(container: &JsValue);
+            fn is_clean() -> bool;
+            fn text(container: &JsValue) -> String;
+        }
+
+        #[wasm_bindgen_test]
+        fn sandbox_is_cleaned_up() {
+            assert!(is_clean());
+            {
+                let sandbox = Sandbox::new();
+                let container: JsValue = sandbox.container();
+                pollute(&container);
+                assert_eq!(text(&container), "IN_SANDBOX");
+                assert!(!is_clean());
+            }
+            assert!(is_clean());
+        }
+    "#,
+    );
+
+    project.cargo_toml();
+    let runner = REPO_ROOT.join("crates").join("cli").join("Cargo.toml");
+    let output = Command::new("cargo")
+        .current_dir(&project.root)
+        .arg("test")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .env("CARGO_TARGET_DIR", &*TARGET_DIR)
+        .env(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            format!(
+                "cargo run --manifest-path {} --bin wasm-bindgen-test-runner --",
+                runner.display()
+            ),
+        )
+        .env(driver_env, driver_path)
+        .output()
+        .expect("failed to execute cargo test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stdout.contains("test sandbox_is_cleaned_up ... ok"),
+        "Expected the sandbox to be cleaned up.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
@@ -139,6 +139,119 @@ fn test_wasm_bindgen_test_runner_list() {
     assert_eq!(lines.next(), None);
 }
 
+#[test]
+fn test_wasm_bindgen_test_runner_list_suites() {
+    let mut project = Project::new("test_wasm_bindgen_test_runner_list_suites");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            use wasm_bindgen_test::*;
+
+            #[cfg(test)]
+            #[wasm_bindgen_test]
+            fn test_root() {}
+
+            #[cfg(test)]
+            mod parser {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_parse() {}
+
+                #[wasm_bindgen_test(suite = "slow")]
+                fn test_parse_large() {}
+            }
+        "#,
+    );
+
+    let output = project
+        .wasm_bindgen_test("--list --group-by-suite")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout,
+        "parser:\n    parser::test_parse: test\n\
+         slow:\n    parser::test_parse_large: test\n\
+         test_wasm_bindgen_test_runner_list_suites:\n    test_root: test\n"
+    );
+
+    let output = project.wasm_bindgen_test("--list").unwrap();
+    let mut lines: Vec<_> = output.stdout.lines().map(|l| l.unwrap()).collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "parser::test_parse: test",
+            "parser::test_parse_large: test",
+            "test_root: test"
+        ]
+    );
+}
+
+#[test]
+fn test_wasm_bindgen_test_runner_report_suites() {
+    let mut project = Project::new("test_wasm_bindgen_test_runner_report_suites");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod parser {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_parse() {}
+
+                #[wasm_bindgen_test(suite = "slow")]
+                fn test_parse_large() {
+                    panic!("too large");
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("--format json").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let mut tests: Vec<_> = events
+        .iter()
+        .filter(|event| event["type"] == "test")
+        .map(|event| {
+            let field = |key: &str| event[key].as_str().unwrap();
+            format!("{}/{}: {}", field("suite"), field("name"), field("event"))
+        })
+        .collect();
+    tests.sort();
+    assert_eq!(
+        tests,
+        [
+            "parser/parser::test_parse: ok",
+            "slow/parser::test_parse_large: failed"
+        ]
+    );
+    let summary = events.last().unwrap();
+    assert_eq!(summary["type"], "suite");
+    assert_eq!(summary["event"], "failed");
+    assert_eq!(summary["passed"], 1);
+    assert_eq!(summary["failed"], 1);
+    // The failure output goes to stderr, keeping stdout for the events.
+    assert!(!stdout.contains("too large"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("too large"));
+
+    let output = project.wasm_bindgen_test("--format junit").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(
+        r#"<testsuite name="parser" package="test" id="0" errors="0" failures="0" tests="1" skipped="0"><testcase classname="parser" name="parser::test_parse" time=""#
+    ));
+    assert!(stdout.contains(
+        r#"<testsuite name="slow" package="test" id="1" errors="0" failures="1" tests="1" skipped="0"><testcase classname="slow" name="parser::test_parse_large" time=""#
+    ));
+    assert!(stdout.contains(r#"<failure type="assert"/></testcase>"#));
+}
+
 /// Test that console.log output in dedicated worker mode is not duplicated.
 /// See: https://github.com/wasm-bindgen/wasm-bindgen/pull/4845#issuecomment-3660688206
 #[test]
@@ -175,3 +288,1526 @@ fn test_worker_console_log_no_duplicates() {
         "Expected console_log message to appear exactly once, but it appeared {count} times.\nstdout:\n{stdout}\nstderr:\n{stderr}"
     );
 }
+
+/// Test that `fail_on_unhandled_rejection` fails a test during which a promise
+/// is rejected without a handler, and reports the rejection reason.
+#[test]
+fn test_fail_on_unhandled_rejection() {
+    let output = Project::new("test_fail_on_unhandled_rejection")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::prelude::*;
+                use wasm_bindgen_test::*;
+
+                wasm_bindgen_test_configure!(fail_on_unhandled_rejection);
+
+                #[wasm_bindgen(inline_js = "
+                    export function reject_unhandled() {
+                        Promise.reject(new Error('UNHANDLED_REJECTION_MARKER'));
+                    }
+                ")]
+                extern "C" {
+                    fn reject_unhandled();
+                }
+
+                #[wasm_bindgen_test]
+                fn test_rejects() {
+                    reject_unhandled();
+                }
+
+                #[wasm_bindgen_test]
+                fn test_passes() {}
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        !output.status.success(),
+        "Expected the test to fail.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("test tests::test_rejects ... FAIL")
+            && stdout.contains("test tests::test_passes ... ok"),
+        "Expected only the rejecting test to fail.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("promise rejections that were not handled")
+            && stdout.contains("UNHANDLED_REJECTION_MARKER"),
+        "Expected the rejection reason in the failure.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that `console.group`, `console.table` and `console.dir` keep their
+/// formatting in captured output.
+#[test]
+fn test_console_group_table_dir_formatting() {
+    let output = Project::new("test_console_group_table_dir_formatting")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::prelude::*;
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen(inline_js = "
+                    export function log_structured() {
+                        console.group('GROUP_LABEL');
+                        console.log('inside');
+                        console.groupEnd();
+                        console.log('outside');
+                        console.table([{ a: 1, b: 'x' }, { a: 2 }]);
+                        console.dir({ nested: { value: [1, 2] } });
+                    }
+                ")]
+                extern "C" {
+                    fn log_structured();
+                }
+
+                #[wasm_bindgen_test]
+                fn test_structured() {
+                    log_structured();
+                    panic!("show the captured output");
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    for expected in [
+        "GROUP_LABEL\n",
+        "  inside\n",
+        "\n        outside\n",
+        "│ (index) │ a │ b   │",
+        "│ 0       │ 1 │ 'x' │",
+        "│ 1       │ 2 │     │",
+        "{ nested: { value: [ 1, 2 ] } }",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "Expected {expected:?} in the output.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+        );
+    }
+}
+
+#[test]
+fn test_split_console_output() {
+    let output = Project::new("test_split_console_output")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_logs() {
+                    console_log!("LOGGED_TO_STDOUT");
+                    console_warn("WARNED_TO_STDERR");
+                    panic!("show the captured output");
+                }
+
+                #[wasm_bindgen::prelude::wasm_bindgen]
+                extern "C" {
+                    #[wasm_bindgen(js_namespace = console, js_name = warn)]
+                    fn console_warn(s: &str);
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("--split-console-output")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        stdout.contains("LOGGED_TO_STDOUT") && !stderr.contains("LOGGED_TO_STDOUT"),
+        "Expected the log output on stdout.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stderr.contains("WARNED_TO_STDERR") && !stdout.contains("WARNED_TO_STDERR"),
+        "Expected the warn output on stderr.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_console_depth_and_max_length() {
+    let output = Project::new("test_console_depth_and_max_length")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::prelude::*;
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen(inline_js = "
+                    export function log_objects() {
+                        console.log('nested:', { a: { b: { c: 1 } } });
+                        console.log('long:', [1, 2, 3, 4, 5]);
+                    }
+                ")]
+                extern "C" {
+                    fn log_objects();
+                }
+
+                #[wasm_bindgen_test]
+                fn test_objects() {
+                    log_objects();
+                    panic!("show the captured output");
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("--console-depth 1 --console-max-length 2")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    for expected in [
+        "nested: { a: { b: [Object] } }",
+        "long: [ 1, 2, ... 3 more items ]",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "Expected {expected:?} in the output.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+        );
+    }
+}
+
+#[test]
+fn test_max_test_output_lines() {
+    let output = Project::new("test_max_test_output_lines")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_runaway_logging() {
+                    for i in 0..100 {
+                        console_log!("line {i}");
+                    }
+                    panic!("show the captured output");
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("--max-test-output-lines 5")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        !stdout.contains("line 99"),
+        "Expected the output to be truncated.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    let path = stdout
+        .split_once("more lines suppressed (see ")
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(path, _)| path)
+        .unwrap_or_else(|| {
+            panic!("Expected a truncation marker.\nstdout:\n{stdout}\nstderr:\n{stderr}")
+        });
+    let suppressed = fs::read_to_string(path).unwrap();
+    assert!(suppressed.contains("line 99"), "{suppressed}");
+}
+
+#[test]
+fn test_snippet_stack_is_source_mapped() {
+    let output = Project::new("test_snippet_stack_is_source_mapped")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::prelude::*;
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen(inline_js = "
+                    export function throw_from_snippet() {
+                        throw new Error('THROWN_FROM_SNIPPET');
+                    }
+                ")]
+                extern "C" {
+                    fn throw_from_snippet();
+                }
+
+                #[wasm_bindgen_test]
+                fn test_throws() {
+                    throw_from_snippet();
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let location = format!("src{}lib.rs:9", std::path::MAIN_SEPARATOR);
+    assert!(
+        stdout.contains("THROWN_FROM_SNIPPET") && stdout.contains(&location),
+        "Expected the stack to point at the snippet in `{location}`.\nstdout:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_wasm_frames_are_symbolicated() {
+    let output = Project::new("test_wasm_frames_are_symbolicated")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_panics() {
+                    panic!("PANICKED_IN_WASM");
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("PANICKED_IN_WASM")
+            && stdout.contains("+0x")
+            && !stdout.contains("wasm-function["),
+        "Expected the Wasm frames to be named with offsets.\nstdout:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_should_panic_expected_matches_message() {
+    let output = Project::new("test_should_panic_expected_matches_message")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                #[should_panic(expected = "boom")]
+                fn test_message() {
+                    panic!("boom");
+                }
+
+                #[wasm_bindgen_test]
+                #[should_panic(expected = "lib.rs")]
+                fn test_location() {
+                    panic!("boom");
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("test_message ... ok")
+            && stdout.contains("test_location ... FAIL")
+            && stdout.contains("panic message: `\"boom\"`"),
+        "Expected the expected string to be matched against the panic message only.\nstdout:\n{stdout}"
+    );
+}
+
+#[test]
+fn test_panic_reported_once() {
+    let output = Project::new("test_panic_reported_once")
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_panics() {
+                    panic!("REPORTED_PANIC");
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.matches("REPORTED_PANIC").count(),
+        1,
+        "Expected the panic to be reported once.\nstdout:\n{stdout}"
+    );
+    assert!(
+        stdout.contains("thread 'main' ")
+            && stdout.contains("src/lib.rs:")
+            && !stdout.contains("JS exception that was thrown"),
+        "Expected a panic report in place of the exception it ends in.\nstdout:\n{stdout}"
+    );
+}
+
+/// Tests that ignored tests are reported with their reason, and that
+/// `--ignored` runs only them.
+#[test]
+fn test_ignore_reason() {
+    let mut project = Project::new("test_ignore_reason");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                #[ignore = "IGNORE_REASON_MARKER"]
+                fn test_ignored_with_reason() {}
+
+                #[wasm_bindgen_test]
+                #[ignore]
+                fn test_ignored() {}
+
+                #[wasm_bindgen_test]
+                fn test_not_ignored() {}
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("test tests::test_ignored_with_reason ... ignored, IGNORE_REASON_MARKER")
+            && stdout.contains("test tests::test_ignored ... ignored\n")
+            && stdout.contains("test tests::test_not_ignored ... ok")
+            && stdout.contains("1 passed; 0 failed; 2 ignored; 0 filtered out"),
+        "Expected the ignored tests to be reported with their reason.\nstdout:\n{stdout}"
+    );
+
+    let output = project.wasm_bindgen_test("--ignored").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("test tests::test_ignored_with_reason ... ok")
+            && stdout.contains("test tests::test_ignored ... ok")
+            && !stdout.contains("test_not_ignored")
+            && stdout.contains("2 passed; 0 failed; 0 ignored; 1 filtered out"),
+        "Expected `--ignored` to run only the ignored tests.\nstdout:\n{stdout}"
+    );
+}
+
+/// Tests that `timeout_ms` fails async tests that don't finish in time and
+/// sync tests that return late, with a timeout note in place of the error.
+#[test]
+fn test_timeout_ms() {
+    let mut project = Project::new("test_timeout_ms");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    let output = project
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::prelude::*;
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen(inline_js = "
+                    export function never() {
+                        return new Promise(() => {});
+                    }
+
+                    export function busy(ms) {
+                        const end = Date.now() + ms;
+                        while (Date.now() < end) {}
+                    }
+                ")]
+                extern "C" {
+                    async fn never();
+                    fn busy(ms: u32);
+                }
+
+                #[wasm_bindgen_test(timeout_ms = 100)]
+                async fn test_never_finishes() {
+                    never().await;
+                }
+
+                #[wasm_bindgen_test(timeout_ms = 100)]
+                fn test_returns_late() {
+                    busy(300);
+                }
+
+                #[wasm_bindgen_test(timeout_ms = 100)]
+                #[should_panic]
+                async fn test_should_panic_never_finishes() {
+                    never().await;
+                }
+
+                #[wasm_bindgen_test(timeout_ms = 5000)]
+                async fn test_finishes_in_time() {}
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test tests::test_never_finishes ... FAIL")
+            && stdout.contains("test tests::test_returns_late ... FAIL")
+            && stdout.contains("test tests::test_should_panic_never_finishes ... FAIL")
+            && stdout.contains("test tests::test_finishes_in_time ... ok"),
+        "Expected the tests exceeding their timeout to fail.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains(
+            "note: tests::test_never_finishes did not finish within its timeout of 100 ms"
+        ) && !stdout.contains("JS exception that was thrown"),
+        "Expected a timeout note in the failure.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+/// Test that the setup and teardown hooks of a module run around each of its
+/// tests, and that a failing hook fails the test.
+#[test]
+fn test_result_error_display() {
+    let mut project = Project::new("test_result_error_display");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    let output = project
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use std::error::Error;
+                use std::fmt;
+                use wasm_bindgen::JsValue;
+                use wasm_bindgen_test::*;
+
+                #[derive(Debug)]
+                struct Outer(Inner);
+
+                #[derive(Debug)]
+                struct Inner;
+
+                impl fmt::Display for Outer {
+                    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("OUTER_DISPLAY_MARKER")
+                    }
+                }
+
+                impl fmt::Display for Inner {
+                    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("INNER_DISPLAY_MARKER")
+                    }
+                }
+
+                impl Error for Outer {
+                    fn source(&self) -> Option<&(dyn Error + 'static)> {
+                        Some(&self.0)
+                    }
+                }
+
+                impl Error for Inner {}
+
+                #[wasm_bindgen_test]
+                fn test_error_chain() -> Result<(), Outer> {
+                    Err(Outer(Inner))
+                }
+
+                #[wasm_bindgen_test]
+                async fn test_boxed_error() -> Result<(), Box<dyn Error>> {
+                    Err(Outer(Inner).into())
+                }
+
+                #[wasm_bindgen_test]
+                fn test_js_value() -> Result<(), JsValue> {
+                    Err(js_sys::Error::new("JS_ERROR_MARKER").into())
+                }
+
+                #[wasm_bindgen_test]
+                fn test_debug() -> Result<(), Inner> {
+                    Err(Inner)
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.matches("Error: OUTER_DISPLAY_MARKER").count() == 2
+            && stdout.matches("Caused by:").count() == 2
+            && stdout.matches("0: INNER_DISPLAY_MARKER").count() == 2,
+        "Expected errors to show their `Display` output and sources.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("Error: JS_ERROR_MARKER") && !stdout.contains("JsValue("),
+        "Expected a `JsValue` error to show its stack.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("test tests::test_debug ... FAIL") && stdout.contains("Error: Inner"),
+        "Expected other errors to use their `Debug` output.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_dynamic_tests() {
+    let mut project = Project::new("test_dynamic_tests");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            pub mod fixtures {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test_generator]
+                async fn cases() -> Vec<DynamicTest> {
+                    let names = wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(
+                        &js_sys::Array::of3(&"one".into(), &"two".into(), &"three".into()),
+                    ))
+                    .await
+                    .unwrap();
+                    js_sys::Array::from(&names)
+                        .iter()
+                        .map(|name| {
+                            let name = name.as_string().unwrap();
+                            DynamicTest::new(name.clone(), move || async move {
+                                assert_ne!(name, "three", "DYNAMIC_FAILURE_MARKER");
+                            })
+                        })
+                        .collect()
+                }
+
+                #[wasm_bindgen_test]
+                fn test_static() {}
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("running 4 tests")
+            && stdout.contains("test fixtures::test_static ... ok")
+            && stdout.contains("test fixtures::cases::one ... ok")
+            && stdout.contains("test fixtures::cases::two ... ok")
+            && stdout.contains("test fixtures::cases::three ... FAIL")
+            && stdout.contains("DYNAMIC_FAILURE_MARKER"),
+        "Expected the dynamic tests to run with the static ones.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let output = project.wasm_bindgen_test("cases::t").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("running 2 tests")
+            && stdout.contains("test fixtures::cases::two ... ok")
+            && stdout.contains("test fixtures::cases::three ... FAIL")
+            && stdout.contains("2 filtered out"),
+        "Expected the filter to apply to dynamic tests.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_requires_capability() {
+    let mut project = Project::new("test_requires_capability");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test(requires = "dom")]
+                fn test_dom() {
+                    panic!("DOM_TEST_RAN_MARKER");
+                }
+
+                #[wasm_bindgen_test(requires = "webgpu")]
+                async fn test_webgpu() {
+                    panic!("WEBGPU_TEST_RAN_MARKER");
+                }
+
+                #[wasm_bindgen_test]
+                fn test_plain() {}
+            }
+        "#,
+    );
+
+    for args in ["", "--include-ignored"] {
+        let output = project.wasm_bindgen_test(args).unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            output.status.success()
+                && stdout.contains("test tests::test_dom ... ignored, requires `dom`")
+                && stdout.contains("test tests::test_webgpu ... ignored, requires `webgpu`")
+                && stdout.contains("test tests::test_plain ... ok")
+                && stdout.contains("2 ignored")
+                && !stdout.contains("TEST_RAN_MARKER"),
+            "Expected tests requiring missing capabilities to be skipped with {args:?}.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+        );
+    }
+}
+
+#[test]
+fn test_shared_test_context() {
+    let mut project = Project::new("test_shared_test_context");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use std::sync::atomic::{AtomicU32, Ordering};
+                use wasm_bindgen_test::*;
+
+                static INITIALIZED: AtomicU32 = AtomicU32::new(0);
+
+                #[wasm_bindgen_test_context]
+                async fn fixture() -> Vec<u32> {
+                    INITIALIZED.fetch_add(1, Ordering::SeqCst);
+                    vec![1, 2, 3]
+                }
+
+                #[wasm_bindgen_test]
+                fn test_one() {
+                    assert_eq!(fixture().len(), 3);
+                    assert_eq!(INITIALIZED.load(Ordering::SeqCst), 1);
+                }
+
+                #[wasm_bindgen_test]
+                async fn test_two() {
+                    assert_eq!(fixture()[0], 1);
+                    assert_eq!(INITIALIZED.load(Ordering::SeqCst), 1);
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains("test tests::test_one ... ok")
+            && stdout.contains("test tests::test_two ... ok"),
+        "Expected the context to be initialized once for both tests.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let mut project = Project::new("test_shared_test_context_failure");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test_context]
+                fn fixture() -> Result<u32, String> {
+                    console_log!("CONTEXT_OUTPUT_MARKER");
+                    Err("no database".to_string())
+                }
+
+                #[wasm_bindgen_test]
+                fn test_one() {
+                    panic!("TEST_RAN_MARKER {}", fixture());
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !output.status.success()
+            && stdout.contains(
+                "error: the test context `tests::fixture` failed to initialize, so none of the 1 test ran"
+            )
+            && stdout.contains("no database")
+            && stdout.contains("CONTEXT_OUTPUT_MARKER")
+            && stdout.contains("test result: FAILED")
+            && !stdout.contains("TEST_RAN_MARKER"),
+        "Expected the failing context to be reported instead of the tests.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_assert_js_eq() {
+    let mut project = Project::new("test_assert_js_eq");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    project.file(
+        "src/lib.rs",
+        r##"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::JsValue;
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_equal() {
+                    assert_json_eq!(r#"{"a": 1, "b": [1, 2]}"#, r#"{"b": [1, 2], "a": 1}"#);
+                    let map = js_sys::Map::new();
+                    map.set(&"key".into(), &js_sys::Array::of1(&JsValue::from(1)));
+                    let other = js_sys::Map::new();
+                    other.set(&"key".into(), &js_sys::Array::of1(&JsValue::from(1)));
+                    assert_js_eq!(map, other);
+                }
+
+                #[wasm_bindgen_test]
+                fn test_json_diff() {
+                    assert_json_eq!(
+                        r#"{"a": 1, "b": [1, 2], "c": "x"}"#,
+                        r#"{"a": 1, "b": [1, 3], "c": "x"}"#,
+                        "JSON_MESSAGE_MARKER"
+                    );
+                }
+
+                #[wasm_bindgen_test]
+                fn test_js_diff() {
+                    assert_js_eq!(JsValue::from(-0.0), JsValue::from(0.0));
+                }
+            }
+        "##,
+    );
+
+    let output = project.wasm_bindgen_test("").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test tests::test_equal ... ok")
+            && stdout.contains("assertion `left == right` failed: JSON_MESSAGE_MARKER")
+            && stdout.contains("diff (- left, + right):")
+            && stdout.contains("-     2,")
+            && stdout.contains("+     3,")
+            && stdout.contains("- -0")
+            && stdout.contains("+ 0")
+            && stdout.contains("test result: FAILED. 1 passed; 2 failed"),
+        "Expected the failed assertions to show a diff.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_coverage_report_requires_objects() {
+    let mut project = Project::new("test_coverage_report_requires_objects");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_one() {}
+            }
+        "#,
+    );
+
+    let output = project
+        .wasm_bindgen_test("--coverage-format lcov --coverage-format cobertura")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !output.status.success()
+            && stderr.contains("exporting a coverage report requires the object files"),
+        "Expected the coverage report to require object files.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_time_limits() {
+    let mut project = Project::new("test_time_limits");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_fast() {}
+
+                #[wasm_bindgen_test]
+                fn test_slow() {
+                    let started = js_sys::Date::now();
+                    while js_sys::Date::now() - started < 300.0 {}
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("--report-time").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains("test tests::test_fast ... ok <0.")
+            && stdout.contains("test tests::test_slow ... ok <0."),
+        "Expected the durations of the tests.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let output = project
+        .wasm_bindgen_test("--ensure-time --time-limits 100,200")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !output.status.success()
+            && stdout.contains("test tests::test_fast ... ok <0.")
+            && stdout.contains("test tests::test_slow ... FAIL <0.")
+            && stdout.contains("exceeding the time limit of 200 ms")
+            && stdout.contains("warning: 1 test exceeded the time limit of 100 ms:"),
+        "Expected the slow test to fail.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_memory_growth() {
+    let mut project = Project::new("test_memory_growth");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_small() {}
+
+                #[wasm_bindgen_test]
+                fn test_leak() {
+                    core::mem::forget(vec![1u8; 16 << 20]);
+                }
+            }
+        "#,
+    );
+
+    let output = project
+        .wasm_bindgen_test("--memory-threshold 4MiB")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains("test tests::test_small ... ok [wasm +")
+            && stdout.contains("test tests::test_leak ... ok [wasm +1")
+            && stdout.contains("warning: 1 test grew the memory in use by more than 4.0 MiB:")
+            && stdout.contains("    tests::test_leak [wasm +1"),
+        "Expected the leaking test to be listed.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_detect_leaks() {
+    let mut project = Project::new("test_detect_leaks");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen::prelude::*;
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen]
+                extern "C" {
+                    #[wasm_bindgen(js_name = setInterval)]
+                    fn set_interval(f: &js_sys::Function, ms: i32) -> JsValue;
+                    #[wasm_bindgen(js_name = clearInterval)]
+                    fn clear_interval(id: &JsValue);
+                }
+
+                #[wasm_bindgen_test]
+                fn test_clean() {
+                    let id = set_interval(&js_sys::Function::new_no_args(""), 1000);
+                    clear_interval(&id);
+                }
+
+                #[wasm_bindgen_test]
+                fn test_leaky() {
+                    set_interval(&js_sys::Function::new_no_args(""), 1000);
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("--detect-leaks").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains(
+                "warning: 1 test left DOM elements, timers or event listeners behind:\n    \
+                 tests::test_leaky: 1 timer\n"
+            ),
+        "Expected the leaky test to be listed.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_count_instructions() {
+    let mut project = Project::new("test_count_instructions");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                fn sum(n: u64) -> u64 {
+                    (0..std::hint::black_box(n)).fold(0, |sum, i| sum ^ i.wrapping_mul(i))
+                }
+
+                #[wasm_bindgen_test]
+                fn test_light() {
+                    sum(10);
+                }
+
+                #[wasm_bindgen_test]
+                fn test_heavy() {
+                    sum(10_000);
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("--count-instructions").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let count = |test: &str| {
+        let line = stdout.lines().find(|line| line.contains(test))?;
+        let (_, count) = line.split_once('<')?;
+        count.strip_suffix(" instructions>")?.parse::<u64>().ok()
+    };
+    let (light, heavy) = (count("test_light"), count("test_heavy"));
+    assert!(
+        output.status.success() && light.zip(heavy).is_some_and(|(light, heavy)| heavy > light),
+        "Expected the heavy test to execute more instructions.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_size_report() {
+    let mut project = Project::new("test_size_report");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test() {}
+            }
+        "#,
+    );
+
+    let output = project
+        .wasm_bindgen_test("--size-report --size-baseline sizes.json")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains("saved the size of `test_size_report` to the size baseline")
+            && stdout.contains("size of `test_size_report` after wasm-bindgen: ")
+            && stdout.contains("    sections:")
+            && stdout.contains("        code  ")
+            && stdout.contains("    largest functions:"),
+        "Expected a size report.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let output = project
+        .wasm_bindgen_test("--size-report --size-baseline sizes.json")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains("after wasm-bindgen: ")
+            && stdout.contains(" (unchanged)"),
+        "Expected the size to be unchanged.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    // Shrink the baseline for the module to have regressed.
+    let path = project.root.join("sizes.json");
+    let mut baseline: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let total = baseline["test_size_report"]["total"].as_u64().unwrap();
+    baseline["test_size_report"]["total"] = (total / 2).into();
+    fs::write(&path, baseline.to_string()).unwrap();
+    let output = project
+        .wasm_bindgen_test("--size-baseline sizes.json --max-size-regression 50")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !output.status.success()
+            && stdout.contains("test result: ok.")
+            && stderr.contains("the Wasm module of `test_size_report` grew from")
+            && stderr.contains("more than the allowed 50% over the size baseline"),
+        "Expected a size regression.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_bindgen_output_cached() {
+    let mut project = Project::new("test_bindgen_output_cached");
+    let lib = r#"
+        #[cfg(test)]
+        mod tests {
+            use wasm_bindgen_test::*;
+
+            #[wasm_bindgen_test]
+            fn test() {
+                assert_eq!(1 + 1, VALUE);
+            }
+
+            const VALUE: u32 = 2;
+        }
+    "#;
+    project.file("src/lib.rs", lib);
+
+    let cache = TARGET_DIR
+        .join("wasm32-unknown-unknown")
+        .join("debug")
+        .join("wbg_cache");
+    let entries = || -> Vec<(PathBuf, std::time::SystemTime)> {
+        fs::read_dir(&cache)
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_string_lossy();
+                name.starts_with("test_bindgen_output_cached-") && !name.contains(".tmp")
+            })
+            .map(|path| {
+                let key = fs::metadata(path.join(".wbg-cache-key")).unwrap();
+                (path, key.modified().unwrap())
+            })
+            .collect()
+    };
+    for (path, _) in entries() {
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    let run = |project: &mut Project| {
+        let output = project.wasm_bindgen_test("").unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            output.status.success() && stdout.contains("test result: ok. 1 passed"),
+            "Expected the test to pass.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+        );
+        entries()
+    };
+
+    let first = run(&mut project);
+    assert_eq!(first.len(), 1, "Expected a cache entry: {first:?}");
+    assert_eq!(
+        run(&mut project),
+        first,
+        "Expected the cache entry to be reused"
+    );
+
+    // A changed test binary replaces its entry.
+    project.file("src/lib.rs", &lib.replace("1 + 1", "2 + 0"));
+    let changed = run(&mut project);
+    assert_eq!(
+        changed.len(),
+        1,
+        "Expected a single cache entry: {changed:?}"
+    );
+    assert_ne!(changed, first, "Expected the cache entry to be replaced");
+}
+
+/// Test that the start function of a crate is neither run nor exported for
+/// its tests: only doctests export it, as `__wbindgen_test_start`.
+#[test]
+fn test_start_function_not_exported() {
+    let mut project = Project::new("test_start_function_not_exported");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(start)]
+            fn start() {
+                panic!("the start function ran");
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test() {}
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stdout.contains("test result: ok. 1 passed"),
+        "Expected the test to pass.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    // The bindings of the test binary are cached, so they can be inspected.
+    let cache = TARGET_DIR
+        .join("wasm32-unknown-unknown")
+        .join("debug")
+        .join("wbg_cache");
+    let outputs: Vec<PathBuf> = fs::read_dir(&cache)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("test_start_function_not_exported-") && !name.contains(".tmp")
+        })
+        .flat_map(|dir| fs::read_dir(dir).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert!(!outputs.is_empty(), "Expected cached bindings in {cache:?}");
+    for path in outputs {
+        let contents = fs::read(&path).unwrap();
+        assert!(
+            !contents
+                .windows(b"__wbindgen_test_start".len())
+                .any(|window| window == b"__wbindgen_test_start"),
+            "Expected no `__wbindgen_test_start` export in {path:?}"
+        );
+    }
+}
+
+#[test]
+fn test_cpu_profile() {
+    let mut project = Project::new("test_cpu_profile");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_fast() {}
+
+                #[wasm_bindgen_test]
+                fn test_slow() {
+                    let started = js_sys::Date::now();
+                    while js_sys::Date::now() - started < 100.0 {}
+                }
+            }
+        "#,
+    );
+
+    let output = project.wasm_bindgen_test("--cpu-profile").unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stdout.contains("CPU profiles of 2 test(s) saved to"),
+        "Expected the CPU profiles to be saved.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    // The artifacts are in a directory named after the test binary.
+    let profiles: Vec<_> = fs::read_dir(project.root.join("target").join("wbg_artifacts"))
+        .unwrap()
+        .map(|dir| dir.unwrap().path().join("tests__test_slow.cpuprofile"))
+        .filter(|path| path.is_file())
+        .collect();
+    assert_eq!(profiles.len(), 1, "Expected a profile of `test_slow`");
+    let profile = fs::read_to_string(&profiles[0]).unwrap();
+    assert!(
+        profile.starts_with(r#"{"nodes":["#),
+        "Expected a `.cpuprofile`:\n{profile}"
+    );
+}
+
+#[test]
+fn test_flamegraph() {
+    let mut project = Project::new("test_flamegraph");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    project.file(
+        "src/lib.rs",
+        r#"
+            #[cfg(test)]
+            mod tests {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_fast() {}
+
+                #[inline(never)]
+                fn spin() {
+                    let started = js_sys::Date::now();
+                    while js_sys::Date::now() - started < 300.0 {}
+                }
+
+                #[wasm_bindgen_test]
+                fn test_slow() {
+                    spin();
+                }
+            }
+        "#,
+    );
+
+    // Only the tests exceeding the time limit get a flame graph.
+    let output = project
+        .wasm_bindgen_test("--flamegraph --report-time --time-limits 100,1000")
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stdout.contains("flame graphs of 1 test(s) saved to"),
+        "Expected a flame graph.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+
+    let dir = fs::read_dir(project.root.join("target").join("wbg_artifacts"))
+        .unwrap()
+        .map(|dir| dir.unwrap().path())
+        .find(|dir| dir.join("tests__test_slow.svg").is_file())
+        .expect("Expected a flame graph of `test_slow`");
+    assert!(!dir.join("tests__test_fast.svg").exists());
+    let folded = fs::read_to_string(dir.join("tests__test_slow.folded")).unwrap();
+    assert!(
+        folded
+            .lines()
+            .any(|line| line.contains("test_flamegraph::tests::spin")),
+        "Expected the demangled Wasm frames:\n{folded}"
+    );
+}
+
+#[test]
+fn test_setup_teardown_hooks() {
+    let mut project = Project::new("test_setup_teardown_hooks");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    let output = project
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            pub mod hooked {
+                use std::cell::Cell;
+                use wasm_bindgen_test::*;
+
+                thread_local!(static STATE: Cell<u32> = Cell::new(0));
+
+                #[wasm_bindgen_test_setup]
+                fn setup() {
+                    assert_eq!(STATE.with(Cell::get), 0, "teardown didn't run");
+                    STATE.with(|state| state.set(1));
+                }
+
+                #[wasm_bindgen_test_teardown]
+                async fn teardown() {
+                    wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&1.into()))
+                        .await
+                        .unwrap();
+                    STATE.with(|state| state.set(0));
+                }
+
+                #[wasm_bindgen_test]
+                fn test_first() {
+                    assert_eq!(STATE.with(Cell::get), 1);
+                }
+
+                #[wasm_bindgen_test]
+                async fn test_second() {
+                    assert_eq!(STATE.with(Cell::get), 1);
+                }
+            }
+
+            #[cfg(test)]
+            pub mod failing_setup {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test_setup]
+                fn setup() -> Result<(), String> {
+                    Err("SETUP_FAILED_MARKER".into())
+                }
+
+                #[wasm_bindgen_test]
+                fn test_skipped() {
+                    panic!("TEST_RAN_MARKER");
+                }
+            }
+
+            #[cfg(test)]
+            pub mod failing_teardown {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test_teardown]
+                fn teardown() {
+                    panic!("TEARDOWN_FAILED_MARKER");
+                }
+
+                #[wasm_bindgen_test]
+                fn test_passes() {}
+            }
+
+            #[cfg(test)]
+            pub mod unhooked {
+                use wasm_bindgen_test::*;
+
+                #[wasm_bindgen_test]
+                fn test_unaffected() {}
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("test hooked::test_first ... ok")
+            && stdout.contains("test hooked::test_second ... ok")
+            && stdout.contains("test unhooked::test_unaffected ... ok"),
+        "Expected the hooks to run around each test of their module.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("test failing_setup::test_skipped ... FAIL")
+            && stdout.contains("note: the setup of the test's module failed, so it didn't run")
+            && stdout.contains("SETUP_FAILED_MARKER")
+            && !stdout.contains("TEST_RAN_MARKER"),
+        "Expected a failing setup to fail the test without running it.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+    assert!(
+        stdout.contains("test failing_teardown::test_passes ... FAIL")
+            && stdout.contains("note: the teardown of the test's module failed")
+            && stdout.contains("TEARDOWN_FAILED_MARKER"),
+        "Expected a failing teardown to fail the test.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
+
+#[test]
+fn test_test_executor() {
+    let mut project = Project::new("test_test_executor");
+    project
+        .deps
+        .push_str("wasm-bindgen-futures = { path = '{root}/crates/futures' }\n");
+    project
+        .deps
+        .push_str("js-sys = { path = '{root}/crates/js-sys' }\n");
+    let output = project
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(test)]
+            mod tests {
+                use std::cell::Cell;
+                use std::future::Future;
+                use std::pin::Pin;
+                use std::task::{Context, Poll};
+                use wasm_bindgen::JsValue;
+                use wasm_bindgen_test::*;
+
+                thread_local!(static IN_EXECUTOR: Cell<bool> = Cell::new(false));
+
+                type TestFuture = Pin<Box<dyn Future<Output = Result<(), JsValue>>>>;
+
+                struct InExecutor(TestFuture);
+
+                impl Future for InExecutor {
+                    type Output = Result<(), JsValue>;
+
+                    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+                        IN_EXECUTOR.with(|flag| flag.set(true));
+                        let poll = self.0.as_mut().poll(cx);
+                        IN_EXECUTOR.with(|flag| flag.set(false));
+                        poll
+                    }
+                }
+
+                struct Executor;
+
+                impl TestExecutor for Executor {
+                    fn run(&self, test: TestFuture) -> TestFuture {
+                        Box::pin(InExecutor(test))
+                    }
+                }
+
+                #[wasm_bindgen_test_executor]
+                fn executor() -> Executor {
+                    Executor
+                }
+
+                #[wasm_bindgen_test]
+                fn test_sync() {
+                    assert!(IN_EXECUTOR.with(Cell::get));
+                }
+
+                #[wasm_bindgen_test]
+                async fn test_async() {
+                    wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&1.into()))
+                        .await
+                        .unwrap();
+                    assert!(IN_EXECUTOR.with(Cell::get));
+                }
+            }
+        "#,
+        )
+        .wasm_bindgen_test("")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success()
+            && stdout.contains("test tests::test_sync ... ok")
+            && stdout.contains("test tests::test_async ... ok"),
+        "Expected the tests to run in the registered executor.\nstdout:\n{stdout}\nstderr:\n{stderr}"
+    );
+}
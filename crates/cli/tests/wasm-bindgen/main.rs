@@ -50,6 +50,9 @@ struct Project {
     deps: String,
     cargo_cmd: Command,
     built: bool,
+    /// Whether `wasm_bindgen` generates the externref fallback, as it does by
+    /// default, rather than checking the bindings of a single version.
+    externref_fallback: bool,
 }
 
 impl Project {
@@ -71,6 +74,7 @@ impl Project {
             deps: "wasm-bindgen = { path = '{root}' }\n".to_owned(),
             cargo_cmd,
             built: false,
+            externref_fallback: false,
         }
     }
 
@@ -106,11 +110,17 @@ impl Project {
                 self.build().as_os_str(),
             ]
             .into_iter()
-            .chain(args.split_whitespace().map(str::as_ref)),
+            .chain(args.split_whitespace().map(str::as_ref))
+            .chain((!self.externref_fallback).then_some("--no-externref-fallback".as_ref())),
         )?;
         Ok(output)
     }
 
+    fn externref_fallback(&mut self) -> &mut Project {
+        self.externref_fallback = true;
+        self
+    }
+
     fn dep(&mut self, line: &str) -> &mut Project {
         self.deps.push_str(line);
         self.deps.push('\n');
@@ -408,6 +418,1054 @@ fn default_module_path_target_no_modules() {
     assert!(contents.contains("module_or_path = script_src.replace(",));
 }
 
+#[test]
+fn import_map_remaps_modules() {
+    let mut project = Project::new("import_map_remaps_modules");
+    project
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(module = "chart-lib")]
+                extern "C" {
+                    fn draw(value: u32);
+                }
+
+                #[wasm_bindgen(module = "/js/local.js")]
+                extern "C" {
+                    fn tick(value: &str);
+                }
+
+                #[wasm_bindgen]
+                pub fn render(value: u32) {
+                    tick("render");
+                    draw(value);
+                }
+            "#,
+        )
+        .file("js/local.js", "export function tick(value) {}\n")
+        .file(
+            "importmap.json",
+            r#"{
+                "imports": {
+                    "chart-lib": "https://esm.sh/chart-lib@1",
+                    "/js/": "/static/js/"
+                }
+            }"#,
+        );
+    let import_map = project.root.join("importmap.json");
+    let out_dir = project
+        .wasm_bindgen(&format!(
+            "--target web --import-map {}",
+            import_map.display()
+        ))
+        .unwrap();
+
+    let contents = fs::read_to_string(out_dir.join("import_map_remaps_modules.js")).unwrap();
+    assert!(contents.contains("https://esm.sh/chart-lib@1"));
+    assert!(contents.contains("/static/js/local.js"));
+    assert!(!contents.contains("snippets/"));
+}
+
+#[test]
+fn no_modules_global_names_bindings_and_typings() {
+    let out_dir = Project::new("no_modules_global_names_bindings_and_typings")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn add(a: u32, b: u32) -> u32 {
+                    a + b
+                }
+            "#,
+        )
+        .wasm_bindgen("--target no-modules --no-modules-global my_lib")
+        .unwrap();
+
+    let contents =
+        fs::read_to_string(out_dir.join("no_modules_global_names_bindings_and_typings.js"))
+            .unwrap();
+    assert!(contents.starts_with("let my_lib = (function(exports) {"));
+    let contents =
+        fs::read_to_string(out_dir.join("no_modules_global_names_bindings_and_typings.d.ts"))
+            .unwrap();
+    // The init function must precede the namespace to merge with it.
+    let function = contents.find("declare function my_lib (").unwrap();
+    let namespace = contents.find("declare namespace my_lib {").unwrap();
+    assert!(function < namespace);
+    assert!(contents.contains("Promise<my_lib.InitOutput>;"));
+    assert!(contents.contains("export function add(a: number, b: number): number;"));
+    assert!(contents.contains("export function initSync("));
+    assert!(!contents.contains("wasm_bindgen"));
+    // The types stay global, deprecated, for the code still using them.
+    assert!(contents.contains("declare type InitInput = my_lib.InitInput;"));
+    assert!(contents.contains("declare type InitOutput = my_lib.InitOutput;"));
+}
+
+#[test]
+fn split_chunk_imports_module_on_load() {
+    let out_dir = Project::new("split_chunk_imports_module_on_load")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(module = "/js/charts.js")]
+                extern "C" {
+                    fn draw(value: u32);
+                }
+
+                #[wasm_bindgen(inline_js = "export function tick() {}")]
+                extern "C" {
+                    fn tick();
+                }
+
+                #[wasm_bindgen]
+                pub fn render(value: u32) {
+                    tick();
+                    draw(value);
+                }
+            "#,
+        )
+        .file("js/charts.js", "export function draw(value) {}\n")
+        .wasm_bindgen("--target web --split-chunk charts=/js/charts.js")
+        .unwrap();
+
+    let contents =
+        fs::read_to_string(out_dir.join("split_chunk_imports_module_on_load.js")).unwrap();
+    assert!(contents.contains("/js/charts.js').then("));
+    assert!(!contents.contains("/js/charts.js';"));
+    // Other snippets are still imported statically.
+    assert!(contents.contains("/inline0.js"));
+    assert!(!contents.contains("/inline0.js').then("));
+    assert!(contents.contains("export function __wbg_load_chunk(name) {"));
+    let contents =
+        fs::read_to_string(out_dir.join("split_chunk_imports_module_on_load.d.ts")).unwrap();
+    assert!(contents.contains("export function __wbg_load_chunk(name: \"charts\"): Promise<void>;"));
+}
+
+#[cfg(unix)]
+#[test]
+fn wasm_opt_runs_on_emitted_module() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut project = Project::new("wasm_opt_runs_on_emitted_module");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn foo() {}
+        "#,
+    );
+    // A stand-in for `wasm-opt` recording its arguments.
+    let args = project.root.join("wasm-opt-args");
+    let wasm_opt = project.root.join("wasm-opt");
+    fs::write(
+        &wasm_opt,
+        format!("#!/bin/sh\necho \"$@\" > '{}'\n", args.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&wasm_opt, fs::Permissions::from_mode(0o755)).unwrap();
+
+    // `WASM_OPT` is set for a separate process, as other tests run in this
+    // one concurrently.
+    let out_dir = project.root.join("pkg");
+    cargo_bin_cmd!("wasm-bindgen")
+        .env("WASM_OPT", &wasm_opt)
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(project.build())
+        .args(
+            "--target web --wasm-opt z --remove-producers-section --no-externref-fallback"
+                .split_whitespace(),
+        )
+        .assert()
+        .success();
+
+    let wasm = out_dir.join("wasm_opt_runs_on_emitted_module_bg.wasm");
+    assert_eq!(
+        fs::read_to_string(args).unwrap().trim(),
+        format!(
+            "{0} -o {0} -Oz --debuginfo --strip-producers",
+            wasm.display()
+        )
+    );
+}
+
+#[test]
+fn inline_wasm_embeds_module_in_js() {
+    use base64::{prelude::BASE64_STANDARD, Engine as _};
+
+    let out_dir = Project::new("inline_wasm_embeds_module_in_js")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target web --inline-wasm")
+        .unwrap();
+
+    assert!(!out_dir
+        .join("inline_wasm_embeds_module_in_js_bg.wasm")
+        .exists());
+    assert!(!out_dir
+        .join("inline_wasm_embeds_module_in_js_bg.wasm.d.ts")
+        .exists());
+    let js = fs::read_to_string(out_dir.join("inline_wasm_embeds_module_in_js.js")).unwrap();
+    let base64 = js
+        .split_once("const base64 = \"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .unwrap()
+        .0;
+    let wasm = BASE64_STANDARD.decode(base64).unwrap();
+    assert!(wasm.starts_with(b"\0asm"));
+    assert!(js.contains("module_or_path = __wbg_inline_wasm();"));
+}
+
+#[test]
+fn snippet_layouts() {
+    let mut project = Project::new("snippet_layouts");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(inline_js = "export function tick() {}")]
+            extern "C" {
+                fn tick();
+            }
+
+            #[wasm_bindgen(inline_js = "export function tock() {}")]
+            extern "C" {
+                fn tock();
+            }
+
+            #[wasm_bindgen]
+            pub fn foo() {
+                tick();
+                tock();
+            }
+        "#,
+    );
+
+    let out_dir = project
+        .wasm_bindgen("--target web --snippets stable")
+        .unwrap();
+    let js = fs::read_to_string(out_dir.join("snippet_layouts.js")).unwrap();
+    assert!(js.contains("./snippets/snippet_layouts/inline0.js"));
+    assert!(js.contains("./snippets/snippet_layouts/inline1.js"));
+    assert!(out_dir.join("snippets/snippet_layouts/inline0.js").exists());
+    assert!(out_dir.join("snippets/snippet_layouts/inline1.js").exists());
+
+    let out_dir = project
+        .wasm_bindgen("--target web --snippets concat")
+        .unwrap();
+    let js = fs::read_to_string(out_dir.join("snippet_layouts.js")).unwrap();
+    assert!(js.contains("./snippets/index.js"));
+    assert!(!js.contains("inline0.js"));
+    let index = fs::read_to_string(out_dir.join("snippets/index.js")).unwrap();
+    assert!(index.contains("export function tick() {}"));
+    assert!(index.contains("export function tock() {}"));
+    assert_eq!(fs::read_dir(out_dir.join("snippets")).unwrap().count(), 1);
+}
+
+#[test]
+fn minify_js_shrinks_working_glue() {
+    let out_dir = Project::new("minify_js_shrinks_working_glue")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                /// Greets `name`.
+                #[wasm_bindgen]
+                pub fn greet(name: &str) -> String {
+                    format!("hello, {name}")
+                }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs --minify-js")
+        .unwrap();
+
+    let js = fs::read_to_string(out_dir.join("minify_js_shrinks_working_glue.js")).unwrap();
+    assert!(!js.contains("Greets"));
+    assert!(!js.contains("function passStringToWasm0"));
+    assert!(js.lines().all(|line| !line.starts_with(' ')));
+
+    Command::new("node")
+        .arg("-e")
+        .arg("console.log(require('./minify_js_shrinks_working_glue.js').greet('world'))")
+        .current_dir(out_dir)
+        .assert()
+        .success()
+        .stdout("hello, world\n");
+}
+
+#[test]
+fn track_handles_lists_live_objects() {
+    let out_dir = Project::new("track_handles_lists_live_objects")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub struct Counter(u32);
+
+                #[wasm_bindgen]
+                impl Counter {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new() -> Counter {
+                        Counter(0)
+                    }
+                }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs --track-handles")
+        .unwrap();
+
+    let ts = fs::read_to_string(out_dir.join("track_handles_lists_live_objects.d.ts")).unwrap();
+    assert!(ts.contains("export function __wbg_live_handles()"));
+
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            r#"
+            const { Counter, __wbg_live_handles } = require('./track_handles_lists_live_objects.js');
+            function makeCounter() {
+                return new Counter();
+            }
+            const kept = makeCounter();
+            new Counter().free();
+            const live = __wbg_live_handles();
+            if (live.length !== 1) throw new Error(`expected one live handle: ${JSON.stringify(live)}`);
+            if (live[0].kind !== 'Counter') throw new Error(live[0].kind);
+            if (!live[0].stack.includes('makeCounter')) throw new Error(live[0].stack);
+            kept.free();
+            if (__wbg_live_handles().length !== 0) throw new Error('handle still live after free');
+            "#,
+        )
+        .current_dir(out_dir)
+        .assert()
+        .success();
+}
+
+#[test]
+fn memory_chooses_the_memory_by_name() {
+    let mut project = Project::new("memory_chooses_the_memory_by_name");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greet(name: &str) -> String {
+                format!("hello, {name}")
+            }
+        "#,
+    );
+
+    let out_dir = project
+        .wasm_bindgen("--target nodejs --memory memory")
+        .unwrap();
+    Command::new("node")
+        .arg("-e")
+        .arg("console.log(require('./memory_chooses_the_memory_by_name.js').greet('world'))")
+        .current_dir(out_dir)
+        .assert()
+        .success()
+        .stdout("hello, world\n");
+
+    let err = project
+        .wasm_bindgen("--target nodejs --memory scratch")
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("module has no memory exported or imported as `scratch`"));
+}
+
+#[test]
+fn experimental_emit_wit_describes_exports() {
+    let out_dir = Project::new("experimental_emit_wit_describes_exports")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                #[derive(Clone, Copy)]
+                pub enum Color {
+                    Red,
+                    DarkGreen,
+                }
+
+                /// Counts.
+                #[wasm_bindgen]
+                pub struct Counter(u32);
+
+                #[wasm_bindgen]
+                impl Counter {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new(start: u32) -> Counter {
+                        Counter(start)
+                    }
+
+                    pub fn get_value(&self) -> u32 {
+                        self.0
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub fn greet_user(name: &str) -> String {
+                    format!("hello, {name}")
+                }
+
+                #[wasm_bindgen]
+                pub fn next_color(color: Color) -> Color {
+                    match color {
+                        Color::Red => Color::DarkGreen,
+                        Color::DarkGreen => Color::Red,
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub fn log_value(_value: JsValue) {}
+            "#,
+        )
+        .wasm_bindgen("--target experimental-nodejs-module --experimental-emit-wit")
+        .unwrap();
+
+    let wit =
+        fs::read_to_string(out_dir.join("experimental_emit_wit_describes_exports.wit")).unwrap();
+    assert!(wit.contains("package local:experimental-emit-wit-describes-exports;"));
+    assert!(wit.contains("constructor(start: u32);"));
+    assert!(wit.contains("get-value: func() -> u32;"));
+    assert!(wit.contains("greet-user: func(name: string) -> string;"));
+    assert!(wit.contains("next-color: func(color: color) -> color;"));
+    assert!(wit.contains("// - `log_value`: JS values have no WIT type"));
+
+    Command::new("node")
+        .arg("--input-type=module")
+        .arg("-e")
+        .arg(
+            r#"
+            import { api } from './experimental_emit_wit_describes_exports.wit.js';
+            console.log(api.greetUser('world'));
+            console.log(new api.Counter(3).getValue());
+            console.log(api.nextColor('red'));
+            "#,
+        )
+        .current_dir(out_dir)
+        .assert()
+        .success()
+        .stdout("hello, world\n3\ndark-green\n");
+}
+
+#[test]
+fn experimental_emit_wit_needs_es_modules() {
+    let err = Project::new("experimental_emit_wit_needs_es_modules")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target nodejs --experimental-emit-wit")
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("only supported for targets emitting ES modules"));
+}
+
+#[test]
+fn externref_fallback_loads_either_version() {
+    let out_dir = Project::new("externref_fallback_loads_either_version")
+        .externref_fallback()
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn describe(value: JsValue) -> String {
+                    format!("{:?}", value.as_string())
+                }
+            "#,
+        )
+        .wasm_bindgen("--target experimental-nodejs-module")
+        .unwrap();
+
+    let name = "externref_fallback_loads_either_version";
+    for file in ["_externref_bg.wasm", "_fallback_bg.wasm", ".d.ts"] {
+        assert!(out_dir.join(format!("{name}{file}")).is_file(), "{file}");
+    }
+    let externref = fs::read_to_string(out_dir.join(format!("{name}_externref.js"))).unwrap();
+    let fallback = fs::read_to_string(out_dir.join(format!("{name}_fallback.js"))).unwrap();
+    assert!(!externref.contains("addHeapObject"));
+    assert!(fallback.contains("addHeapObject"));
+
+    for (setup, loaded) in [
+        ("", "externref"),
+        ("WebAssembly.validate = () => false;", "fallback"),
+    ] {
+        Command::new("node")
+            .arg("--input-type=module")
+            .arg("-e")
+            .arg(format!(
+                r#"
+                {setup}
+                const {{ describe }} = await import('./{name}.js');
+                const loaded = await import('./{name}_{loaded}.js');
+                console.log(describe('hi'), describe === loaded.describe);
+                "#
+            ))
+            .current_dir(&out_dir)
+            .assert()
+            .success()
+            .stdout("Some(\"hi\") true\n");
+    }
+}
+
+#[test]
+fn externref_fallback_opt_out() {
+    let mut project = Project::new("externref_fallback_opt_out");
+    project.externref_fallback().file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn foo() {}
+        "#,
+    );
+    let name = "externref_fallback_opt_out";
+
+    let out_dir = project
+        .wasm_bindgen("--target web --no-externref-fallback")
+        .unwrap();
+    assert!(out_dir.join(format!("{name}_bg.wasm")).is_file());
+    assert!(!out_dir.join(format!("{name}_fallback.js")).exists());
+
+    // Targets the fallback doesn't support only get the usual bindings.
+    let out_dir = project.wasm_bindgen("--target no-modules").unwrap();
+    assert!(out_dir.join(format!("{name}_bg.wasm")).is_file());
+    assert!(!out_dir.join(format!("{name}_fallback.js")).exists());
+    let out_dir = project
+        .wasm_bindgen("--target web --experimental-emit-wit")
+        .unwrap();
+    assert!(out_dir.join(format!("{name}.wit")).is_file());
+    assert!(!out_dir.join(format!("{name}_fallback.js")).exists());
+}
+
+#[test]
+fn web_worker_bootstrap_needs_threads() {
+    let out_dir = Project::new("web_worker_bootstrap_needs_threads")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn add(a: u32, b: u32) -> u32 {
+                    a + b
+                }
+            "#,
+        )
+        .wasm_bindgen("--target web")
+        .unwrap();
+
+    let name = "web_worker_bootstrap_needs_threads";
+    assert!(!out_dir.join(format!("{name}.worker.js")).exists());
+    let js = fs::read_to_string(out_dir.join(format!("{name}.js"))).unwrap();
+    assert!(!js.contains("__wbg_init_thread_pool"));
+}
+
+#[test]
+fn dual_package_exports_both_formats() {
+    let mut project = Project::new("dual_package_exports_both_formats");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#,
+    );
+    let out_dir = project
+        .wasm_bindgen("--target nodejs --dual-package")
+        .unwrap();
+
+    let name = "dual_package_exports_both_formats";
+    let package: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("package.json")).unwrap()).unwrap();
+    assert_eq!(package["main"], format!("./{name}.js"));
+    assert_eq!(package["exports"]["."]["import"], format!("./{name}.mjs"));
+    assert_eq!(package["exports"]["."]["require"], format!("./{name}.js"));
+    assert_eq!(package["exports"]["."]["types"], format!("./{name}.d.ts"));
+
+    // Both entry points share the one instance.
+    Command::new("node")
+        .arg("--input-type=module")
+        .arg("-e")
+        .arg(format!(
+            r#"
+            import {{ createRequire }} from 'node:module';
+            import {{ add }} from './{name}.mjs';
+            const require = createRequire(import.meta.url);
+            console.log(add(1, 2), add === require('./{name}.js').add);
+            "#
+        ))
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("3 true\n");
+
+    let err = project
+        .wasm_bindgen("--target web --dual-package")
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("--dual-package is only supported for --target nodejs"));
+}
+
+#[test]
+fn deno_json_maps_npm_packages() {
+    let mut project = Project::new("deno_json_maps_npm_packages");
+    project
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(module = "lodash/fp")]
+                extern "C" {
+                    fn identity(value: u32) -> u32;
+                }
+
+                #[wasm_bindgen(module = "@scope/pkg")]
+                extern "C" {
+                    fn tick();
+                }
+
+                #[wasm_bindgen]
+                pub fn run(value: u32) -> u32 {
+                    tick();
+                    identity(value)
+                }
+            "#,
+        )
+        .file(
+            "package.json",
+            r#"{ "dependencies": { "lodash": "^4.17.0" } }"#,
+        );
+    let out_dir = project.wasm_bindgen("--target deno --deno-json").unwrap();
+
+    let deno: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("deno.json")).unwrap()).unwrap();
+    assert_eq!(
+        deno,
+        serde_json::json!({
+            "exports": "./deno_json_maps_npm_packages.js",
+            "imports": {
+                "@scope/pkg": "npm:@scope/pkg",
+                "lodash": "npm:lodash@^4.17.0",
+                "lodash/": "npm:/lodash@^4.17.0/",
+            },
+            "permissions": {
+                "default": { "read": ["./deno_json_maps_npm_packages_bg.wasm"] },
+            },
+            "compilerOptions": { "lib": ["deno.window", "esnext.disposable"] },
+        })
+    );
+
+    let err = project
+        .wasm_bindgen("--target web --deno-json")
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("--deno-json is only supported for --target deno"));
+}
+
+#[test]
+fn deno_json_permissions_follow_the_wasm_loading() {
+    let mut project = Project::new("deno_json_permissions_follow_the_wasm_loading");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn run() {}
+        "#,
+    );
+    let deno_json = |out_dir: PathBuf| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(out_dir.join("deno.json")).unwrap()).unwrap()
+    };
+
+    // An inlined module needs no permission, and there are no declarations
+    // to configure the compiler for.
+    let out_dir = project
+        .wasm_bindgen("--target deno --deno-json --inline-wasm --no-typescript")
+        .unwrap();
+    assert_eq!(
+        deno_json(out_dir),
+        serde_json::json!({
+            "exports": "./deno_json_permissions_follow_the_wasm_loading.js",
+        })
+    );
+
+    // Either version of the module may be read.
+    let out_dir = project
+        .externref_fallback()
+        .wasm_bindgen("--target deno --deno-json")
+        .unwrap();
+    let deno = deno_json(out_dir);
+    assert_eq!(
+        deno["permissions"],
+        serde_json::json!({
+            "default": {
+                "read": [
+                    "./deno_json_permissions_follow_the_wasm_loading_externref_bg.wasm",
+                    "./deno_json_permissions_follow_the_wasm_loading_fallback_bg.wasm",
+                ],
+            },
+        })
+    );
+}
+
+#[test]
+fn jsdoc_types_define_typedefs() {
+    let mut project = Project::new("jsdoc_types_define_typedefs");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub enum Color {
+                Red = "red",
+                Green = "green",
+            }
+
+            #[wasm_bindgen]
+            pub fn paint(color: Color) -> Color {
+                color
+            }
+        "#,
+    );
+    let out_dir = project
+        .wasm_bindgen("--target web --no-typescript --jsdoc-types")
+        .unwrap();
+
+    let js = fs::read_to_string(out_dir.join("jsdoc_types_define_typedefs.js")).unwrap();
+    assert!(js.contains("@typedef {\"red\" | \"green\"} Color"));
+    assert!(js.contains("@param {Color} color"));
+    for typedef in ["} InitInput", "} SyncInitInput", "} InitOutput"] {
+        assert!(js.contains(typedef), "missing typedef ending `{typedef}`");
+    }
+    assert!(js.contains("@returns {Promise<InitOutput>}"));
+    assert!(js.contains("readonly paint: (a: number) => number;"));
+}
+
+#[test]
+fn strip_custom_sections_keeps_listed() {
+    let mut project = Project::new("strip_custom_sections_keeps_listed");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[used]
+            #[link_section = "keep_me"]
+            static KEPT: [u8; 4] = *b"kept";
+
+            #[used]
+            #[link_section = "drop_me"]
+            static DROPPED: [u8; 7] = *b"dropped";
+
+            #[wasm_bindgen]
+            pub fn run() {}
+        "#,
+    );
+    let out_dir = project
+        .wasm_bindgen("--target web --strip-custom-sections=name,keep_*")
+        .unwrap();
+
+    let wasm = fs::read(out_dir.join("strip_custom_sections_keeps_listed_bg.wasm")).unwrap();
+    let mut sections = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+        if let Payload::CustomSection(reader) = payload.unwrap() {
+            sections.push(reader.name().to_string());
+        }
+    }
+    sections.sort();
+    assert_eq!(sections, ["keep_me", "name"]);
+}
+
+#[test]
+fn data_enums_are_tagged_objects() {
+    let out_dir = Project::new("data_enums_are_tagged_objects")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub enum Shape {
+                    Circle { radius: f64 },
+                    Rect {
+                        width: u32,
+                        #[wasm_bindgen(js_name = tall)]
+                        height: u32,
+                        label: Option<String>,
+                    },
+                    Empty,
+                }
+
+                #[wasm_bindgen]
+                pub fn area(shape: Shape) -> f64 {
+                    match shape {
+                        Shape::Circle { radius } => 3.0 * radius * radius,
+                        Shape::Rect { width, height, .. } => (width * height).into(),
+                        Shape::Empty => 0.0,
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub fn square(side: u32) -> Shape {
+                    Shape::Rect { width: side, height: side, label: Some("square".into()) }
+                }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs")
+        .unwrap();
+
+    let ts = fs::read_to_string(out_dir.join("data_enums_are_tagged_objects.d.ts")).unwrap();
+    assert!(ts.contains("export type Shape ="));
+    assert!(ts.contains(r#"| { kind: "Circle"; radius: number }"#));
+    assert!(ts
+        .contains(r#"| { kind: "Rect"; width: number; tall: number; label: string | undefined }"#));
+    assert!(ts.contains(r#"| { kind: "Empty" }"#));
+    assert!(ts.contains("export function area(shape: Shape): number;"));
+
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            "const m = require('./data_enums_are_tagged_objects.js');
+            console.log(m.area({ kind: 'Circle', radius: 2 }));
+            console.log(m.area({ kind: 'Rect', width: 2, tall: 3 }));
+            console.log(m.area({ kind: 'Empty' }));
+            console.log(JSON.stringify(m.square(4)));
+            try { m.area({ kind: 'Triangle' }); } catch (e) { console.log(e.message); }",
+        )
+        .current_dir(out_dir)
+        .assert()
+        .success()
+        .stdout(
+            "12\n6\n0\n{\"kind\":\"Rect\",\"width\":4,\"tall\":4,\"label\":\"square\"}\n\
+             invalid enum value passed\n",
+        );
+}
+
+#[test]
+fn streams_are_async_iterators() {
+    let out_dir = Project::new("streams_are_async_iterators")
+        .dep(
+            "wasm-bindgen-futures = { path = '{root}/crates/futures', \
+             features = ['futures-core-03-stream'] }",
+        )
+        .dep("futures-util = { version = '0.3', default-features = false }")
+        .file(
+            "src/lib.rs",
+            r#"
+                use futures_util::stream::{self, Stream};
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn count(n: u32) -> impl Stream<Item = u32> {
+                    stream::iter(0..n)
+                }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs")
+        .unwrap();
+
+    let ts = fs::read_to_string(out_dir.join("streams_are_async_iterators.d.ts")).unwrap();
+    assert!(ts.contains("export function count(n: number): AsyncIterableIterator<any>;"));
+
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            "const { count } = require('./streams_are_async_iterators.js');
+            (async () => {
+                for await (const n of count(3)) console.log(n);
+                const iter = count(5);
+                console.log(JSON.stringify(await Promise.all([iter.next(), iter.next()])));
+                console.log(JSON.stringify(await iter.return()));
+                console.log(JSON.stringify(await iter.next()));
+            })();",
+        )
+        .current_dir(out_dir)
+        .assert()
+        .success()
+        .stdout(
+            "0\n1\n2\n\
+             [{\"value\":0,\"done\":false},{\"value\":1,\"done\":false}]\n\
+             {\"done\":true}\n\
+             {\"done\":true}\n",
+        );
+}
+
+#[test]
+fn traits_are_interfaces() {
+    let out_dir = Project::new("traits_are_interfaces")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub trait Shape {
+                    fn area(&self) -> f64;
+                    fn name(&self, prefix: &str) -> String;
+                    fn describe(&self) -> String {
+                        format!("{} of {}", self.name("a "), self.area())
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub struct Circle {
+                    radius: f64,
+                }
+
+                #[wasm_bindgen]
+                impl Circle {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new(radius: f64) -> Circle {
+                        Circle { radius }
+                    }
+                }
+
+                #[wasm_bindgen]
+                impl Shape for Circle {
+                    fn area(&self) -> f64 {
+                        3.0 * self.radius * self.radius
+                    }
+                    fn name(&self, prefix: &str) -> String {
+                        format!("{prefix}circle")
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub fn describe(shape: &JsShape) -> String {
+                    shape.describe()
+                }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs")
+        .unwrap();
+
+    let ts = fs::read_to_string(out_dir.join("traits_are_interfaces.d.ts")).unwrap();
+    assert!(ts.contains("export interface Shape {"));
+    assert!(ts.contains("  area(): number;"));
+    assert!(ts.contains("  name(prefix: string): string;"));
+    assert!(ts.contains("  describe?(): string;"));
+    assert!(ts.contains("class Circle implements Shape {"));
+    assert!(ts.contains("export function describe(shape: Shape): string;"));
+
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            "const m = require('./traits_are_interfaces.js');
+            const circle = new m.Circle(1);
+            console.log(circle.area(), circle.name('my '));
+            console.log(m.describe({ area: () => 4, name: p => p + 'square' }));",
+        )
+        .current_dir(out_dir)
+        .assert()
+        .success()
+        .stdout("3 my circle\na square of 4\n");
+}
+
+#[test]
+fn sri_hashes_emitted_files() {
+    use base64::{prelude::BASE64_STANDARD, Engine as _};
+    use sha2::{Digest, Sha384};
+
+    let out_dir = Project::new("sri_hashes_emitted_files")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(inline_js = "export function tick() {}")]
+                extern "C" {
+                    fn tick();
+                }
+
+                #[wasm_bindgen]
+                pub fn foo() {
+                    tick();
+                }
+            "#,
+        )
+        .wasm_bindgen("--target web --sri")
+        .unwrap();
+
+    let map = fs::read_to_string(out_dir.join("sri_hashes_emitted_files.importmap.json")).unwrap();
+    let map: serde_json::Value = serde_json::from_str(&map).unwrap();
+    let integrity = map["integrity"].as_object().unwrap();
+    for (name, hash) in integrity {
+        let contents = fs::read(out_dir.join(name)).unwrap();
+        let expected = BASE64_STANDARD.encode(Sha384::digest(&contents));
+        assert_eq!(hash.as_str().unwrap(), format!("sha384-{expected}"));
+    }
+    assert!(integrity.contains_key("./sri_hashes_emitted_files.js"));
+    assert!(integrity.contains_key("./sri_hashes_emitted_files_bg.wasm"));
+    assert!(integrity
+        .keys()
+        .any(|name| name.starts_with("./snippets/") && name.ends_with("/inline0.js")));
+}
+
+#[test]
+fn source_map_maps_glue_to_rust() {
+    let out_dir = Project::new("source_map_maps_glue_to_rust")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn add(a: u32, b: u32) -> u32 {
+                    a + b
+                }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs --keep-debug --source-map")
+        .unwrap();
+
+    let contents = fs::read_to_string(out_dir.join("source_map_maps_glue_to_rust.js")).unwrap();
+    assert!(contents.ends_with("//# sourceMappingURL=source_map_maps_glue_to_rust.js.map\n"));
+    let map = fs::read_to_string(out_dir.join("source_map_maps_glue_to_rust.js.map")).unwrap();
+    let map: serde_json::Value = serde_json::from_str(&map).unwrap();
+    let sources = map["sources"].as_array().unwrap();
+    let lib = sources
+        .iter()
+        .position(|source| source.as_str().unwrap().ends_with("/src/lib.rs"))
+        .unwrap_or_else(|| panic!("Expected the glue to map to `src/lib.rs`.\n{map:#}"));
+    assert!(map["sourcesContent"][lib]
+        .as_str()
+        .unwrap()
+        .contains("pub fn add"));
+}
+
 #[test]
 fn omit_default_module_path_target_web() {
     let out_dir = Project::new("omit_default_module_path_target_web")
@@ -1,16 +1,23 @@
-//! Converting JavaScript `AsyncIterator`s to Rust `Stream`s.
+//! Converting between JavaScript `AsyncIterator`s and Rust `Stream`s.
 //!
 //! Analogous to the promise to future conversion, this module allows
 //! turning objects implementing the async iterator protocol into `Stream`s
-//! that produce values that can be awaited from.
+//! that produce values that can be awaited from, and the other way around
+//! with [`stream_to_async_iterator`].
 //!
 
-use crate::JsFuture;
-use core::future::Future;
+use crate::{future_to_promise, JsFuture};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::future::{poll_fn, Future};
+use core::panic::AssertUnwindSafe;
 use core::pin::Pin;
 use core::task::{Context, Poll};
 use futures_core::stream::Stream;
-use js_sys::{AsyncIterator, IteratorNext};
+use js_sys::{AsyncIterator, IteratorNext, Object, Promise, Reflect, Symbol};
+use wasm_bindgen::__rt::IntoJsResult;
+use wasm_bindgen::closure::Closure;
 use wasm_bindgen::prelude::*;
 
 /// A `Stream` that yields values from an underlying `AsyncIterator`.
@@ -79,3 +86,106 @@ impl Stream for JsStream {
         }
     }
 }
+
+/// Converts a Rust `Stream` into a JavaScript async iterator.
+///
+/// The returned object implements `Symbol.asyncIterator`, so it can be
+/// consumed with `for await`. Each call to `next()` polls the stream for one
+/// more item, so the stream only makes progress as fast as JS consumes it.
+/// Calls made before the previous one settled are queued behind it.
+///
+/// `Ok` items are yielded, while an `Err` item rejects the promise returned by
+/// `next()` and ends the iteration. Calling `return()`, as a `for await` loop
+/// does when exited early, drops the stream.
+pub fn stream_to_async_iterator<S>(stream: S) -> AsyncIterator
+where
+    S: Stream<Item = Result<JsValue, JsValue>> + 'static,
+{
+    into_async_iterator(stream, |item| item)
+}
+
+/// The conversion of the streams returned by exported functions, whose items
+/// are converted like the output of `async` functions.
+#[doc(hidden)]
+pub fn __into_async_iterator<S>(stream: S) -> AsyncIterator
+where
+    S: Stream + 'static,
+    S::Item: IntoJsResult,
+{
+    into_async_iterator(stream, IntoJsResult::into_js_result)
+}
+
+struct IteratorState<S> {
+    /// The stream, until it ends or `return()` is called.
+    stream: RefCell<Option<Pin<Box<S>>>>,
+    /// The promise of the last call to `next()`, which the next call waits on.
+    last: RefCell<Option<Promise>>,
+}
+
+fn into_async_iterator<S>(
+    stream: S,
+    convert: fn(S::Item) -> Result<JsValue, JsValue>,
+) -> AsyncIterator
+where
+    S: Stream + 'static,
+{
+    let state = Rc::new(IteratorState {
+        stream: RefCell::new(Some(Box::pin(stream))),
+        last: RefCell::new(None),
+    });
+
+    let next = {
+        let state = AssertUnwindSafe(Rc::clone(&state));
+        Closure::<dyn FnMut() -> Promise>::new(move || {
+            let previous = state.last.borrow_mut().take();
+            let pending = AssertUnwindSafe(Rc::clone(&state));
+            let promise = future_to_promise(AssertUnwindSafe(async move {
+                if let Some(previous) = previous {
+                    // Its result already went to its own caller.
+                    let _ = JsFuture::from(previous).await;
+                }
+                let item = poll_fn(|cx| match pending.stream.borrow_mut().as_mut() {
+                    Some(stream) => stream.as_mut().poll_next(cx),
+                    None => Poll::Ready(None),
+                })
+                .await;
+                match item.map(convert) {
+                    Some(Ok(value)) => Ok(iterator_result(&value, false)),
+                    Some(Err(e)) => {
+                        pending.stream.borrow_mut().take();
+                        Err(e)
+                    }
+                    None => {
+                        pending.stream.borrow_mut().take();
+                        Ok(iterator_result(&JsValue::undefined(), true))
+                    }
+                }
+            }));
+            *state.last.borrow_mut() = Some(promise.clone());
+            promise
+        })
+    };
+    let return_ = {
+        let state = AssertUnwindSafe(state);
+        Closure::<dyn FnMut(JsValue) -> Promise>::new(move |value: JsValue| {
+            state.stream.borrow_mut().take();
+            Promise::resolve(&iterator_result(&value, true))
+        })
+    };
+
+    let iter = Object::new();
+    Reflect::set(&iter, &"next".into(), &next.into_js_value()).unwrap_throw();
+    Reflect::set(&iter, &"return".into(), &return_.into_js_value()).unwrap_throw();
+    // `Object.prototype.valueOf` returns `this`, as `[Symbol.asyncIterator]()`
+    // of an iterator should.
+    let this = Reflect::get(&iter, &"valueOf".into()).unwrap_throw();
+    Reflect::set(&iter, &Symbol::async_iterator(), &this).unwrap_throw();
+    iter.unchecked_into()
+}
+
+fn iterator_result(value: &JsValue, done: bool) -> JsValue {
+    let result = Object::new();
+    Reflect::set(&result, &"value".into(), value).unwrap_throw();
+    Reflect::set(&result, &"done".into(), &done.into()).unwrap_throw();
+    result.into()
+}
@@ -224,6 +224,52 @@ async fn can_use_an_async_iterable_as_stream() {
     assert_eq!(stream.next().await, None);
 }
 
+#[cfg(feature = "futures-core-03-stream")]
+#[wasm_bindgen_test]
+async fn can_use_a_stream_as_an_async_iterable() {
+    use futures_lite::stream;
+    use wasm_bindgen_futures::stream::stream_to_async_iterator;
+
+    let async_iter = stream_to_async_iterator(stream::iter([
+        Ok(JsValue::from(1)),
+        Ok(JsValue::from(2)),
+        Ok(JsValue::from(3)),
+    ]));
+
+    let sum = js_sys::Function::new_with_args(
+        "iter",
+        "return (async () => {
+            let sum = 0;
+            for await (const x of iter) sum += x;
+            return sum;
+        })()",
+    )
+    .call1(&JsValue::undefined(), &async_iter)
+    .unwrap();
+    assert_eq!(
+        JsFuture::from(Promise::from(sum)).await,
+        Ok(JsValue::from(6))
+    );
+}
+
+#[cfg(feature = "futures-core-03-stream")]
+#[wasm_bindgen_test]
+async fn async_iterator_from_stream_rejects_errors() {
+    use futures_lite::stream::{self, StreamExt};
+    use wasm_bindgen_futures::stream::{stream_to_async_iterator, JsStream};
+
+    let async_iter = stream_to_async_iterator(stream::iter([
+        Ok(JsValue::from(42)),
+        Err(JsValue::from("error")),
+        Ok(JsValue::from(24)),
+    ]));
+
+    let mut stream = JsStream::from(async_iter);
+    assert_eq!(stream.next().await, Some(Ok(JsValue::from(42))));
+    assert_eq!(stream.next().await, Some(Err(JsValue::from("error"))));
+    assert_eq!(stream.next().await, None);
+}
+
 #[wasm_bindgen_test]
 #[should_panic]
 async fn should_panic() {
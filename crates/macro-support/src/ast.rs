@@ -21,6 +21,14 @@ pub struct Program {
     pub linked_modules: Vec<ImportModule>,
     /// rust enums
     pub enums: Vec<Enum>,
+    /// rust enums with data
+    pub data_enums: Vec<DataEnum>,
+    /// rust traits exported as interfaces
+    pub traits: Vec<Trait>,
+    /// impls of exported traits for rust structs
+    pub trait_impls: Vec<TraitImpl>,
+    /// methods exported by impl blocks, checked for colliding names
+    pub impl_methods: Vec<ImplMethods>,
     /// rust structs
     pub structs: Vec<Struct>,
     /// custom typescript sections to be included in the definition file
@@ -42,6 +50,10 @@ impl Default for Program {
             imports: Default::default(),
             linked_modules: Default::default(),
             enums: Default::default(),
+            data_enums: Default::default(),
+            traits: Default::default(),
+            trait_impls: Default::default(),
+            impl_methods: Default::default(),
             structs: Default::default(),
             typescript_custom_sections: Default::default(),
             inline_js: Default::default(),
@@ -92,6 +104,8 @@ pub struct Export {
     pub rust_class: Option<Ident>,
     /// The name of the rust function/method on the rust side.
     pub rust_name: Ident,
+    /// The trait of the impl the method is from, if any
+    pub rust_trait: Option<syn::Path>,
     /// Whether or not this function should be flagged as the Wasm start
     /// function.
     pub start: bool,
@@ -402,6 +416,22 @@ pub struct FunctionReturnData {
     pub desc: Option<String>,
 }
 
+/// Whether `ty` is an `impl Stream`, which exported functions return to JS as
+/// an async iterator.
+pub(crate) fn is_impl_stream(ty: &syn::Type) -> bool {
+    let syn::Type::ImplTrait(ty) = ty else {
+        return false;
+    };
+    ty.bounds.iter().any(|bound| match bound {
+        syn::TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Stream"),
+        _ => false,
+    })
+}
+
 /// Information about a function's argument
 #[cfg_attr(feature = "extra-traits", derive(Debug))]
 #[derive(Clone)]
@@ -513,6 +543,127 @@ pub struct Variant {
     pub comments: Vec<String>,
 }
 
+/// A Rust enum whose variants carry data, passed to JS as objects tagged with
+/// the name of their variant in a `kind` field
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct DataEnum {
+    /// The name of this enum in Rust code
+    pub rust_name: Ident,
+    /// The export name of this enum in JS code
+    pub js_name: String,
+    /// The variants provided by this enum
+    pub variants: Vec<DataVariant>,
+    /// The doc comments on this enum, if any
+    pub comments: Vec<String>,
+    /// Whether to generate a typescript definition for this enum
+    pub generate_typescript: bool,
+    /// Path to wasm_bindgen
+    pub wasm_bindgen: Path,
+}
+
+/// The variant of an enum with data
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct DataVariant {
+    /// The name of this variant
+    pub name: Ident,
+    /// The fields of this variant, which are empty for a unit variant
+    pub fields: Vec<DataVariantField>,
+    /// The doc comments on this variant, if any
+    pub comments: Vec<String>,
+}
+
+/// The field of a variant of an enum with data
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct DataVariantField {
+    /// The name of the field in Rust code
+    pub rust_name: Ident,
+    /// The name of the field in JS code
+    pub js_name: String,
+    /// The type of the field
+    pub ty: syn::Type,
+    /// The name of the export describing the type of the field
+    pub describe: Ident,
+}
+
+/// A Rust trait exported to JS as an interface
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct Trait {
+    /// The name of this trait in Rust code
+    pub rust_name: Ident,
+    /// The export name of this trait in JS code
+    pub js_name: String,
+    /// The name of the imported type implementing this trait for JS objects
+    pub js_type: Ident,
+    /// The visibility of this trait
+    pub vis: syn::Visibility,
+    /// The methods of this trait which take `self`
+    pub methods: Vec<TraitMethod>,
+    /// The doc comments on this trait, if any
+    pub comments: Vec<String>,
+    /// Whether to generate a typescript definition for this trait
+    pub generate_typescript: bool,
+    /// Path to wasm_bindgen
+    pub wasm_bindgen: Path,
+}
+
+/// The method of an exported trait
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct TraitMethod {
+    /// The signature of this method in Rust code
+    pub sig: syn::Signature,
+    /// The names of the arguments, excluding `self`
+    pub arg_names: Vec<String>,
+    /// The types of the arguments, excluding `self`
+    pub arg_tys: Vec<syn::Type>,
+    /// The return type, if any
+    pub ret: Option<syn::Type>,
+    /// Whether the method has a default body, making it optional in JS
+    pub optional: bool,
+    /// The doc comments on this method, if any
+    pub comments: Vec<String>,
+    /// The name of the export describing the signature of the method
+    pub describe: Ident,
+}
+
+/// An impl of an exported trait for an exported struct
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct TraitImpl {
+    /// The JS name of the class implementing the trait
+    pub class: String,
+    /// The JS name of the implemented interface
+    pub interface: String,
+    /// The struct implementing the trait
+    pub rust_class: syn::Path,
+    /// The path of the implemented trait
+    pub trait_: syn::Path,
+}
+
+/// The methods an impl block exports on the class of a struct
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct ImplMethods {
+    /// The struct the methods are exported on
+    pub rust_class: syn::Path,
+    /// The exported methods
+    pub methods: Vec<ImplMethod>,
+}
+
+/// A method exported by an impl block
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
+#[derive(Clone)]
+pub struct ImplMethod {
+    /// The name of the method in Rust
+    pub name: Ident,
+    /// The `#[cfg]` attributes of the method
+    pub cfgs: Vec<syn::Attribute>,
+}
+
 /// An enum representing either a literal value (`Lit`) or an expression (`syn::Expr`).
 #[cfg_attr(feature = "extra-traits", derive(Debug))]
 #[derive(Clone)]
@@ -8,6 +8,7 @@ use quote::quote_spanned;
 use quote::{quote, ToTokens};
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use syn::ext::IdentExt;
 use syn::parse_quote;
 use syn::spanned::Spanned;
 use wasm_bindgen_shared as shared;
@@ -81,6 +82,18 @@ impl TryToTokens for ast::Program {
         for e in self.enums.iter() {
             e.to_tokens(tokens);
         }
+        for e in self.data_enums.iter() {
+            e.to_tokens(tokens);
+        }
+        for t in self.traits.iter() {
+            t.to_tokens(tokens);
+        }
+        for i in self.trait_impls.iter() {
+            i.to_tokens(tokens);
+        }
+        for m in self.impl_methods.iter() {
+            m.to_tokens(tokens);
+        }
 
         Diagnostic::from_vec(errors)?;
 
@@ -575,6 +588,16 @@ impl TryToTokens for ast::Export {
         let name = &self.rust_name;
         let wasm_bindgen = &self.wasm_bindgen;
         let wasm_bindgen_futures = &self.wasm_bindgen_futures;
+        // Methods of trait impls are called through their trait, since other
+        // traits in scope may have methods of the same name.
+        let method =
+            |class: &Ident, converted_arguments: &mut Vec<TokenStream>| match &self.rust_trait {
+                Some(trait_) => {
+                    converted_arguments.push(quote! { me });
+                    quote! { <#class as #trait_>::#name }
+                }
+                None => quote! { me.#name },
+            };
         let receiver = match self.method_self {
             Some(ast::MethodSelf::ByValue) => {
                 let class = self.rust_class.as_ref().unwrap();
@@ -583,7 +606,7 @@ impl TryToTokens for ast::Export {
                         <#class as #wasm_bindgen::convert::FromWasmAbi>::from_abi(me)
                     };
                 });
-                quote! { me.#name }
+                method(class, &mut converted_arguments)
             }
             Some(ast::MethodSelf::RefMutable) => {
                 let class = self.rust_class.as_ref().unwrap();
@@ -594,7 +617,7 @@ impl TryToTokens for ast::Export {
                     };
                     let me = &mut *me;
                 });
-                quote! { me.#name }
+                method(class, &mut converted_arguments)
             }
             Some(ast::MethodSelf::RefShared) => {
                 let class = self.rust_class.as_ref().unwrap();
@@ -617,11 +640,12 @@ impl TryToTokens for ast::Export {
                     };
                     let me = #borrow;
                 });
-                quote! { me.#name }
+                method(class, &mut converted_arguments)
             }
-            None => match &self.rust_class {
-                Some(class) => quote! { #class::#name },
-                None => quote! { #name },
+            None => match (&self.rust_class, &self.rust_trait) {
+                (Some(class), Some(trait_)) => quote! { <#class as #trait_>::#name },
+                (Some(class), None) => quote! { #class::#name },
+                (None, _) => quote! { #name },
             },
         };
 
@@ -747,6 +771,14 @@ impl TryToTokens for ast::Export {
                 quote! { () },
                 quote! { <#syn_ret as #wasm_bindgen::__rt::Start>::start(#ret) },
             )
+        } else if ast::is_impl_stream(syn_ret) {
+            // A stream is returned as an async iterator polling it on demand.
+            let async_iterator = quote! { #wasm_bindgen_futures::js_sys::AsyncIterator };
+            (
+                async_iterator.clone(),
+                async_iterator,
+                quote! { #wasm_bindgen_futures::stream::__into_async_iterator(#ret) },
+            )
         } else {
             (quote! { #syn_ret }, quote! { #syn_ret }, quote! { #ret })
         };
@@ -1710,6 +1742,182 @@ impl ToTokens for ast::Enum {
     }
 }
 
+impl ToTokens for ast::DataEnum {
+    fn to_tokens(&self, into: &mut TokenStream) {
+        let enum_name = &self.rust_name;
+        let name_str = self.js_name.to_string();
+        let name_len = name_str.len() as u32;
+        let name_chars = name_str.chars().map(|c| c as u32);
+        let wasm_bindgen = &self.wasm_bindgen;
+
+        let into_arms = self.variants.iter().map(|variant| {
+            let variant_name = &variant.name;
+            let kind = variant.name.unraw().to_string();
+            let fields = variant.fields.iter().map(|f| &f.rust_name);
+            let sets = variant.fields.iter().map(|f| {
+                let rust_name = &f.rust_name;
+                let js_name = &f.js_name;
+                quote! {
+                    #wasm_bindgen::__rt::data_enum_set(
+                        &obj,
+                        #js_name,
+                        #wasm_bindgen::__rt::wbg_cast(#rust_name),
+                    );
+                }
+            });
+            quote! {
+                #enum_name::#variant_name { #(#fields,)* } => {
+                    let obj = #wasm_bindgen::__rt::data_enum_new(#kind);
+                    #(#sets)*
+                    obj
+                }
+            }
+        });
+        let try_from_arms = self.variants.iter().map(|variant| {
+            let variant_name = &variant.name;
+            let kind = variant.name.unraw().to_string();
+            let fields = variant.fields.iter().map(|f| {
+                let rust_name = &f.rust_name;
+                let js_name = &f.js_name;
+                let ty = &f.ty;
+                quote! {
+                    #rust_name: #wasm_bindgen::__rt::wbg_cast::<_, #ty>(
+                        #wasm_bindgen::__rt::data_enum_get(value, #js_name),
+                    )
+                }
+            });
+            quote! {
+                #kind => #enum_name::#variant_name { #(#fields,)* },
+            }
+        });
+
+        (quote! {
+            #[automatically_derived]
+            impl #wasm_bindgen::__rt::core::convert::From<#enum_name> for
+                #wasm_bindgen::JsValue
+            {
+                fn from(value: #enum_name) -> Self {
+                    match value {
+                        #(#into_arms)*
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::TryFromJsValue for #enum_name {
+                fn try_from_js_value_ref(value: &#wasm_bindgen::JsValue) -> #wasm_bindgen::__rt::core::option::Option<Self> {
+                    let kind = #wasm_bindgen::__rt::data_enum_kind(value)?;
+                    #wasm_bindgen::__rt::core::option::Option::Some(match kind.as_str() {
+                        #(#try_from_arms)*
+                        _ => return #wasm_bindgen::__rt::core::option::Option::None,
+                    })
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::IntoWasmAbi for #enum_name {
+                type Abi = <#wasm_bindgen::JsValue as #wasm_bindgen::convert::IntoWasmAbi>::Abi;
+
+                #[inline]
+                fn into_abi(self) -> Self::Abi {
+                    #wasm_bindgen::JsValue::from(self).into_abi()
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::FromWasmAbi for #enum_name {
+                type Abi = <#wasm_bindgen::JsValue as #wasm_bindgen::convert::FromWasmAbi>::Abi;
+
+                #[inline]
+                unsafe fn from_abi(js: Self::Abi) -> Self {
+                    let js = <#wasm_bindgen::JsValue as #wasm_bindgen::convert::FromWasmAbi>::from_abi(js);
+                    match <#enum_name as #wasm_bindgen::convert::TryFromJsValue>::try_from_js_value_ref(&js) {
+                        #wasm_bindgen::__rt::core::option::Option::Some(value) => value,
+                        #wasm_bindgen::__rt::core::option::Option::None => {
+                            #wasm_bindgen::throw_str("invalid enum value passed")
+                        }
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::OptionIntoWasmAbi for #enum_name {
+                #[inline]
+                fn none() -> Self::Abi { 0 }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::OptionFromWasmAbi for #enum_name {
+                #[inline]
+                fn is_none(abi: &Self::Abi) -> bool { *abi == 0 }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::describe::WasmDescribe for #enum_name {
+                fn describe() {
+                    use #wasm_bindgen::describe::*;
+                    inform(NAMED_EXTERNREF);
+                    inform(#name_len);
+                    #(inform(#name_chars);)*
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::describe::WasmDescribeVector for #enum_name {
+                fn describe_vector() {
+                    use #wasm_bindgen::describe::*;
+                    inform(VECTOR);
+                    <#enum_name as #wasm_bindgen::describe::WasmDescribe>::describe();
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::VectorIntoWasmAbi for #enum_name {
+                type Abi = <
+                    #wasm_bindgen::__rt::alloc::boxed::Box<[#wasm_bindgen::JsValue]>
+                    as #wasm_bindgen::convert::IntoWasmAbi
+                >::Abi;
+
+                fn vector_into_abi(
+                    vector: #wasm_bindgen::__rt::alloc::boxed::Box<[#enum_name]>
+                ) -> Self::Abi {
+                    #wasm_bindgen::convert::js_value_vector_into_abi(vector)
+                }
+            }
+
+            #[automatically_derived]
+            impl #wasm_bindgen::convert::VectorFromWasmAbi for #enum_name {
+                type Abi = <
+                    #wasm_bindgen::__rt::alloc::boxed::Box<[#wasm_bindgen::JsValue]>
+                    as #wasm_bindgen::convert::FromWasmAbi
+                >::Abi;
+
+                unsafe fn vector_from_abi(
+                    js: Self::Abi
+                ) -> #wasm_bindgen::__rt::alloc::boxed::Box<[#enum_name]> {
+                    #wasm_bindgen::convert::js_value_vector_from_abi(js)
+                }
+            }
+        })
+        .to_tokens(into);
+
+        // The types of the fields are only known to the compiler, so describe
+        // each of them for the CLI to generate the TypeScript union.
+        for field in self.variants.iter().flat_map(|v| &v.fields) {
+            let ty = &field.ty;
+            Descriptor {
+                ident: &field.describe,
+                inner: quote! {
+                    <#ty as WasmDescribe>::describe();
+                },
+                attrs: vec![],
+                wasm_bindgen: &self.wasm_bindgen,
+            }
+            .to_tokens(into);
+        }
+    }
+}
+
 impl ToTokens for ast::ImportStatic {
     fn to_tokens(&self, into: &mut TokenStream) {
         let ty = &self.ty;
@@ -1839,6 +2047,146 @@ fn static_init(wasm_bindgen: &syn::Path, ty: &syn::Type, shim_name: &Ident) -> T
 
 /// Emits the necessary glue tokens for "descriptor", generating an appropriate
 /// symbol name as well as attributes around the descriptor function itself.
+impl ToTokens for ast::Trait {
+    fn to_tokens(&self, into: &mut TokenStream) {
+        let wasm_bindgen = &self.wasm_bindgen;
+        let trait_name = &self.rust_name;
+        let js_type = &self.js_type;
+        let vis = &self.vis;
+        let js_name = &self.js_name;
+        let doc = format!(" A JS object implementing the [`{trait_name}`] interface.");
+
+        let mut shims = Vec::new();
+        let mut methods = Vec::new();
+        for method in self.methods.iter() {
+            let js_method = method.sig.ident.unraw().to_string();
+            let shim = format_ident!("__wbg_{}", js_method);
+            let args = (0..method.arg_tys.len())
+                .map(|i| format_ident!("arg{}", i))
+                .collect::<Vec<_>>();
+            let arg_tys = &method.arg_tys;
+            let inform_ret = match &method.ret {
+                Some(ty) => quote! { <#ty as WasmDescribe>::describe(); },
+                None => quote! { <() as WasmDescribe>::describe(); },
+            };
+            let nargs = method.arg_tys.len() as u32;
+            Descriptor {
+                ident: &method.describe,
+                inner: quote! {
+                    inform(FUNCTION);
+                    inform(0);
+                    inform(#nargs);
+                    #(<#arg_tys as WasmDescribe>::describe();)*
+                    #inform_ret
+                    #inform_ret
+                },
+                attrs: vec![],
+                wasm_bindgen,
+            }
+            .to_tokens(into);
+
+            // Methods with a default body are optional in the interface, so
+            // the JS object falls back to the Rust implementation.
+            if method.optional {
+                continue;
+            }
+            let ret = method.ret.as_ref().map(|ty| quote! { -> #ty });
+            shims.push(quote! {
+                #[wasm_bindgen(method, structural, js_name = #js_method)]
+                fn #shim(this: &#js_type, #(#args: #arg_tys),*) #ret;
+            });
+            let mut sig = method.sig.clone();
+            let mut this = quote! { self };
+            for (i, input) in sig.inputs.iter_mut().enumerate() {
+                match input {
+                    syn::FnArg::Receiver(receiver) => {
+                        receiver.attrs.clear();
+                        if receiver.reference.is_none() {
+                            receiver.mutability = None;
+                            this = quote! { &self };
+                        }
+                    }
+                    syn::FnArg::Typed(arg) => {
+                        arg.attrs.clear();
+                        let ident = &args[i - 1];
+                        *arg.pat = parse_quote! { #ident };
+                    }
+                }
+            }
+            methods.push(quote! {
+                #sig {
+                    #js_type::#shim(#this, #(#args),*)
+                }
+            });
+        }
+
+        (quote! {
+            #[#wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = #wasm_bindgen)]
+            extern "C" {
+                #[doc = #doc]
+                #[wasm_bindgen(typescript_type = #js_name)]
+                #vis type #js_type;
+
+                #(#shims)*
+            }
+
+            #[automatically_derived]
+            impl #trait_name for #js_type {
+                #(#methods)*
+            }
+        })
+        .to_tokens(into);
+    }
+}
+
+/// Checks that the trait of an impl is a `#[wasm_bindgen]` trait, whose
+/// methods are all part of its interface and so may all be exported.
+impl ToTokens for ast::TraitImpl {
+    fn to_tokens(&self, into: &mut TokenStream) {
+        let class = &self.rust_class;
+        let trait_ = &self.trait_;
+        let marker = Ident::new(TRAIT_MARKER, trait_.span());
+        (quote! {
+            const _: fn(&#class) = <#class as #trait_>::#marker;
+        })
+        .to_tokens(into);
+    }
+}
+
+/// Declares a hidden associated const on the struct for every method exported
+/// on its class, so two impls exporting methods of the same name, like the
+/// `fmt` of two traits, fail to compile with both methods pointed out instead
+/// of exporting colliding symbols.
+impl ToTokens for ast::ImplMethods {
+    fn to_tokens(&self, into: &mut TokenStream) {
+        let class = &self.rust_class;
+        let consts = self.methods.iter().map(|method| {
+            let name = Ident::new(
+                &format!("__wasm_bindgen_method_{}", method.name.unraw()),
+                method.name.span(),
+            );
+            let cfgs = &method.cfgs;
+            quote_spanned! {method.name.span()=>
+                #(#cfgs)*
+                #[doc(hidden)]
+                #[allow(dead_code, non_upper_case_globals)]
+                const #name: () = ();
+            }
+        });
+        (quote! {
+            #[automatically_derived]
+            impl #class {
+                #(#consts)*
+            }
+        })
+        .to_tokens(into);
+    }
+}
+
+/// The hidden method the macro adds to `#[wasm_bindgen]` traits, by which
+/// their impls tell them apart from other traits.
+pub(crate) const TRAIT_MARKER: &str = "__wasm_bindgen_trait_marker";
+
 struct Descriptor<'a, T> {
     ident: &'a Ident,
     inner: T,
@@ -151,6 +151,17 @@ fn shared_program<'a>(
             .map(|a| shared_struct(a, intern))
             .collect(),
         enums: prog.enums.iter().map(|a| shared_enum(a, intern)).collect(),
+        data_enums: prog
+            .data_enums
+            .iter()
+            .map(|a| shared_data_enum(a, intern))
+            .collect(),
+        traits: prog
+            .traits
+            .iter()
+            .map(|a| shared_trait(a, intern))
+            .collect(),
+        trait_impls: prog.trait_impls.iter().map(shared_trait_impl).collect(),
         imports: prog
             .imports
             .iter()
@@ -277,6 +288,56 @@ fn shared_variant<'a>(v: &'a ast::Variant, intern: &'a Interner) -> EnumVariant<
     }
 }
 
+fn shared_data_enum<'a>(e: &'a ast::DataEnum, intern: &'a Interner) -> DataEnum<'a> {
+    DataEnum {
+        name: &e.js_name,
+        variants: e
+            .variants
+            .iter()
+            .map(|v| shared_data_variant(v, intern))
+            .collect(),
+        comments: e.comments.iter().map(|s| &**s).collect(),
+        generate_typescript: e.generate_typescript,
+    }
+}
+
+fn shared_data_variant<'a>(v: &'a ast::DataVariant, intern: &'a Interner) -> DataEnumVariant<'a> {
+    DataEnumVariant {
+        name: intern.intern_str(&v.name.unraw().to_string()),
+        fields: v.fields.iter().map(|f| &*f.js_name).collect(),
+        comments: v.comments.iter().map(|s| &**s).collect(),
+    }
+}
+
+fn shared_trait<'a>(t: &'a ast::Trait, intern: &'a Interner) -> Trait<'a> {
+    Trait {
+        name: &t.js_name,
+        methods: t
+            .methods
+            .iter()
+            .map(|m| shared_trait_method(m, intern))
+            .collect(),
+        comments: t.comments.iter().map(|s| &**s).collect(),
+        generate_typescript: t.generate_typescript,
+    }
+}
+
+fn shared_trait_method<'a>(m: &'a ast::TraitMethod, intern: &'a Interner) -> TraitMethod<'a> {
+    TraitMethod {
+        name: intern.intern_str(&m.sig.ident.unraw().to_string()),
+        args: m.arg_names.iter().map(|s| &**s).collect(),
+        optional: m.optional,
+        comments: m.comments.iter().map(|s| &**s).collect(),
+    }
+}
+
+fn shared_trait_impl(i: &ast::TraitImpl) -> TraitImpl<'_> {
+    TraitImpl {
+        class: &i.class,
+        interface: &i.interface,
+    }
+}
+
 fn shared_import<'a>(i: &'a ast::Import, intern: &'a Interner) -> Result<Import<'a>, Diagnostic> {
     // Resolve reexport name: use explicit rename if provided, otherwise use the import's name
     let reexport = i.reexport.as_ref().map(|rename_opt| {
@@ -126,6 +126,7 @@ struct ClassMarker {
     js_class: String,
     wasm_bindgen: syn::Path,
     wasm_bindgen_futures: syn::Path,
+    trait_: Option<syn::Path>,
 }
 
 impl Parse for ClassMarker {
@@ -140,6 +141,7 @@ impl Parse for ClassMarker {
 
         let mut wasm_bindgen = None;
         let mut wasm_bindgen_futures = None;
+        let mut trait_ = None;
 
         loop {
             if input.parse::<Option<Token![,]>>()?.is_some() {
@@ -165,10 +167,17 @@ impl Parse for ClassMarker {
 
                     input.parse::<Token![=]>()?;
                     wasm_bindgen_futures = Some(input.parse::<syn::Path>()?);
+                } else if ident == "trait_" {
+                    if trait_.is_some() {
+                        return Err(syn::Error::new(ident.span(), "found duplicate `trait_`"));
+                    }
+
+                    input.parse::<Token![=]>()?;
+                    trait_ = Some(input.parse::<syn::Path>()?);
                 } else {
                     return Err(syn::Error::new(
                         ident.span(),
-                        "expected `wasm_bindgen` or `wasm_bindgen_futures`",
+                        "expected `wasm_bindgen`, `wasm_bindgen_futures` or `trait_`",
                     ));
                 }
             } else {
@@ -182,6 +191,7 @@ impl Parse for ClassMarker {
             wasm_bindgen: wasm_bindgen.unwrap_or_else(|| syn::parse_quote! { wasm_bindgen }),
             wasm_bindgen_futures: wasm_bindgen_futures
                 .unwrap_or_else(|| syn::parse_quote! { wasm_bindgen_futures }),
+            trait_,
         })
     }
 }
@@ -1120,13 +1120,18 @@ fn function_from_decl(
     let ret = match output {
         syn::ReturnType::Default => None,
         syn::ReturnType::Type(_, ty) => Some(ast::FunctionReturnData {
-            r#type: replace_self(*ty),
             js_type: ret_ty_override
                 .as_ref()
                 .map_or::<Result<_, Diagnostic>, _>(Ok(None), |(ty, span)| {
                     check_invalid_type(ty, *span)?;
                     Ok(Some(ty.to_string()))
-                })?,
+                })?
+                .or_else(|| {
+                    // `for await` needs `Symbol.asyncIterator`, which the
+                    // `AsyncIterator` the stream is returned as lacks in TS.
+                    ast::is_impl_stream(&ty).then(|| "AsyncIterableIterator<any>".to_string())
+                }),
+            r#type: replace_self(*ty),
             desc: ret_desc.as_ref().map_or::<Result<_, Diagnostic>, _>(
                 Ok(None),
                 |(desc, span)| {
@@ -1320,6 +1325,7 @@ impl<'a> MacroParse<(Option<BindgenAttrs>, &'a mut TokenStream)> for syn::Item {
                     method_self: None,
                     rust_class: None,
                     rust_name,
+                    rust_trait: None,
                     start,
                     wasm_bindgen: program.wasm_bindgen.clone(),
                     wasm_bindgen_futures: program.wasm_bindgen_futures.clone(),
@@ -1351,11 +1357,18 @@ impl<'a> MacroParse<(Option<BindgenAttrs>, &'a mut TokenStream)> for syn::Item {
                 };
                 c.macro_parse(program, opts)?;
             }
+            syn::Item::Trait(mut t) => {
+                let opts = match opts {
+                    Some(opts) => opts,
+                    None => BindgenAttrs::find(&mut t.attrs)?,
+                };
+                t.macro_parse(program, (tokens, opts))?;
+            }
             _ => {
                 bail_span!(
                     self,
                     "#[wasm_bindgen] can only be applied to a function, \
-                     struct, enum, impl, or extern block",
+                     struct, enum, trait, impl, or extern block",
                 );
             }
         }
@@ -1378,8 +1391,11 @@ impl MacroParse<BindgenAttrs> for &mut syn::ItemImpl {
                 "#[wasm_bindgen] unsafe impls are not supported"
             );
         }
-        if let Some((_, path, _)) = &self.trait_ {
-            bail_span!(path, "#[wasm_bindgen] trait impls are not supported");
+        if let Some((Some(bang), _, _)) = &self.trait_ {
+            bail_span!(
+                bang,
+                "#[wasm_bindgen] negative trait impls are not supported"
+            );
         }
         if !self.generics.params.is_empty() {
             bail_span!(
@@ -1397,13 +1413,51 @@ impl MacroParse<BindgenAttrs> for &mut syn::ItemImpl {
                 "unsupported self type in #[wasm_bindgen] impl"
             ),
         };
+        let trait_ = self.trait_.as_ref().map(|(_, path, _)| path);
+        // All the methods of trait impls are exported, so they may collide
+        // with those of other impls for the same struct.
+        let methods = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::ImplItem::Fn(f)
+                    if trait_.is_some() || matches!(f.vis, syn::Visibility::Public(_)) =>
+                {
+                    Some(ast::ImplMethod {
+                        name: f.sig.ident.clone(),
+                        cfgs: f
+                            .attrs
+                            .iter()
+                            .filter(|attr| attr.path().is_ident("cfg"))
+                            .cloned()
+                            .collect(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        program.impl_methods.push(ast::ImplMethods {
+            rust_class: name.clone(),
+            methods,
+        });
         let mut errors = Vec::new();
         for item in self.items.iter_mut() {
-            if let Err(e) = prepare_for_impl_recursion(item, name, program, &opts) {
+            if let Err(e) = prepare_for_impl_recursion(item, name, trait_, program, &opts) {
                 errors.push(e);
             }
         }
         Diagnostic::from_vec(errors)?;
+        if let Some(trait_) = trait_ {
+            program.trait_impls.push(ast::TraitImpl {
+                class: opts
+                    .js_class()
+                    .map(|s| s.0.to_string())
+                    .unwrap_or(extract_path_ident(name)?.unraw().to_string()),
+                interface: extract_path_ident(trait_)?.unraw().to_string(),
+                rust_class: name.clone(),
+                trait_: trait_.clone(),
+            });
+        }
         opts.check_used();
         Ok(())
     }
@@ -1420,6 +1474,7 @@ impl MacroParse<BindgenAttrs> for &mut syn::ItemImpl {
 fn prepare_for_impl_recursion(
     item: &mut syn::ImplItem,
     class: &syn::Path,
+    trait_: Option<&syn::Path>,
     program: &ast::Program,
     impl_opts: &BindgenAttrs,
 ) -> Result<(), Diagnostic> {
@@ -1455,13 +1510,14 @@ fn prepare_for_impl_recursion(
 
     let wasm_bindgen = &program.wasm_bindgen;
     let wasm_bindgen_futures = &program.wasm_bindgen_futures;
+    let trait_ = trait_.map(|path| quote::quote! { , trait_ = #path });
     method.attrs.insert(
         0,
         syn::Attribute {
             pound_token: Default::default(),
             style: syn::AttrStyle::Outer,
             bracket_token: Default::default(),
-            meta: syn::parse_quote! { #wasm_bindgen::prelude::__wasm_bindgen_class_marker(#class = #js_class, wasm_bindgen = #wasm_bindgen, wasm_bindgen_futures = #wasm_bindgen_futures) },
+            meta: syn::parse_quote! { #wasm_bindgen::prelude::__wasm_bindgen_class_marker(#class = #js_class, wasm_bindgen = #wasm_bindgen, wasm_bindgen_futures = #wasm_bindgen_futures #trait_) },
         },
     );
 
@@ -1477,13 +1533,16 @@ impl MacroParse<&ClassMarker> for &mut syn::ImplItemFn {
             js_class,
             wasm_bindgen,
             wasm_bindgen_futures,
+            trait_,
         }: &ClassMarker,
     ) -> Result<(), Diagnostic> {
         program.wasm_bindgen = wasm_bindgen.clone();
         program.wasm_bindgen_futures = wasm_bindgen_futures.clone();
 
+        // Methods of trait impls are as public as the trait itself.
         match self.vis {
             syn::Visibility::Public(_) => {}
+            _ if trait_.is_some() => {}
             _ => return Ok(()),
         }
         if self.defaultness.is_some() {
@@ -1543,6 +1602,7 @@ impl MacroParse<&ClassMarker> for &mut syn::ImplItemFn {
             method_self,
             rust_class: Some(class.clone()),
             rust_name: self.sig.ident.clone(),
+            rust_trait: trait_.clone(),
             start: false,
             wasm_bindgen: program.wasm_bindgen.clone(),
             wasm_bindgen_futures: program.wasm_bindgen_futures.clone(),
@@ -1606,6 +1666,253 @@ fn string_enum(
     Ok(())
 }
 
+/// Parses an enum whose variants carry data, which is passed to JS as an
+/// object with the name of its variant in `kind` and its fields alongside.
+fn data_enum(
+    mut enum_: syn::ItemEnum,
+    tokens: &mut TokenStream,
+    program: &mut ast::Program,
+    js_name: String,
+    generate_typescript: bool,
+    comments: Vec<String>,
+) -> Result<(), Diagnostic> {
+    match enum_.vis {
+        syn::Visibility::Public(_) => {}
+        _ => bail_span!(enum_, "only public enums are allowed with #[wasm_bindgen]"),
+    }
+    if enum_.generics.lt_token.is_some() {
+        bail_span!(
+            enum_.generics,
+            "enums with data and #[wasm_bindgen] cannot have generic parameters"
+        );
+    }
+
+    let mut variants = Vec::new();
+    for v in enum_.variants.iter_mut() {
+        if let Some((_, expr)) = &v.discriminant {
+            bail_span!(
+                expr,
+                "enums with data and #[wasm_bindgen] cannot have discriminants"
+            );
+        }
+        let mut fields = Vec::new();
+        match &mut v.fields {
+            syn::Fields::Unit => {}
+            syn::Fields::Named(named) => {
+                for field in named.named.iter_mut() {
+                    let ident = field.ident.clone().unwrap();
+                    let attrs = BindgenAttrs::find(&mut field.attrs)?;
+                    let field_js_name = match attrs.js_name() {
+                        Some((name, _)) => name.to_string(),
+                        None => ident.unraw().to_string(),
+                    };
+                    attrs.check_used();
+                    if field_js_name == "kind" {
+                        bail_span!(
+                            ident,
+                            "`kind` holds the variant of enums with data and cannot be used \
+                             as the name of a field"
+                        );
+                    }
+                    let describe = wasm_bindgen_shared::data_enum_field(
+                        &js_name,
+                        &v.ident.unraw().to_string(),
+                        &field_js_name,
+                    );
+                    fields.push(ast::DataVariantField {
+                        rust_name: ident,
+                        js_name: field_js_name,
+                        ty: field.ty.clone(),
+                        describe: Ident::new(&describe, Span::call_site()),
+                    });
+                }
+            }
+            syn::Fields::Unnamed(_) => bail_span!(
+                v.fields,
+                "enum variants with #[wasm_bindgen] can only have named fields"
+            ),
+        }
+        variants.push(ast::DataVariant {
+            name: v.ident.clone(),
+            fields,
+            comments: extract_doc_comments(&v.attrs),
+        });
+    }
+
+    enum_.to_tokens(tokens);
+
+    program.data_enums.push(ast::DataEnum {
+        rust_name: enum_.ident,
+        js_name,
+        variants,
+        comments,
+        generate_typescript,
+        wasm_bindgen: program.wasm_bindgen.clone(),
+    });
+    Ok(())
+}
+
+impl<'a> MacroParse<(&'a mut TokenStream, BindgenAttrs)> for syn::ItemTrait {
+    fn macro_parse(
+        mut self,
+        program: &mut ast::Program,
+        (tokens, opts): (&'a mut TokenStream, BindgenAttrs),
+    ) -> Result<(), Diagnostic> {
+        match self.vis {
+            syn::Visibility::Public(_) => {}
+            _ => bail_span!(self, "only public traits are allowed with #[wasm_bindgen]"),
+        }
+        if let Some(unsafety) = self.unsafety {
+            bail_span!(unsafety, "#[wasm_bindgen] unsafe traits are not supported");
+        }
+        if let Some(auto) = self.auto_token {
+            bail_span!(auto, "#[wasm_bindgen] auto traits are not supported");
+        }
+        if self.generics.lt_token.is_some() {
+            bail_span!(
+                self.generics,
+                "traits with #[wasm_bindgen] cannot have generic parameters"
+            );
+        }
+        if !self.supertraits.is_empty() {
+            bail_span!(
+                self.supertraits,
+                "traits with #[wasm_bindgen] cannot have supertraits"
+            );
+        }
+
+        let generate_typescript = opts.skip_typescript().is_none();
+        opts.check_used();
+
+        let js_name = self.ident.unraw().to_string();
+        let mut methods = Vec::new();
+        for item in self.items.iter() {
+            let method = match item {
+                syn::TraitItem::Fn(f) => f,
+                syn::TraitItem::Const(_) => bail_span!(
+                    item,
+                    "associated consts are not supported in #[wasm_bindgen] traits"
+                ),
+                syn::TraitItem::Type(_) => bail_span!(
+                    item,
+                    "associated types are not supported in #[wasm_bindgen] traits"
+                ),
+                _ => bail_span!(item, "unsupported item in #[wasm_bindgen] trait"),
+            };
+            let sig = &method.sig;
+            // Static methods can't be called on a JS object, so they're only
+            // allowed when they don't need to be implemented for one.
+            let receiver = match sig.receiver() {
+                Some(receiver) => receiver,
+                None if method.default.is_some() => continue,
+                None => bail_span!(
+                    sig.ident,
+                    "methods without a default body in #[wasm_bindgen] traits must take `self`"
+                ),
+            };
+            if receiver.colon_token.is_some() {
+                bail_span!(
+                    receiver,
+                    "only `self`, `&self` and `&mut self` receivers are supported \
+                     in #[wasm_bindgen] traits"
+                );
+            }
+            if let Some(asyncness) = sig.asyncness {
+                bail_span!(
+                    asyncness,
+                    "async methods are not supported in #[wasm_bindgen] traits"
+                );
+            }
+            if sig.generics.lt_token.is_some() {
+                bail_span!(
+                    sig.generics,
+                    "methods of #[wasm_bindgen] traits cannot have generic parameters"
+                );
+            }
+            if let Some(variadic) = &sig.variadic {
+                bail_span!(variadic, "variadic methods are not supported");
+            }
+
+            let mut arg_names = Vec::new();
+            let mut arg_tys = Vec::new();
+            for (i, arg) in sig.inputs.iter().skip(1).enumerate() {
+                let arg = match arg {
+                    syn::FnArg::Typed(arg) => arg,
+                    syn::FnArg::Receiver(_) => unreachable!("receiver is always first"),
+                };
+                if mentions_self(arg.ty.to_token_stream()) {
+                    bail_span!(
+                        arg.ty,
+                        "`Self` cannot be used in the signature of a #[wasm_bindgen] trait method"
+                    );
+                }
+                arg_names.push(match &*arg.pat {
+                    syn::Pat::Ident(pat) => pat.ident.unraw().to_string(),
+                    _ => format!("arg{i}"),
+                });
+                arg_tys.push((*arg.ty).clone());
+            }
+            let ret = match &sig.output {
+                syn::ReturnType::Default => None,
+                syn::ReturnType::Type(_, ty) => {
+                    if mentions_self(ty.to_token_stream()) {
+                        bail_span!(
+                            ty,
+                            "`Self` cannot be used in the signature of a #[wasm_bindgen] \
+                             trait method"
+                        );
+                    }
+                    Some((**ty).clone())
+                }
+            };
+
+            let describe = wasm_bindgen_shared::trait_method_describe(
+                &js_name,
+                &sig.ident.unraw().to_string(),
+            );
+            methods.push(ast::TraitMethod {
+                sig: sig.clone(),
+                arg_names,
+                arg_tys,
+                ret,
+                optional: method.default.is_some(),
+                comments: extract_doc_comments(&method.attrs),
+                describe: Ident::new(&describe, Span::call_site()),
+            });
+        }
+
+        // Only the impls of `#[wasm_bindgen]` traits can be exported, which
+        // they check for with this method.
+        let marker = Ident::new(crate::codegen::TRAIT_MARKER, Span::call_site());
+        self.items.push(syn::parse_quote! {
+            #[doc(hidden)]
+            fn #marker(&self) {}
+        });
+        self.to_tokens(tokens);
+
+        program.traits.push(ast::Trait {
+            js_type: Ident::new(&format!("Js{js_name}"), self.ident.span()),
+            rust_name: self.ident,
+            js_name,
+            vis: self.vis,
+            methods,
+            comments: extract_doc_comments(&self.attrs),
+            generate_typescript,
+            wasm_bindgen: program.wasm_bindgen.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// Returns whether `Self` appears anywhere in the given tokens.
+fn mentions_self(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(i) => i == "Self",
+        TokenTree::Group(g) => mentions_self(g.stream()),
+        _ => false,
+    })
+}
+
 /// Represents a possibly negative numeric value as base 10 digits.
 struct NumericValue<'a> {
     negative: bool,
@@ -1655,15 +1962,10 @@ impl<'a> MacroParse<(&'a mut TokenStream, BindgenAttrs)> for syn::ItemEnum {
         if self.variants.is_empty() {
             bail_span!(self, "cannot export empty enums to JS");
         }
-        for variant in self.variants.iter() {
-            match variant.fields {
-                syn::Fields::Unit => (),
-                _ => bail_span!(
-                    variant.fields,
-                    "enum variants with associated data are not supported with #[wasm_bindgen]"
-                ),
-            }
-        }
+        let has_data = self
+            .variants
+            .iter()
+            .any(|v| !matches!(v.fields, syn::Fields::Unit));
 
         let generate_typescript = opts.skip_typescript().is_none();
         let private = opts.private().is_some();
@@ -1683,6 +1985,23 @@ impl<'a> MacroParse<(&'a mut TokenStream, BindgenAttrs)> for syn::ItemEnum {
         let js_namespace = opts.js_namespace().map(|(ns, _)| ns.0);
         opts.check_used();
 
+        if has_data {
+            if js_namespace.is_some() || private {
+                bail_span!(
+                    self.ident,
+                    "`js_namespace` and `private` are not supported on enums with data"
+                );
+            }
+            return data_enum(
+                self,
+                tokens,
+                program,
+                js_name,
+                generate_typescript,
+                comments,
+            );
+        }
+
         // Check if the enum is a string enum, by checking whether any variant has a string discriminant.
         let is_string_enum = self.variants.iter().any(|v| {
             if let Some((_, expr)) = &v.discriminant {
@@ -75,4 +75,14 @@ pub enum M {
     B = 2147483648, // i32::MAX + 1
 }
 
+#[wasm_bindgen]
+pub enum N {
+    A { kind: u32 },
+}
+
+#[wasm_bindgen]
+pub enum O {
+    A { x: u32 } = 1,
+}
+
 fn main() {}
@@ -10,7 +10,7 @@ default impl A {}
 unsafe impl A {}
 
 #[wasm_bindgen]
-impl Clone for A {}
+impl !Clone for A {}
 
 #[wasm_bindgen]
 impl<T> A {}
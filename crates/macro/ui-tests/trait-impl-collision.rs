@@ -0,0 +1,30 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub trait Shape {
+    fn describe(&self) -> String;
+}
+
+#[wasm_bindgen]
+pub trait Animal {
+    fn describe(&self) -> String;
+}
+
+#[wasm_bindgen]
+pub struct A;
+
+#[wasm_bindgen]
+impl Shape for A {
+    fn describe(&self) -> String {
+        "a shape".to_string()
+    }
+}
+
+#[wasm_bindgen]
+impl Animal for A {
+    fn describe(&self) -> String {
+        "an animal".to_string()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use wasm_bindgen::prelude::*;
+
+pub trait Named {
+    fn name(&self) -> String;
+}
+
+#[wasm_bindgen]
+pub struct A;
+
+#[wasm_bindgen]
+impl Named for A {
+    fn name(&self) -> String {
+        "A".to_string()
+    }
+}
+
+fn main() {}
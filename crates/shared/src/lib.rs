@@ -13,7 +13,7 @@ pub mod tys;
 // This gets changed whenever our schema changes.
 // At this time versions of wasm-bindgen and wasm-bindgen-cli are required to have the exact same
 // SCHEMA_VERSION in order to work together.
-pub const SCHEMA_VERSION: &str = "0.2.107";
+pub const SCHEMA_VERSION: &str = "0.2.108";
 
 #[macro_export]
 macro_rules! shared_api {
@@ -22,6 +22,9 @@ macro_rules! shared_api {
         struct Program<'a> {
             exports: Vec<Export<'a>>,
             enums: Vec<Enum<'a>>,
+            data_enums: Vec<DataEnum<'a>>,
+            traits: Vec<Trait<'a>>,
+            trait_impls: Vec<TraitImpl<'a>>,
             imports: Vec<Import<'a>>,
             structs: Vec<Struct<'a>>,
             // NOTE: Originally typescript_custom_sections are just some strings
@@ -147,6 +150,38 @@ macro_rules! shared_api {
             comments: Vec<&'a str>,
         }
 
+        struct DataEnum<'a> {
+            name: &'a str,
+            variants: Vec<DataEnumVariant<'a>>,
+            comments: Vec<&'a str>,
+            generate_typescript: bool,
+        }
+
+        struct DataEnumVariant<'a> {
+            name: &'a str,
+            fields: Vec<&'a str>,
+            comments: Vec<&'a str>,
+        }
+
+        struct Trait<'a> {
+            name: &'a str,
+            methods: Vec<TraitMethod<'a>>,
+            comments: Vec<&'a str>,
+            generate_typescript: bool,
+        }
+
+        struct TraitMethod<'a> {
+            name: &'a str,
+            args: Vec<&'a str>,
+            optional: bool,
+            comments: Vec<&'a str>,
+        }
+
+        struct TraitImpl<'a> {
+            class: &'a str,
+            interface: &'a str,
+        }
+
         struct Function<'a> {
             args: Vec<FunctionArgumentData<'a>>,
             asyncness: bool,
@@ -242,6 +277,24 @@ pub fn struct_field_set(struct_: &str, f: &str) -> String {
     name
 }
 
+pub fn data_enum_field(enum_: &str, variant: &str, f: &str) -> String {
+    let mut name = String::from("__wbg_field_");
+    name.extend(enum_.chars().flat_map(|s| s.to_lowercase()));
+    name.push('_');
+    name.push_str(variant);
+    name.push('_');
+    name.push_str(f);
+    name
+}
+
+pub fn trait_method_describe(trait_: &str, f: &str) -> String {
+    let mut name = String::from("__wbg_trait_");
+    name.extend(trait_.chars().flat_map(|s| s.to_lowercase()));
+    name.push('_');
+    name.push_str(f);
+    name
+}
+
 pub fn version() -> String {
     let mut v = env!("CARGO_PKG_VERSION").to_string();
     if let Some(s) = option_env!("WBG_VERSION") {
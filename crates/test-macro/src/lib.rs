@@ -23,6 +23,259 @@ pub fn wasm_bindgen_test(
     bindgen(attr, body, false)
 }
 
+#[proc_macro_attribute]
+pub fn wasm_bindgen_test_setup(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    hook(attr, body, "setup")
+}
+
+#[proc_macro_attribute]
+pub fn wasm_bindgen_test_teardown(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    hook(attr, body, "teardown")
+}
+
+#[proc_macro_attribute]
+pub fn wasm_bindgen_test_executor(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    hook(attr, body, "executor")
+}
+
+#[proc_macro_attribute]
+pub fn wasm_bindgen_test_generator(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    hook(attr, body, "generator")
+}
+
+#[proc_macro_attribute]
+pub fn wasm_bindgen_test_context(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut wasm_bindgen_path = syn::parse_quote!(::wasm_bindgen_test);
+    let attribute_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("crate") {
+            wasm_bindgen_path = meta.value()?.parse::<syn::Path>()?;
+            Ok(())
+        } else {
+            Err(meta.error("unknown attribute"))
+        }
+    });
+
+    syn::parse_macro_input!(attr with attribute_parser);
+    let mut r#async = false;
+
+    let mut body = TokenStream::from(body).into_iter();
+
+    // Skip over other attributes to `fn #ident ...`, and extract `#ident`
+    let mut leading_tokens = Vec::new();
+    for token in body.by_ref() {
+        if let TokenTree::Ident(token) = &token {
+            if token == "async" {
+                r#async = true;
+                continue;
+            }
+        }
+        leading_tokens.push(token.clone());
+        if let TokenTree::Ident(token) = token {
+            if token == "fn" {
+                break;
+            }
+        }
+    }
+    let ident = find_ident(&mut body).expect("expected a function name");
+
+    match body.next() {
+        Some(TokenTree::Group(params))
+            if params.delimiter() == Delimiter::Parenthesis && params.stream().is_empty() => {}
+        _ => {
+            return compile_error(
+                ident.span(),
+                "the test context function can't be generic or take parameters",
+            )
+        }
+    }
+    let mut rest: Vec<_> = body.collect();
+    let block = match rest.pop() {
+        Some(TokenTree::Group(block)) if block.delimiter() == Delimiter::Brace => block,
+        _ => return compile_error(ident.span(), "expected the body of the function"),
+    };
+    let ty: TokenStream = match &rest[..] {
+        [TokenTree::Punct(minus), TokenTree::Punct(gt), ty @ ..]
+            if minus.as_char() == '-' && gt.as_char() == '>' =>
+        {
+            ty.iter().cloned().collect()
+        }
+        _ => {
+            return compile_error(
+                ident.span(),
+                "the test context function must return the value of the context",
+            )
+        }
+    };
+
+    // A `Result` fails the initialization with its error.
+    let ok_ty = match syn::parse2::<syn::Type>(ty.clone()) {
+        Ok(syn::Type::Path(path)) => match path.path.segments.into_iter().last() {
+            Some(segment) if segment.ident == "Result" => match segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => match args.args.into_iter().next() {
+                    Some(syn::GenericArgument::Type(ty)) => Some(ty),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        },
+        Ok(_) => None,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let path = quote! { ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#ident)) };
+    let call = if r#async {
+        quote! { __wbgt_context().await }
+    } else {
+        quote! { __wbgt_context() }
+    };
+    let set = quote! { #wasm_bindgen_path::__rt::test_context::set(#path, value) };
+    let (value_ty, init) = match ok_ty {
+        Some(ok_ty) => (
+            ok_ty,
+            convert_result(&wasm_bindgen_path, quote! { #call.map(|value| #set) }),
+        ),
+        None => (syn::parse_quote!(#ty), quote! { let value = #call; #set }),
+    };
+    let r#async = r#async.then(|| quote! { async });
+
+    let mut tokens = quote! {
+        const _: () = {
+            #[cfg_attr(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))), allow(dead_code))]
+            #r#async fn __wbgt_context() -> #ty #block
+
+            #wasm_bindgen_path::__rt::wasm_bindgen::__wbindgen_coverage! {
+            #[export_name = ::core::concat!("__wbgh_context_", ::core::module_path!(), "::", ::core::stringify!(#ident))]
+            #[cfg(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))]
+            extern "C" fn __wbgt_hook(cx: &#wasm_bindgen_path::__rt::Context) {
+                cx.context(#path, || async { #init });
+            }
+            }
+        };
+
+        #[cfg_attr(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))), allow(dead_code))]
+    };
+
+    tokens.extend(leading_tokens);
+    tokens.extend(quote! {
+        #ident() -> &'static #value_ty {
+            #wasm_bindgen_path::__rt::test_context::get(#path)
+        }
+    });
+
+    tokens.into()
+}
+
+fn hook(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+    kind: &str,
+) -> proc_macro::TokenStream {
+    let mut wasm_bindgen_path = syn::parse_quote!(::wasm_bindgen_test);
+    let attribute_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("crate") {
+            wasm_bindgen_path = meta.value()?.parse::<syn::Path>()?;
+            Ok(())
+        } else {
+            Err(meta.error("unknown attribute"))
+        }
+    });
+
+    syn::parse_macro_input!(attr with attribute_parser);
+    let mut r#async = false;
+
+    let mut body = TokenStream::from(body).into_iter();
+
+    // Skip over other attributes to `fn #ident ...`, and extract `#ident`
+    let mut leading_tokens = Vec::new();
+    for token in body.by_ref() {
+        leading_tokens.push(token.clone());
+        if let TokenTree::Ident(token) = token {
+            if token == "async" {
+                r#async = true;
+            }
+            if token == "fn" {
+                break;
+            }
+        }
+    }
+    let ident = find_ident(&mut body).expect("expected a function name");
+
+    let register = if kind == "executor" {
+        if r#async {
+            return compile_error(ident.span(), "the executor function can't be `async`");
+        }
+        quote! { cx.executor(#ident()); }
+    } else if kind == "generator" {
+        let generator = if r#async {
+            quote! { #ident }
+        } else {
+            quote! { || async { #ident() } }
+        };
+        quote! {
+            cx.generator(
+                ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#ident)),
+                #generator,
+            );
+        }
+    } else {
+        let kind = Ident::new(kind, Span::call_site());
+        let call = if r#async {
+            quote! { #ident().await }
+        } else {
+            quote! { #ident() }
+        };
+        let result = convert_result(&wasm_bindgen_path, call);
+        quote! { cx.#kind(::core::module_path!(), || async { #result }); }
+    };
+    let prefix = "__wbgh_";
+
+    let mut tokens = quote! {
+        const _: () = {
+            #wasm_bindgen_path::__rt::wasm_bindgen::__wbindgen_coverage! {
+            #[export_name = ::core::concat!(#prefix, #kind, "_", ::core::module_path!(), "::", ::core::stringify!(#ident))]
+            #[cfg(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))]
+            extern "C" fn __wbgt_hook(cx: &#wasm_bindgen_path::__rt::Context) {
+                #register
+            }
+            }
+        };
+
+        #[cfg_attr(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))), allow(dead_code))]
+    };
+
+    tokens.extend(leading_tokens);
+    tokens.extend(Some(TokenTree::from(ident)));
+    tokens.extend(body);
+
+    tokens.into()
+}
+
+/// Converts the result of `call` with the most specific conversion of
+/// `__rt::termination`, to show errors at their best.
+fn convert_result(wasm_bindgen_path: &syn::Path, call: TokenStream) -> TokenStream {
+    quote! {
+        use #wasm_bindgen_path::__rt::termination::*;
+        let result = #call;
+        (&&&result).__wbgt_kind().wrap(result)
+    }
+}
+
 fn bindgen(
     attr: proc_macro::TokenStream,
     body: proc_macro::TokenStream,
@@ -82,6 +335,20 @@ fn bindgen(
     }
     let ident = find_ident(&mut body).expect("expected a function name");
 
+    if attributes.proptest {
+        if is_bench || attributes.unsupported.is_some() {
+            return compile_error(
+                ident.span(),
+                "`proptest` is only supported on `#[wasm_bindgen_test]` without `unsupported`",
+            );
+        }
+        match proptest_body(&mut leading_tokens, body, &attributes.wasm_bindgen_path) {
+            Ok(proptest_body) => body = proptest_body.into_iter().peekable(),
+            Err(error) => return error.to_compile_error().into(),
+        }
+        attributes.r#async = true;
+    }
+
     let mut tokens = Vec::<TokenTree>::new();
 
     let should_panic_par = match &should_panic {
@@ -119,21 +386,41 @@ fn bindgen(
         ident.clone()
     };
 
-    let test_body = if attributes.r#async || is_bench {
-        quote! { cx.execute_async(test_name, #exec_ident, #should_panic_par, #ignore_par); }
+    let timeout_par = match attributes.timeout_ms {
+        Some(timeout_ms) => quote! { ::core::option::Option::Some(#timeout_ms) },
+        None => quote! { ::core::option::Option::None },
+    };
+
+    let requires = &attributes.requires;
+    let requires_par = quote! { &[#(#requires),*] };
+
+    let wasm_bindgen_path = attributes.wasm_bindgen_path;
+    let test_body = if is_bench {
+        quote! { cx.execute_async(test_name, #exec_ident, #should_panic_par, #ignore_par, #timeout_par, #requires_par); }
+    } else if attributes.r#async {
+        let result = convert_result(&wasm_bindgen_path, quote! { #ident().await });
+        quote! { cx.execute_async(test_name, || async { #result }, #should_panic_par, #ignore_par, #timeout_par, #requires_par); }
     } else {
-        quote! { cx.execute_sync(test_name, #exec_ident, #should_panic_par, #ignore_par); }
+        let result = convert_result(&wasm_bindgen_path, quote! { #ident() });
+        quote! { cx.execute_sync(test_name, || { #result }, #should_panic_par, #ignore_par, #timeout_par, #requires_par); }
     };
 
     let ignore_name = if ignore.is_some() { "$" } else { "" };
+    let run_in_name = attributes.run_in.unwrap_or_default();
+    // The timeout is also encoded in the export name for the runner.
+    let timeout_name = attributes
+        .timeout_ms
+        .map_or(String::new(), |timeout_ms| format!("t{timeout_ms}"));
+    let suite_name = attributes
+        .suite
+        .map_or(String::new(), |suite| format!("@{}", suite.value()));
 
-    let wasm_bindgen_path = attributes.wasm_bindgen_path;
     let prefix = if is_bench { "__wbgb_" } else { "__wbgt_" };
     tokens.extend(
         quote! {
             const _: () = {
                 #wasm_bindgen_path::__rt::wasm_bindgen::__wbindgen_coverage! {
-                #[export_name = ::core::concat!(#prefix, #ignore_name, "_", ::core::module_path!(), "::", ::core::stringify!(#ident))]
+                #[export_name = ::core::concat!(#prefix, #ignore_name, #run_in_name, #timeout_name, "_", ::core::module_path!(), "::", ::core::stringify!(#ident), #suite_name)]
                 #[cfg(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))]
                 extern "C" fn __wbgt_test(cx: &#wasm_bindgen_path::__rt::Context) {
                     let test_name = ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#ident));
@@ -322,6 +609,121 @@ fn parse_ignore(
     Err(compile_error(span, "malformed `#[ignore]` attribute"))
 }
 
+/// Rewrites the parameters of a `proptest` test into inputs generated by
+/// proptest, returning the tokens following the name of the test.
+fn proptest_body(
+    leading_tokens: &mut Vec<TokenTree>,
+    mut body: impl Iterator<Item = TokenTree>,
+    wasm_bindgen_path: &syn::Path,
+) -> syn::Result<TokenStream> {
+    let params = match body.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => group,
+        token => {
+            let span = token.map_or(Span::call_site(), |token| token.span());
+            return Err(syn::Error::new(span, "`proptest` tests can't be generic"));
+        }
+    };
+    let params = syn::parse::Parser::parse2(
+        syn::punctuated::Punctuated::<ProptestParam, syn::Token![,]>::parse_terminated,
+        params.stream(),
+    )?;
+    let rest = body.collect::<TokenStream>();
+
+    // The test itself becomes an `async fn` without parameters, running the
+    // property on the inputs.
+    let is_async = |token: &TokenTree| matches!(token, TokenTree::Ident(ident) if ident == "async");
+    let r#async = leading_tokens.iter().any(is_async);
+    leading_tokens.retain(|token| !is_async(token));
+    leading_tokens.insert(
+        leading_tokens.len() - 1,
+        Ident::new("async", Span::call_site()).into(),
+    );
+
+    let mut inputs = Vec::new();
+    let mut strategies = Vec::new();
+    let mut property_params = Vec::new();
+    for (i, param) in params.into_iter().enumerate() {
+        let ProptestParam {
+            attrs,
+            strategy,
+            pat,
+            ty,
+        } = param;
+        inputs.push(quote::format_ident!("__wbgt_input_{i}"));
+        strategies.push(
+            strategy.unwrap_or_else(|| quote! { #wasm_bindgen_path::__rt::proptest::any::<#ty>() }),
+        );
+        property_params.push(quote! { #(#attrs)* #pat: #ty });
+    }
+
+    let (property, call) = if r#async {
+        (
+            quote! { async fn },
+            quote! { __wbgt_property(#(#inputs),*) },
+        )
+    } else {
+        (
+            quote! { fn },
+            quote! { ::core::future::ready(__wbgt_property(#(#inputs),*)) },
+        )
+    };
+    Ok(quote! {
+        () {
+            #property __wbgt_property(#(#property_params),*) #rest
+
+            #wasm_bindgen_path::__rt::proptest::run((#(#strategies,)*), |(#(#inputs,)*)| #call).await
+        }
+    })
+}
+
+/// A parameter of a `proptest` test, generated by its `#[strategy(...)]`
+/// attribute or by `any` of its type.
+struct ProptestParam {
+    attrs: Vec<syn::Attribute>,
+    strategy: Option<TokenStream>,
+    pat: TokenStream,
+    ty: syn::Type,
+}
+
+impl syn::parse::Parse for ProptestParam {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let (strategies, attrs): (Vec<_>, Vec<_>) = syn::Attribute::parse_outer(input)?
+            .into_iter()
+            .partition(|attr| attr.path().is_ident("strategy"));
+        let strategy = match strategies.as_slice() {
+            [] => None,
+            [attr] => Some(attr.meta.require_list()?.tokens.clone()),
+            [_, attr, ..] => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `strategy` attribute",
+                ))
+            }
+        };
+
+        let mut pat = TokenStream::new();
+        loop {
+            if input.peek(syn::Token![::]) {
+                let colons = input.parse::<syn::Token![::]>()?;
+                pat.extend(quote! { #colons });
+            } else if input.peek(syn::Token![:]) {
+                break;
+            } else {
+                pat.extend(Some(input.parse::<TokenTree>()?));
+            }
+        }
+        input.parse::<syn::Token![:]>()?;
+        let ty = input.parse()?;
+
+        Ok(Self {
+            attrs,
+            strategy,
+            pat,
+            ty,
+        })
+    }
+}
+
 fn find_ident(iter: &mut impl Iterator<Item = TokenTree>) -> Option<Ident> {
     match iter.next()? {
         TokenTree::Ident(i) => Some(i),
@@ -340,14 +742,48 @@ struct Attributes {
     r#async: bool,
     wasm_bindgen_path: syn::Path,
     unsupported: Option<syn::Meta>,
+    timeout_ms: Option<u32>,
+    // Encoded in the export name for the runner, see `RUN_IN_MODES`.
+    run_in: Option<&'static str>,
+    proptest: bool,
+    requires: Vec<syn::LitStr>,
+    // Appended to the export name after `@`, for the runner.
+    suite: Option<syn::LitStr>,
 }
 
+/// The modes of `run_in`, and how they're encoded in the export name.
+const RUN_IN_MODES: [(&str, &str); 5] = [
+    ("node", "n"),
+    ("browser", "b"),
+    ("dedicated_worker", "d"),
+    ("shared_worker", "s"),
+    ("service_worker", "w"),
+];
+
+/// The capabilities of `requires`, which the harness probes for at runtime.
+const CAPABILITIES: [&str; 9] = [
+    "dom",
+    "indexeddb",
+    "service_worker",
+    "shared_array_buffer",
+    "webgl",
+    "webgl2",
+    "webgpu",
+    "webrtc",
+    "worker",
+];
+
 impl Default for Attributes {
     fn default() -> Self {
         Self {
             r#async: false,
             wasm_bindgen_path: syn::parse_quote!(::wasm_bindgen_test),
             unsupported: None,
+            timeout_ms: None,
+            run_in: None,
+            proptest: false,
+            requires: Vec::new(),
+            suite: None,
         }
     }
 }
@@ -360,6 +796,43 @@ impl Attributes {
             self.wasm_bindgen_path = meta.value()?.parse::<syn::Path>()?;
         } else if meta.path.is_ident("unsupported") {
             self.unsupported = Some(meta.value()?.parse::<syn::Meta>()?);
+        } else if meta.path.is_ident("timeout_ms") {
+            let timeout_ms = meta.value()?.parse::<syn::LitInt>()?.base10_parse()?;
+            if timeout_ms == 0 {
+                return Err(meta.error("`timeout_ms` must be greater than 0"));
+            }
+            self.timeout_ms = Some(timeout_ms);
+        } else if meta.path.is_ident("run_in") {
+            let mode = meta.value()?.parse::<syn::LitStr>()?;
+            let Some((_, modifier)) = RUN_IN_MODES.iter().find(|(name, _)| mode.value() == *name)
+            else {
+                return Err(syn::Error::new(
+                    mode.span(),
+                    "`run_in` must be one of `node`, `browser`, `dedicated_worker`, \
+                     `shared_worker` or `service_worker`",
+                ));
+            };
+            self.run_in = Some(modifier);
+        } else if meta.path.is_ident("proptest") {
+            self.proptest = true;
+        } else if meta.path.is_ident("requires") {
+            let capability = meta.value()?.parse::<syn::LitStr>()?;
+            if !CAPABILITIES.contains(&capability.value().as_str()) {
+                return Err(syn::Error::new(
+                    capability.span(),
+                    format!("`requires` must be one of `{}`", CAPABILITIES.join("`, `")),
+                ));
+            }
+            self.requires.push(capability);
+        } else if meta.path.is_ident("suite") {
+            let suite = meta.value()?.parse::<syn::LitStr>()?;
+            if suite.value().is_empty() || suite.value().contains('@') {
+                return Err(syn::Error::new(
+                    suite.span(),
+                    "`suite` must be a non-empty name without `@`",
+                ));
+            }
+            self.suite = Some(suite);
         } else {
             return Err(meta.error("unknown attribute"));
         }
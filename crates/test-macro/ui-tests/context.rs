@@ -0,0 +1,31 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::wasm_bindgen_test_context;
+
+#[wasm_bindgen_test_context]
+fn success_1() -> u32 {
+    42
+}
+
+#[wasm_bindgen_test_context(crate = ::wasm_bindgen_test)]
+pub async fn success_2(
+) -> ::core::result::Result<u32, ::wasm_bindgen_test::__rt::wasm_bindgen::JsValue> {
+    ::core::result::Result::Ok(*success_1())
+}
+
+#[wasm_bindgen_test_context(timeout_ms = 100)]
+fn fail_1() -> u32 {
+    42
+}
+
+#[wasm_bindgen_test_context]
+fn fail_2(value: u32) -> u32 {
+    value
+}
+
+#[wasm_bindgen_test_context]
+fn fail_3() {}
+
+fn main() {}
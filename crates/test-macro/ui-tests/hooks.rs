@@ -0,0 +1,27 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::{
+    wasm_bindgen_test_executor, wasm_bindgen_test_setup, wasm_bindgen_test_teardown,
+};
+
+#[wasm_bindgen_test_setup]
+fn setup() {}
+
+#[wasm_bindgen_test_teardown]
+async fn teardown() {}
+
+#[wasm_bindgen_test_setup(crate = ::wasm_bindgen_test)]
+fn success_1() {}
+
+#[wasm_bindgen_test_setup(async)]
+fn fail_1() {}
+
+#[wasm_bindgen_test_teardown(timeout_ms = 100)]
+fn fail_2() {}
+
+#[wasm_bindgen_test_executor]
+async fn fail_3() {}
+
+fn main() {}
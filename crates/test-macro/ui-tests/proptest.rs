@@ -0,0 +1,24 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::{wasm_bindgen_bench, wasm_bindgen_test};
+
+#[wasm_bindgen_test(proptest)]
+fn fail_1<T>(value: T) {}
+
+#[wasm_bindgen_test(proptest, unsupported = test)]
+fn fail_2(value: u32) {}
+
+#[wasm_bindgen_bench(proptest)]
+fn fail_3(value: u32) {}
+
+#[wasm_bindgen_test(proptest)]
+fn fail_4(
+    #[strategy(0..10u32)]
+    #[strategy(0..20u32)]
+    value: u32,
+) {
+}
+
+fn main() {}
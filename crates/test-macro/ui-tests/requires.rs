@@ -0,0 +1,19 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::wasm_bindgen_test;
+
+#[wasm_bindgen_test(requires = "webgpu")]
+async fn success_1() {}
+
+#[wasm_bindgen_test(requires = "dom", requires = "webgl2")]
+fn success_2() {}
+
+#[wasm_bindgen_test(requires = "vulkan")]
+fn fail_1() {}
+
+#[wasm_bindgen_test(requires = webgpu)]
+fn fail_2() {}
+
+fn main() {}
@@ -0,0 +1,23 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::wasm_bindgen_test;
+
+#[wasm_bindgen_test(run_in = "browser")]
+fn success_1() {}
+
+#[wasm_bindgen_test(run_in = "dedicated_worker", timeout_ms = 5000)]
+async fn success_2() {}
+
+#[wasm_bindgen_test(run_in = "node")]
+#[ignore]
+fn success_3() {}
+
+#[wasm_bindgen_test(run_in = "deno")]
+fn fail_1() {}
+
+#[wasm_bindgen_test(run_in = browser)]
+fn fail_2() {}
+
+fn main() {}
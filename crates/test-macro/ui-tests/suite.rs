@@ -0,0 +1,19 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::wasm_bindgen_test;
+
+#[wasm_bindgen_test(suite = "parser")]
+fn success_1() {}
+
+#[wasm_bindgen_test(suite = "slow tests", timeout_ms = 5000)]
+async fn success_2() {}
+
+#[wasm_bindgen_test(suite = "")]
+fn fail_1() {}
+
+#[wasm_bindgen_test(suite = "a@b")]
+fn fail_2() {}
+
+fn main() {}
@@ -0,0 +1,30 @@
+#![no_implicit_prelude]
+
+extern crate wasm_bindgen_test_macro;
+
+use wasm_bindgen_test_macro::wasm_bindgen_test;
+
+#[wasm_bindgen_test(timeout_ms = 5000)]
+fn success_1() {}
+
+#[wasm_bindgen_test(timeout_ms = 5000)]
+async fn success_2() {}
+
+#[wasm_bindgen_test(timeout_ms = 5000)]
+#[should_panic]
+#[ignore]
+async fn success_3() {}
+
+#[wasm_bindgen_test(timeout_ms)]
+fn fail_1() {}
+
+#[wasm_bindgen_test(timeout_ms = "5000")]
+fn fail_2() {}
+
+#[wasm_bindgen_test(timeout_ms = 0)]
+fn fail_3() {}
+
+#[wasm_bindgen_test(timeout_ms = 5000000000)]
+fn fail_4() {}
+
+fn main() {}
@@ -9,7 +9,10 @@
 
 extern crate alloc;
 
-pub use wasm_bindgen_test_macro::{wasm_bindgen_bench, wasm_bindgen_test};
+pub use wasm_bindgen_test_macro::{
+    wasm_bindgen_bench, wasm_bindgen_test, wasm_bindgen_test_context, wasm_bindgen_test_executor,
+    wasm_bindgen_test_generator, wasm_bindgen_test_setup, wasm_bindgen_test_teardown,
+};
 
 // Custom allocator that only returns pointers in the 2GB-4GB range
 // To ensure we actually support more than 2GB of memory
@@ -35,6 +38,50 @@ macro_rules! console_log {
     )
 }
 
+/// Asserts that two JS values are structurally equal, like `assert_eq!`.
+///
+/// The values can be anything implementing `AsRef<JsValue>`. Arrays, typed
+/// arrays, objects, `Map`s and `Set`s are compared by their contents, ignoring
+/// the order of properties and entries, and other values with `Object.is`.
+/// Functions are only compared by name. When the values differ, the panic
+/// message shows a line-oriented diff of them:
+///
+/// ```ignore
+/// assert_js_eq!(response, expected, "unexpected response to {request}");
+/// ```
+#[macro_export]
+macro_rules! assert_js_eq {
+    ($left:expr, $right:expr $(,)?) => (
+        $crate::__rt::assert::js_eq(&$left, &$right, ::core::option::Option::None)
+    );
+    ($left:expr, $right:expr, $($arg:tt)+) => (
+        $crate::__rt::assert::js_eq(
+            &$left,
+            &$right,
+            ::core::option::Option::Some(::core::format_args!($($arg)+)),
+        )
+    );
+}
+
+/// Asserts that two JSON strings are structurally equal, like
+/// [`assert_js_eq!`] does for the values they parse to.
+///
+/// The strings can be anything implementing `AsRef<str>`, and the order of
+/// the properties of objects doesn't matter. Invalid JSON fails the assertion.
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($left:expr, $right:expr $(,)?) => (
+        $crate::__rt::assert::json_eq(&$left, &$right, ::core::option::Option::None)
+    );
+    ($left:expr, $right:expr, $($arg:tt)+) => (
+        $crate::__rt::assert::json_eq(
+            &$left,
+            &$right,
+            ::core::option::Option::Some(::core::format_args!($($arg)+)),
+        )
+    );
+}
+
 /// A macro used to configured how this test is executed by the
 /// `wasm-bindgen-test-runner` harness.
 ///
@@ -55,6 +102,12 @@ macro_rules! console_log {
 ///   node.js, which is the default for executing tests.
 /// * `run_in_service_worker` - requires that this test is run in a service worker rather than
 ///   node.js, which is the default for executing tests.
+/// * `fail_on_unhandled_rejection` - fails a test if a promise is rejected
+///   without a handler while it runs, reporting the rejection reason, instead
+///   of letting the rejection go unnoticed.
+/// * `fail_on_uncaught_error` - fails a test if an error isn't caught while it
+///   runs, like one reported to `window.onerror` or by a worker. Otherwise
+///   such errors are only included in the output of the test if it fails.
 ///
 /// This macro may be invoked at most one time per test suite (an entire binary
 /// like `tests/foo.rs`, not per module)
@@ -108,6 +161,22 @@ macro_rules! wasm_bindgen_test_configure {
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
     );
+    (fail_on_unhandled_rejection $($others:tt)*) => (
+        const _: () = {
+            #[link_section = "__wasm_bindgen_test_unstable"]
+            #[cfg(target_arch = "wasm32")]
+            pub static __WBG_TEST_FAIL_ON_UNHANDLED_REJECTION: [u8; 1] = [0x06];
+            $crate::wasm_bindgen_test_configure!($($others)*);
+        };
+    );
+    (fail_on_uncaught_error $($others:tt)*) => (
+        const _: () = {
+            #[link_section = "__wasm_bindgen_test_unstable"]
+            #[cfg(target_arch = "wasm32")]
+            pub static __WBG_TEST_FAIL_ON_UNCAUGHT_ERROR: [u8; 1] = [0x07];
+            $crate::wasm_bindgen_test_configure!($($others)*);
+        };
+    );
     () => ()
 }
 
@@ -128,3 +197,7 @@ pub use __rt::criterion::Criterion;
 
 // web_time Instant
 pub use __rt::web_time::Instant;
+
+pub use __rt::sandbox::Sandbox;
+
+pub use __rt::{DynamicTest, TestExecutor};
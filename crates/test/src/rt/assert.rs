@@ -0,0 +1,307 @@
+//! Support for the `assert_js_eq!` and `assert_json_eq!` macros, which compare
+//! JS values structurally and show a line-oriented diff when they differ.
+//!
+//! Both values are rendered to lines in a canonical form, with the properties
+//! of objects and the entries of `Map`s and `Set`s sorted, and are equal if
+//! their lines are.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use js_sys::{Array, ArrayBuffer, Date, Function, Map, Object, Reflect, Set, JSON};
+use wasm_bindgen::prelude::*;
+
+use super::stringify;
+
+/// Whether diffs are colored, set with [`Context::colors`](super::Context::colors).
+pub(crate) static COLORS: AtomicBool = AtomicBool::new(false);
+
+/// Lines of unchanged values shown around the differences.
+const CONTEXT: usize = 3;
+
+/// Internal implementation detail of the `assert_js_eq!` macro.
+#[track_caller]
+pub fn js_eq<L, R>(left: &L, right: &R, args: Option<fmt::Arguments>)
+where
+    L: AsRef<JsValue> + ?Sized,
+    R: AsRef<JsValue> + ?Sized,
+{
+    compare(left.as_ref(), right.as_ref(), args);
+}
+
+/// Internal implementation detail of the `assert_json_eq!` macro.
+#[track_caller]
+pub fn json_eq<L, R>(left: &L, right: &R, args: Option<fmt::Arguments>)
+where
+    L: AsRef<str> + ?Sized,
+    R: AsRef<str> + ?Sized,
+{
+    let left = parse("left", left.as_ref());
+    let right = parse("right", right.as_ref());
+    compare(&left, &right, args);
+}
+
+/// Parses the `side` of an `assert_json_eq!`.
+#[track_caller]
+fn parse(side: &str, json: &str) -> JsValue {
+    match JSON::parse(json) {
+        Ok(value) => value,
+        Err(error) => panic!(
+            "assertion `left == right` failed: {side} isn't valid JSON: {}\n{side}: {json}",
+            stringify(&error)
+        ),
+    }
+}
+
+#[track_caller]
+fn compare(left: &JsValue, right: &JsValue, args: Option<fmt::Arguments>) {
+    let left = lines(left);
+    let right = lines(right);
+    if left == right {
+        return;
+    }
+    let message = match args {
+        Some(args) => format!("assertion `left == right` failed: {args}"),
+        None => "assertion `left == right` failed".to_string(),
+    };
+    panic!(
+        "{message}\ndiff (- left, + right):\n{}",
+        diff(&left, &right, COLORS.load(Ordering::Relaxed))
+    );
+}
+
+/// Renders `value` to lines in the canonical form.
+fn lines(value: &JsValue) -> Vec<String> {
+    let mut lines = Vec::new();
+    render(value, 0, "", "", &mut Vec::new(), &mut lines);
+    lines
+}
+
+/// Renders `value` to `out`, indented by `depth` levels, with `head` before it
+/// and `tail` after it. `seen` holds the objects being rendered, to show cycles.
+fn render(
+    value: &JsValue,
+    depth: usize,
+    head: &str,
+    tail: &str,
+    seen: &mut Vec<JsValue>,
+    out: &mut Vec<String>,
+) {
+    let pad = "  ".repeat(depth);
+    let (open, children, close) = match children(value, seen) {
+        Ok(Some(children)) => children,
+        Ok(None) => {
+            out.push(format!("{pad}{head}{}{tail}", scalar(value)));
+            return;
+        }
+        Err(placeholder) => {
+            out.push(format!("{pad}{head}{placeholder}{tail}"));
+            return;
+        }
+    };
+    if children.is_empty() {
+        out.push(format!("{pad}{head}{open}{close}{tail}"));
+        return;
+    }
+    out.push(format!("{pad}{head}{open}"));
+    seen.push(value.clone());
+    for (key, child) in children {
+        render(&child, depth + 1, &key, ",", seen, out);
+    }
+    seen.pop();
+    out.push(format!("{pad}{close}{tail}"));
+}
+
+type Children = (String, Vec<(String, JsValue)>, &'static str);
+
+/// Returns the opening, children and closing of `value`, or `None` if it's
+/// rendered on a single line. Fails with a placeholder for cycles.
+fn children(value: &JsValue, seen: &[JsValue]) -> Result<Option<Children>, String> {
+    if !value.is_object() || value.is_function() {
+        return Ok(None);
+    }
+    if seen.iter().any(|seen| seen == value) {
+        return Err("[Circular]".to_string());
+    }
+    if Array::is_array(value) {
+        return Ok(Some(("[".to_string(), items(value), "]")));
+    }
+    if ArrayBuffer::is_view(value) {
+        if let Some(name) = constructor(value).filter(|name| name != "DataView") {
+            return Ok(Some((format!("{name} ["), items(value), "]")));
+        }
+    }
+    if value.is_instance_of::<Date>() || value.is_instance_of::<js_sys::RegExp>() {
+        return Ok(None);
+    }
+    if value.is_instance_of::<js_sys::Error>() {
+        return Ok(None);
+    }
+    if let Some(map) = value.dyn_ref::<Map>() {
+        let mut entries = Vec::new();
+        map.for_each(&mut |value, key| {
+            let key = single_line(&key, seen);
+            entries.push((format!("{key} => "), value));
+        });
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        return Ok(Some(("Map {".to_string(), entries, "}")));
+    }
+    if let Some(set) = value.dyn_ref::<Set>() {
+        let mut items = Vec::new();
+        set.for_each(&mut |item, _, _| items.push(item));
+        let mut items: Vec<_> = items
+            .into_iter()
+            .map(|item| (single_line(&item, seen), item))
+            .collect();
+        items.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let items = items.into_iter().map(|(_, item)| (String::new(), item));
+        return Ok(Some(("Set {".to_string(), items.collect(), "}")));
+    }
+
+    let mut keys: Vec<String> = Object::keys(value.unchecked_ref())
+        .iter()
+        .filter_map(|key| key.as_string())
+        .collect();
+    keys.sort();
+    let entries = keys
+        .into_iter()
+        .map(|key| {
+            let child = Reflect::get(value, &JsValue::from_str(&key)).unwrap_or(JsValue::UNDEFINED);
+            (format!("{key:?}: "), child)
+        })
+        .collect();
+    let open = match constructor(value).filter(|name| name != "Object") {
+        Some(name) => format!("{name} {{"),
+        None => "{".to_string(),
+    };
+    Ok(Some((open, entries, "}")))
+}
+
+/// The items of an array or typed array, which have no keys.
+fn items(value: &JsValue) -> Vec<(String, JsValue)> {
+    let array = Array::from(value);
+    array.iter().map(|item| (String::new(), item)).collect()
+}
+
+/// Renders `value` on a single line, for the keys of `Map`s and to sort the
+/// items of `Set`s.
+fn single_line(value: &JsValue, seen: &[JsValue]) -> String {
+    let mut out = Vec::new();
+    render(value, 0, "", "", &mut seen.to_vec(), &mut out);
+    out.iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Renders a value that doesn't have children.
+fn scalar(value: &JsValue) -> String {
+    if let Some(s) = value.as_string() {
+        return format!("{s:?}");
+    }
+    if let Some(n) = value.as_f64() {
+        // `String(-0)` is `"0"`, but `Object.is` tells them apart.
+        return if n == 0.0 && n.is_sign_negative() {
+            "-0".to_string()
+        } else {
+            stringify(value)
+        };
+    }
+    if value.is_bigint() {
+        return format!("{}n", stringify(value));
+    }
+    if value.is_function() {
+        let name = String::from(Function::from(value.clone()).name());
+        return if name.is_empty() {
+            "[Function (anonymous)]".to_string()
+        } else {
+            format!("[Function: {name}]")
+        };
+    }
+    if let Some(date) = value.dyn_ref::<Date>() {
+        return if date.get_time().is_nan() {
+            "Invalid Date".to_string()
+        } else {
+            format!("Date({})", String::from(date.to_iso_string()))
+        };
+    }
+    stringify(value)
+}
+
+/// The name of the constructor of `value`'s prototype, if any.
+fn constructor(value: &JsValue) -> Option<String> {
+    let prototype = Object::get_prototype_of(value);
+    if prototype.is_null() {
+        return None;
+    }
+    Reflect::get(&prototype, &"constructor".into())
+        .ok()
+        .and_then(|constructor| constructor.dyn_into::<Function>().ok())
+        .map(|constructor| String::from(constructor.name()))
+        .filter(|name| !name.is_empty())
+}
+
+/// Renders the differences between `left` and `right`, with the lines only in
+/// `left` marked with `-` and the lines only in `right` with `+`.
+fn diff(left: &[String], right: &[String], colors: bool) -> String {
+    // The longest common subsequence of the lines following each pair of lines.
+    let mut lcs = vec![vec![0; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            changes.push((' ', &left[i]));
+            i += 1;
+            j += 1;
+        } else if j == right.len() || (i < left.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(('-', &left[i]));
+            i += 1;
+        } else {
+            changes.push(('+', &right[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..changes.len())
+        .filter(|&k| changes[k].0 != ' ')
+        .collect();
+    let mut out = String::new();
+    let mut skipped = false;
+    for (k, (mark, line)) in changes.iter().enumerate() {
+        let near = changed.iter().any(|&c| c.abs_diff(k) <= CONTEXT);
+        if !near {
+            if !skipped {
+                out.push_str("  ...\n");
+                skipped = true;
+            }
+            continue;
+        }
+        skipped = false;
+        let line = format!("{mark} {line}");
+        let color = match mark {
+            '-' => "31",
+            '+' => "32",
+            _ => "",
+        };
+        if colors && !color.is_empty() {
+            out.push_str(&format!("\x1b[{color}m{line}\x1b[0m\n"));
+        } else {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
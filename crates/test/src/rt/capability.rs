@@ -0,0 +1,84 @@
+//! Probes for the capabilities of `#[wasm_bindgen_test(requires = "...")]`,
+//! skipping the tests requiring one the environment lacks rather than failing
+//! them.
+
+use js_sys::{Array, Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+/// Returns the first capability of `requires` that isn't supported.
+pub(super) async fn missing(requires: &'static [&'static str]) -> Option<&'static str> {
+    for &capability in requires {
+        if !supported(capability).await {
+            return Some(capability);
+        }
+    }
+    None
+}
+
+async fn supported(capability: &str) -> bool {
+    let global = js_sys::global().into();
+    match capability {
+        "dom" => get(&global, "document").is_some(),
+        "indexeddb" => get(&global, "indexedDB").is_some(),
+        "service_worker" => navigator(&global, "serviceWorker").is_some(),
+        // Shared memory is only available to cross-origin isolated pages.
+        "shared_array_buffer" => {
+            get(&global, "SharedArrayBuffer").is_some()
+                && get(&global, "crossOriginIsolated").map_or(true, |isolated| isolated.is_truthy())
+        }
+        "webgl" => canvas_context(&global, "webgl").is_some(),
+        "webgl2" => canvas_context(&global, "webgl2").is_some(),
+        "webgpu" => webgpu_adapter(&global).await.is_some(),
+        "webrtc" => get(&global, "RTCPeerConnection").is_some(),
+        "worker" => get(&global, "Worker").is_some(),
+        _ => false,
+    }
+}
+
+/// Returns an adapter of WebGPU. `navigator.gpu` is exposed even without an
+/// adapter to use, as in headless browsers without a GPU.
+async fn webgpu_adapter(global: &JsValue) -> Option<JsValue> {
+    let adapter = call(&navigator(global, "gpu")?, "requestAdapter")?;
+    let adapter = JsFuture::from(adapter.dyn_into::<Promise>().ok()?).await;
+    adapter.ok().filter(|adapter| !adapter.is_null())
+}
+
+/// Returns a rendering context of kind `kind`, from a canvas of the document
+/// or an `OffscreenCanvas` in workers.
+fn canvas_context(global: &JsValue, kind: &str) -> Option<JsValue> {
+    let canvas = match get(global, "document") {
+        Some(document) => {
+            let create = get(&document, "createElement")?
+                .dyn_into::<Function>()
+                .ok()?;
+            create.call1(&document, &"canvas".into()).ok()?
+        }
+        None => {
+            let offscreen = get(global, "OffscreenCanvas")?
+                .dyn_into::<Function>()
+                .ok()?;
+            Reflect::construct(&offscreen, &Array::of2(&1.into(), &1.into())).ok()?
+        }
+    };
+    let context = get(&canvas, "getContext")?.dyn_into::<Function>().ok()?;
+    context
+        .call1(&canvas, &kind.into())
+        .ok()
+        .filter(|context| !context.is_null())
+}
+
+fn navigator(global: &JsValue, key: &str) -> Option<JsValue> {
+    get(&get(global, "navigator")?, key)
+}
+
+fn call(target: &JsValue, method: &str) -> Option<JsValue> {
+    let method = get(target, method)?.dyn_into::<Function>().ok()?;
+    method.call0(target).ok()
+}
+
+fn get(target: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(target, &key.into())
+        .ok()
+        .filter(|value| !value.is_undefined() && !value.is_null())
+}
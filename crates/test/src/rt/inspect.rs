@@ -0,0 +1,230 @@
+//! Rendering of JS values for `console.dir` and `console.table`, in the style
+//! of Node.js' `util.inspect`, so captured output shows the contents of
+//! objects rather than `[object Object]`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use js_sys::{Array, Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use super::stringify;
+
+/// How much of objects and arrays [`inspect`] renders.
+#[derive(Clone, Copy)]
+pub(crate) struct Options {
+    /// Levels of nested objects and arrays rendered, like `util.inspect`'s
+    /// `depth` option.
+    pub(crate) depth: usize,
+    /// Elements of an array or properties of an object rendered, like
+    /// `util.inspect`'s `maxArrayLength` option.
+    pub(crate) max_length: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            depth: 2,
+            max_length: 100,
+        }
+    }
+}
+
+/// Renders `val`, showing the contents of objects and arrays nested up to
+/// `options.depth` levels below it.
+pub(crate) fn inspect(val: &JsValue, options: &Options) -> String {
+    render(val, options.depth + 1, options.max_length)
+}
+
+/// Renders `val`, replacing objects and arrays by placeholders once `levels`
+/// of them have been rendered.
+fn render(val: &JsValue, levels: usize, max_length: usize) -> String {
+    if let Some(s) = val.as_string() {
+        return format!("'{}'", s.replace('\'', "\\'"));
+    }
+    if val.is_function() {
+        let name = String::from(Function::from(val.clone()).name());
+        return if name.is_empty() {
+            "[Function (anonymous)]".to_string()
+        } else {
+            format!("[Function: {name}]")
+        };
+    }
+    if !val.is_object() || val.is_instance_of::<js_sys::Error>() {
+        return stringify(val);
+    }
+
+    if Array::is_array(val) {
+        if levels == 0 {
+            return "[Array]".to_string();
+        }
+        let array = Array::from(val);
+        let mut items: Vec<_> = array
+            .iter()
+            .take(max_length)
+            .map(|item| render(&item, levels - 1, max_length))
+            .collect();
+        let more = array.length() as usize - items.len();
+        if more > 0 {
+            items.push(format!(
+                "... {more} more item{}",
+                if more == 1 { "" } else { "s" }
+            ));
+        }
+        return if items.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[ {} ]", items.join(", "))
+        };
+    }
+
+    if levels == 0 {
+        return "[Object]".to_string();
+    }
+    let keys = keys(val);
+    let mut entries: Vec<_> = keys
+        .iter()
+        .take(max_length)
+        .map(|key| {
+            let value = Reflect::get(val, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED);
+            format!("{key}: {}", render(&value, levels - 1, max_length))
+        })
+        .collect();
+    let more = keys.len() - entries.len();
+    if more > 0 {
+        entries.push(format!(
+            "... {more} more propert{}",
+            if more == 1 { "y" } else { "ies" }
+        ));
+    }
+    if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", entries.join(", "))
+    }
+}
+
+/// Options of the cells of [`table`], which show objects as placeholders.
+const CELL: Options = Options {
+    depth: 0,
+    max_length: 100,
+};
+
+/// Renders `data` as a table like `console.table`, with a row per property
+/// and a column per property of the rows, optionally limited to `columns`.
+///
+/// Returns `None` if `data` isn't an object, which `console.table` logs as is.
+pub(crate) fn table(data: &JsValue, columns: &JsValue) -> Option<String> {
+    if !data.is_object() {
+        return None;
+    }
+
+    let rows: Vec<(String, JsValue)> = keys(data)
+        .into_iter()
+        .map(|key| {
+            let row = Reflect::get(data, &JsValue::from_str(&key)).unwrap_or(JsValue::UNDEFINED);
+            (key, row)
+        })
+        .collect();
+
+    let mut headers: Vec<String> = if Array::is_array(columns) {
+        Array::from(columns)
+            .iter()
+            .map(|column| column.as_string().unwrap_or_else(|| stringify(&column)))
+            .collect()
+    } else {
+        let mut headers = Vec::new();
+        for (_, row) in &rows {
+            if row.is_object() && !row.is_function() {
+                for key in keys(row) {
+                    if !headers.contains(&key) {
+                        headers.push(key);
+                    }
+                }
+            }
+        }
+        headers
+    };
+    let has_values = rows
+        .iter()
+        .any(|(_, row)| !row.is_object() || row.is_function());
+
+    let mut lines: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(key, row)| {
+            let mut line = alloc::vec![key.clone()];
+            let is_object = row.is_object() && !row.is_function();
+            for header in &headers {
+                let cell = if is_object {
+                    Reflect::get(row, &JsValue::from_str(header))
+                        .ok()
+                        .filter(|value| !value.is_undefined())
+                        .map(|value| inspect(&value, &CELL))
+                } else {
+                    None
+                };
+                line.push(cell.unwrap_or_default());
+            }
+            if has_values {
+                line.push(if is_object {
+                    String::new()
+                } else {
+                    inspect(row, &CELL)
+                });
+            }
+            line
+        })
+        .collect();
+
+    headers.insert(0, "(index)".to_string());
+    if has_values {
+        headers.push("Values".to_string());
+    }
+    lines.insert(0, headers);
+
+    let widths: Vec<usize> = (0..lines[0].len())
+        .map(|column| {
+            lines
+                .iter()
+                .map(|line| line[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let border = |left: &str, middle: &str, right: &str| {
+        let segments: Vec<_> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+        format!("{left}{}{right}", segments.join(middle))
+    };
+    let row = |line: &[String]| {
+        let cells: Vec<_> = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| {
+                let padding = width - cell.chars().count();
+                format!(" {cell}{} ", " ".repeat(padding))
+            })
+            .collect();
+        format!("│{}│", cells.join("│"))
+    };
+
+    let mut out = border("┌", "┬", "┐");
+    out.push('\n');
+    out.push_str(&row(&lines[0]));
+    out.push('\n');
+    out.push_str(&border("├", "┼", "┤"));
+    for line in &lines[1..] {
+        out.push('\n');
+        out.push_str(&row(line));
+    }
+    out.push('\n');
+    out.push_str(&border("└", "┴", "┘"));
+    Some(out)
+}
+
+/// The own enumerable property names of `val`.
+fn keys(val: &JsValue) -> Vec<String> {
+    Object::keys(val.unchecked_ref())
+        .iter()
+        .filter_map(|key| key.as_string())
+        .collect()
+}
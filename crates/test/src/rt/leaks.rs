@@ -0,0 +1,103 @@
+//! Detection of the DOM elements, timers, event listeners and handles a test
+//! leaves behind, for `--detect-leaks`.
+//!
+//! The runner counts them in JS, wrapping `setTimeout`, `setInterval` and
+//! `EventTarget.prototype.addEventListener`, and passes the harness a function
+//! returning the counts, which are compared before and after each test. The
+//! `JsValue`s and exported objects alive across the boundary are listed by
+//! the bindings, which the runner generates with `--track-handles`.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use js_sys::{Array, Function, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// What the page or worker holds at some point.
+#[derive(Clone)]
+pub(super) struct Counts {
+    elements: f64,
+    timers: f64,
+    listeners: f64,
+    handles: Vec<Handle>,
+}
+
+/// A `JsValue` or exported object alive across the boundary.
+#[derive(Clone)]
+struct Handle {
+    /// Unique among all handles ever created.
+    id: f64,
+    /// `JsValue` or the name of the exported type.
+    kind: String,
+    /// Where the handle was created.
+    stack: String,
+}
+
+impl Counts {
+    /// Calls `snapshot`, the function passed by the runner, to count what's
+    /// held now.
+    pub fn now(snapshot: &Function) -> Option<Counts> {
+        let counts = snapshot.call0(&JsValue::UNDEFINED).ok()?;
+        let counts = counts.dyn_into::<Array>().ok()?;
+        let count = |i| counts.get(i).as_f64().unwrap_or_default();
+        let handles = counts
+            .get(3)
+            .dyn_into::<Array>()
+            .map(|handles| handles.iter().filter_map(Handle::new).collect())
+            .unwrap_or_default();
+        Some(Counts {
+            elements: count(0),
+            timers: count(1),
+            listeners: count(2),
+            handles,
+        })
+    }
+
+    /// Describes what was leaked since `self`, like `2 DOM elements, 1
+    /// timer`, if anything, followed by where each leaked handle was created.
+    pub fn leaked(&self, after: &Counts) -> Option<String> {
+        let handles = after
+            .handles
+            .iter()
+            .filter(|handle| !self.handles.iter().any(|held| held.id == handle.id))
+            .collect::<Vec<_>>();
+
+        let mut leaks = Vec::new();
+        for (before, after, what) in [
+            (self.elements, after.elements, "DOM element"),
+            (self.timers, after.timers, "timer"),
+            (self.listeners, after.listeners, "event listener"),
+            (0., handles.len() as f64, "handle"),
+        ] {
+            let leaked = after - before;
+            if leaked > 0. {
+                let plural = if leaked == 1. { "" } else { "s" };
+                leaks.push(format!("{leaked} {what}{plural}"));
+            }
+        }
+        if leaks.is_empty() {
+            return None;
+        }
+
+        let mut leaked = leaks.join(", ");
+        for handle in handles {
+            leaked.push_str(&format!("\n        `{}` created at:", handle.kind));
+            for line in handle.stack.lines() {
+                leaked.push_str(&format!("\n            {}", line.trim()));
+            }
+        }
+        Some(leaked)
+    }
+}
+
+impl Handle {
+    /// Reads a handle listed by the bindings' `__wbg_live_handles`.
+    fn new(handle: JsValue) -> Option<Handle> {
+        let get = |key: &str| Reflect::get(&handle, &JsValue::from_str(key)).ok();
+        Some(Handle {
+            id: get("id")?.as_f64()?,
+            kind: get("kind")?.as_string()?,
+            stack: get("stack")?.as_string().unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,111 @@
+//! Measures how much a test grew the memory of the Wasm module and the JS
+//! heap, for `--report-memory`.
+
+use alloc::format;
+use alloc::string::String;
+use js_sys::{Function, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// The memory in use at some point, in bytes.
+#[derive(Clone, Copy)]
+pub(super) struct Usage {
+    /// Size of the memory of the Wasm module.
+    wasm: f64,
+    /// Size of the JS heap in use, if the environment reports it.
+    heap: Option<f64>,
+}
+
+/// How much the memory in use grew during a test, in bytes.
+#[derive(Clone, Copy)]
+pub(super) struct Growth {
+    pub wasm: f64,
+    pub heap: Option<f64>,
+}
+
+impl Usage {
+    /// Measures the memory in use now.
+    pub fn now() -> Usage {
+        Usage {
+            wasm: wasm_memory_size(),
+            heap: heap_used(),
+        }
+    }
+
+    /// Returns how much the memory in use grew since `self`.
+    pub fn growth(&self) -> Growth {
+        let now = Usage::now();
+        Growth {
+            wasm: now.wasm - self.wasm,
+            heap: self
+                .heap
+                .zip(now.heap)
+                .map(|(before, after)| after - before),
+        }
+    }
+}
+
+impl Growth {
+    /// Whether the Wasm memory or the JS heap grew by more than `threshold`.
+    pub fn exceeds(&self, threshold: f64) -> bool {
+        self.wasm > threshold || self.heap.is_some_and(|heap| heap > threshold)
+    }
+
+    /// Renders the growth like `[wasm +64.0 KiB, heap +1.2 MiB]`.
+    pub fn render(&self) -> String {
+        match self.heap {
+            Some(heap) => format!("[wasm {}, heap {}]", bytes(self.wasm), bytes(heap)),
+            None => format!("[wasm {}]", bytes(self.wasm)),
+        }
+    }
+}
+
+/// Renders a number of bytes with a sign and a binary unit.
+pub(super) fn bytes(bytes: f64) -> String {
+    let sign = if bytes < 0. { "-" } else { "+" };
+    let mut size = bytes.abs();
+    if size < 1024. {
+        return format!("{sign}{size} B");
+    }
+    for unit in ["KiB", "MiB"] {
+        size /= 1024.;
+        if size < 1024. {
+            return format!("{sign}{size:.1} {unit}");
+        }
+    }
+    format!("{sign}{:.1} GiB", size / 1024.)
+}
+
+fn wasm_memory_size() -> f64 {
+    let buffer = get(&wasm_bindgen::memory(), "buffer");
+    buffer
+        .and_then(|buffer| get(&buffer, "byteLength"))
+        .and_then(|length| length.as_f64())
+        .unwrap_or_default()
+}
+
+/// The size of the JS heap in use, from `process.memoryUsage()` in Node.js,
+/// `Deno.memoryUsage()` in Deno and the non-standard `performance.memory` in
+/// Chromium.
+fn heap_used() -> Option<f64> {
+    let global = js_sys::global().into();
+    let usage = get(&global, "process")
+        .and_then(|process| call(&process, "memoryUsage"))
+        .or_else(|| get(&global, "Deno").and_then(|deno| call(&deno, "memoryUsage")));
+    let heap = match usage {
+        Some(usage) => get(&usage, "heapUsed"),
+        None => get(&get(&global, "performance")?, "memory")
+            .and_then(|memory| get(&memory, "usedJSHeapSize")),
+    };
+    heap?.as_f64()
+}
+
+fn call(target: &JsValue, method: &str) -> Option<JsValue> {
+    let method = get(target, method)?.dyn_into::<Function>().ok()?;
+    method.call0(target).ok()
+}
+
+fn get(target: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(target, &key.into())
+        .ok()
+        .filter(|value| !value.is_undefined() && !value.is_null())
+}
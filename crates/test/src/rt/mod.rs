@@ -89,6 +89,7 @@
 
 use alloc::borrow::ToOwned;
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::{String, ToString};
@@ -112,14 +113,25 @@ use wasm_bindgen_futures::future_to_promise;
 // conccurrently doing things by default would likely end up in a bad situation.
 const CONCURRENCY: usize = 1;
 
+pub mod assert;
 pub mod browser;
+mod capability;
 
 /// A modified `criterion.rs`, retaining only the basic benchmark capabilities.
 #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
 pub mod criterion;
 pub mod detect;
+mod inspect;
+mod leaks;
+mod memory;
 pub mod node;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+mod report;
+pub mod sandbox;
 mod scoped_tls;
+pub mod termination;
+pub mod test_context;
 /// Directly depending on wasm-bindgen-test-based libraries should be avoided,
 /// as it creates a circular dependency that breaks their usage within `wasm-bindgen-test`.
 ///
@@ -153,6 +165,75 @@ struct State {
     /// Number of tests that have been ignored.
     ignored_count: Cell<usize>,
 
+    /// Fail tests during which a promise rejection wasn't handled.
+    fail_on_unhandled_rejection: Cell<bool>,
+
+    /// Reasons of the unhandled promise rejections reported since the last
+    /// test finished.
+    rejections: RefCell<Vec<JsValue>>,
+
+    /// Fail tests during which an error wasn't caught.
+    fail_on_uncaught_error: Cell<bool>,
+
+    /// Uncaught errors reported since the last test finished.
+    uncaught_errors: RefCell<Vec<JsValue>>,
+
+    /// Print the captured output of `console.warn` and `console.error` to
+    /// stderr, and the rest to stdout.
+    split_console_output: Cell<bool>,
+
+    /// Color the captured output by console level.
+    console_colors: Cell<bool>,
+
+    /// Color the reports of panics.
+    colors: Cell<bool>,
+
+    /// Report how long each test took, compared to these limits.
+    time_limits: Cell<Option<TimeLimits>>,
+
+    /// Tests that took more than the soft time limit, with their duration.
+    slow_tests: RefCell<Vec<(String, f64)>>,
+
+    /// Record a CPU profile of each test with `console.profile`.
+    cpu_profile: Cell<bool>,
+
+    /// Report how much each test grew the memory in use, listing the tests
+    /// that grew it by more than this many bytes.
+    memory_threshold: Cell<Option<f64>>,
+
+    /// Tests that grew the memory in use by more than the threshold.
+    memory_growths: RefCell<Vec<(String, memory::Growth)>>,
+
+    /// Counts the DOM elements, timers, event listeners and handles held, to
+    /// report the tests leaking them.
+    leak_counter: RefCell<Option<Function>>,
+
+    /// Tests that leaked, with what they leaked.
+    leaky_tests: RefCell<Vec<(String, String)>>,
+
+    /// Returns the number of Wasm instructions executed so far, to report
+    /// how many each test executed.
+    instruction_counter: RefCell<Option<Function>>,
+
+    /// Render the objects passed to `console` methods with these options,
+    /// rather than with `String`.
+    console_inspect: Cell<Option<inspect::Options>>,
+
+    /// Lines of failure output printed per test, unlimited by default.
+    max_test_output_lines: Cell<Option<usize>>,
+
+    /// Lines of failure output printed in total, unlimited by default.
+    max_output_lines: Cell<Option<usize>>,
+
+    /// Lines of failure output printed so far.
+    output_lines: Cell<usize>,
+
+    /// Failure output suppressed by the limits above, saved by the runner.
+    suppressed_output: RefCell<String>,
+
+    /// Where the runner saves the suppressed output.
+    suppressed_output_path: RefCell<Option<String>>,
+
     /// A list of all tests which have failed.
     ///
     /// Each test listed here is paired with a `JsValue` that represents the
@@ -173,6 +254,115 @@ struct State {
 
     /// Timing the total duration.
     timer: Option<Timer>,
+
+    /// Setup and teardown hooks of test modules, by module path.
+    hooks: RefCell<BTreeMap<String, ModuleHooks>>,
+
+    /// The executor driving the tests, if not the harness itself.
+    executor: RefCell<Option<Rc<dyn TestExecutor>>>,
+
+    /// Generators of dynamic tests, by path.
+    generators: RefCell<Vec<(String, Generator)>>,
+
+    /// Initializers of shared test contexts, by path.
+    contexts: RefCell<Vec<(String, Initializer)>>,
+
+    /// The filters of the runner, applied to dynamic tests.
+    filter: RefCell<TestFilter>,
+
+    /// How the results are reported, by default a line per test.
+    report: Cell<report::Format>,
+
+    /// Suites of the tests the runner found, by test name. Dynamic tests
+    /// belong to the suite of their module.
+    suites: RefCell<BTreeMap<String, String>>,
+
+    /// Results of the tests by suite, for the JUnit report.
+    cases: RefCell<BTreeMap<String, Vec<report::Case>>>,
+}
+
+/// Drives the futures of tests, for example in the context of an async
+/// runtime so that tests can spawn tasks onto it. Registered with the
+/// `#[wasm_bindgen_test_executor]` attribute on a function returning it.
+///
+/// Without an executor, the harness polls the futures of tests itself.
+pub trait TestExecutor {
+    /// Returns a future running `test`, which includes the setup hook of the
+    /// test's module, to completion.
+    ///
+    /// The output and panics of `test` are attributed to the test while the
+    /// returned future is polled, so executors should poll `test` from there
+    /// rather than from a task of their own.
+    fn run(
+        &self,
+        test: Pin<Box<dyn Future<Output = Result<(), JsValue>>>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JsValue>>>>;
+}
+
+/// A test registered at runtime by a `#[wasm_bindgen_test_generator]`
+/// function, for example one test per fixture file of a data-driven suite.
+///
+/// Dynamic tests are named after the path of their generator, followed by
+/// their own name, and run after the tests of `#[wasm_bindgen_test]`.
+pub struct DynamicTest {
+    name: String,
+    test: Pin<Box<dyn Future<Output = Result<(), JsValue>>>>,
+}
+
+impl DynamicTest {
+    /// Creates a test named `name`, running the future returned by `test`.
+    /// Like `#[wasm_bindgen_test]` functions, it can return `()` or a `Result`.
+    pub fn new<F>(name: impl Into<String>, test: impl FnOnce() -> F + 'static) -> Self
+    where
+        F: Future + 'static,
+        F::Output: Termination,
+    {
+        DynamicTest {
+            name: name.into(),
+            test: Box::pin(async { test().await.into_js_result() }),
+        }
+    }
+}
+
+/// The initializer of a shared test context.
+type Initializer = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = Result<(), JsValue>>>>>;
+
+/// A generator of dynamic tests.
+type Generator = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = Vec<DynamicTest>>>>>;
+
+/// The filters of the runner, which it applies itself to the tests it finds.
+#[derive(Default)]
+struct TestFilter {
+    filter: Option<String>,
+    skip: Vec<String>,
+    exact: bool,
+    only_ignored: bool,
+}
+
+impl TestFilter {
+    fn matches(&self, name: &str) -> bool {
+        let matches = |filter: &String| {
+            if self.exact {
+                name == filter
+            } else {
+                name.contains(filter.as_str())
+            }
+        };
+        // Dynamic tests can't be ignored.
+        !self.only_ignored
+            && self.filter.as_ref().map_or(true, matches)
+            && !self.skip.iter().any(matches)
+    }
+}
+
+/// A setup or teardown hook of a test module.
+type Hook = Rc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), JsValue>>>>>;
+
+/// The hooks run around each test of a module.
+#[derive(Default, Clone)]
+struct ModuleHooks {
+    setup: Option<Hook>,
+    teardown: Option<Hook>,
 }
 
 /// Failure reasons.
@@ -184,6 +374,27 @@ enum Failure {
     /// A test that `should_panic` with a specific message,
     /// but panicked with a different message.
     ShouldPanicExpected,
+    /// A test during which promises were rejected without a handler.
+    UnhandledRejection(Vec<JsValue>),
+    /// A test during which errors weren't caught.
+    UncaughtError,
+    /// A test that didn't finish within its timeout, in milliseconds.
+    Timeout(u32),
+    /// A test whose module's `setup` or `teardown` hook failed, with the error
+    /// of the hook, or of the test if it failed too.
+    Hook(&'static str, JsValue),
+    /// A test that took longer than the hard time limit, with its duration and
+    /// the limit in milliseconds.
+    TimeLimit(f64, f64),
+}
+
+/// The time limits of tests, in milliseconds.
+#[derive(Clone, Copy)]
+struct TimeLimits {
+    /// Whether tests taking more than `critical_ms` fail.
+    ensure: bool,
+    warn_ms: f64,
+    critical_ms: f64,
 }
 
 /// Representation of one test that needs to be executed.
@@ -192,6 +403,7 @@ enum Failure {
 /// future is polled.
 struct Test {
     name: String,
+    suite: String,
     future: Pin<Box<dyn Future<Output = Result<(), JsValue>>>>,
     output: Rc<RefCell<Output>>,
     should_panic: Option<Option<&'static str>>,
@@ -205,8 +417,214 @@ struct Output {
     info: String,
     warn: String,
     error: String,
-    panic: String,
-    should_panic: bool,
+    /// The first panic of the test, if it panicked.
+    panic: Option<Panic>,
+    /// Whether a worker spawned by the test panicked, which fails the test.
+    worker_panicked: bool,
+    /// Wakes the test, to fail it once a worker it spawned panicked.
+    waker: Option<task::Waker>,
+    /// The timeout of the test in milliseconds, if it didn't finish within it.
+    timed_out: Option<u32>,
+    /// The hook of the test's module that failed, `setup` or `teardown`.
+    failed_hook: Option<&'static str>,
+    /// The input and seed of the running case of a property test.
+    proptest_case: Option<String>,
+    /// When the test started, from `performance.now()`.
+    started: Option<f64>,
+    /// The memory in use when the test started, with `--report-memory`.
+    memory: Option<memory::Usage>,
+    /// What was held when the test started, with `--detect-leaks`.
+    held: Option<leaks::Counts>,
+    /// The instructions executed when the test started, with
+    /// `--count-instructions`.
+    instructions: Option<f64>,
+    /// The capability the test requires that's missing, skipping it.
+    unsupported: Option<&'static str>,
+    uncaught: String,
+    /// Number of open `console.group`s, which indent the output.
+    group_depth: usize,
+    /// How objects passed to `console` methods are rendered, if not with
+    /// `String`.
+    inspect: Option<inspect::Options>,
+}
+
+/// A panic, as reported by the panic hook.
+#[derive(Clone)]
+struct Panic {
+    message: String,
+    /// File, line and column the panic happened at.
+    location: Option<(String, u32, u32)>,
+    /// Name of the thread or worker that panicked, `main` outside of workers.
+    thread: String,
+    /// The JS stack trace of the panic hook.
+    stack: String,
+    /// Where the worker that panicked was spawned, for panics of workers
+    /// spawned by a test.
+    spawn: Option<Spawn>,
+}
+
+impl Panic {
+    /// Creates a panic from the rendering of the `PanicInfo` of the hook,
+    /// `panicked at <location>:\n<message>`, and its location.
+    fn new(text: &str, location: Option<&core::panic::Location<'_>>) -> Panic {
+        #[wasm_bindgen]
+        extern "C" {
+            type Error;
+
+            #[wasm_bindgen(constructor)]
+            fn new() -> Error;
+
+            #[wasm_bindgen(method, getter)]
+            fn stack(error: &Error) -> String;
+        }
+
+        let message = match text.strip_prefix("panicked at ") {
+            Some(rest) => rest.split_once(":\n").map_or("", |(_, message)| message),
+            None => text,
+        };
+        let thread = match detect::detect() {
+            detect::Runtime::Worker => js_sys::Reflect::get(&js_sys::global(), &"name".into())
+                .ok()
+                .and_then(|name| name.as_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| String::from("worker")),
+            _ => String::from("main"),
+        };
+        Panic {
+            message: message.to_string(),
+            location: location.map(|l| (l.file().to_string(), l.line(), l.column())),
+            thread,
+            stack: Error::new().stack(),
+            spawn: None,
+        }
+    }
+
+    /// Creates a panic from one reported to `__wbg_report_panic` in a worker.
+    fn from_js(info: &JsValue) -> Panic {
+        let get = |key: &str| js_sys::Reflect::get(info, &key.into()).unwrap_or_default();
+        let location = match (
+            get("file").as_string(),
+            get("line").as_f64(),
+            get("column").as_f64(),
+        ) {
+            (Some(file), Some(line), Some(column)) => Some((file, line as u32, column as u32)),
+            _ => None,
+        };
+        Panic {
+            message: get("message").as_string().unwrap_or_default(),
+            location,
+            thread: get("thread")
+                .as_string()
+                .unwrap_or_else(|| String::from("worker")),
+            stack: get("stack").as_string().unwrap_or_default(),
+            spawn: Spawn::from_js(info),
+        }
+    }
+
+    /// Renders the panic for failure output, like libtest but with the stack
+    /// it happened at, optionally colored.
+    fn render(&self, colors: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if colors {
+                format!("\x1b[{code}m{text}\x1b[0m")
+            } else {
+                text.to_string()
+            }
+        };
+        let mut out = format!("thread '{}' ", self.thread);
+        if let Some(spawn) = &self.spawn {
+            out.push_str(&format!("(worker #{}) ", spawn.id));
+        }
+        out.push_str(&paint("1;31", "panicked"));
+        if let Some((file, line, column)) = &self.location {
+            out.push_str(&format!(
+                " at {}",
+                paint("36", &format!("{file}:{line}:{column}"))
+            ));
+        }
+        out.push_str(":\n");
+        // The diffs of `assert_js_eq!` are already colored.
+        if self.message.contains('\x1b') {
+            out.push_str(&self.message);
+        } else {
+            out.push_str(&paint("1", &self.message));
+        }
+        out.push('\n');
+        if !self.stack.is_empty() {
+            out.push_str("stack:\n");
+            out.push_str(&tab(&paint("2", &self.stack)));
+        }
+        if let Some(spawn) = &self.spawn {
+            out.push_str(&spawn.render());
+        }
+        out
+    }
+
+    /// Hands the panic to the `__wbg_report_panic` function the runner may
+    /// define on the global object, returning whether it did.
+    fn report(&self) -> bool {
+        let global = js_sys::global();
+        let report = js_sys::Reflect::get(&global, &"__wbg_report_panic".into());
+        let Some(report) = report.ok().and_then(|f| f.dyn_into::<Function>().ok()) else {
+            return false;
+        };
+        let (file, line, column) = match &self.location {
+            Some((file, line, column)) => (
+                JsValue::from_str(file),
+                JsValue::from(*line),
+                JsValue::from(*column),
+            ),
+            None => (JsValue::UNDEFINED, JsValue::UNDEFINED, JsValue::UNDEFINED),
+        };
+        let info = js_sys::Object::new();
+        for (key, value) in [
+            ("message", JsValue::from_str(&self.message)),
+            ("file", file),
+            ("line", line),
+            ("column", column),
+            ("thread", JsValue::from_str(&self.thread)),
+            ("stack", JsValue::from_str(&self.stack)),
+        ] {
+            let _ = js_sys::Reflect::set(&info, &key.into(), &value);
+        }
+        let _ = report.call1(&JsValue::UNDEFINED, &info);
+        true
+    }
+}
+
+/// Where a worker spawned by a test was spawned, as the runner reports it
+/// along with the panics and errors of the worker.
+#[derive(Clone)]
+struct Spawn {
+    /// Id the runner generated for the worker, which correlates its reports
+    /// with where it was spawned.
+    id: String,
+    /// The JS stack trace of the page where the worker was spawned.
+    stack: String,
+}
+
+impl Spawn {
+    fn from_js(info: &JsValue) -> Option<Spawn> {
+        let get = |key: &str| js_sys::Reflect::get(info, &key.into()).ok()?.as_string();
+        Some(Spawn {
+            id: get("worker")?,
+            stack: get("spawned_at").unwrap_or_default(),
+        })
+    }
+
+    fn render(&self) -> String {
+        format!("worker #{} spawned at:\n{}", self.id, tab(&self.stack))
+    }
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some((file, line, column)) => write!(f, "panicked at {file}:{line}:{column}:")?,
+            None => write!(f, "panicked:")?,
+        }
+        write!(f, "\n{}", self.message)
+    }
 }
 
 enum TestResult {
@@ -235,14 +653,29 @@ impl Display for TestResult {
     }
 }
 
+/// Marks the lines of output which the runner relays to stderr rather than
+/// stdout.
+const STDERR_MARKER: char = '\u{1e}';
+
 trait Formatter {
     /// Writes a line of output, typically status information.
     fn writeln(&self, line: &str);
 
-    /// Log the result of a test, either passing or failing.
-    fn log_test(&self, is_bench: bool, name: &str, result: &TestResult) {
+    /// Writes a line of output meant for stderr, by default marking each of
+    /// its lines with [`STDERR_MARKER`] for the runner to relay them there.
+    fn writeln_stderr(&self, line: &str) {
+        let lines: Vec<_> = line
+            .split('\n')
+            .map(|line| format!("{STDERR_MARKER}{line}"))
+            .collect();
+        self.writeln(&lines.join("\n"));
+    }
+
+    /// Log the result of a test, either passing or failing, followed by the
+    /// `time` it took if reported.
+    fn log_test(&self, is_bench: bool, name: &str, result: &TestResult, time: &str) {
         if !is_bench {
-            self.writeln(&format!("test {} ... {}", name, result));
+            self.writeln(&format!("test {} ... {}{}", name, result, time));
         }
     }
 
@@ -261,6 +694,12 @@ extern "C" {
     #[doc(hidden)]
     pub fn js_console_error(s: &str);
 
+    #[wasm_bindgen(js_namespace = console, js_name = profile)]
+    fn console_profile(label: &str);
+
+    #[wasm_bindgen(js_namespace = console, js_name = profileEnd)]
+    fn console_profile_end(label: &str);
+
     // General-purpose conversion into a `String`.
     #[wasm_bindgen(js_name = String)]
     fn stringify(val: &JsValue) -> String;
@@ -288,6 +727,46 @@ pub fn console_error(args: &fmt::Arguments) {
     js_console_error(&args.to_string());
 }
 
+/// Installs the panic hook, which keeps the panics of tests for their failure
+/// output and reports the rest to the runner or logs them.
+fn install_panic_hook() {
+    fn panic_handling(panic: Panic) {
+        break_on_panic();
+        if CURRENT_OUTPUT.is_set() {
+            CURRENT_OUTPUT.with(|output| {
+                output.borrow_mut().panic.get_or_insert(panic);
+            });
+        } else if !panic.report() {
+            js_console_error(&format!("{panic}\n\nStack:\n\n{}\n\n", panic.stack));
+        }
+    }
+    #[cfg(feature = "std")]
+    static SET_HOOK: std::sync::Once = std::sync::Once::new();
+    #[cfg(feature = "std")]
+    SET_HOOK.call_once(|| {
+        std::panic::set_hook(Box::new(|panic_info| {
+            panic_handling(Panic::new(&panic_info.to_string(), panic_info.location()));
+        }));
+    });
+    #[cfg(not(feature = "std"))]
+    #[panic_handler]
+    fn panic_handler(panic_info: &core::panic::PanicInfo<'_>) -> ! {
+        panic_handling(Panic::new(&panic_info.to_string(), panic_info.location()));
+        unreachable!();
+    }
+}
+
+/// Calls the `__wbg_break_on_panic` function the runner defines on the global
+/// object in interactive runs with `--break-on-panic`, which pauses in the
+/// debugger while the frames of the panic are still on the stack.
+fn break_on_panic() {
+    let global = js_sys::global();
+    let f = js_sys::Reflect::get(&global, &"__wbg_break_on_panic".into());
+    if let Some(f) = f.ok().and_then(|f| f.dyn_into::<Function>().ok()) {
+        let _ = f.call0(&JsValue::UNDEFINED);
+    }
+}
+
 #[wasm_bindgen(js_class = WasmBindgenTestContext)]
 impl Context {
     /// Creates a new context ready to run tests.
@@ -297,53 +776,7 @@ impl Context {
     /// tests.
     #[wasm_bindgen(constructor)]
     pub fn new(is_bench: bool) -> Context {
-        fn panic_handling(mut message: String) {
-            let should_panic = if !CURRENT_OUTPUT.is_set() {
-                false
-            } else {
-                CURRENT_OUTPUT.with(|output| {
-                    let mut output = output.borrow_mut();
-                    output.panic.push_str(&message);
-                    output.should_panic
-                })
-            };
-
-            // See https://github.com/rustwasm/console_error_panic_hook/blob/4dc30a5448ed3ffcfb961b1ad54d000cca881b84/src/lib.rs#L83-L123.
-            if !should_panic {
-                #[wasm_bindgen]
-                extern "C" {
-                    type Error;
-
-                    #[wasm_bindgen(constructor)]
-                    fn new() -> Error;
-
-                    #[wasm_bindgen(method, getter)]
-                    fn stack(error: &Error) -> String;
-                }
-
-                message.push_str("\n\nStack:\n\n");
-                let e = Error::new();
-                message.push_str(&e.stack());
-
-                message.push_str("\n\n");
-
-                js_console_error(&message);
-            }
-        }
-        #[cfg(feature = "std")]
-        static SET_HOOK: std::sync::Once = std::sync::Once::new();
-        #[cfg(feature = "std")]
-        SET_HOOK.call_once(|| {
-            std::panic::set_hook(Box::new(|panic_info| {
-                panic_handling(panic_info.to_string());
-            }));
-        });
-        #[cfg(not(feature = "std"))]
-        #[panic_handler]
-        fn panic_handler(panic_info: &core::panic::PanicInfo<'_>) -> ! {
-            panic_handling(panic_info.to_string());
-            unreachable!();
-        }
+        install_panic_hook();
 
         let formatter = match detect::detect() {
             detect::Runtime::Browser => Box::new(browser::Browser::new()) as Box<dyn Formatter>,
@@ -361,10 +794,39 @@ impl Context {
                 succeeded_count: Default::default(),
                 filtered_count: Default::default(),
                 ignored_count: Default::default(),
+                fail_on_unhandled_rejection: Default::default(),
+                rejections: Default::default(),
+                fail_on_uncaught_error: Default::default(),
+                uncaught_errors: Default::default(),
+                split_console_output: Default::default(),
+                console_colors: Default::default(),
+                colors: Default::default(),
+                console_inspect: Default::default(),
+                time_limits: Default::default(),
+                slow_tests: Default::default(),
+                cpu_profile: Default::default(),
+                memory_threshold: Default::default(),
+                memory_growths: Default::default(),
+                leak_counter: Default::default(),
+                instruction_counter: Default::default(),
+                leaky_tests: Default::default(),
+                max_test_output_lines: Default::default(),
+                max_output_lines: Default::default(),
+                output_lines: Default::default(),
+                suppressed_output: Default::default(),
+                suppressed_output_path: Default::default(),
                 remaining: Default::default(),
                 running: Default::default(),
                 formatter,
                 timer,
+                hooks: Default::default(),
+                executor: Default::default(),
+                generators: Default::default(),
+                contexts: Default::default(),
+                filter: Default::default(),
+                report: Default::default(),
+                suites: Default::default(),
+                cases: Default::default(),
             }),
         }
     }
@@ -374,11 +836,163 @@ impl Context {
         self.state.include_ignored.set(include_ignored);
     }
 
+    /// Registers the setup and teardown hooks of test modules, the executor
+    /// of the tests, the generators of dynamic tests and the shared test
+    /// contexts by calling their `__wbgh_*` exports, whose names the test
+    /// runner collects.
+    pub fn hooks(&self, exports: Vec<String>) {
+        let wasm = wasm_bindgen::exports();
+        let cx_arg = (self as *const Context as u32).into();
+        for export in exports {
+            let hook = js_sys::Reflect::get(&wasm, &JsValue::from_str(&export))
+                .and_then(|hook| Function::from(hook).call1(&JsValue::null(), &cx_arg));
+            if let Err(e) = hook {
+                panic!(
+                    "exception thrown while registering `{export}`: {}",
+                    self.state.formatter.stringify_error(&e)
+                );
+            }
+        }
+    }
+
+    /// Forwards the filters of the runner, to apply them to dynamic tests.
+    pub fn filter(
+        &mut self,
+        filter: Option<String>,
+        skip: Vec<String>,
+        exact: bool,
+        ignored: bool,
+    ) {
+        self.state.filter.replace(TestFilter {
+            filter,
+            skip,
+            exact,
+            only_ignored: ignored,
+        });
+    }
+
     /// Handle filter argument.
     pub fn filtered_count(&mut self, filtered: usize) {
         self.state.filtered_count.set(filtered);
     }
 
+    /// Handle the `--format json` and `--format junit` options, reporting
+    /// the tests of `names` in the `suites` paired with them.
+    pub fn report_format(&mut self, format: &str, names: Vec<String>, suites: Vec<String>) {
+        if let Some(format) = report::Format::from_name(format) {
+            self.state.report.set(format);
+        }
+        *self.state.suites.borrow_mut() = names.into_iter().zip(suites).collect();
+    }
+
+    /// Handle the `fail_on_unhandled_rejection` option.
+    pub fn fail_on_unhandled_rejection(&mut self, enabled: bool) {
+        self.state.fail_on_unhandled_rejection.set(enabled);
+    }
+
+    /// Handler for promise rejections without a handler, which fail the
+    /// running test with `fail_on_unhandled_rejection`.
+    pub fn unhandled_rejection(&self, reason: JsValue) {
+        if self.state.fail_on_unhandled_rejection.get() {
+            self.state.rejections.borrow_mut().push(reason);
+        }
+    }
+
+    /// Handle the `--report-time` and `--ensure-time` options, reporting how
+    /// long each test took. Tests taking more than `warn_ms` are listed in a
+    /// warning, and with `ensure` the tests taking more than `critical_ms` fail.
+    pub fn time_limits(&mut self, ensure: bool, warn_ms: u32, critical_ms: u32) {
+        self.state.time_limits.set(Some(TimeLimits {
+            ensure,
+            warn_ms: f64::from(warn_ms),
+            critical_ms: f64::from(critical_ms),
+        }));
+    }
+
+    /// Handle the `--cpu-profile` option, recording a CPU profile of each test
+    /// with `console.profile` for the runner to save.
+    pub fn cpu_profile(&mut self, enabled: bool) {
+        self.state.cpu_profile.set(enabled);
+    }
+
+    /// Handle the `--report-memory` and `--memory-threshold` options, reporting
+    /// how much each test grew the Wasm memory and the JS heap. Tests growing
+    /// either by more than `threshold` bytes are listed in a warning.
+    pub fn memory_threshold(&mut self, threshold: f64) {
+        self.state.memory_threshold.set(Some(threshold));
+    }
+
+    /// Handle the `--detect-leaks` option, listing the tests that leave DOM
+    /// elements, timers, event listeners or handles behind. `counter` returns
+    /// an array of how many of each of the first three are held, then the
+    /// handles alive, as listed by the bindings' `__wbg_live_handles`.
+    pub fn detect_leaks(&mut self, counter: Function) {
+        *self.state.leak_counter.borrow_mut() = Some(counter);
+    }
+
+    /// Handle the `--count-instructions` option, reporting how many Wasm
+    /// instructions each test executed. `counter` returns how many were
+    /// executed so far, from the instrumentation of the runner.
+    pub fn count_instructions(&mut self, counter: Function) {
+        *self.state.instruction_counter.borrow_mut() = Some(counter);
+    }
+
+    /// Handle the `fail_on_uncaught_error` option.
+    pub fn fail_on_uncaught_error(&mut self, enabled: bool) {
+        self.state.fail_on_uncaught_error.set(enabled);
+    }
+
+    /// Colors the reports of panics and the diffs of failed assertions in
+    /// failure output, as the runner does when printing to a terminal.
+    pub fn colors(&mut self, enabled: bool) {
+        self.state.colors.set(enabled);
+        assert::COLORS.store(enabled, core::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Handle the `--split-console-output` option, coloring the captured
+    /// output by level if `colors` is set.
+    pub fn split_console_output(&mut self, colors: bool) {
+        self.state.split_console_output.set(true);
+        self.state.console_colors.set(colors);
+    }
+
+    /// Handle the `--console-depth` and `--console-max-length` options, which
+    /// render the objects passed to `console` methods like `util.inspect`.
+    pub fn console_inspect(&mut self, depth: Option<u32>, max_length: Option<u32>) {
+        let default = inspect::Options::default();
+        self.state.console_inspect.set(Some(inspect::Options {
+            depth: depth.map_or(default.depth, |depth| depth as usize),
+            max_length: max_length.map_or(default.max_length, |max| max as usize),
+        }));
+    }
+
+    /// Handle the `--max-test-output-lines` and `--max-output-lines` options,
+    /// `0` lifting a limit, and where the runner saves the output they
+    /// suppress.
+    pub fn output_limits(&mut self, per_test: Option<u32>, total: Option<u32>, path: String) {
+        let limit = |max: Option<u32>, cell: &Cell<Option<usize>>| match max {
+            Some(0) => cell.set(None),
+            Some(max) => cell.set(Some(max as usize)),
+            None => (),
+        };
+        limit(per_test, &self.state.max_test_output_lines);
+        limit(total, &self.state.max_output_lines);
+        *self.state.suppressed_output_path.borrow_mut() = Some(path);
+    }
+
+    /// Failure output suppressed by the limits of `output_limits`, if any.
+    pub fn suppressed_output(&self) -> Option<String> {
+        let output = self.state.suppressed_output.borrow();
+        (!output.is_empty()).then(|| output.clone())
+    }
+
+    /// Handler for uncaught errors, like those of `window.onerror` or of
+    /// workers, which are included in the output of the running test and fail
+    /// it with `fail_on_uncaught_error`.
+    pub fn uncaught_error(&self, error: JsValue) {
+        self.state.uncaught_errors.borrow_mut().push(error);
+    }
+
     /// Executes a list of tests, returning a promise representing their
     /// eventual completion.
     ///
@@ -389,42 +1003,121 @@ impl Context {
     /// The promise returned resolves to either `true` if all tests passed or
     /// `false` if at least one test failed.
     pub fn run(&self, tests: Vec<JsValue>) -> Promise {
-        if !self.state.is_bench {
-            let noun = if tests.len() == 1 { "test" } else { "tests" };
-            self.state
-                .formatter
-                .writeln(&format!("running {} {}", tests.len(), noun));
-        }
+        let cx_arg: JsValue = (self as *const Context as u32).into();
+        let state = AssertUnwindSafe(self.state.clone());
+        future_to_promise(async move {
+            // Tests and generators can rely on the shared contexts.
+            if !state.init_contexts(tests.len()).await {
+                return Ok(JsValue::FALSE);
+            }
 
-        // Execute all our test functions through their Wasm shims (unclear how
-        // to pass native function pointers around here). Each test will
-        // execute one of the `execute_*` tests below which will push a
-        // future onto our `remaining` list, which we'll process later.
-        let cx_arg = (self as *const Context as u32).into();
-        for test in tests {
-            match Function::from(test).call1(&JsValue::null(), &cx_arg) {
-                Ok(_) => {}
-                Err(e) => {
-                    panic!(
-                        "exception thrown while creating a test: {}",
-                        self.state.formatter.stringify_error(&e)
-                    );
+            // Dynamic tests are generated first, to count them in.
+            let dynamic = state.generate().await;
+            if !state.is_bench {
+                let count = tests.len() + dynamic.len();
+                match state.report.get() {
+                    report::Format::Pretty => {
+                        let noun = if count == 1 { "test" } else { "tests" };
+                        state.formatter.writeln(&format!("running {count} {noun}"));
+                    }
+                    report::Format::Json => state.formatter.writeln(&report::started(count)),
+                    report::Format::Junit => {}
                 }
             }
-        }
 
-        // Now that we've collected all our tests we wrap everything up in a
-        // future to actually do all the processing, and pass it out to JS as a
-        // `Promise`.
-        let state = AssertUnwindSafe(self.state.clone());
-        future_to_promise(async {
+            // Execute all our test functions through their Wasm shims (unclear
+            // how to pass native function pointers around here). Each test
+            // will execute one of the `execute_*` tests below which will push
+            // a future onto our `remaining` list, which we'll process later.
+            for test in tests {
+                match Function::from(test).call1(&JsValue::null(), &cx_arg) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        panic!(
+                            "exception thrown while creating a test: {}",
+                            state.formatter.stringify_error(&e)
+                        );
+                    }
+                }
+            }
+            for (module, name, test) in dynamic {
+                state.execute(&module, &name, test, None, None, None, &[]);
+            }
+
+            // Now that we've collected all our tests, process them.
             let passed = ExecuteTests(state).await;
             Ok(JsValue::from(passed))
         })
     }
 }
 
-crate::scoped_thread_local!(static CURRENT_OUTPUT: RefCell<Output>);
+crate::scoped_thread_local!(static CURRENT_OUTPUT: Rc<RefCell<Output>>);
+
+/// Captured output of a test, handed to JS to attribute output arriving later,
+/// like the logs of workers spawned by the test, to that test.
+#[wasm_bindgen(js_name = WasmBindgenTestOutput)]
+pub struct TestOutput(Rc<RefCell<Output>>);
+
+/// Returns the captured output of the running test, if any.
+#[wasm_bindgen]
+pub fn __wbgtest_current_output() -> Option<TestOutput> {
+    if !CURRENT_OUTPUT.is_set() {
+        return None;
+    }
+
+    Some(CURRENT_OUTPUT.with(|output| TestOutput(output.clone())))
+}
+
+/// Calls `f`, capturing its console output into `output` rather than into
+/// that of the running test.
+#[wasm_bindgen]
+pub fn __wbgtest_with_output(output: &TestOutput, f: &Function) -> Result<JsValue, JsValue> {
+    CURRENT_OUTPUT.set(&output.0, || f.call0(&JsValue::UNDEFINED))
+}
+
+/// Installs the panic hook in an instance of the tests' module that doesn't run
+/// them, like one instantiated by a worker, for the runner to forward its
+/// panics.
+#[wasm_bindgen]
+pub fn __wbgtest_install_panic_hook() {
+    install_panic_hook();
+}
+
+/// Fails the test of `output` with the panic of a worker it spawned, which the
+/// runner forwards from the panic hook of the worker.
+#[wasm_bindgen]
+pub fn __wbgtest_worker_panic(output: &TestOutput, panic: JsValue) {
+    let mut output = output.0.borrow_mut();
+    output.panic.get_or_insert_with(|| Panic::from_js(&panic));
+    output.worker_panicked = true;
+    if let Some(waker) = output.waker.take() {
+        waker.wake();
+    }
+}
+
+/// Records an uncaught error of a worker spawned by the test of `output`,
+/// which the runner forwards with where the worker was spawned. Whether it
+/// fails the test is up to the error the page sees as well.
+#[wasm_bindgen]
+pub fn __wbgtest_worker_error(output: &TestOutput, error: JsValue) {
+    let text = js_sys::Reflect::get(&error, &"error".into())
+        .ok()
+        .and_then(|text| text.as_string())
+        .unwrap_or_default();
+    let mut output = output.0.borrow_mut();
+    match Spawn::from_js(&error) {
+        Some(spawn) => {
+            output
+                .uncaught
+                .push_str(&format!("worker #{}: {text}\n", spawn.id));
+            output.uncaught.push_str(&spawn.render());
+        }
+        None => {
+            output.uncaught.push_str(&text);
+            output.uncaught.push('\n');
+        }
+    }
+}
 
 /// Handler for `console.log` invocations.
 ///
@@ -467,21 +1160,89 @@ pub fn __wbgtest_console_error(args: &Array) {
     record(args, |output| &mut output.error)
 }
 
+/// Handler for `console.group` and `console.groupCollapsed` invocations,
+/// which log their label and indent the output until `console.groupEnd`.
+#[wasm_bindgen]
+pub fn __wbgtest_console_group(args: &Array) {
+    if args.length() != 0 {
+        record(args, |output| &mut output.log);
+    }
+    if CURRENT_OUTPUT.is_set() {
+        CURRENT_OUTPUT.with(|output| output.borrow_mut().group_depth += 1);
+    }
+}
+
+/// Handler for `console.groupEnd` invocations. See above.
+#[wasm_bindgen]
+pub fn __wbgtest_console_group_end(_args: &Array) {
+    if CURRENT_OUTPUT.is_set() {
+        CURRENT_OUTPUT.with(|output| {
+            let mut output = output.borrow_mut();
+            output.group_depth = output.group_depth.saturating_sub(1);
+        });
+    }
+}
+
+/// Handler for `console.table` invocations, which renders its data as a table.
+#[wasm_bindgen]
+pub fn __wbgtest_console_table(args: &Array) {
+    match inspect::table(&args.get(0), &args.get(1)) {
+        Some(table) => record_text(&table, |output| &mut output.log),
+        None => record(args, |output| &mut output.log),
+    }
+}
+
+/// Handler for `console.dir` invocations, which renders the properties of
+/// its object.
+#[wasm_bindgen]
+pub fn __wbgtest_console_dir(args: &Array) {
+    if !CURRENT_OUTPUT.is_set() {
+        return;
+    }
+
+    let options = CURRENT_OUTPUT.with(|output| output.borrow().inspect.unwrap_or_default());
+    record_text(&inspect::inspect(&args.get(0), &options), |output| {
+        &mut output.log
+    })
+}
+
 fn record(args: &Array, dst: impl FnOnce(&mut Output) -> &mut String) {
     if !CURRENT_OUTPUT.is_set() {
         return;
     }
 
+    let options = CURRENT_OUTPUT.with(|output| output.borrow().inspect);
+    let mut text = String::new();
+    args.for_each(&mut |val, idx, _array| {
+        if idx != 0 {
+            text.push(' ');
+        }
+        match &options {
+            Some(options) if val.as_string().is_none() => {
+                text.push_str(&inspect::inspect(&val, options))
+            }
+            _ => text.push_str(&stringify(&val)),
+        }
+    });
+    record_text(&text, dst);
+}
+
+/// Appends `text` as lines of the output of the current test, indented by its
+/// open console groups.
+fn record_text(text: &str, dst: impl FnOnce(&mut Output) -> &mut String) {
+    if !CURRENT_OUTPUT.is_set() {
+        return;
+    }
+
     CURRENT_OUTPUT.with(|output| {
         let mut out = output.borrow_mut();
+        let indent = "  ".repeat(out.group_depth);
         let dst = dst(&mut out);
-        args.for_each(&mut |val, idx, _array| {
-            if idx != 0 {
-                dst.push(' ');
-            }
-            dst.push_str(&stringify(&val));
-        });
-        dst.push('\n');
+        for line in text.split('\n') {
+            dst.push_str(&indent);
+            dst.push_str(line);
+            dst.push('\n');
+        }
     });
 }
 
@@ -512,8 +1273,17 @@ impl Context {
         f: impl 'static + FnOnce() -> T,
         should_panic: Option<Option<&'static str>>,
         ignore: Option<Option<&'static str>>,
+        timeout_ms: Option<u32>,
+        requires: &'static [&'static str],
     ) {
-        self.execute(name, async { f().into_js_result() }, should_panic, ignore);
+        self.execute(
+            name,
+            async { f().into_js_result() },
+            should_panic,
+            ignore,
+            timeout_ms,
+            requires,
+        );
     }
 
     /// Entry point for an asynchronous in wasm. The
@@ -525,6 +1295,8 @@ impl Context {
         f: impl FnOnce() -> F + 'static,
         should_panic: Option<Option<&'static str>>,
         ignore: Option<Option<&'static str>>,
+        timeout_ms: Option<u32>,
+        requires: &'static [&'static str],
     ) where
         F: Future + 'static,
         F::Output: Termination,
@@ -534,50 +1306,120 @@ impl Context {
             async { f().await.into_js_result() },
             should_panic,
             ignore,
+            timeout_ms,
+            requires,
         )
     }
 
+    /// Registers the setup hook of the tests of `module`, run before each of
+    /// them. The `#[wasm_bindgen_test_setup]` macro generates invocations of
+    /// this method.
+    pub fn setup<F>(&self, module: &str, f: impl Fn() -> F + 'static)
+    where
+        F: Future + 'static,
+        F::Output: Termination,
+    {
+        let hook: Hook = Rc::new(move || {
+            let future = f();
+            Box::pin(async { future.await.into_js_result() })
+        });
+        let mut hooks = self.state.hooks.borrow_mut();
+        let hooks = hooks.entry(module.to_string()).or_default();
+        if hooks.setup.replace(hook).is_some() {
+            wasm_bindgen::throw_str(&format!("`{module}` has more than one setup hook"));
+        }
+    }
+
+    /// Registers the teardown hook of the tests of `module`, run after each of
+    /// them. The `#[wasm_bindgen_test_teardown]` macro generates invocations
+    /// of this method.
+    pub fn teardown<F>(&self, module: &str, f: impl Fn() -> F + 'static)
+    where
+        F: Future + 'static,
+        F::Output: Termination,
+    {
+        let hook: Hook = Rc::new(move || {
+            let future = f();
+            Box::pin(async { future.await.into_js_result() })
+        });
+        let mut hooks = self.state.hooks.borrow_mut();
+        let hooks = hooks.entry(module.to_string()).or_default();
+        if hooks.teardown.replace(hook).is_some() {
+            wasm_bindgen::throw_str(&format!("`{module}` has more than one teardown hook"));
+        }
+    }
+
+    /// Registers a generator of dynamic tests, at `path`. The
+    /// `#[wasm_bindgen_test_generator]` macro generates invocations of this
+    /// method.
+    pub fn generator<F>(&self, path: &str, f: impl FnOnce() -> F + 'static)
+    where
+        F: Future + 'static,
+        F::Output: IntoIterator<Item = DynamicTest>,
+    {
+        let generator: Generator = Box::new(move || {
+            let future = f();
+            Box::pin(async { future.await.into_iter().collect() })
+        });
+        self.state
+            .generators
+            .borrow_mut()
+            .push((path.to_string(), generator));
+    }
+
+    /// Registers the initializer of the shared test context at `path`. The
+    /// `#[wasm_bindgen_test_context]` macro generates invocations of this
+    /// method.
+    pub fn context<F>(&self, path: &str, f: impl FnOnce() -> F + 'static)
+    where
+        F: Future + 'static,
+        F::Output: Termination,
+    {
+        let initializer: Initializer = Box::new(move || {
+            let future = f();
+            Box::pin(async { future.await.into_js_result() })
+        });
+        self.state
+            .contexts
+            .borrow_mut()
+            .push((path.to_string(), initializer));
+    }
+
+    /// Registers the executor of the tests. The `#[wasm_bindgen_test_executor]`
+    /// macro generates invocations of this method.
+    pub fn executor(&self, executor: impl TestExecutor + 'static) {
+        if self
+            .state
+            .executor
+            .replace(Some(Rc::new(executor)))
+            .is_some()
+        {
+            wasm_bindgen::throw_str("more than one test executor is registered");
+        }
+    }
+
     fn execute(
         &self,
         name: &str,
         test: impl Future<Output = Result<(), JsValue>> + 'static,
         should_panic: Option<Option<&'static str>>,
         ignore: Option<Option<&'static str>>,
+        timeout_ms: Option<u32>,
+        requires: &'static [&'static str],
     ) {
+        let module = name.rsplit_once("::").map_or("", |(module, _)| module);
         // Remove the crate name to mimic libtest more closely.
         // This also removes our `__wbgt_` or `__wbgb_` prefix and the `ignored` and `should_panic` modifiers.
         let name = name.split_once("::").unwrap().1;
-
-        if let Some(ignore) = ignore {
-            if !self.state.include_ignored.get() {
-                self.state.formatter.log_test(
-                    self.state.is_bench,
-                    name,
-                    &TestResult::Ignored(ignore.map(str::to_owned)),
-                );
-                let ignored = self.state.ignored_count.get();
-                self.state.ignored_count.set(ignored + 1);
-                return;
-            }
-        }
-
-        // Looks like we've got a test that needs to be executed! Push it onto
-        // the list of remaining tests.
-        let output = Output {
-            should_panic: should_panic.is_some(),
-            ..Default::default()
-        };
-        let output = Rc::new(RefCell::new(output));
-        let future = TestFuture {
-            output: output.clone(),
+        self.state.execute(
+            module,
+            name,
             test,
-        };
-        self.state.remaining.borrow_mut().push(Test {
-            name: name.to_string(),
-            future: Pin::from(Box::new(future)),
-            output,
             should_panic,
-        });
+            ignore,
+            timeout_ms,
+            requires,
+        );
     }
 }
 
@@ -610,6 +1452,19 @@ impl Future for ExecuteTests {
                 Some(test) => test,
                 None => break,
             };
+            test.output.borrow_mut().started = performance_now();
+            if self.0.memory_threshold.get().is_some() {
+                test.output.borrow_mut().memory = Some(memory::Usage::now());
+            }
+            if let Some(counter) = &*self.0.leak_counter.borrow() {
+                test.output.borrow_mut().held = leaks::Counts::now(counter);
+            }
+            if let Some(counter) = &*self.0.instruction_counter.borrow() {
+                test.output.borrow_mut().instructions = instructions(counter);
+            }
+            if self.0.cpu_profile.get() {
+                console_profile(&test.name);
+            }
             let result = match test.future.as_mut().poll(cx) {
                 Poll::Ready(result) => result,
                 Poll::Pending => {
@@ -637,18 +1492,314 @@ impl Future for ExecuteTests {
 }
 
 impl State {
+    /// Initializes the shared test contexts, capturing their output like that
+    /// of tests. If one fails, it's reported as the only failure instead of
+    /// running any of the `count` tests.
+    async fn init_contexts(&self, count: usize) -> bool {
+        let executor = self.executor.borrow().clone();
+        for (path, initializer) in self.contexts.take() {
+            let output = Output {
+                inspect: self.console_inspect.get(),
+                ..Default::default()
+            };
+            let output = Rc::new(RefCell::new(output));
+            let init = initializer();
+            let init = TestFuture {
+                output: output.clone(),
+                test: match &executor {
+                    Some(executor) => executor.run(init),
+                    None => init,
+                },
+            };
+            if let Err(e) = init.await {
+                // Remove the crate name, like for tests.
+                let name = path
+                    .split_once("::")
+                    .map_or(path.as_str(), |(_, path)| path);
+                let noun = if count == 1 { "test" } else { "tests" };
+                self.write_status(&format!(
+                    "error: the test context `{name}` failed to initialize, so none of the \
+                     {count} {noun} ran"
+                ));
+                let module = path.rsplit_once("::").map_or("", |(module, _)| module);
+                let test = Test {
+                    name: name.to_string(),
+                    suite: report::default_suite(module).to_string(),
+                    future: Box::pin(async { Ok(()) }),
+                    output,
+                    should_panic: None,
+                };
+                self.failures.borrow_mut().push((test, Failure::Error(e)));
+                self.print_results();
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Runs the generators of dynamic tests, returning the module, name and
+    /// future of the tests left after filtering.
+    async fn generate(
+        &self,
+    ) -> Vec<(
+        String,
+        String,
+        Pin<Box<dyn Future<Output = Result<(), JsValue>>>>,
+    )> {
+        let generators = self.generators.take();
+        let mut tests = Vec::new();
+        for (path, generator) in generators {
+            let module = path.rsplit_once("::").map_or("", |(module, _)| module);
+            // Remove the crate name, like for other tests.
+            let prefix = path
+                .split_once("::")
+                .map_or(path.as_str(), |(_, path)| path);
+            for test in generator().await {
+                let name = format!("{prefix}::{}", test.name);
+                if self.filter.borrow().matches(&name) {
+                    tests.push((module.to_string(), name, test.test));
+                } else {
+                    self.filtered_count.set(self.filtered_count.get() + 1);
+                }
+            }
+        }
+        tests
+    }
+
+    /// Pushes `test`, named `name` without its crate, onto the remaining
+    /// tests, with the hooks of `module`.
+    fn execute(
+        &self,
+        module: &str,
+        name: &str,
+        test: impl Future<Output = Result<(), JsValue>> + 'static,
+        should_panic: Option<Option<&'static str>>,
+        ignore: Option<Option<&'static str>>,
+        timeout_ms: Option<u32>,
+        requires: &'static [&'static str],
+    ) {
+        let hooks = self.hooks.borrow().get(module).cloned().unwrap_or_default();
+        let suite = self.suites.borrow().get(name).cloned();
+        let suite = suite.unwrap_or_else(|| report::default_suite(module).to_string());
+
+        if let Some(ignore) = ignore {
+            if !self.include_ignored.get() {
+                self.log_test(
+                    name,
+                    &suite,
+                    &TestResult::Ignored(ignore.map(str::to_owned)),
+                    "",
+                    None,
+                );
+                let ignored = self.ignored_count.get();
+                self.ignored_count.set(ignored + 1);
+                return;
+            }
+        }
+
+        // Looks like we've got a test that needs to be executed! Push it onto
+        // the list of remaining tests.
+        let output = Output {
+            inspect: self.console_inspect.get(),
+            ..Default::default()
+        };
+        let output = Rc::new(RefCell::new(output));
+        let fail_on_unhandled =
+            self.fail_on_unhandled_rejection.get() || self.fail_on_uncaught_error.get();
+        let test_output = output.clone();
+        let executor = self.executor.borrow().clone();
+        let test: Pin<Box<dyn Future<Output = Result<(), JsValue>>>> = Box::pin(async move {
+            if let Some(setup) = hooks.setup {
+                // Recorded up front, as a panicking setup doesn't return.
+                test_output.borrow_mut().failed_hook = Some("setup");
+                setup().await?;
+                test_output.borrow_mut().failed_hook = None;
+            }
+            match timeout_ms {
+                Some(timeout_ms) => with_timeout(test, timeout_ms, &test_output).await,
+                None => test.await,
+            }
+        });
+        let run = move |test| match &executor {
+            Some(executor) => executor.run(test),
+            None => test,
+        };
+        let test = TestFuture {
+            output: output.clone(),
+            test: run(test),
+        };
+        let teardown_output = output.clone();
+        let future = async move {
+            // Tests requiring a capability that's missing are skipped.
+            if let Some(capability) = capability::missing(requires).await {
+                teardown_output.borrow_mut().unsupported = Some(capability);
+                return Ok(());
+            }
+            let mut result = test.await;
+            // Nothing is left to tear down if the setup failed, and a worker
+            // panic fails the teardown as well.
+            let skip_teardown = {
+                let output = teardown_output.borrow();
+                output.failed_hook.is_some() || output.worker_panicked
+            };
+            if let (Some(teardown), false) = (hooks.teardown, skip_teardown) {
+                teardown_output.borrow_mut().failed_hook = Some("teardown");
+                let teardown = TestFuture {
+                    output: teardown_output.clone(),
+                    test: run(teardown()),
+                };
+                match teardown.await {
+                    Ok(()) => teardown_output.borrow_mut().failed_hook = None,
+                    // The failure of the test itself takes precedence.
+                    Err(e) => result = result.and(Err(e)),
+                }
+            }
+            // A test that panicked didn't drop its sandboxes.
+            sandbox::close_all();
+            // Rejections and errors are only reported once the microtasks of
+            // the test have run, so give them a chance to be attributed to it.
+            if fail_on_unhandled {
+                next_task().await;
+            }
+            result
+        };
+        self.remaining.borrow_mut().push(Test {
+            name: name.to_string(),
+            suite,
+            future: Pin::from(Box::new(future)),
+            output,
+            should_panic,
+        });
+    }
+
     fn log_test_result(&self, test: Test, result: TestResult) {
+        if self.cpu_profile.get() {
+            console_profile_end(&test.name);
+        }
+
+        let unsupported = test.output.borrow().unsupported;
+        if let Some(capability) = unsupported {
+            let reason = format!("requires `{capability}`, which isn't supported");
+            self.log_test(
+                &test.name,
+                &test.suite,
+                &TestResult::Ignored(Some(reason)),
+                "",
+                None,
+            );
+            self.ignored_count.set(self.ignored_count.get() + 1);
+            return;
+        }
+
+        // How long the test took, if it's reported.
+        let started = test.output.borrow().started;
+        let elapsed = started.and_then(|started| Some(performance_now()? - started));
+        let limits = self.time_limits.get();
+        let duration = limits.and(elapsed);
+        let mut time = match (limits, duration) {
+            (Some(limits), Some(duration)) => {
+                if duration > limits.warn_ms {
+                    self.slow_tests
+                        .borrow_mut()
+                        .push((test.name.clone(), duration));
+                }
+                self.render_time(duration, &limits)
+            }
+            _ => String::new(),
+        };
+        // Followed by how much it grew the memory in use.
+        let usage = test.output.borrow().memory;
+        if let (Some(threshold), Some(usage)) = (self.memory_threshold.get(), usage) {
+            let growth = usage.growth();
+            if growth.exceeds(threshold) {
+                self.memory_growths
+                    .borrow_mut()
+                    .push((test.name.clone(), growth));
+            }
+            time.push_str(&self.render_growth(&growth, threshold));
+        }
+        // And by how many instructions it executed.
+        let started = test.output.borrow().instructions;
+        if let (Some(counter), Some(started)) = (&*self.instruction_counter.borrow(), started) {
+            if let Some(now) = instructions(counter) {
+                time.push_str(&format!(" <{} instructions>", now - started));
+            }
+        }
+
+        let log = |result: &TestResult| {
+            self.log_test(&test.name, &test.suite, result, &time, elapsed);
+        };
+
+        let held = test.output.borrow_mut().held.take();
+        if let (Some(counter), Some(held)) = (&*self.leak_counter.borrow(), held) {
+            let leaked = leaks::Counts::now(counter).and_then(|now| held.leaked(&now));
+            if let Some(leaked) = leaked {
+                self.leaky_tests
+                    .borrow_mut()
+                    .push((test.name.clone(), leaked));
+            }
+        }
+
+        let uncaught_errors = self.uncaught_errors.take();
+        for error in &uncaught_errors {
+            let mut output = test.output.borrow_mut();
+            output
+                .uncaught
+                .push_str(&self.formatter.stringify_error(error));
+            output.uncaught.push('\n');
+        }
+        if !uncaught_errors.is_empty() && self.fail_on_uncaught_error.get() {
+            log(&TestResult::Err(JsValue::NULL));
+            self.failures
+                .borrow_mut()
+                .push((test, Failure::UncaughtError));
+            return;
+        }
+
+        // A test that ran out of time fails, even if it should panic.
+        let timed_out = test.output.borrow().timed_out;
+        if let Some(timeout_ms) = timed_out {
+            log(&TestResult::Err(JsValue::NULL));
+            self.failures
+                .borrow_mut()
+                .push((test, Failure::Timeout(timeout_ms)));
+            return;
+        }
+
+        // So does a test whose setup or teardown failed.
+        let failed_hook = test.output.borrow().failed_hook;
+        if let Some(hook) = failed_hook {
+            let error = match result {
+                TestResult::Err(e) => e,
+                _ => JsValue::NULL,
+            };
+            log(&TestResult::Err(JsValue::NULL));
+            self.failures
+                .borrow_mut()
+                .push((test, Failure::Hook(hook, error)));
+            return;
+        }
+
+        let rejections = self.rejections.take();
+        if !rejections.is_empty() {
+            log(&TestResult::Err(JsValue::NULL));
+            self.failures
+                .borrow_mut()
+                .push((test, Failure::UnhandledRejection(rejections)));
+            return;
+        }
+
         // Save off the test for later processing when we print the final
         // results.
         if let Some(should_panic) = test.should_panic {
             if let TestResult::Err(_e) = result {
                 if let Some(expected) = should_panic {
-                    if !test.output.borrow().panic.contains(expected) {
-                        self.formatter.log_test(
-                            self.is_bench,
-                            &test.name,
-                            &TestResult::Err(JsValue::NULL),
-                        );
+                    let matched = match &test.output.borrow().panic {
+                        Some(panic) => panic.message.contains(expected),
+                        None => expected.is_empty(),
+                    };
+                    if !matched {
+                        log(&TestResult::Err(JsValue::NULL));
                         self.failures
                             .borrow_mut()
                             .push((test, Failure::ShouldPanicExpected));
@@ -656,18 +1807,25 @@ impl State {
                     }
                 }
 
-                self.formatter
-                    .log_test(self.is_bench, &test.name, &TestResult::Ok);
+                log(&TestResult::Ok);
                 self.succeeded_count.set(self.succeeded_count.get() + 1);
             } else {
-                self.formatter
-                    .log_test(self.is_bench, &test.name, &TestResult::Err(JsValue::NULL));
+                log(&TestResult::Err(JsValue::NULL));
                 self.failures
                     .borrow_mut()
                     .push((test, Failure::ShouldPanic));
             }
         } else {
-            self.formatter.log_test(self.is_bench, &test.name, &result);
+            if let (TestResult::Ok, Some(limits), Some(duration)) = (&result, limits, duration) {
+                if limits.ensure && duration > limits.critical_ms {
+                    log(&TestResult::Err(JsValue::NULL));
+                    self.failures
+                        .borrow_mut()
+                        .push((test, Failure::TimeLimit(duration, limits.critical_ms)));
+                    return;
+                }
+            }
+            log(&result);
 
             match result {
                 TestResult::Ok => self.succeeded_count.set(self.succeeded_count.get() + 1),
@@ -677,38 +1835,172 @@ impl State {
         }
     }
 
+    /// Reports the `result` of test `name` of `suite`, followed by its `time`
+    /// in the default format, or by the `elapsed` milliseconds if measured in
+    /// the JSON and JUnit ones.
+    fn log_test(
+        &self,
+        name: &str,
+        suite: &str,
+        result: &TestResult,
+        time: &str,
+        elapsed: Option<f64>,
+    ) {
+        match self.report.get() {
+            report::Format::Pretty => self.formatter.log_test(self.is_bench, name, result, time),
+            _ if self.is_bench => {}
+            report::Format::Json => {
+                let event = report::test_event(name, suite, result, elapsed);
+                self.formatter.writeln(&event);
+            }
+            report::Format::Junit => self
+                .cases
+                .borrow_mut()
+                .entry(suite.to_string())
+                .or_default()
+                .push(report::Case::new(name, result, elapsed)),
+        }
+    }
+
+    /// Writes a line of status information, to stderr when stdout is kept for
+    /// the JSON or JUnit report.
+    fn write_status(&self, line: &str) {
+        if self.report.get() == report::Format::Pretty {
+            self.formatter.writeln(line);
+        } else {
+            self.formatter.writeln_stderr(line);
+        }
+    }
+
+    /// Renders the `duration` of a test, in milliseconds, like libtest's
+    /// `--report-time`, colored if it exceeds the time limits.
+    fn render_time(&self, duration: f64, limits: &TimeLimits) -> String {
+        let time = format!("<{:.3}s>", duration / 1000.);
+        let color = if duration > limits.critical_ms {
+            "31"
+        } else if duration > limits.warn_ms {
+            "33"
+        } else {
+            ""
+        };
+        if self.colors.get() && !color.is_empty() {
+            format!(" \x1b[{color}m{time}\x1b[0m")
+        } else {
+            format!(" {time}")
+        }
+    }
+
+    /// Renders the memory `growth` of a test, colored if it exceeds
+    /// `threshold`.
+    fn render_growth(&self, growth: &memory::Growth, threshold: f64) -> String {
+        if self.colors.get() && growth.exceeds(threshold) {
+            format!(" \x1b[33m{}\x1b[0m", growth.render())
+        } else {
+            format!(" {}", growth.render())
+        }
+    }
+
     fn print_results(&self) {
         let failures = self.failures.borrow();
         if !failures.is_empty() {
-            self.formatter.writeln("\nfailures:\n");
+            self.write_status("\nfailures:\n");
             for (test, failure) in failures.iter() {
                 self.print_failure(test, failure);
             }
-            self.formatter.writeln("failures:\n");
+            self.write_status("failures:\n");
             for (test, _) in failures.iter() {
-                self.formatter.writeln(&format!("    {}", test.name));
+                self.write_status(&format!("    {}", test.name));
+            }
+        }
+        let slow_tests = self.slow_tests.borrow();
+        if let (Some(limits), false) = (self.time_limits.get(), slow_tests.is_empty()) {
+            self.write_status(&format!(
+                "\nwarning: {} {} the time limit of {} ms:",
+                slow_tests.len(),
+                if slow_tests.len() == 1 {
+                    "test exceeded"
+                } else {
+                    "tests exceeded"
+                },
+                limits.warn_ms,
+            ));
+            for (name, duration) in slow_tests.iter() {
+                self.write_status(&format!(
+                    "    {name}{}",
+                    self.render_time(*duration, &limits)
+                ));
+            }
+        }
+        let memory_growths = self.memory_growths.borrow();
+        if let (Some(threshold), false) = (self.memory_threshold.get(), memory_growths.is_empty()) {
+            self.write_status(&format!(
+                "\nwarning: {} {} the memory in use by more than {}:",
+                memory_growths.len(),
+                if memory_growths.len() == 1 {
+                    "test grew"
+                } else {
+                    "tests grew"
+                },
+                memory::bytes(threshold).trim_start_matches('+'),
+            ));
+            for (name, growth) in memory_growths.iter() {
+                self.write_status(&format!(
+                    "    {name}{}",
+                    self.render_growth(growth, threshold)
+                ));
+            }
+        }
+        let leaky_tests = self.leaky_tests.borrow();
+        if !leaky_tests.is_empty() {
+            self.write_status(&format!(
+                "\nwarning: {} {} left DOM elements, timers, event listeners or handles behind:",
+                leaky_tests.len(),
+                if leaky_tests.len() == 1 {
+                    "test"
+                } else {
+                    "tests"
+                },
+            ));
+            for (name, leaked) in leaky_tests.iter() {
+                self.write_status(&format!("    {name}: {leaked}"));
+            }
+        }
+        match self.report.get() {
+            report::Format::Pretty => {
+                let finished_in = if let Some(timer) = &self.timer {
+                    format!("; finished in {:.2?}s", timer.elapsed())
+                } else {
+                    String::new()
+                };
+                self.formatter.writeln("");
+                self.formatter.writeln(&format!(
+                    "test result: {}. \
+                     {} passed; \
+                     {} failed; \
+                     {} ignored; \
+                     {} filtered out\
+                     {}\n",
+                    if failures.is_empty() { "ok" } else { "FAILED" },
+                    self.succeeded_count.get(),
+                    failures.len(),
+                    self.ignored_count.get(),
+                    self.filtered_count.get(),
+                    finished_in,
+                ));
+            }
+            report::Format::Json => {
+                self.formatter.writeln(&report::finished(&report::Summary {
+                    passed: self.succeeded_count.get(),
+                    failed: failures.len(),
+                    ignored: self.ignored_count.get(),
+                    filtered_out: self.filtered_count.get(),
+                    elapsed: self.timer.as_ref().map(Timer::elapsed),
+                }));
+            }
+            report::Format::Junit => {
+                self.formatter.writeln(&report::junit(&self.cases.borrow()));
             }
         }
-        let finished_in = if let Some(timer) = &self.timer {
-            format!("; finished in {:.2?}s", timer.elapsed())
-        } else {
-            String::new()
-        };
-        self.formatter.writeln("");
-        self.formatter.writeln(&format!(
-            "test result: {}. \
-             {} passed; \
-             {} failed; \
-             {} ignored; \
-             {} filtered out\
-             {}\n",
-            if failures.is_empty() { "ok" } else { "FAILED" },
-            self.succeeded_count.get(),
-            failures.len(),
-            self.ignored_count.get(),
-            self.filtered_count.get(),
-            finished_in,
-        ));
     }
 
     fn accumulate_console_output(&self, logs: &mut String, which: &str, output: &str) {
@@ -717,7 +2009,22 @@ impl State {
         }
         logs.push_str(which);
         logs.push_str(" output:\n");
-        logs.push_str(&tab(output));
+        let color = match which {
+            "debug" => "2",
+            "info" => "36",
+            "warn" => "33",
+            "error" => "31",
+            _ => "",
+        };
+        if self.console_colors.get() && !color.is_empty() {
+            let colored: String = output
+                .lines()
+                .map(|line| format!("\x1b[{color}m{line}\x1b[0m\n"))
+                .collect();
+            logs.push_str(&tab(&colored));
+        } else {
+            logs.push_str(&tab(output));
+        }
         logs.push('\n');
     }
 
@@ -734,29 +2041,133 @@ impl State {
             }
             Failure::ShouldPanicExpected => {
                 logs.push_str("note: panic did not contain expected string\n");
-                logs.push_str(&format!("      panic message: `\"{}\"`,\n", output.panic));
+                let message = output.panic.as_ref().map_or("", |p| p.message.as_str());
+                logs.push_str(&format!("      panic message: `\"{}\"`,\n", message));
                 logs.push_str(&format!(
                     " expected substring: `\"{}\"`\n\n",
                     test.should_panic.unwrap().unwrap()
                 ));
             }
+            Failure::Hook("setup", _) => {
+                logs.push_str("note: the setup of the test's module failed, so it didn't run\n\n");
+            }
+            Failure::Hook(hook, _) => {
+                logs.push_str(&format!("note: the {hook} of the test's module failed\n\n"));
+            }
+            Failure::Timeout(timeout_ms) => {
+                logs.push_str(&format!(
+                    "note: {} did not finish within its timeout of {} ms\n\n",
+                    test.name, timeout_ms
+                ));
+            }
+            Failure::TimeLimit(duration, limit) => {
+                logs.push_str(&format!(
+                    "note: {} took {:.3}s, exceeding the time limit of {} ms\n\n",
+                    test.name,
+                    duration / 1000.,
+                    limit
+                ));
+            }
             _ => (),
         }
+        // A panic aborts the property test before its input could be shrunk.
+        if let (Some(case), Some(_)) = (&output.proptest_case, &output.panic) {
+            logs.push_str(
+                "note: the property panicked on the input below, which can't be shrunk\n",
+            );
+            logs.push_str("      as panics abort in Wasm, use `prop_assert!` to shrink it:\n");
+            logs.push_str(&tab(case));
+            logs.push('\n');
+        }
 
         self.accumulate_console_output(&mut logs, "debug", &output.debug);
         self.accumulate_console_output(&mut logs, "log", &output.log);
         self.accumulate_console_output(&mut logs, "info", &output.info);
-        self.accumulate_console_output(&mut logs, "warn", &output.warn);
-        self.accumulate_console_output(&mut logs, "error", &output.error);
+        // With `--split-console-output`, warnings and errors are printed to
+        // stderr separately.
+        let mut errors = String::new();
+        let split = self.split_console_output.get();
+        let dst = if split { &mut errors } else { &mut logs };
+        self.accumulate_console_output(dst, "warn", &output.warn);
+        self.accumulate_console_output(dst, "error", &output.error);
+
+        if !output.uncaught.is_empty() {
+            logs.push_str("errors that were not caught:\n");
+            logs.push_str(&tab(&output.uncaught));
+        }
 
-        if let Failure::Error(error) = failure {
+        // The exception a panic ends in adds nothing to the panic itself.
+        if let Some(panic) = &output.panic {
+            logs.push_str(&panic.render(self.colors.get()));
+        } else if let Failure::Error(error) | Failure::Hook(_, error) = failure {
             logs.push_str("JS exception that was thrown:\n");
             let error_string = self.formatter.stringify_error(error);
             logs.push_str(&tab(&error_string));
         }
 
+        if let Failure::UnhandledRejection(reasons) = failure {
+            logs.push_str("promise rejections that were not handled:\n");
+            for reason in reasons {
+                let reason_string = self.formatter.stringify_error(reason);
+                logs.push_str(&tab(&reason_string));
+            }
+        }
+
+        let mut lines = 0;
         let msg = format!("---- {} output ----\n{}", test.name, tab(&logs));
-        self.formatter.writeln(&msg);
+        self.write_failure_output(&msg, &mut lines, false);
+        if !errors.is_empty() {
+            let msg = format!("---- {} stderr ----\n{}", test.name, tab(&errors));
+            self.write_failure_output(&msg, &mut lines, true);
+        }
+    }
+
+    /// Writes `msg`, failure output of a test of which `lines` lines were
+    /// already written, truncated to the limits on printed output. Suppressed
+    /// output is kept for the runner to save.
+    fn write_failure_output(&self, msg: &str, lines: &mut usize, stderr: bool) {
+        let total = self
+            .max_output_lines
+            .get()
+            .map(|max| max.saturating_sub(self.output_lines.get()));
+        let per_test = self
+            .max_test_output_lines
+            .get()
+            .map(|max| max.saturating_sub(*lines));
+        // Always keep the header naming the test.
+        let allowed = total
+            .into_iter()
+            .chain(per_test)
+            .min()
+            .unwrap_or(usize::MAX)
+            .max(1);
+
+        let msg_lines: Vec<&str> = msg.lines().collect();
+        let kept = msg_lines.len().min(allowed);
+        *lines += kept;
+        self.output_lines.set(self.output_lines.get() + kept);
+
+        let msg = if kept < msg_lines.len() {
+            let suppressed = msg_lines.len() - kept;
+            let see = match &*self.suppressed_output_path.borrow() {
+                Some(path) => format!(" (see {path})"),
+                None => String::new(),
+            };
+            let mut output = self.suppressed_output.borrow_mut();
+            output.push_str(msg);
+            output.push('\n');
+            format!(
+                "{}\n    … {suppressed} more lines suppressed{see}\n",
+                msg_lines[..kept].join("\n")
+            )
+        } else {
+            msg.to_string()
+        };
+        if stderr {
+            self.formatter.writeln_stderr(&msg);
+        } else {
+            self.write_status(&msg);
+        }
     }
 }
 
@@ -803,6 +2214,11 @@ impl<F: Future<Output = Result<(), JsValue>>> Future for TestFuture<F> {
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
         let output = self.output.clone();
+        if output.borrow().worker_panicked {
+            return Poll::Ready(Err(JsValue::from_str(
+                "a worker spawned by the test panicked",
+            )));
+        }
         // Use `new_unchecked` here to project our own pin, and we never
         // move `test` so this should be safe
         let test = unsafe { Pin::map_unchecked_mut(self, |me| &mut me.test) };
@@ -816,13 +2232,67 @@ impl<F: Future<Output = Result<(), JsValue>>> Future for TestFuture<F> {
         });
         match (result, future_output) {
             (_, Some(Poll::Ready(result))) => Poll::Ready(result),
-            (_, Some(Poll::Pending)) => Poll::Pending,
+            (_, Some(Poll::Pending)) => {
+                output.borrow_mut().waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
             (Err(e), _) => Poll::Ready(Err(e)),
             (Ok(_), None) => wasm_bindgen::throw_str("invalid poll state"),
         }
     }
 }
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(f: &Function, ms: i32) -> JsValue;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(timer: &JsValue);
+}
+
+/// Runs `test`, recording in `output` that it timed out if it doesn't finish
+/// within `timeout_ms` milliseconds. A synchronous stretch of a test can't be
+/// interrupted, so a test that returns late times out as well.
+async fn with_timeout(
+    test: impl Future<Output = Result<(), JsValue>>,
+    timeout_ms: u32,
+    output: &RefCell<Output>,
+) -> Result<(), JsValue> {
+    let started = js_sys::Date::now();
+    let delay = i32::try_from(timeout_ms).unwrap_or(i32::MAX);
+    let mut handle = JsValue::UNDEFINED;
+    let promise = Promise::new(&mut |resolve, _| handle = set_timeout(&resolve, delay));
+    let mut timer = wasm_bindgen_futures::JsFuture::from(promise);
+    let mut test = Box::pin(test);
+    let result = core::future::poll_fn(|cx| {
+        if let Poll::Ready(result) = test.as_mut().poll(cx) {
+            return Poll::Ready(Some(result));
+        }
+        Pin::new(&mut timer).poll(cx).map(|_| None)
+    })
+    .await;
+    // Don't keep runtimes like Deno waiting for the timer once the test is done.
+    clear_timeout(&handle);
+    match result {
+        Some(result) if js_sys::Date::now() - started <= f64::from(timeout_ms) => result,
+        _ => {
+            output.borrow_mut().timed_out = Some(timeout_ms);
+            Err(JsValue::from_str(&format!(
+                "test timed out after {timeout_ms} ms"
+            )))
+        }
+    }
+}
+
+/// Resolves once the JS event loop has moved on to its next task.
+async fn next_task() {
+    let promise = Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, 0);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
 fn tab(s: &str) -> String {
     let mut result = String::new();
     for line in s.lines() {
@@ -833,6 +2303,18 @@ fn tab(s: &str) -> String {
     result
 }
 
+/// The current time in milliseconds, from `performance.now()` if available.
+fn performance_now() -> Option<f64> {
+    let global: Global = js_sys::global().unchecked_into();
+    let performance = global.performance();
+    (!performance.is_undefined()).then(|| performance.unchecked_into::<Performance>().now())
+}
+
+/// The number of Wasm instructions executed so far, from `counter`.
+fn instructions(counter: &Function) -> Option<f64> {
+    counter.call0(&JsValue::UNDEFINED).ok()?.as_f64()
+}
+
 struct Timer {
     performance: Performance,
     started: f64,
@@ -20,6 +20,8 @@ extern "C" {
     fn to_string(this: &NodeError) -> Result<String, JsValue>;
     #[wasm_bindgen(js_name = __wbgtest_og_console_log)]
     fn og_console_log(s: &str);
+    #[wasm_bindgen(js_name = __wbgtest_og_console_error)]
+    fn og_console_error(s: &str);
 }
 
 impl Node {
@@ -34,6 +36,10 @@ impl super::Formatter for Node {
         og_console_log(line);
     }
 
+    fn writeln_stderr(&self, line: &str) {
+        og_console_error(line);
+    }
+
     fn stringify_error(&self, err: &JsValue) -> String {
         // TODO: should do a checked cast to `NodeError`
         let err = NodeError::from(err.clone());
@@ -0,0 +1,160 @@
+//! Support for `#[wasm_bindgen_test(proptest)]`, running property tests with
+//! [`proptest`](https://docs.rs/proptest).
+//!
+//! proptest's own `TestRunner` can't drive `async` properties and relies on
+//! catching panics, which abort in Wasm. So the cases are run and shrunk here
+//! instead, reporting the shrunk input and the seed to reproduce it in the
+//! failure output of the test.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Debug;
+use core::future::Future;
+use proptest::strategy::{Strategy, ValueTree};
+use proptest::test_runner::{Config, Reason, RngAlgorithm, TestCaseError, TestRng, TestRunner};
+
+pub use proptest::arbitrary::any;
+
+use super::CURRENT_OUTPUT;
+
+/// The result of a case of a property test: `()`, or a `Result` to use
+/// `prop_assert!` and `prop_assume!`.
+pub trait PropertyResult {
+    /// Converts the result into that of the case.
+    fn into_case_result(self) -> Result<(), TestCaseError>;
+}
+
+impl PropertyResult for () {
+    fn into_case_result(self) -> Result<(), TestCaseError> {
+        Ok(())
+    }
+}
+
+impl PropertyResult for Result<(), TestCaseError> {
+    fn into_case_result(self) -> Result<(), TestCaseError> {
+        self
+    }
+}
+
+/// Runs `test` on inputs generated by `strategy`, panicking with the shrunk
+/// input if it fails. The `#[wasm_bindgen_test(proptest)]` macro generates
+/// invocations of this function.
+pub async fn run<S, F, Fut>(strategy: S, test: F)
+where
+    S: Strategy,
+    F: Fn(S::Value) -> Fut,
+    Fut: Future,
+    Fut::Output: PropertyResult,
+{
+    let seed = seed();
+    let config = Config {
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let mut rng_seed = [0; 32];
+    for chunk in rng_seed.chunks_mut(8) {
+        chunk.copy_from_slice(&seed.to_le_bytes());
+    }
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &rng_seed);
+    let mut runner = TestRunner::new_with_rng(config.clone(), rng);
+
+    let mut passed = 0;
+    let mut rejected = 0;
+    while passed < config.cases {
+        let mut tree = match strategy.new_tree(&mut runner) {
+            Ok(tree) => tree,
+            Err(reason) => panic!("proptest: failed to generate an input: {reason}"),
+        };
+        match case(&test, tree.current(), seed).await {
+            Ok(()) => passed += 1,
+            Err(TestCaseError::Reject(_)) => {
+                rejected += 1;
+                if rejected > config.max_global_rejects {
+                    set_case(None);
+                    panic!("proptest: too many inputs rejected ({rejected})");
+                }
+            }
+            Err(TestCaseError::Fail(reason)) => {
+                let (reason, input, steps) =
+                    shrink(&test, &mut tree, reason, seed, config.max_shrink_iters).await;
+                set_case(None);
+                panic!(
+                    "proptest: test failed: {reason}\n\
+                     minimal failing input: {input:#?}\n\
+                     \tseed: {seed}, rerun with WASM_BINDGEN_TEST_PROPTEST_SEED={seed}\n\
+                     \tsuccesses: {passed}, shrink steps: {steps}"
+                );
+            }
+        }
+    }
+    set_case(None);
+}
+
+/// Shrinks the failing input of `tree`, returning the reason of the last
+/// failure, its input and the number of steps taken.
+async fn shrink<T, F, Fut>(
+    test: &F,
+    tree: &mut T,
+    reason: Reason,
+    seed: u64,
+    max_steps: u32,
+) -> (String, T::Value, u32)
+where
+    T: ValueTree,
+    F: Fn(T::Value) -> Fut,
+    Fut: Future,
+    Fut::Output: PropertyResult,
+{
+    let mut last = (reason.message().into(), tree.current());
+    let mut steps = 0;
+    if !tree.simplify() {
+        return (last.0, last.1, steps);
+    }
+    while steps < max_steps {
+        steps += 1;
+        match case(test, tree.current(), seed).await {
+            Err(TestCaseError::Fail(reason)) => {
+                last = (reason.message().into(), tree.current());
+                if !tree.simplify() {
+                    break;
+                }
+            }
+            _ => {
+                if !tree.complicate() {
+                    break;
+                }
+            }
+        }
+    }
+    (last.0, last.1, steps)
+}
+
+async fn case<T, F, Fut>(test: &F, input: T, seed: u64) -> Result<(), TestCaseError>
+where
+    T: Debug,
+    F: Fn(T) -> Fut,
+    Fut: Future,
+    Fut::Output: PropertyResult,
+{
+    // Recorded for the failure output, in case the test panics on it.
+    set_case(Some(format!("{input:#?}\nseed: {seed}")));
+    test(input).await.into_case_result()
+}
+
+fn set_case(case: Option<String>) {
+    if CURRENT_OUTPUT.is_set() {
+        CURRENT_OUTPUT.with(|output| output.borrow_mut().proptest_case = case);
+    }
+}
+
+/// Returns the seed forwarded by the test runner, or a random one.
+fn seed() -> u64 {
+    let global = js_sys::global();
+    let seed = js_sys::Reflect::get(&global, &"__wbg_proptest_seed".into())
+        .ok()
+        .and_then(|seed| seed.as_string());
+    match seed {
+        Some(seed) => seed.parse().expect("invalid proptest seed"),
+        None => (js_sys::Math::random() * (1u64 << 53) as f64) as u64,
+    }
+}
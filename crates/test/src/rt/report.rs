@@ -0,0 +1,190 @@
+//! Structured reports of the results of tests, for `--format json` and
+//! `--format junit`, which carry the suite of each test.
+//!
+//! A test belongs to the suite named by its `suite` attribute, which the
+//! runner passes to the harness, or else to its module without the crate
+//! name, or to its crate at the root.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::TestResult;
+
+/// How the results of tests are reported.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(super) enum Format {
+    /// A line per test, like libtest's default output.
+    #[default]
+    Pretty,
+    /// A JSON event per line, like libtest's `--format json`.
+    Json,
+    /// A JUnit XML document once all tests finished, like libtest's
+    /// `--format junit`.
+    Junit,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "junit" => Some(Format::Junit),
+            _ => None,
+        }
+    }
+}
+
+/// The suite of a test of `module`, including the crate name, when its
+/// attribute doesn't name one.
+pub(super) fn default_suite(module: &str) -> &str {
+    module.split_once("::").map_or(module, |(_, module)| module)
+}
+
+/// The result of a test, kept for the JUnit report.
+pub(super) struct Case {
+    name: String,
+    outcome: Outcome,
+    /// How long the test took, in milliseconds, if measured.
+    elapsed: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    Failed,
+    Ignored,
+}
+
+impl Outcome {
+    fn of(result: &TestResult) -> Outcome {
+        match result {
+            TestResult::Ok => Outcome::Ok,
+            TestResult::Err(_) => Outcome::Failed,
+            TestResult::Ignored(_) => Outcome::Ignored,
+        }
+    }
+
+    fn event(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Failed => "failed",
+            Outcome::Ignored => "ignored",
+        }
+    }
+}
+
+impl Case {
+    pub fn new(name: &str, result: &TestResult, elapsed: Option<f64>) -> Case {
+        Case {
+            name: name.to_string(),
+            outcome: Outcome::of(result),
+            elapsed,
+        }
+    }
+}
+
+/// The JSON event reporting that `count` tests are about to run.
+pub(super) fn started(count: usize) -> String {
+    format!(r#"{{ "type": "suite", "event": "started", "test_count": {count} }}"#)
+}
+
+/// The JSON event reporting the `result` of test `name` of `suite`, which
+/// took `elapsed` milliseconds if measured.
+pub(super) fn test_event(
+    name: &str,
+    suite: &str,
+    result: &TestResult,
+    elapsed: Option<f64>,
+) -> String {
+    let mut event = format!(
+        r#"{{ "type": "test", "event": "{}", "name": {}, "suite": {}"#,
+        Outcome::of(result).event(),
+        json_string(name),
+        json_string(suite),
+    );
+    if let TestResult::Ignored(Some(reason)) = result {
+        event.push_str(&format!(r#", "message": {}"#, json_string(reason)));
+    }
+    if let Some(elapsed) = elapsed {
+        event.push_str(&format!(r#", "exec_time": {}"#, elapsed / 1000.));
+    }
+    event.push_str(" }");
+    event
+}
+
+/// The counts of tests at the end of the run.
+pub(super) struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub filtered_out: usize,
+    /// How long the run took, in seconds, if measured.
+    pub elapsed: Option<f64>,
+}
+
+/// The JSON event reporting the end of the run.
+pub(super) fn finished(summary: &Summary) -> String {
+    let mut event = format!(
+        r#"{{ "type": "suite", "event": "{}", "passed": {}, "failed": {}, "ignored": {}, "measured": 0, "filtered_out": {}"#,
+        if summary.failed == 0 { "ok" } else { "failed" },
+        summary.passed,
+        summary.failed,
+        summary.ignored,
+        summary.filtered_out,
+    );
+    if let Some(elapsed) = summary.elapsed {
+        event.push_str(&format!(r#", "exec_time": {elapsed}"#));
+    }
+    event.push_str(" }");
+    event
+}
+
+/// The JUnit XML document of the `cases`, with a `<testsuite>` per suite.
+pub(super) fn junit(cases: &BTreeMap<String, Vec<Case>>) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?><testsuites>"#);
+    for (id, (suite, cases)) in cases.iter().enumerate() {
+        let count = |outcome| cases.iter().filter(|case| case.outcome == outcome).count();
+        let suite = xml_escape(suite);
+        xml.push_str(&format!(
+            r#"<testsuite name="{suite}" package="test" id="{id}" errors="0" failures="{}" tests="{}" skipped="{}">"#,
+            count(Outcome::Failed),
+            cases.len(),
+            count(Outcome::Ignored),
+        ));
+        for case in cases {
+            xml.push_str(&format!(
+                r#"<testcase classname="{suite}" name="{}" time="{:.3}""#,
+                xml_escape(&case.name),
+                case.elapsed.unwrap_or(0.) / 1000.,
+            ));
+            xml.push_str(match case.outcome {
+                Outcome::Ok => "/>",
+                Outcome::Failed => r#"><failure type="assert"/></testcase>"#,
+                Outcome::Ignored => "><skipped/></testcase>",
+            });
+        }
+        xml.push_str("<system-out/><system-err/></testsuite>");
+    }
+    xml.push_str("</testsuites>");
+    xml
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("strings serialize to JSON")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,74 @@
+//! Fresh container elements for DOM tests, see [`Sandbox`].
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// A fresh `<div>` appended to `document.body`, for a browser test to render
+/// into without leaking state into the tests after it.
+///
+/// Once the last `Sandbox` of a test is dropped, or the test finishes, even by
+/// panicking, the document is put back as it was before the first one was
+/// created: the nodes added to `<head>` and `<body>`, including the containers,
+/// and the event listeners added meanwhile are removed, and the attributes of
+/// `<html>` and `<body>` and the adopted style sheets are restored.
+///
+/// ```ignore
+/// let sandbox = Sandbox::new();
+/// let container: web_sys::HtmlElement = sandbox.container();
+/// container.set_inner_html("<button>Click</button>");
+/// ```
+///
+/// Only available to tests running in a browser, see
+/// `wasm_bindgen_test_configure!(run_in_browser)`.
+pub struct Sandbox {
+    container: JsValue,
+}
+
+impl Sandbox {
+    /// Appends a fresh container element to `document.body`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the test doesn't run in a browser.
+    pub fn new() -> Sandbox {
+        let open = sandbox()
+            .and_then(|sandbox| call(&sandbox, "open", &JsValue::UNDEFINED))
+            .expect("`Sandbox` is only available to tests running in a browser");
+        Sandbox { container: open }
+    }
+
+    /// Returns the container element, as a `web_sys::HtmlElement` for
+    /// example.
+    pub fn container<T: JsCast>(&self) -> T {
+        self.container.clone().unchecked_into()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if let Some(sandbox) = sandbox() {
+            call(&sandbox, "close", &JsValue::FALSE);
+        }
+    }
+}
+
+/// Closes the sandboxes left open by the test that just finished.
+pub(super) fn close_all() {
+    if let Some(sandbox) = sandbox() {
+        call(&sandbox, "close", &JsValue::TRUE);
+    }
+}
+
+/// The sandbox support of the test runner, only defined in browsers.
+fn sandbox() -> Option<JsValue> {
+    let sandbox = js_sys::Reflect::get(&js_sys::global(), &"__wbg_sandbox".into()).ok()?;
+    (!sandbox.is_undefined()).then_some(sandbox)
+}
+
+fn call(sandbox: &JsValue, method: &str, arg: &JsValue) -> Option<JsValue> {
+    let method = js_sys::Reflect::get(sandbox, &method.into()).ok()?;
+    method
+        .unchecked_into::<js_sys::Function>()
+        .call1(sandbox, arg)
+        .ok()
+}
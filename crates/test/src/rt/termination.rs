@@ -0,0 +1,170 @@
+//! Picks how the failure of a test returning a `Result` is shown.
+//!
+//! [`Termination`] can only rely on `Debug` for the error of any `Result`. The
+//! `#[wasm_bindgen_test]` macro calls `(&&&result).__wbgt_kind().wrap(result)`
+//! with these traits in scope instead, so that autoref-based specialization
+//! picks the most specific conversion available for the type of `result`:
+//!
+//! - `JsValue` and `JsError` errors are thrown as they are, so that their stack
+//!   is shown.
+//! - Errors implementing `std::error::Error`, and boxed ones, show their
+//!   `Display` output and the chain of their sources.
+//! - Anything else goes through [`Termination`].
+
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use alloc::format;
+#[cfg(feature = "std")]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::error::Error;
+use wasm_bindgen::{JsError, JsValue};
+
+use super::Termination;
+
+/// The result of a test, already converted.
+pub struct Converted(Result<(), JsValue>);
+
+impl Termination for Converted {
+    fn into_js_result(self) -> Result<(), JsValue> {
+        self.0
+    }
+}
+
+/// Throws `JsValue` and `JsError` errors as they are.
+pub struct JsValueTag;
+
+impl JsValueTag {
+    /// Converts `result`.
+    pub fn wrap<E: Into<JsValue>>(self, result: Result<(), E>) -> Converted {
+        Converted(result.map_err(Into::into))
+    }
+}
+
+/// Selects [`JsValueTag`] for `&&&Result<(), JsValue | JsError>`.
+pub trait JsValueKind {
+    /// Returns the tag converting the result.
+    fn __wbgt_kind(self) -> JsValueTag;
+}
+
+impl JsValueKind for &&&Result<(), JsValue> {
+    fn __wbgt_kind(self) -> JsValueTag {
+        JsValueTag
+    }
+}
+
+impl JsValueKind for &&&Result<(), JsError> {
+    fn __wbgt_kind(self) -> JsValueTag {
+        JsValueTag
+    }
+}
+
+/// Shows the `Display` output and sources of boxed errors.
+#[cfg(feature = "std")]
+pub struct BoxedErrorTag;
+
+#[cfg(feature = "std")]
+impl BoxedErrorTag {
+    /// Converts `result`.
+    pub fn wrap<E: BoxedError>(self, result: Result<(), E>) -> Converted {
+        Converted(result.map_err(|error| JsError::new(&error_chain(error.as_error())).into()))
+    }
+}
+
+/// A boxed error, which doesn't implement `Error` itself.
+#[cfg(feature = "std")]
+pub trait BoxedError {
+    /// Returns the boxed error.
+    fn as_error(&self) -> &dyn Error;
+}
+
+#[cfg(feature = "std")]
+impl BoxedError for Box<dyn Error> {
+    fn as_error(&self) -> &dyn Error {
+        &**self
+    }
+}
+
+#[cfg(feature = "std")]
+impl BoxedError for Box<dyn Error + Send + Sync> {
+    fn as_error(&self) -> &dyn Error {
+        &**self
+    }
+}
+
+/// Selects [`BoxedErrorTag`] for `&&&Result<(), Box<dyn Error>>`.
+#[cfg(feature = "std")]
+pub trait BoxedErrorKind {
+    /// Returns the tag converting the result.
+    fn __wbgt_kind(self) -> BoxedErrorTag;
+}
+
+#[cfg(feature = "std")]
+impl<E: BoxedError> BoxedErrorKind for &&&Result<(), E> {
+    fn __wbgt_kind(self) -> BoxedErrorTag {
+        BoxedErrorTag
+    }
+}
+
+/// Shows the `Display` output and sources of errors.
+#[cfg(feature = "std")]
+pub struct ErrorTag;
+
+#[cfg(feature = "std")]
+impl ErrorTag {
+    /// Converts `result`.
+    pub fn wrap<E: Error>(self, result: Result<(), E>) -> Converted {
+        Converted(result.map_err(|error| JsError::new(&error_chain(&error)).into()))
+    }
+}
+
+/// Selects [`ErrorTag`] for `&&Result<(), E>` where `E: Error`.
+#[cfg(feature = "std")]
+pub trait ErrorKind {
+    /// Returns the tag converting the result.
+    fn __wbgt_kind(self) -> ErrorTag;
+}
+
+#[cfg(feature = "std")]
+impl<E: Error> ErrorKind for &&Result<(), E> {
+    fn __wbgt_kind(self) -> ErrorTag {
+        ErrorTag
+    }
+}
+
+/// Converts anything else with [`Termination`].
+pub struct TerminationTag;
+
+impl TerminationTag {
+    /// Converts `result`.
+    pub fn wrap<T: Termination>(self, result: T) -> T {
+        result
+    }
+}
+
+/// Selects [`TerminationTag`] for `&T` where `T: Termination`.
+pub trait TerminationKind {
+    /// Returns the tag converting the result.
+    fn __wbgt_kind(self) -> TerminationTag;
+}
+
+impl<T: Termination> TerminationKind for &T {
+    fn __wbgt_kind(self) -> TerminationTag {
+        TerminationTag
+    }
+}
+
+/// Formats `error` followed by its sources, like `anyhow` does.
+#[cfg(feature = "std")]
+fn error_chain(error: &dyn Error) -> String {
+    let mut chain = format!("{error}");
+    let mut sources = core::iter::successors(error.source(), |&error| error.source()).peekable();
+    if sources.peek().is_some() {
+        chain.push_str("\n\nCaused by:");
+        for (i, source) in sources.enumerate() {
+            chain.push_str(&format!("\n    {i}: {source}"));
+        }
+    }
+    chain
+}
@@ -0,0 +1,33 @@
+//! Values of `#[wasm_bindgen_test_context]` functions, initialized once by the
+//! harness before any test runs and shared by all the tests of the binary.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use core::any::Any;
+use core::cell::RefCell;
+use wasm_bindgen::__rt::LazyCell;
+
+#[cfg_attr(target_feature = "atomics", thread_local)]
+static CONTEXTS: LazyCell<RefCell<BTreeMap<&'static str, &'static dyn Any>>> =
+    LazyCell::new(|| RefCell::new(BTreeMap::new()));
+
+/// Stores the value of the context at `path`, once it's initialized.
+pub fn set<T: 'static>(path: &'static str, value: T) {
+    let value: &'static dyn Any = Box::leak(Box::new(value));
+    CONTEXTS.borrow_mut().insert(path, value);
+}
+
+/// Returns the value of the context at `path`. The function marked with
+/// `#[wasm_bindgen_test_context]` is replaced by a call to this function.
+pub fn get<T: 'static>(path: &'static str) -> &'static T {
+    let value = CONTEXTS.borrow().get(path).copied();
+    match value {
+        Some(value) => value
+            .downcast_ref()
+            .expect("the test context has another type"),
+        None => panic!(
+            "the test context `{path}` isn't initialized, it's only available to the tests run \
+             by the harness"
+        ),
+    }
+}
@@ -810,3 +810,42 @@ pub fn maybe_catch_unwind<F: FnOnce() -> R + std::panic::UnwindSafe, R>(f: F) ->
 pub fn maybe_catch_unwind<F: FnOnce() -> R, R>(f: F) -> R {
     f()
 }
+
+#[wasm_bindgen_macro::wasm_bindgen(wasm_bindgen = crate)]
+extern "C" {
+    #[wasm_bindgen(js_name = Object)]
+    fn __wbindgen_object_new() -> JsValue;
+
+    #[wasm_bindgen(js_namespace = Reflect, js_name = get)]
+    fn __wbindgen_reflect_get(target: &JsValue, key: &str) -> JsValue;
+
+    #[wasm_bindgen(js_namespace = Reflect, js_name = set)]
+    fn __wbindgen_reflect_set(target: &JsValue, key: &str, value: JsValue) -> bool;
+}
+
+/// Creates the object `{ kind }` that a variant of an enum with data is
+/// passed to JS as, for its fields to be set on.
+pub fn data_enum_new(kind: &str) -> JsValue {
+    let obj = __wbindgen_object_new();
+    data_enum_set(&obj, "kind", JsValue::from_str(kind));
+    obj
+}
+
+/// Sets the field `name` of the object of a variant of an enum with data.
+pub fn data_enum_set(obj: &JsValue, name: &str, value: JsValue) {
+    __wbindgen_reflect_set(obj, name, value);
+}
+
+/// Returns the `kind` of an object passed for an enum with data, if it is an
+/// object with a string `kind`.
+pub fn data_enum_kind(value: &JsValue) -> Option<alloc::string::String> {
+    if !value.is_object() {
+        return None;
+    }
+    __wbindgen_reflect_get(value, "kind").as_string()
+}
+
+/// Returns the field `name` of an object passed for an enum with data.
+pub fn data_enum_get(obj: &JsValue, name: &str) -> JsValue {
+    __wbindgen_reflect_get(obj, name)
+}